@@ -28,6 +28,7 @@ pub(crate) mod operation;
 pub mod query_graph;
 pub mod query_plan;
 pub mod schema;
+pub(crate) mod sources;
 pub mod subgraph;
 pub(crate) mod utils;
 
@@ -44,6 +45,7 @@ use crate::link::join_spec_definition::JoinSpecDefinition;
 use crate::link::link_spec_definition::LinkSpecDefinition;
 use crate::link::spec::Identity;
 use crate::link::spec_definition::SpecDefinitions;
+use crate::merge::merge_changed_subgraph;
 use crate::merge::merge_subgraphs;
 use crate::merge::MergeFailure;
 pub use crate::query_graph::extract_subgraphs_from_supergraph::ValidFederationSubgraph;
@@ -118,6 +120,20 @@ impl Supergraph {
         })
     }
 
+    /// Recomposes this supergraph after a change to the subgraph named `changed`, merging again
+    /// only the types that subgraph defines or used to define. `subgraphs` must contain the current
+    /// version of every subgraph of this supergraph, and may add the changed subgraph if it is new.
+    pub fn recompose(
+        &self,
+        subgraphs: Vec<&ValidSubgraph>,
+        changed: &str,
+    ) -> Result<Self, MergeFailure> {
+        let schema = merge_changed_subgraph(self.schema.schema(), subgraphs, changed)?.schema;
+        Ok(Self {
+            schema: ValidFederationSchema::new(schema).map_err(Into::<MergeFailure>::into)?,
+        })
+    }
+
     /// Generates an API Schema from this supergraph schema. The API Schema represents the combined
     /// API of the supergraph that's visible to end users.
     pub fn to_api_schema(
@@ -100,8 +100,15 @@ impl Debug for MergeFailure {
     }
 }
 
-pub fn merge_subgraphs(subgraphs: Vec<&ValidSubgraph>) -> Result<MergeSuccess, MergeFailure> {
-    let mut merger = Merger::new();
+/// Previous supergraph of a recomposition, and the types to merge again
+struct Recomposition<'a> {
+    previous: &'a Schema,
+    affected: IndexSet<NamedType>,
+}
+
+fn to_federation_subgraphs(
+    subgraphs: Vec<&ValidSubgraph>,
+) -> Result<ValidFederationSubgraphs, MergeFailure> {
     let mut federation_subgraphs = ValidFederationSubgraphs::new();
     for subgraph in subgraphs {
         federation_subgraphs.add(ValidFederationSubgraph {
@@ -110,14 +117,87 @@ pub fn merge_subgraphs(subgraphs: Vec<&ValidSubgraph>) -> Result<MergeSuccess, M
             schema: ValidFederationSchema::new(subgraph.schema.clone())?,
         })?;
     }
-    merger.merge(federation_subgraphs)
+    Ok(federation_subgraphs)
+}
+
+pub fn merge_subgraphs(subgraphs: Vec<&ValidSubgraph>) -> Result<MergeSuccess, MergeFailure> {
+    let mut merger = Merger::new();
+    merger.merge(to_federation_subgraphs(subgraphs)?, None)
 }
 
 pub fn merge_federation_subgraphs(
     subgraphs: ValidFederationSubgraphs,
 ) -> Result<MergeSuccess, MergeFailure> {
     let mut merger = Merger::new();
-    merger.merge(subgraphs)
+    merger.merge(subgraphs, None)
+}
+
+/// Recomposes a supergraph after a change to the subgraph named `changed`.
+///
+/// Only the types defined by the previous or the new version of the changed subgraph are merged
+/// again. The other types are copied from the previous supergraph, whose type order is kept.
+/// `subgraphs` must contain the current version of every subgraph of the previous supergraph,
+/// and may add the changed subgraph if it is new.
+pub fn merge_changed_subgraph(
+    previous: &Schema,
+    subgraphs: Vec<&ValidSubgraph>,
+    changed: &str,
+) -> Result<MergeSuccess, MergeFailure> {
+    let failure = |errors: Vec<MergeError>| MergeFailure {
+        schema: None,
+        errors,
+        composition_hints: vec![],
+    };
+
+    let Some(changed_subgraph) = subgraphs.iter().find(|subgraph| subgraph.name == changed) else {
+        return Err(failure(vec![format!(
+            "changed subgraph {changed} is not in the subgraphs"
+        )]));
+    };
+    let graph_names: IndexSet<Name> = subgraphs
+        .iter()
+        .filter_map(|subgraph| Name::new(&subgraph.name.to_uppercase()).ok())
+        .collect();
+    if let Some(join_graph) = previous.get_enum("join__Graph") {
+        let missing: Vec<MergeError> = join_graph
+            .values
+            .keys()
+            .filter(|graph| !graph_names.contains(*graph))
+            .map(|graph| {
+                format!("subgraph {graph} of the supergraph is missing from the subgraphs")
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(failure(missing));
+        }
+    }
+
+    // the types the changed subgraph contributed to, and the types it now defines
+    let mut affected: IndexSet<NamedType> = IndexSet::default();
+    if let Ok(changed_graph) = Name::new(&changed.to_uppercase()) {
+        for (type_name, ty) in &previous.types {
+            let contributes = ty.directives().get_all("join__type").any(|directive| {
+                matches!(
+                    directive_arg_value(directive, &name!("graph")),
+                    Some(Value::Enum(graph)) if *graph == changed_graph
+                )
+            });
+            if contributes {
+                affected.insert(type_name.clone());
+            }
+        }
+    }
+    for (type_name, ty) in &changed_subgraph.schema.types {
+        if !ty.is_built_in() && is_mergeable_type(type_name) {
+            affected.insert(type_name.clone());
+        }
+    }
+
+    let mut merger = Merger::new();
+    merger.merge(
+        to_federation_subgraphs(subgraphs)?,
+        Some(&Recomposition { previous, affected }),
+    )
 }
 
 impl Merger {
@@ -128,7 +208,11 @@ impl Merger {
             needs_inaccessible: false,
         }
     }
-    fn merge(&mut self, subgraphs: ValidFederationSubgraphs) -> Result<MergeSuccess, MergeFailure> {
+    fn merge(
+        &mut self,
+        subgraphs: ValidFederationSubgraphs,
+        recomposition: Option<&Recomposition>,
+    ) -> Result<MergeSuccess, MergeFailure> {
         let mut subgraphs = subgraphs
             .into_iter()
             .map(|(_, subgraph)| subgraph)
@@ -180,6 +264,10 @@ impl Merger {
                     // skip built-ins and federation specific types
                     continue;
                 }
+                if recomposition.is_some_and(|r| !r.affected.contains(type_name)) {
+                    // copied from the previous supergraph
+                    continue;
+                }
 
                 match ty {
                     ExtendedType::Enum(value) => self.merge_enum_type(
@@ -239,6 +327,10 @@ impl Merger {
             }
         }
 
+        if let Some(recomposition) = recomposition {
+            self.copy_unaffected_types(&mut supergraph, recomposition);
+        }
+
         if self.needs_inaccessible {
             add_core_feature_inaccessible(&mut supergraph);
         }
@@ -259,6 +351,38 @@ impl Merger {
         }
     }
 
+    fn copy_unaffected_types(&mut self, supergraph: &mut Schema, recomposition: &Recomposition) {
+        let previous = recomposition.previous;
+        for (type_name, ty) in &previous.types {
+            if ty.is_built_in()
+                || !is_mergeable_type(type_name)
+                || type_name.starts_with("join__")
+                || recomposition.affected.contains(type_name)
+            {
+                continue;
+            }
+            supergraph
+                .types
+                .entry(type_name.clone())
+                .or_insert_with(|| ty.clone());
+        }
+        if previous
+            .directive_definitions
+            .contains_key(&INACCESSIBLE_DIRECTIVE_NAME_IN_SPEC)
+        {
+            self.needs_inaccessible = true;
+        }
+
+        // keep the order of the previous supergraph, so that both can be compared
+        let mut types = std::mem::take(&mut supergraph.types);
+        supergraph.types = previous
+            .types
+            .keys()
+            .filter_map(|type_name| types.shift_remove_entry(type_name))
+            .collect();
+        supergraph.types.extend(types);
+    }
+
     fn merge_descriptions<T: Eq + Clone>(&mut self, merged: &mut Option<T>, new: &Option<T>) {
         match (&mut *merged, new) {
             (_, None) => {}
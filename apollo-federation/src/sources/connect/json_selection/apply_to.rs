@@ -141,7 +141,9 @@ impl ApplyTo for JSONSelection {
         errors: &mut IndexSet<ApplyToError>,
     ) -> Option<JSON> {
         if let JSON::Array(array) = data {
-            return self.apply_to_array(array, vars, input_path, errors);
+            if !matches!(self, Self::Path(path) if path.applies_to_arrays()) {
+                return self.apply_to_array(array, vars, input_path, errors);
+            }
         }
 
         match self {
@@ -251,7 +253,9 @@ impl ApplyTo for PathSelection {
         errors: &mut IndexSet<ApplyToError>,
     ) -> Option<JSON> {
         if let JSON::Array(array) = data {
-            return self.apply_to_array(array, vars, input_path, errors);
+            if !self.applies_to_arrays() {
+                return self.apply_to_array(array, vars, input_path, errors);
+            }
         }
 
         match self {
@@ -311,6 +315,14 @@ impl ApplyTo for PathSelection {
 
                 result
             }
+            Self::Method(method, args, tail) => {
+                input_path.push(json!(format!("->{method}")));
+                let args = args.as_ref().map_or(&[][..], |args| args.0.as_slice());
+                let result = apply_method(method, args, data, vars, input_path, errors)
+                    .and_then(|value| tail.apply_to_path(&value, vars, input_path, errors));
+                input_path.pop();
+                result
+            }
             Self::Selection(selection) => {
                 // If data is not an object here, this recursive apply_to_path
                 // call will handle the error.
@@ -325,6 +337,170 @@ impl ApplyTo for PathSelection {
     }
 }
 
+impl PathSelection {
+    // Methods like ->first and ->size apply to arrays as a whole, so arrays are
+    // not mapped over when the next step of the path is a method, as in
+    // $->first.
+    fn applies_to_arrays(&self) -> bool {
+        match self {
+            Self::Method(_, _, _) => true,
+            Self::Var(var_name, tail) if var_name == "$" => {
+                matches!(tail.as_ref(), Self::Method(_, _, _))
+            }
+            _ => false,
+        }
+    }
+}
+
+fn apply_method(
+    method: &str,
+    args: &[JSLiteral],
+    data: &JSON,
+    vars: &IndexMap<String, JSON>,
+    input_path: &mut Vec<JSON>,
+    errors: &mut IndexSet<ApplyToError>,
+) -> Option<JSON> {
+    if method != "match" && !args.is_empty() {
+        errors.insert(ApplyToError::new(
+            format!("Method ->{} does not take any arguments", method).as_str(),
+            input_path,
+        ));
+        return None;
+    }
+
+    match method {
+        // A value that is not an array is its own first and last item.
+        "first" => match data {
+            JSON::Array(array) => array.first().cloned(),
+            JSON::String(string) => string
+                .as_str()
+                .chars()
+                .next()
+                .map(|c| JSON::String(c.to_string().into())),
+            _ => Some(data.clone()),
+        },
+        "last" => match data {
+            JSON::Array(array) => array.last().cloned(),
+            JSON::String(string) => string
+                .as_str()
+                .chars()
+                .last()
+                .map(|c| JSON::String(c.to_string().into())),
+            _ => Some(data.clone()),
+        },
+        "size" => match data {
+            JSON::Array(array) => Some(JSON::Number(array.len().into())),
+            JSON::String(string) => Some(JSON::Number(string.as_str().chars().count().into())),
+            JSON::Object(map) => Some(JSON::Number(map.len().into())),
+            _ => {
+                errors.insert(ApplyToError::new(
+                    format!(
+                        "Method ->size cannot be applied to {}",
+                        json_type_name(data)
+                    )
+                    .as_str(),
+                    input_path,
+                ));
+                None
+            }
+        },
+        "jsonStringify" => Some(JSON::String(data.to_string().into())),
+        "match" => {
+            // The cases are matched against strings, and against the JSON
+            // representation of other primitive values, like "true" or "1".
+            let cases = match args {
+                [cases] => cases.apply_to_path(data, vars, input_path, errors),
+                _ => None,
+            };
+            let Some(JSON::Object(cases)) = cases else {
+                errors.insert(ApplyToError::new(
+                    "Method ->match requires a single object argument",
+                    input_path,
+                ));
+                return None;
+            };
+            let key = match data {
+                JSON::String(string) => string.as_str().to_string(),
+                JSON::Array(_) | JSON::Object(_) => {
+                    errors.insert(ApplyToError::new(
+                        format!(
+                            "Method ->match cannot be applied to {}",
+                            json_type_name(data)
+                        )
+                        .as_str(),
+                        input_path,
+                    ));
+                    return None;
+                }
+                _ => data.to_string(),
+            };
+            let value = cases.get(key.as_str()).cloned();
+            if value.is_none() {
+                errors.insert(ApplyToError::new(
+                    format!("Method ->match has no case for {}", data).as_str(),
+                    input_path,
+                ));
+            }
+            value
+        }
+        _ => {
+            errors.insert(ApplyToError::new(
+                format!("Method ->{} not found", method).as_str(),
+                input_path,
+            ));
+            None
+        }
+    }
+}
+
+impl ApplyTo for JSLiteral {
+    // PathSelection literals are applied to the value the method is invoked on,
+    // so $ refers to that value, and variables are available as usual.
+    fn apply_to_path(
+        &self,
+        data: &JSON,
+        vars: &IndexMap<String, JSON>,
+        input_path: &mut Vec<JSON>,
+        errors: &mut IndexSet<ApplyToError>,
+    ) -> Option<JSON> {
+        match self {
+            Self::String(string) => Some(JSON::String(string.clone().into())),
+            Self::Number(number) => {
+                if let Ok(integer) = number.parse::<i64>() {
+                    Some(JSON::Number(integer.into()))
+                } else {
+                    number
+                        .parse::<f64>()
+                        .ok()
+                        .and_then(serde_json::Number::from_f64)
+                        .map(JSON::Number)
+                }
+            }
+            Self::Bool(value) => Some(JSON::Bool(*value)),
+            Self::Null => Some(JSON::Null),
+            Self::Object(properties) => {
+                let mut output = Map::new();
+                for (key, value) in properties {
+                    if let Some(value) = value.apply_to_path(data, vars, input_path, errors) {
+                        output.insert(key.as_string(), value);
+                    }
+                }
+                Some(JSON::Object(output))
+            }
+            Self::Array(items) => Some(JSON::Array(
+                items
+                    .iter()
+                    .map(|item| {
+                        item.apply_to_path(data, vars, input_path, errors)
+                            .unwrap_or(JSON::Null)
+                    })
+                    .collect(),
+            )),
+            Self::Path(path) => path.apply_to_path(data, vars, input_path, errors),
+        }
+    }
+}
+
 impl ApplyTo for SubSelection {
     fn apply_to_path(
         &self,
@@ -1309,4 +1485,113 @@ mod tests {
             (Some(json!(123)), vec![],),
         );
     }
+
+    #[test]
+    fn test_apply_to_arrow_methods() {
+        let data = json!({
+            "items": [
+                { "id": 1, "kind": "dog" },
+                { "id": 2, "kind": "cat" },
+                { "id": 3, "kind": "fish" },
+            ],
+            "empty": [],
+            "name": "héllo",
+            "object": { "a": 1, "b": [true, null] },
+            "flag": true,
+        });
+
+        assert_eq!(
+            selection!("$.items->first { id }").apply_to(&data),
+            (Some(json!({ "id": 1 })), vec![]),
+        );
+        assert_eq!(
+            selection!("$.items->last.kind").apply_to(&data),
+            (Some(json!("fish")), vec![]),
+        );
+        assert_eq!(selection!("$.empty->first").apply_to(&data), (None, vec![]),);
+        assert_eq!(
+            selection!("$.name->first").apply_to(&data),
+            (Some(json!("h")), vec![]),
+        );
+        assert_eq!(
+            selection!("$.flag->last").apply_to(&data),
+            (Some(json!(true)), vec![]),
+        );
+        // Arrays are not mapped over when $ is followed by a method
+        assert_eq!(
+            selection!("$->first").apply_to(&json!([1, 2, 3])),
+            (Some(json!(1)), vec![]),
+        );
+
+        assert_eq!(
+            selection!(
+                "count: items->size
+                length: name->size
+                keys: object->size
+                json: object->jsonStringify"
+            )
+            .apply_to(&data),
+            (
+                Some(json!({
+                    "count": 3,
+                    "length": 5,
+                    "keys": 2,
+                    "json": "{\"a\":1,\"b\":[true,null]}",
+                })),
+                vec![],
+            ),
+        );
+
+        assert_eq!(
+            selection!(
+                "pets: items {
+                    id
+                    __typename: kind->match({ dog: 'Dog', cat: 'Cat' })
+                }"
+            )
+            .apply_to(&data),
+            (
+                Some(json!({
+                    "pets": [
+                        { "id": 1, "__typename": "Dog" },
+                        { "id": 2, "__typename": "Cat" },
+                        { "id": 3 },
+                    ],
+                })),
+                vec![ApplyToError::from_json(&json!({
+                    "message": "Method ->match has no case for \"fish\"",
+                    "path": ["items", 2, "kind", "->match"],
+                }))],
+            ),
+        );
+
+        let mut vars = IndexMap::default();
+        vars.insert("$args".to_string(), json!({ "yes": 1 }));
+        assert_eq!(
+            selection!("$.flag->match({ true: $args.yes, false: 'no' })")
+                .apply_with_vars(&data, &vars),
+            (Some(json!(1)), vec![]),
+        );
+
+        assert_eq!(
+            selection!("$.flag->size").apply_to(&data),
+            (
+                None,
+                vec![ApplyToError::from_json(&json!({
+                    "message": "Method ->size cannot be applied to boolean",
+                    "path": ["flag", "->size"],
+                }))],
+            ),
+        );
+        assert_eq!(
+            selection!("$.items->first(1)").apply_to(&data),
+            (
+                None,
+                vec![ApplyToError::from_json(&json!({
+                    "message": "Method ->first does not take any arguments",
+                    "path": ["items", "->first"],
+                }))],
+            ),
+        );
+    }
 }
@@ -102,6 +102,12 @@ impl From<PathSelection> for Vec<GraphQLSelection> {
                 let tail = *tail;
                 tail.into()
             }
+            PathSelection::Method(_, _, tail) => {
+                // Methods transform the selected value, which is then selected
+                // by the rest of the path.
+                let tail = *tail;
+                tail.into()
+            }
             PathSelection::Selection(selection) => {
                 GraphQLSelections::from(selection).valid_selections()
             }
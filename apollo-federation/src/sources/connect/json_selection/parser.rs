@@ -1,13 +1,17 @@
 use std::fmt::Display;
 
 use nom::branch::alt;
+use nom::bytes::complete::tag;
 use nom::character::complete::char;
+use nom::character::complete::digit0;
+use nom::character::complete::digit1;
 use nom::character::complete::one_of;
 use nom::combinator::all_consuming;
 use nom::combinator::map;
 use nom::combinator::opt;
 use nom::combinator::recognize;
 use nom::multi::many0;
+use nom::multi::separated_list0;
 use nom::sequence::delimited;
 use nom::sequence::pair;
 use nom::sequence::preceded;
@@ -203,6 +207,7 @@ pub enum PathSelection {
     // the selection to a JSON value easier.
     Var(String, Box<PathSelection>),
     Key(Key, Box<PathSelection>),
+    Method(String, Option<MethodArgs>, Box<PathSelection>),
     Selection(SubSelection),
     Empty,
 }
@@ -269,6 +274,23 @@ impl PathSelection {
             )));
         }
 
+        // The ->method case is applicable after any $var or key, but only the
+        // methods listed in ARROW_METHODS are recognized.
+        if let Ok((suffix, (method, args))) = tuple((
+            preceded(tuple((spaces_or_comments, tag("->"))), parse_identifier),
+            opt(MethodArgs::parse),
+        ))(input)
+        {
+            if !ARROW_METHODS.contains(&method.as_str()) {
+                return Err(nom::Err::Error(nom::error::Error::new(
+                    input,
+                    nom::error::ErrorKind::IsNot,
+                )));
+            }
+            let (input, rest) = Self::parse_with_depth(suffix, depth + 1)?;
+            return Ok((input, Self::Method(method, args, Box::new(rest))));
+        }
+
         // If the PathSelection has a SubSelection, it must appear at the end of
         // a non-empty path.
         if let Ok((suffix, selection)) = SubSelection::parse(input) {
@@ -312,6 +334,7 @@ impl PathSelection {
         match self {
             PathSelection::Var(_, path) => path.next_subselection(),
             PathSelection::Key(_, path) => path.next_subselection(),
+            PathSelection::Method(_, _, path) => path.next_subselection(),
             PathSelection::Selection(sub) => Some(sub),
             PathSelection::Empty => None,
         }
@@ -322,12 +345,111 @@ impl PathSelection {
         match self {
             PathSelection::Var(_, path) => path.next_mut_subselection(),
             PathSelection::Key(_, path) => path.next_mut_subselection(),
+            PathSelection::Method(_, _, path) => path.next_mut_subselection(),
             PathSelection::Selection(sub) => Some(sub),
             PathSelection::Empty => None,
         }
     }
 }
 
+// Methods that can be invoked with a ->method PathStep. See apply_to.rs for
+// their implementations.
+pub(super) const ARROW_METHODS: &[&str] = &["first", "last", "size", "jsonStringify", "match"];
+
+// MethodArgs ::= "(" (JSLiteral ("," JSLiteral)*)? ")"
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub struct MethodArgs(pub(super) Vec<JSLiteral>);
+
+impl MethodArgs {
+    fn parse(input: &str) -> IResult<&str, Self> {
+        delimited(
+            tuple((spaces_or_comments, char('('))),
+            separated_list0(char(','), JSLiteral::parse),
+            tuple((spaces_or_comments, char(')'), spaces_or_comments)),
+        )(input)
+        .map(|(input, args)| (input, Self(args)))
+    }
+}
+
+// JSLiteral   ::= JSPrimitive | JSObject | JSArray | PathSelection
+// JSPrimitive ::= StringLiteral | JSNumber | "true" | "false" | "null"
+// JSObject    ::= "{" (JSProperty ("," JSProperty)*)? "}"
+// JSProperty  ::= Key ":" JSLiteral
+// JSArray     ::= "[" (JSLiteral ("," JSLiteral)*)? "]"
+
+#[derive(Debug, PartialEq, Clone, Serialize)]
+pub enum JSLiteral {
+    String(String),
+    // Numbers are kept as they were written, so they can be reprinted, and are
+    // converted to JSON numbers when the literal is applied.
+    Number(String),
+    Bool(bool),
+    Null,
+    Object(Vec<(Key, JSLiteral)>),
+    Array(Vec<JSLiteral>),
+    Path(PathSelection),
+}
+
+impl JSLiteral {
+    fn parse(input: &str) -> IResult<&str, Self> {
+        delimited(
+            spaces_or_comments,
+            alt((
+                // A PathSelection must have at least one step after its first
+                // key, so bare keywords and string literals are not mistaken
+                // for paths.
+                map(PathSelection::parse, Self::Path),
+                map(parse_string_literal, Self::String),
+                map(parse_number, Self::Number),
+                map(tag("true"), |_| Self::Bool(true)),
+                map(tag("false"), |_| Self::Bool(false)),
+                map(tag("null"), |_| Self::Null),
+                map(
+                    delimited(
+                        char('{'),
+                        separated_list0(
+                            char(','),
+                            map(
+                                tuple((Key::parse, char(':'), Self::parse)),
+                                |(key, _, value)| (key, value),
+                            ),
+                        ),
+                        tuple((spaces_or_comments, char('}'))),
+                    ),
+                    Self::Object,
+                ),
+                map(
+                    delimited(
+                        char('['),
+                        separated_list0(char(','), Self::parse),
+                        tuple((spaces_or_comments, char(']'))),
+                    ),
+                    Self::Array,
+                ),
+            )),
+            spaces_or_comments,
+        )(input)
+    }
+}
+
+// JSNumber    ::= "-"? (UnsignedInt ("." [0-9]*)? | "." [0-9]+)
+// UnsignedInt ::= "0" | [1-9] NO_SPACE [0-9]*
+
+fn parse_number(input: &str) -> IResult<&str, String> {
+    recognize(pair(
+        opt(char('-')),
+        alt((
+            recognize(pair(
+                alt((tag("0"), recognize(pair(one_of("123456789"), digit0)))),
+                opt(pair(char('.'), digit0)),
+            )),
+            recognize(pair(char('.'), digit1)),
+        )),
+    ))(input)
+    .map(|(input, number)| (input, number.to_string()))
+}
+
 // SubSelection ::= "{" NakedSubSelection "}"
 
 #[derive(Debug, PartialEq, Clone, Serialize, Default)]
@@ -1381,6 +1503,96 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_path_selection_methods() {
+        assert_eq!(
+            PathSelection::parse("items->first { id }"),
+            Ok((
+                "",
+                PathSelection::Key(
+                    Key::Field("items".to_string()),
+                    Box::new(PathSelection::Method(
+                        "first".to_string(),
+                        None,
+                        Box::new(PathSelection::Selection(SubSelection {
+                            selections: vec![NamedSelection::Field(None, "id".to_string(), None)],
+                            star: None,
+                        })),
+                    )),
+                ),
+            )),
+        );
+
+        assert_eq!(
+            PathSelection::parse("$ -> last . name"),
+            Ok((
+                "",
+                PathSelection::Var(
+                    "$".to_string(),
+                    Box::new(PathSelection::Method(
+                        "last".to_string(),
+                        None,
+                        Box::new(PathSelection::Key(
+                            Key::Field("name".to_string()),
+                            Box::new(PathSelection::Empty),
+                        )),
+                    )),
+                ),
+            )),
+        );
+
+        assert_eq!(
+            PathSelection::parse(
+                "kind->match({ dog: 'Dog', \"big cat\": [-1.5, .5, 0, true, null], cat: $.x })"
+            ),
+            Ok((
+                "",
+                PathSelection::Key(
+                    Key::Field("kind".to_string()),
+                    Box::new(PathSelection::Method(
+                        "match".to_string(),
+                        Some(MethodArgs(vec![JSLiteral::Object(vec![
+                            (
+                                Key::Field("dog".to_string()),
+                                JSLiteral::String("Dog".to_string()),
+                            ),
+                            (
+                                Key::Quoted("big cat".to_string()),
+                                JSLiteral::Array(vec![
+                                    JSLiteral::Number("-1.5".to_string()),
+                                    JSLiteral::Number(".5".to_string()),
+                                    JSLiteral::Number("0".to_string()),
+                                    JSLiteral::Bool(true),
+                                    JSLiteral::Null,
+                                ]),
+                            ),
+                            (
+                                Key::Field("cat".to_string()),
+                                JSLiteral::Path(PathSelection::Var(
+                                    "$".to_string(),
+                                    Box::new(PathSelection::Key(
+                                        Key::Field("x".to_string()),
+                                        Box::new(PathSelection::Empty),
+                                    )),
+                                )),
+                            ),
+                        ])])),
+                        Box::new(PathSelection::Empty),
+                    )),
+                ),
+            )),
+        );
+
+        // Methods must follow a variable or key, and must be known
+        assert!(PathSelection::parse("->first").is_err());
+        assert!(PathSelection::parse("items->unknown").is_err());
+        // Trailing commas are not allowed in method arguments
+        assert!(!matches!(
+            PathSelection::parse("items->match({ a: 1, })"),
+            Ok(("", _))
+        ));
+    }
+
     #[test]
     fn test_subselection() {
         assert_eq!(
@@ -5,7 +5,10 @@
 //! pretty printing trait which is then implemented on the various sub types
 //! of the JSONSelection tree.
 
+use crate::sources::connect::json_selection::JSLiteral;
 use crate::sources::connect::json_selection::JSONSelection;
+use crate::sources::connect::json_selection::Key;
+use crate::sources::connect::json_selection::MethodArgs;
 use crate::sources::connect::json_selection::NamedSelection;
 use crate::sources::connect::json_selection::PathSelection;
 use crate::sources::connect::json_selection::StarSelection;
@@ -101,6 +104,16 @@ impl PrettyPrintable for PathSelection {
                 result.push_str(key.dotted().as_str());
                 result.push_str(rest.as_str());
             }
+            PathSelection::Method(method, args, path) => {
+                result.push_str("->");
+                result.push_str(method.as_str());
+                if let Some(args) = args {
+                    let args = args.pretty_print_with_indentation(true, indentation);
+                    result.push_str(args.as_str());
+                }
+                let rest = path.pretty_print_with_indentation(true, indentation);
+                result.push_str(rest.as_str());
+            }
             PathSelection::Selection(sub) => {
                 let sub = sub.pretty_print_with_indentation(true, indentation);
                 result.push(' ');
@@ -113,6 +126,69 @@ impl PrettyPrintable for PathSelection {
     }
 }
 
+impl PrettyPrintable for MethodArgs {
+    fn pretty_print_with_indentation(&self, _inline: bool, indentation: usize) -> String {
+        let args = self
+            .0
+            .iter()
+            .map(|arg| arg.pretty_print_with_indentation(true, indentation))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!("({args})")
+    }
+}
+
+impl PrettyPrintable for JSLiteral {
+    fn pretty_print_with_indentation(&self, inline: bool, indentation: usize) -> String {
+        let mut result = String::new();
+
+        if !inline {
+            result.push_str(indent_chars(indentation).as_str());
+        }
+
+        match self {
+            JSLiteral::String(string) => {
+                let safely_quoted =
+                    serde_json_bytes::Value::String(string.clone().into()).to_string();
+                result.push_str(safely_quoted.as_str());
+            }
+            JSLiteral::Number(number) => result.push_str(number.as_str()),
+            JSLiteral::Bool(value) => result.push_str(if *value { "true" } else { "false" }),
+            JSLiteral::Null => result.push_str("null"),
+            JSLiteral::Object(properties) if properties.is_empty() => result.push_str("{}"),
+            JSLiteral::Object(properties) => {
+                let properties = properties
+                    .iter()
+                    .map(|(key, value)| {
+                        let value = value.pretty_print_with_indentation(true, indentation);
+                        match key {
+                            Key::Field(name) => format!("{name}: {value}"),
+                            // Dotted keys are quoted when needed, without the leading dot
+                            _ => format!("{}: {value}", &key.dotted()[1..]),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                result.push_str(format!("{{ {properties} }}").as_str());
+            }
+            JSLiteral::Array(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.pretty_print_with_indentation(true, indentation))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                result.push_str(format!("[{items}]").as_str());
+            }
+            JSLiteral::Path(path) => {
+                let path = path.pretty_print_with_indentation(true, indentation);
+                result.push_str(path.as_str());
+            }
+        }
+
+        result
+    }
+}
+
 impl PrettyPrintable for NamedSelection {
     fn pretty_print_with_indentation(&self, inline: bool, indentation: usize) -> String {
         let mut result = String::new();
@@ -287,6 +363,11 @@ mod tests {
             ".first",
             ".a.b.c.d.e",
             ".one.two.three {\n  a\n  b\n}",
+            // Method
+            "$->first",
+            ".items->last.name",
+            "$.items->size",
+            ".kind->match({ dog: \"Dog\", \"hot dog\": \"Dog\", cat: $.default })",
         ];
         for path in paths {
             let (unmatched, path_selection) = PathSelection::parse(path).unwrap();
@@ -58,6 +58,12 @@ pub struct VariableExpression {
     // parsed/generated URL path.
     batch_separator: Option<String>,
 
+    // When true, the variable was written with a trailing * and is expected
+    // to be a JSON array, whose values are expanded as repeated query
+    // parameters (?id=1&id=2) rather than joined into a single value. Only
+    // allowed as the whole value of a query parameter.
+    explode: bool,
+
     // Variables in the URL path are required by default, whereas variables in
     // the query parameter list are optional by default, but can be made
     // mandatory by adding a trailing ! to the variable path.
@@ -75,7 +81,14 @@ impl URLPathTemplate {
         if let Some(path_prefix) = path_prefix {
             for path_part in path_prefix.split('/') {
                 if !path_part.is_empty() {
-                    path.push(ParameterValue::parse(path_part, true)?);
+                    let param_value = ParameterValue::parse(path_part, true)?;
+                    if param_value.has_exploded_var() {
+                        return Err(format!(
+                            "Exploded variable expressions are only allowed in query parameters, found in {}",
+                            path_part
+                        ));
+                    }
+                    path.push(param_value);
                 }
             }
         }
@@ -85,7 +98,14 @@ impl URLPathTemplate {
         if let Some(query_suffix) = query_suffix {
             for query_part in query_suffix.split('&') {
                 if let Some((key, value)) = query_part.split_once('=') {
-                    query.insert(key.to_string(), ParameterValue::parse(value, false)?);
+                    let param_value = ParameterValue::parse(value, false)?;
+                    if param_value.has_exploded_var() && param_value.exploded_var().is_none() {
+                        return Err(format!(
+                            "Exploded variable expressions must be the whole value of query parameter {}",
+                            query_part
+                        ));
+                    }
+                    query.insert(key.to_string(), param_value);
                 }
             }
         }
@@ -117,7 +137,11 @@ impl URLPathTemplate {
 
             let mut params = vec![];
             for (key, param_value) in &self.query {
-                if let Some(value) = param_value.interpolate(var_map)? {
+                if let Some(var) = param_value.exploded_var() {
+                    for value in var.interpolate_exploded(var_map)? {
+                        params.push(format!("{}={}", key, value));
+                    }
+                } else if let Some(value) = param_value.interpolate(var_map)? {
                     params.push(format!("{}={}", key, value));
                 }
             }
@@ -163,7 +187,27 @@ impl URLPathTemplate {
         // For each query parameter, extract the corresponding variable(s) from
         // the concrete template text.
         for (key, query_value) in self.query.iter() {
-            if let Some(concrete_value) = concrete_template.query.get(key) {
+            if let Some(var) = query_value.exploded_var() {
+                // The concrete template only keeps the last value of repeated
+                // query parameters, so the values are collected from the path.
+                let values = path
+                    .split_once('?')
+                    .map(|(_, query)| query)
+                    .unwrap_or_default()
+                    .split('&')
+                    .filter_map(|query_part| query_part.split_once('='))
+                    .filter(|(concrete_key, _)| *concrete_key == key.as_str())
+                    .map(|(_, value)| JSON::String(ByteString::from(value)))
+                    .collect_vec();
+                if !values.is_empty() {
+                    var_map.insert(ByteString::from(var.var_path.as_str()), JSON::Array(values));
+                } else if var.required {
+                    return Err(format!(
+                        "Missing required query parameter {}={}",
+                        key, query_value
+                    ));
+                }
+            } else if let Some(concrete_value) = concrete_template.query.get(key) {
                 for (var_path, value) in query_value.extract_vars(concrete_value)? {
                     var_map.insert(var_path, value);
                 }
@@ -284,6 +328,21 @@ impl ParameterValue {
         Ok(ParameterValue { parts })
     }
 
+    fn has_exploded_var(&self) -> bool {
+        self.parts
+            .iter()
+            .any(|part| matches!(part, ValuePart::Var(var) if var.explode))
+    }
+
+    // The variable of a parameter value consisting of a single exploded
+    // variable expression, if any.
+    fn exploded_var(&self) -> Option<&VariableExpression> {
+        match self.parts.as_slice() {
+            [ValuePart::Var(var)] if var.explode => Some(var),
+            _ => None,
+        }
+    }
+
     fn interpolate(&self, vars: &Map<ByteString, JSON>) -> Result<Option<String>, String> {
         let mut value = String::new();
         let mut missing_vars = vec![];
@@ -471,17 +530,24 @@ impl VariableExpression {
         tuple((
             nom_parse_identifier_path,
             opt(char('!')),
+            opt(char('*')),
             opt(pair(one_of(",;|+ "), tag("..."))),
         ))(input)
         .map_err(|err| format!("Error parsing variable expression {}: {}", input, err))
         .and_then(
-            |(remaining, (var_path, exclamation_point, batch_separator))| {
-                if remaining.is_empty() {
+            |(remaining, (var_path, exclamation_point, asterisk, batch_separator))| {
+                if asterisk.is_some() && batch_separator.is_some() {
+                    Err(format!(
+                        "Variable expression {} cannot be both exploded and batched",
+                        input
+                    ))
+                } else if remaining.is_empty() {
                     Ok(VariableExpression {
                         var_path,
                         required: exclamation_point.is_some() || required,
                         batch_separator: batch_separator
                             .map(|(separator, _)| separator.to_string()),
+                        explode: asterisk.is_some(),
                     })
                 } else {
                     Err(format!(
@@ -522,6 +588,28 @@ impl VariableExpression {
         }
     }
 
+    // Interpolates an exploded variable as one string per array value, with
+    // non-array values handled as a single value.
+    fn interpolate_exploded(&self, vars: &Map<ByteString, JSON>) -> Result<Vec<String>, String> {
+        let var_path_bytes = ByteString::from(self.var_path.as_str());
+        let values = match vars.get(&var_path_bytes) {
+            Some(JSON::Array(array)) => array
+                .iter()
+                .map(|value| self.value_as_string(value))
+                .collect_vec(),
+            Some(value) => vec![self.value_as_string(value)],
+            None => vec![],
+        };
+        if values.is_empty() && self.required {
+            return Err(format!(
+                "Missing required variable {} in {}",
+                self.var_path,
+                JSON::Object(vars.clone()),
+            ));
+        }
+        Ok(values)
+    }
+
     fn value_as_string(&self, value: &JSON) -> String {
         // Need to remove quotes from string values, since the quotes don't
         // belong in the URL.
@@ -539,6 +627,9 @@ impl Display for VariableExpression {
         if self.required {
             f.write_str("!")?;
         }
+        if self.explode {
+            f.write_str("*")?;
+        }
         if let Some(separator) = &self.batch_separator {
             f.write_str(separator)?;
             f.write_str("...")?;
@@ -1170,6 +1261,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_exploded_generation() {
+        let template = URLPathTemplate::parse("/users?id={id*}&names={name,...}").unwrap();
+
+        assert_eq!(
+            template.generate_path(&json!({
+                "id": [1, 2, 3],
+                "name": ["a", "b"],
+            })),
+            Ok("/users?id=1&id=2&id=3&names=a,b".to_string()),
+        );
+
+        assert_eq!(
+            template.generate_path(&json!({
+                "id": 123,
+            })),
+            Ok("/users?id=123".to_string()),
+        );
+
+        assert_eq!(
+            template.generate_path(&json!({
+                "id": [],
+            })),
+            Ok("/users".to_string()),
+        );
+
+        let template = URLPathTemplate::parse("/users?id={id!*}").unwrap();
+
+        assert!(template
+            .generate_path(&json!({
+                "id": [],
+            }))
+            .is_err());
+
+        assert_eq!(
+            template.extract_vars("/users?id=1&id=2"),
+            Ok(json!({
+                "id": ["1", "2"],
+            })),
+        );
+
+        assert_eq!(
+            URLPathTemplate::parse("/users/{id*}"),
+            Err("Exploded variable expressions are only allowed in query parameters, found in {id*}".to_string()),
+        );
+
+        assert_eq!(
+            URLPathTemplate::parse("/users?id=id:{id*}"),
+            Err("Exploded variable expressions must be the whole value of query parameter id=id:{id*}".to_string()),
+        );
+
+        assert_eq!(
+            URLPathTemplate::parse("/users?id={id*,...}"),
+            Err("Variable expression id*,... cannot be both exploded and batched".to_string()),
+        );
+    }
+
     #[test]
     fn test_extract_vars_from_url_path() {
         let repo_template = URLPathTemplate::parse("/repository/{user.login}/{repo.name}").unwrap();
@@ -1726,6 +1874,14 @@ mod tests {
             "/users?ids={id!,...}&names={user.name|...}".to_string(),
         );
 
+        assert_eq!(
+            format!(
+                "{}",
+                URLPathTemplate::parse("/users?id={id*}&name={name!*}").unwrap()
+            ),
+            "/users?id={id*}&name={name!*}".to_string(),
+        );
+
         assert_eq!(
             format!("{}", URLPathTemplate::parse("/position/{x},{y}").unwrap(),),
             "/position/{x!},{y!}".to_string(),
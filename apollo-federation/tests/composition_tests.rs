@@ -198,3 +198,87 @@ fn compose_removes_federation_directives() {
             .schema()
     ));
 }
+
+#[test]
+fn can_recompose_changed_subgraph() {
+    let s1 = Subgraph::parse_and_expand(
+        "Subgraph1",
+        "https://subgraph1",
+        r#"
+            type Query {
+              t: T
+            }
+
+            type T @key(fields: "k") {
+              k: ID
+            }
+        "#,
+    )
+    .unwrap();
+    let s2 = Subgraph::parse_and_expand(
+        "Subgraph2",
+        "https://subgraph2",
+        r#"
+            type T @key(fields: "k") {
+              k: ID
+              a: Int
+            }
+
+            enum E {
+              V1
+              V2
+            }
+        "#,
+    )
+    .unwrap();
+    let s3 = Subgraph::parse_and_expand(
+        "Subgraph3",
+        "https://subgraph3",
+        r#"
+            type Query {
+              s: S
+            }
+
+            type S {
+              x: Int
+            }
+        "#,
+    )
+    .unwrap();
+    let supergraph = Supergraph::compose(vec![&s1, &s2, &s3]).unwrap();
+
+    let changed = Subgraph::parse_and_expand(
+        "Subgraph2",
+        "https://subgraph2",
+        r#"
+            type Query {
+              r: R
+            }
+
+            type T @key(fields: "k") {
+              k: ID
+              a: Int
+              b: String
+            }
+
+            type R {
+              y: Int
+            }
+        "#,
+    )
+    .unwrap();
+
+    let recomposed = supergraph
+        .recompose(vec![&s1, &changed, &s3], "Subgraph2")
+        .unwrap();
+    let composed = Supergraph::compose(vec![&s1, &changed, &s3]).unwrap();
+    assert_eq!(
+        print_sdl(recomposed.schema.schema()),
+        print_sdl(composed.schema.schema())
+    );
+
+    // every subgraph of the supergraph is needed to merge the affected types
+    assert!(supergraph
+        .recompose(vec![&s1, &changed], "Subgraph2")
+        .is_err());
+}
@@ -8,6 +8,7 @@ use std::sync::Arc;
 use std::time::Instant;
 
 use axum::error_handling::HandleErrorLayer;
+use axum::extract::ws::WebSocketUpgrade;
 use axum::extract::Extension;
 use axum::extract::State;
 use axum::http::StatusCode;
@@ -62,6 +63,7 @@ use crate::plugins::telemetry::SpanMode;
 use crate::router::ApolloRouterError;
 use crate::router_factory::Endpoint;
 use crate::router_factory::RouterFactory;
+use crate::service_manager;
 use crate::services::http::service::BodyStream;
 use crate::services::router;
 use crate::uplink::license_enforcement::LicenseState;
@@ -128,6 +130,17 @@ pub(crate) fn make_axum_router<RF>(
 where
     RF: RouterFactory,
 {
+    let grpc = &configuration.supergraph.experimental_grpc;
+    if grpc.enabled {
+        for endpoint in super::grpc::endpoints(
+            service_factory.clone(),
+            grpc,
+            configuration.limits.http_max_request_bytes,
+        )? {
+            endpoints.insert(grpc.listen.clone(), endpoint);
+        }
+    }
+
     ensure_listenaddrs_consistency(configuration, &endpoints)?;
 
     if configuration.health_check.enabled {
@@ -404,10 +417,12 @@ impl HttpServerFactory for AxumHttpServerFactory {
 
     fn live(&self, live: bool) {
         self.live.store(live, Ordering::SeqCst);
+        service_manager::live(live);
     }
 
     fn ready(&self, ready: bool) {
         self.ready.store(ready, Ordering::SeqCst);
+        service_manager::ready(ready);
     }
 }
 
@@ -555,16 +570,35 @@ where
 {
     let early_cancel = configuration.supergraph.early_cancel;
     let experimental_log_on_broken_pipe = configuration.supergraph.experimental_log_on_broken_pipe;
+    let websocket = Arc::new(configuration.supergraph.experimental_websocket.clone());
+    let cors = Arc::new(configuration.cors.clone());
+    let max_message_bytes = configuration.limits.http_max_request_bytes;
     let mut router = Router::new().route(
         &configuration.supergraph.sanitized_path(),
         get({
-            move |Extension(service): Extension<RF>, request: Request<DecompressionBody<Body>>| {
-                handle_graphql(
-                    service.create().boxed(),
-                    early_cancel,
-                    experimental_log_on_broken_pipe,
-                    request,
-                )
+            let websocket = websocket.clone();
+            let cors = cors.clone();
+            move |Extension(service): Extension<RF>,
+                  upgrade: Option<WebSocketUpgrade>,
+                  request: Request<DecompressionBody<Body>>| async move {
+                match upgrade {
+                    Some(upgrade) if websocket.enabled => super::websocket::upgrade(
+                        upgrade,
+                        service,
+                        request.into_parts().0,
+                        &websocket,
+                        &cors,
+                        max_message_bytes,
+                    ),
+                    _ => handle_graphql(
+                        service.create().boxed(),
+                        early_cancel,
+                        experimental_log_on_broken_pipe,
+                        request,
+                    )
+                    .await
+                    .into_response(),
+                }
             }
         })
         .post({
@@ -584,13 +618,26 @@ where
             "/",
             get({
                 move |Extension(service): Extension<RF>,
-                      request: Request<DecompressionBody<Body>>| {
-                    handle_graphql(
-                        service.create().boxed(),
-                        early_cancel,
-                        experimental_log_on_broken_pipe,
-                        request,
-                    )
+                      upgrade: Option<WebSocketUpgrade>,
+                      request: Request<DecompressionBody<Body>>| async move {
+                    match upgrade {
+                        Some(upgrade) if websocket.enabled => super::websocket::upgrade(
+                            upgrade,
+                            service,
+                            request.into_parts().0,
+                            &websocket,
+                            &cors,
+                            max_message_bytes,
+                        ),
+                        _ => handle_graphql(
+                            service.create().boxed(),
+                            early_cancel,
+                            experimental_log_on_broken_pipe,
+                            request,
+                        )
+                        .await
+                        .into_response(),
+                    }
                 }
             })
             .post({
@@ -607,7 +654,7 @@ where
         );
     }
 
-    router
+    super::rest::add_routes::<RF>(router, configuration)
 }
 
 async fn handle_graphql(
@@ -616,12 +663,28 @@ async fn handle_graphql(
     experimental_log_on_broken_pipe: bool,
     http_request: Request<DecompressionBody<Body>>,
 ) -> impl IntoResponse {
-    let _guard = SessionCountGuard::start();
-
     let (parts, body) = http_request.into_parts();
 
     let http_request = http::Request::from_parts(parts, Body::wrap_stream(BodyStream::new(body)));
 
+    handle_router_request(
+        service,
+        early_cancel,
+        experimental_log_on_broken_pipe,
+        http_request,
+    )
+    .await
+}
+
+/// Runs a request through the router service, handling client cancellation
+pub(super) async fn handle_router_request(
+    service: router::BoxService,
+    early_cancel: bool,
+    experimental_log_on_broken_pipe: bool,
+    http_request: Request<Body>,
+) -> Response {
+    let _guard = SessionCountGuard::start();
+
     let request: router::Request = http_request.into();
     let context = request.context.clone();
     let accept_encoding = request
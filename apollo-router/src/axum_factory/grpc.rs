@@ -0,0 +1,580 @@
+//! gRPC facade: unary methods executing a configured GraphQL operation.
+//!
+//! The fields of the request message are bound to the variables of the operation, then the
+//! request goes through the router pipeline like any other GraphQL request. Requests are served
+//! over HTTP/2 without TLS, on their own listener.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bytes::Buf;
+use bytes::BufMut;
+use bytes::Bytes;
+use bytes::BytesMut;
+use http::header::ACCEPT;
+use http::header::CONTENT_LENGTH;
+use http::header::CONTENT_TYPE;
+use http::HeaderMap;
+use http::HeaderValue;
+use http::Method;
+use http::StatusCode;
+use http_body::Limited;
+use hyper::Body;
+use mime::APPLICATION_JSON;
+use prost::encoding;
+use prost::encoding::DecodeContext;
+use prost::Message;
+use prost_types::value::Kind;
+use prost_types::ListValue;
+use prost_types::Struct;
+use serde_json_bytes::ByteString;
+use serde_json_bytes::Map;
+use serde_json_bytes::Value;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use crate::configuration::grpc::Field;
+use crate::configuration::grpc::FieldType;
+use crate::configuration::grpc::GrpcFacade;
+use crate::configuration::grpc::GrpcMethod;
+use crate::graphql;
+use crate::router_factory::Endpoint;
+use crate::router_factory::RouterFactory;
+use crate::services::router;
+use crate::ApolloRouterError;
+
+/// Path of the protobuf definition of the service
+const PROTO_PATH: &str = "/operations.proto";
+
+// https://github.com/grpc/grpc/blob/master/doc/statuscodes.md
+const OK: u16 = 0;
+const UNKNOWN: u16 = 2;
+const INVALID_ARGUMENT: u16 = 3;
+const DEADLINE_EXCEEDED: u16 = 4;
+const PERMISSION_DENIED: u16 = 7;
+const RESOURCE_EXHAUSTED: u16 = 8;
+const UNIMPLEMENTED: u16 = 12;
+const INTERNAL: u16 = 13;
+const UNAVAILABLE: u16 = 14;
+const UNAUTHENTICATED: u16 = 16;
+
+/// Response message of every method
+#[derive(Clone, PartialEq, Message)]
+struct OperationResponse {
+    #[prost(message, optional, tag = "1")]
+    data: Option<Struct>,
+    #[prost(message, optional, tag = "2")]
+    errors: Option<ListValue>,
+}
+
+struct Operation {
+    query: String,
+    operation_name: Option<String>,
+    fields: Vec<Field>,
+}
+
+/// Endpoints of the gRPC service, one per method, and one serving the protobuf definition
+pub(super) fn endpoints<RF>(
+    service_factory: RF,
+    facade: &GrpcFacade,
+    max_body_bytes: usize,
+) -> Result<Vec<Endpoint>, ApolloRouterError>
+where
+    RF: RouterFactory,
+{
+    let service_creation_error = |error: String| {
+        ApolloRouterError::ServiceCreationError(format!("gRPC configuration error: {error}").into())
+    };
+
+    let mut endpoints = Vec::with_capacity(facade.methods.len() + 1);
+    for method in &facade.methods {
+        let operation = Arc::new(operation(method).map_err(service_creation_error)?);
+        let service_factory = service_factory.clone();
+        endpoints.push(Endpoint::from_router_service(
+            facade.path(method),
+            tower::service_fn(move |request: router::Request| {
+                call(
+                    service_factory.clone(),
+                    operation.clone(),
+                    request,
+                    max_body_bytes,
+                )
+            })
+            .boxed(),
+        ));
+    }
+
+    let proto = Bytes::from(facade.proto().map_err(service_creation_error)?);
+    endpoints.push(Endpoint::from_router_service(
+        PROTO_PATH.to_string(),
+        tower::service_fn(move |request: router::Request| {
+            let proto = proto.clone();
+            async move {
+                Ok(router::Response {
+                    response: http::Response::builder()
+                        .header(CONTENT_TYPE, "text/plain; charset=utf-8")
+                        .body(Body::from(proto))?,
+                    context: request.context,
+                })
+            }
+        })
+        .boxed(),
+    ));
+
+    tracing::info!(
+        "gRPC service {}.{} exposed at {}, its definition is at {PROTO_PATH}",
+        facade.package,
+        facade.service,
+        facade.listen
+    );
+    Ok(endpoints)
+}
+
+fn operation(method: &GrpcMethod) -> Result<Operation, String> {
+    Ok(Operation {
+        query: method.query.clone(),
+        operation_name: method.operation_name.clone(),
+        fields: method
+            .fields()
+            .map_err(|error| format!("method '{}': {error}", method.name))?,
+    })
+}
+
+async fn call<RF>(
+    service_factory: RF,
+    operation: Arc<Operation>,
+    request: router::Request,
+    max_body_bytes: usize,
+) -> Result<router::Response, BoxError>
+where
+    RF: RouterFactory,
+{
+    let context = request.context;
+    let (mut parts, body) = request.router_request.into_parts();
+
+    let is_grpc = parts
+        .headers
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/grpc"));
+    if parts.method != Method::POST || !is_grpc {
+        return Ok(router::Response {
+            response: http::Response::builder()
+                .status(StatusCode::UNSUPPORTED_MEDIA_TYPE)
+                .body(Body::from(
+                    "gRPC requests must be POST requests of application/grpc",
+                ))?,
+            context,
+        });
+    }
+
+    let body = match hyper::body::to_bytes(Limited::new(body, max_body_bytes)).await {
+        Ok(body) => body,
+        Err(error) => {
+            return status_response(
+                context,
+                RESOURCE_EXHAUSTED,
+                &format!("cannot read the request: {error}"),
+            )
+        }
+    };
+    let variables =
+        match decode_frame(body).and_then(|message| decode_variables(&operation.fields, message)) {
+            Ok(variables) => variables,
+            Err((code, message)) => return status_response(context, code, &message),
+        };
+
+    let graphql_request = graphql::Request::builder()
+        .query(operation.query.clone())
+        .and_operation_name(operation.operation_name.clone())
+        .variables(variables)
+        .build();
+    // metadata is passed along as headers, so authentication and header propagation apply
+    parts.headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(APPLICATION_JSON.essence_str()),
+    );
+    parts.headers.insert(
+        ACCEPT,
+        HeaderValue::from_static(APPLICATION_JSON.essence_str()),
+    );
+    parts.headers.remove(CONTENT_LENGTH);
+    let router_request = router::Request {
+        router_request: http::Request::from_parts(
+            parts,
+            Body::from(serde_json::to_vec(&graphql_request)?),
+        ),
+        context,
+    };
+
+    let response = service_factory
+        .create()
+        .boxed()
+        .oneshot(router_request)
+        .await?;
+    let context = response.context;
+    let (parts, body) = response.response.into_parts();
+    let body = hyper::body::to_bytes(body).await?;
+    let graphql_response: Value = match serde_json::from_slice(&body) {
+        Ok(value) => value,
+        Err(error) => {
+            return status_response(
+                context,
+                INTERNAL,
+                &format!("the router response is not JSON: {error}"),
+            )
+        }
+    };
+
+    let errors = match graphql_response.get("errors") {
+        Some(Value::Array(errors)) if !errors.is_empty() => errors.as_slice(),
+        _ => &[],
+    };
+    if !parts.status.is_success() {
+        let message = errors
+            .first()
+            .and_then(|error| error.get("message"))
+            .and_then(|message| message.as_str())
+            .unwrap_or_else(|| parts.status.canonical_reason().unwrap_or_default());
+        return status_response(context, status_code(parts.status), message);
+    }
+
+    let message = OperationResponse {
+        data: match graphql_response.get("data") {
+            Some(Value::Object(data)) => Some(to_struct(data)),
+            _ => None,
+        },
+        errors: (!errors.is_empty()).then(|| ListValue {
+            values: errors.iter().map(to_proto).collect(),
+        }),
+    };
+    let mut frame = BytesMut::with_capacity(5 + message.encoded_len());
+    frame.put_u8(0);
+    frame.put_u32(message.encoded_len() as u32);
+    message.encode(&mut frame)?;
+
+    Ok(router::Response {
+        response: grpc_response(Some(frame.freeze()), OK, "")?,
+        context,
+    })
+}
+
+fn status_response(
+    context: crate::Context,
+    code: u16,
+    message: &str,
+) -> Result<router::Response, BoxError> {
+    Ok(router::Response {
+        response: grpc_response(None, code, message)?,
+        context,
+    })
+}
+
+/// A response with an optional message, and the status in the trailers
+fn grpc_response(
+    message: Option<Bytes>,
+    code: u16,
+    status_message: &str,
+) -> Result<http::Response<Body>, BoxError> {
+    let (mut sender, body) = Body::channel();
+    if let Some(message) = message {
+        // the channel has room for one chunk, so this cannot fail
+        let _ = sender.try_send_data(message);
+    }
+    let mut trailers = HeaderMap::new();
+    trailers.insert("grpc-status", HeaderValue::from(code));
+    if !status_message.is_empty() {
+        trailers.insert(
+            "grpc-message",
+            HeaderValue::from_str(&urlencoding::encode(status_message))?,
+        );
+    }
+    let _ = sender.send_trailers(trailers);
+
+    Ok(http::Response::builder()
+        .header(CONTENT_TYPE, "application/grpc")
+        .body(body)?)
+}
+
+fn status_code(status: StatusCode) -> u16 {
+    match status {
+        StatusCode::BAD_REQUEST => INVALID_ARGUMENT,
+        StatusCode::UNAUTHORIZED => UNAUTHENTICATED,
+        StatusCode::FORBIDDEN => PERMISSION_DENIED,
+        StatusCode::PAYLOAD_TOO_LARGE | StatusCode::TOO_MANY_REQUESTS => RESOURCE_EXHAUSTED,
+        StatusCode::SERVICE_UNAVAILABLE => UNAVAILABLE,
+        StatusCode::GATEWAY_TIMEOUT => DEADLINE_EXCEEDED,
+        status if status.is_server_error() => INTERNAL,
+        _ => UNKNOWN,
+    }
+}
+
+/// Extracts the message from a length-prefixed gRPC frame
+fn decode_frame(mut body: Bytes) -> Result<Bytes, (u16, String)> {
+    if body.len() < 5 {
+        return Err((
+            INVALID_ARGUMENT,
+            "the request is not a gRPC message".to_string(),
+        ));
+    }
+    if body.get_u8() != 0 {
+        return Err((
+            UNIMPLEMENTED,
+            "compressed messages are not supported".to_string(),
+        ));
+    }
+    let length = body.get_u32() as usize;
+    if body.len() != length {
+        return Err((
+            INVALID_ARGUMENT,
+            "unary methods expect exactly one message".to_string(),
+        ));
+    }
+    Ok(body)
+}
+
+/// Decoded values of a field, singular fields hold one value
+enum Values {
+    Strings(Vec<String>),
+    Ints(Vec<i32>),
+    Doubles(Vec<f64>),
+    Bools(Vec<bool>),
+    Values(Vec<prost_types::Value>),
+}
+
+fn decode_variables(
+    fields: &[Field],
+    mut message: Bytes,
+) -> Result<Map<ByteString, Value>, (u16, String)> {
+    let invalid = |error: prost::DecodeError| (INVALID_ARGUMENT, error.to_string());
+
+    let mut decoded: HashMap<u32, Values> = HashMap::new();
+    while message.has_remaining() {
+        let (tag, wire_type) = encoding::decode_key(&mut message).map_err(invalid)?;
+        let ctx = DecodeContext::default();
+        let Some(field) = fields.iter().find(|field| field.tag == tag) else {
+            encoding::skip_field(wire_type, tag, &mut message, ctx).map_err(invalid)?;
+            continue;
+        };
+        let values = decoded.entry(tag).or_insert_with(|| match field.ty {
+            FieldType::String => Values::Strings(Vec::new()),
+            FieldType::Int32 => Values::Ints(Vec::new()),
+            FieldType::Double => Values::Doubles(Vec::new()),
+            FieldType::Bool => Values::Bools(Vec::new()),
+            FieldType::Value => Values::Values(Vec::new()),
+        });
+        // for singular fields, the last value wins
+        if !field.repeated {
+            values.clear();
+        }
+        let result = match values {
+            Values::Strings(values) if field.repeated => {
+                encoding::string::merge_repeated(wire_type, values, &mut message, ctx)
+            }
+            Values::Strings(values) => {
+                let mut value = String::new();
+                let result = encoding::string::merge(wire_type, &mut value, &mut message, ctx);
+                values.push(value);
+                result
+            }
+            Values::Ints(values) if field.repeated => {
+                encoding::int32::merge_repeated(wire_type, values, &mut message, ctx)
+            }
+            Values::Ints(values) => {
+                let mut value = 0;
+                let result = encoding::int32::merge(wire_type, &mut value, &mut message, ctx);
+                values.push(value);
+                result
+            }
+            Values::Doubles(values) if field.repeated => {
+                encoding::double::merge_repeated(wire_type, values, &mut message, ctx)
+            }
+            Values::Doubles(values) => {
+                let mut value = 0.0;
+                let result = encoding::double::merge(wire_type, &mut value, &mut message, ctx);
+                values.push(value);
+                result
+            }
+            Values::Bools(values) if field.repeated => {
+                encoding::bool::merge_repeated(wire_type, values, &mut message, ctx)
+            }
+            Values::Bools(values) => {
+                let mut value = false;
+                let result = encoding::bool::merge(wire_type, &mut value, &mut message, ctx);
+                values.push(value);
+                result
+            }
+            Values::Values(values) if field.repeated => {
+                encoding::message::merge_repeated(wire_type, values, &mut message, ctx)
+            }
+            Values::Values(values) => {
+                let mut value = prost_types::Value::default();
+                let result = encoding::message::merge(wire_type, &mut value, &mut message, ctx);
+                values.push(value);
+                result
+            }
+        };
+        result.map_err(invalid)?;
+    }
+
+    let mut variables = Map::new();
+    for field in fields {
+        let values: Vec<Value> = match decoded.remove(&field.tag) {
+            Some(Values::Strings(values)) => values.into_iter().map(Value::from).collect(),
+            Some(Values::Ints(values)) => values.into_iter().map(Value::from).collect(),
+            Some(Values::Doubles(values)) => values.into_iter().map(Value::from).collect(),
+            Some(Values::Bools(values)) => values.into_iter().map(Value::from).collect(),
+            Some(Values::Values(values)) => values.iter().map(from_proto).collect(),
+            // proto3 does not send default values, nor empty repeated fields
+            None if field.repeated => Vec::new(),
+            None if field.optional || field.ty == FieldType::Value => continue,
+            None => vec![match field.ty {
+                FieldType::String => Value::from(""),
+                FieldType::Int32 => Value::from(0),
+                FieldType::Double => Value::from(0.0),
+                FieldType::Bool => Value::from(false),
+                FieldType::Value => Value::Null,
+            }],
+        };
+        let value = if field.repeated {
+            Value::Array(values)
+        } else {
+            values.into_iter().next().unwrap_or(Value::Null)
+        };
+        variables.insert(ByteString::from(field.name.as_str()), value);
+    }
+    Ok(variables)
+}
+
+impl Values {
+    fn clear(&mut self) {
+        match self {
+            Values::Strings(values) => values.clear(),
+            Values::Ints(values) => values.clear(),
+            Values::Doubles(values) => values.clear(),
+            Values::Bools(values) => values.clear(),
+            Values::Values(values) => values.clear(),
+        }
+    }
+}
+
+fn to_struct(object: &Map<ByteString, Value>) -> Struct {
+    Struct {
+        fields: object
+            .iter()
+            .map(|(key, value)| (key.as_str().to_string(), to_proto(value)))
+            .collect(),
+    }
+}
+
+fn to_proto(value: &Value) -> prost_types::Value {
+    let kind = match value {
+        Value::Null => Kind::NullValue(0),
+        Value::Bool(value) => Kind::BoolValue(*value),
+        Value::Number(value) => Kind::NumberValue(value.as_f64().unwrap_or_default()),
+        Value::String(value) => Kind::StringValue(value.as_str().to_string()),
+        Value::Array(values) => Kind::ListValue(ListValue {
+            values: values.iter().map(to_proto).collect(),
+        }),
+        Value::Object(object) => Kind::StructValue(to_struct(object)),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+fn from_proto(value: &prost_types::Value) -> Value {
+    match &value.kind {
+        None | Some(Kind::NullValue(_)) => Value::Null,
+        Some(Kind::BoolValue(value)) => Value::Bool(*value),
+        // protobuf numbers are doubles, integral values are sent as integers so they coerce to Int
+        Some(Kind::NumberValue(value)) if value.fract() == 0.0 && value.abs() < i64::MAX as f64 => {
+            Value::from(*value as i64)
+        }
+        Some(Kind::NumberValue(value)) => Value::from(*value),
+        Some(Kind::StringValue(value)) => Value::from(value.as_str()),
+        Some(Kind::ListValue(list)) => Value::Array(list.values.iter().map(from_proto).collect()),
+        Some(Kind::StructValue(object)) => Value::Object(
+            object
+                .fields
+                .iter()
+                .map(|(key, value)| (ByteString::from(key.as_str()), from_proto(value)))
+                .collect(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    fn fields() -> Vec<Field> {
+        GrpcMethod {
+            name: "GetUser".to_string(),
+            query: "query User($id: ID!, $fields: [String!], $limit: Int, $filter: UserFilter, $active: Boolean!) { user(id: $id) { name } }".to_string(),
+            operation_name: None,
+        }
+        .fields()
+        .unwrap()
+    }
+
+    #[test]
+    fn decodes_variables() {
+        let mut message = BytesMut::new();
+        encoding::string::encode(1, &"42".to_string(), &mut message);
+        encoding::string::encode_repeated(
+            2,
+            &["name".to_string(), "email".to_string()],
+            &mut message,
+        );
+        encoding::message::encode(
+            4,
+            &to_proto(&json!({ "role": "ADMIN", "age": 30 })),
+            &mut message,
+        );
+        // unknown fields are skipped
+        encoding::int32::encode(9, &7, &mut message);
+
+        let mut frame = BytesMut::new();
+        frame.put_u8(0);
+        frame.put_u32(message.len() as u32);
+        frame.put(message);
+
+        let variables = decode_variables(&fields(), decode_frame(frame.freeze()).unwrap()).unwrap();
+        assert_eq!(
+            Value::Object(variables),
+            json!({
+                "id": "42",
+                "fields": ["name", "email"],
+                "filter": { "role": "ADMIN", "age": 30 },
+                "active": false,
+            })
+        );
+    }
+
+    #[test]
+    fn invalid_frames() {
+        assert_eq!(
+            decode_frame(Bytes::from_static(&[1, 0, 0, 0, 0]))
+                .unwrap_err()
+                .0,
+            UNIMPLEMENTED
+        );
+        assert_eq!(
+            decode_frame(Bytes::from_static(&[0, 0, 0, 0, 2, 8]))
+                .unwrap_err()
+                .0,
+            INVALID_ARGUMENT
+        );
+        assert_eq!(
+            decode_frame(Bytes::from_static(&[0, 0, 0])).unwrap_err().0,
+            INVALID_ARGUMENT
+        );
+        // a string sent for the int32 field
+        let message = Bytes::from_static(&[0x1a, 0x01, b'a']);
+        assert_eq!(
+            decode_variables(&fields(), message).unwrap_err().0,
+            INVALID_ARGUMENT
+        );
+    }
+}
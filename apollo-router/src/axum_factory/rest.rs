@@ -0,0 +1,318 @@
+//! REST facade: routes of the supergraph listener executing a configured GraphQL operation.
+//!
+//! Path parameters, query parameters and the fields of a JSON object body are bound to the
+//! variables of the operation, then the request goes through the router pipeline like any other
+//! GraphQL request.
+
+use std::collections::HashMap;
+
+use axum::extract::Extension;
+use axum::extract::Path;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use axum::routing::on;
+use axum::routing::MethodFilter;
+use axum::Json;
+use axum::Router;
+use http::header::ACCEPT;
+use http::header::CONTENT_LENGTH;
+use http::HeaderValue;
+use http::Method;
+use http::Request;
+use http::StatusCode;
+use http_body::Limited;
+use hyper::Body;
+use mime::APPLICATION_JSON;
+use serde_json::json;
+use serde_json::Map;
+use serde_json::Value;
+use serde_json_bytes::ByteString;
+use tower::ServiceExt;
+use tower_http::decompression::DecompressionBody;
+
+use super::axum_http_server_factory::handle_router_request;
+use crate::configuration::rest::ParameterType;
+use crate::configuration::rest::RestMethod;
+use crate::configuration::rest::RestRoute;
+use crate::configuration::Configuration;
+use crate::graphql;
+use crate::router_factory::RouterFactory;
+
+/// Adds the routes of the REST facade to the supergraph router
+pub(super) fn add_routes<RF>(
+    mut router: Router<(), DecompressionBody<Body>>,
+    configuration: &Configuration,
+) -> Router<(), DecompressionBody<Body>>
+where
+    RF: RouterFactory,
+{
+    let early_cancel = configuration.supergraph.early_cancel;
+    let experimental_log_on_broken_pipe = configuration.supergraph.experimental_log_on_broken_pipe;
+    let max_body_bytes = configuration.limits.http_max_request_bytes;
+
+    for route in &configuration.supergraph.experimental_rest.routes {
+        let filter = match route.method {
+            RestMethod::Get => MethodFilter::GET,
+            RestMethod::Post => MethodFilter::POST,
+            RestMethod::Put => MethodFilter::PUT,
+            RestMethod::Patch => MethodFilter::PATCH,
+            RestMethod::Delete => MethodFilter::DELETE,
+        };
+        let route = route.clone();
+        router = router.route(
+            &route.axum_path(),
+            on(
+                filter,
+                move |Extension(service): Extension<RF>,
+                      parameters: Option<Path<HashMap<String, String>>>,
+                      request: Request<DecompressionBody<Body>>| async move {
+                    let parameters = parameters
+                        .map(|Path(parameters)| parameters)
+                        .unwrap_or_default();
+                    let request =
+                        match graphql_request(&route, parameters, request, max_body_bytes).await {
+                            Ok(request) => request,
+                            Err(error) => return bad_request(error),
+                        };
+                    handle_router_request(
+                        service.create().boxed(),
+                        early_cancel,
+                        experimental_log_on_broken_pipe,
+                        request,
+                    )
+                    .await
+                },
+            ),
+        );
+    }
+
+    router
+}
+
+/// Translates a REST request to a GraphQL request for the router pipeline
+async fn graphql_request(
+    route: &RestRoute,
+    path_parameters: HashMap<String, String>,
+    request: Request<DecompressionBody<Body>>,
+    max_body_bytes: usize,
+) -> Result<Request<Body>, String> {
+    let (mut parts, body) = request.into_parts();
+
+    let mut variables = Map::new();
+    if parts.method != Method::GET {
+        let body = hyper::body::to_bytes(Limited::new(body, max_body_bytes))
+            .await
+            .map_err(|e| format!("cannot read the request body: {e}"))?;
+        if !body.is_empty() {
+            match serde_json::from_slice(&body) {
+                Ok(Value::Object(fields)) => variables = fields,
+                _ => return Err("the request body must be a JSON object".to_string()),
+            }
+        }
+    }
+    let query_parameters: Vec<(String, String)> =
+        serde_urlencoded::from_str(parts.uri.query().unwrap_or_default())
+            .map_err(|e| format!("invalid query parameters: {e}"))?;
+    // path parameters take precedence over query parameters, which take precedence over the body
+    for (name, value) in query_parameters.into_iter().chain(path_parameters) {
+        let value = parameter_value(&name, value, route.parameter_types.get(&name))?;
+        variables.insert(name, value);
+    }
+
+    let mut request = graphql::Request::builder()
+        .and_query(route.query.clone())
+        .and_operation_name(route.operation_name.clone())
+        .variables(
+            variables
+                .into_iter()
+                .map(|(name, value)| (ByteString::from(name), value.into()))
+                .collect(),
+        )
+        .build();
+    if let Some(id) = &route.persisted_query_id {
+        request.extensions.insert(
+            ByteString::from("persistedQuery"),
+            json!({ "version": 1, "sha256Hash": id }).into(),
+        );
+    }
+
+    parts.headers.insert(
+        ACCEPT,
+        HeaderValue::from_static(APPLICATION_JSON.essence_str()),
+    );
+    parts.headers.remove(CONTENT_LENGTH);
+
+    // mutations are rejected on GET routes, as for any GraphQL request sent with GET, so they
+    // cannot be abused by cross-site requests. Other routes keep the content type sent by the
+    // client, so that the CSRF plugin checks the request as the client sent it
+    let body = if parts.method == Method::GET {
+        parts
+            .headers
+            .insert("apollo-require-preflight", HeaderValue::from_static("true"));
+        let query = urlencoded_request(&request)?;
+        parts.uri = format!("{}?{query}", parts.uri.path())
+            .parse()
+            .map_err(|e| format!("invalid request URI: {e}"))?;
+        Body::empty()
+    } else {
+        parts.method = Method::POST;
+        Body::from(serde_json::to_vec(&request).map_err(|e| e.to_string())?)
+    };
+    Ok(Request::from_parts(parts, body))
+}
+
+fn parameter_value(
+    name: &str,
+    value: String,
+    parameter_type: Option<&ParameterType>,
+) -> Result<Value, String> {
+    let invalid = |expected: &str| format!("parameter '{name}' must be {expected}");
+    Ok(match parameter_type.copied().unwrap_or_default() {
+        ParameterType::String => Value::String(value),
+        ParameterType::Int => value
+            .parse::<i64>()
+            .map_err(|_| invalid("an integer"))?
+            .into(),
+        ParameterType::Float => value
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| invalid("a number"))?,
+        ParameterType::Boolean => value
+            .parse::<bool>()
+            .map_err(|_| invalid("true or false"))?
+            .into(),
+        ParameterType::Json => serde_json::from_str(&value).map_err(|_| invalid("JSON"))?,
+    })
+}
+
+/// Encodes a GraphQL request in the query string format of GET requests
+fn urlencoded_request(request: &graphql::Request) -> Result<String, String> {
+    let mut fields = Vec::new();
+    if let Some(query) = &request.query {
+        fields.push(("query", query.clone()));
+    }
+    if let Some(operation_name) = &request.operation_name {
+        fields.push(("operationName", operation_name.clone()));
+    }
+    if !request.variables.is_empty() {
+        let variables = serde_json::to_string(&request.variables).map_err(|e| e.to_string())?;
+        fields.push(("variables", variables));
+    }
+    if !request.extensions.is_empty() {
+        let extensions = serde_json::to_string(&request.extensions).map_err(|e| e.to_string())?;
+        fields.push(("extensions", extensions));
+    }
+    serde_urlencoded::to_string(fields).map_err(|e| e.to_string())
+}
+
+fn bad_request(message: String) -> Response {
+    let error = graphql::Error::builder()
+        .message(message)
+        .extension_code("INVALID_REST_REQUEST")
+        .build();
+    let response = graphql::Response::builder().error(error).build();
+    (StatusCode::BAD_REQUEST, Json(json!(response))).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use tower::Layer;
+
+    use super::*;
+
+    fn route(method: RestMethod) -> RestRoute {
+        serde_json::from_value(json!({
+            "method": method,
+            "path": "/api/users/{id}",
+            "persisted_query_id": "user",
+            "parameter_types": { "id": "int", "full": "boolean" }
+        }))
+        .unwrap()
+    }
+
+    async fn translate(
+        route: &RestRoute,
+        method: Method,
+        uri: &str,
+        body: &'static str,
+    ) -> Result<Request<Body>, String> {
+        let request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::from(body))
+            .unwrap();
+        let (parts, body) = request.into_parts();
+        // the decompression layer passes uncompressed bodies through
+        let request = tower_http::decompression::RequestDecompressionLayer::new()
+            .layer(tower::service_fn(
+                |request: Request<DecompressionBody<Body>>| async {
+                    Ok::<_, std::convert::Infallible>(request)
+                },
+            ))
+            .oneshot(Request::from_parts(parts, body))
+            .await
+            .unwrap();
+        let parameters = HashMap::from([("id".to_string(), "42".to_string())]);
+        graphql_request(route, parameters, request, 1024).await
+    }
+
+    #[tokio::test]
+    async fn get_route() {
+        let request = translate(
+            &route(RestMethod::Get),
+            Method::GET,
+            "/api/users/42?full=true",
+            "",
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.method(), Method::GET);
+        assert_eq!(request.headers()["apollo-require-preflight"], "true");
+        let graphql_request =
+            graphql::Request::from_urlencoded_query(request.uri().query().unwrap().to_string())
+                .unwrap();
+        assert_eq!(graphql_request.variables.get("id"), Some(&42.into()));
+        assert_eq!(graphql_request.variables.get("full"), Some(&true.into()));
+        assert_eq!(
+            graphql_request.extensions.get("persistedQuery"),
+            Some(&serde_json_bytes::json!({ "version": 1, "sha256Hash": "user" }))
+        );
+
+        let error = translate(
+            &route(RestMethod::Get),
+            Method::GET,
+            "/api/users/42?full=yes",
+            "",
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error, "parameter 'full' must be true or false");
+    }
+
+    #[tokio::test]
+    async fn post_route() {
+        let request = translate(
+            &route(RestMethod::Put),
+            Method::PUT,
+            "/api/users/42",
+            r#"{"id": 1, "name": "Ada"}"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(request.method(), Method::POST);
+        // non-GET routes go through the CSRF check
+        assert!(!request.headers().contains_key("apollo-require-preflight"));
+        let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+        let graphql_request: graphql::Request = serde_json::from_slice(&body).unwrap();
+        assert_eq!(graphql_request.variables.get("id"), Some(&42.into()));
+        assert_eq!(graphql_request.variables.get("name"), Some(&"Ada".into()));
+
+        assert!(
+            translate(&route(RestMethod::Put), Method::PUT, "/api/users/42", "[]")
+                .await
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Websocket connections from clients on the supergraph endpoint, using the graphql-transport-ws
+//! protocol (<https://github.com/enisdenjo/graphql-ws/blob/master/PROTOCOL.md>).
+//!
+//! Each operation of a connection goes through the router pipeline as its own HTTP request, with
+//! the headers of the upgrade request. The payload of `connection_init` can add headers, like an
+//! `Authorization` header, and `ping` messages carrying headers replace them for the next
+//! operations, so clients can refresh their credentials without reconnecting.
+//!
+//! Browsers do not apply CORS to websockets and send cookies with connections opened by any site,
+//! so upgrade requests from origins not allowed by the CORS configuration are rejected, and cookies
+//! are only added to the operations when `forward_cookies` is set.
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use axum::extract::ws::CloseFrame;
+use axum::extract::ws::Message;
+use axum::extract::ws::WebSocket;
+use axum::extract::ws::WebSocketUpgrade;
+use axum::response::IntoResponse;
+use axum::response::Response;
+use futures::SinkExt;
+use futures::StreamExt;
+use http::header::ACCEPT;
+use http::header::ACCEPT_ENCODING;
+use http::header::CONTENT_LENGTH;
+use http::header::CONTENT_TYPE;
+use http::header::COOKIE;
+use http::header::ORIGIN;
+use http::HeaderMap;
+use http::HeaderName;
+use http::HeaderValue;
+use http::Method;
+use http::StatusCode;
+use http::Uri;
+use hyper::Body;
+use mime::APPLICATION_JSON;
+use serde_json_bytes::Value;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use crate::configuration::cors::Cors;
+use crate::configuration::ClientWebSocket;
+use crate::graphql;
+use crate::protocols::websocket::ClientMessage;
+use crate::protocols::websocket::ServerError;
+use crate::protocols::websocket::ServerMessage;
+use crate::router_factory::RouterFactory;
+use crate::services::router;
+
+const PROTOCOL: &str = "graphql-transport-ws";
+
+// close codes defined by the protocol
+const INVALID_MESSAGE: u16 = 4400;
+const UNAUTHORIZED: u16 = 4401;
+const SUBPROTOCOL_NOT_ACCEPTABLE: u16 = 4406;
+const CONNECTION_INIT_TIMEOUT: u16 = 4408;
+const SUBSCRIBER_ALREADY_EXISTS: u16 = 4409;
+const TOO_MANY_INITIALISATION_REQUESTS: u16 = 4429;
+
+/// Upgrades the request to a websocket connection executing the operations sent by the client
+pub(super) fn upgrade<RF>(
+    upgrade: WebSocketUpgrade,
+    service_factory: RF,
+    request: http::request::Parts,
+    config: &ClientWebSocket,
+    cors: &Cors,
+    max_message_bytes: usize,
+) -> Response
+where
+    RF: RouterFactory,
+{
+    if let Err(status) = check_origin(&request.headers, cors) {
+        return status.into_response();
+    }
+    let connection = Connection {
+        service_factory,
+        uri: request.uri,
+        headers: operation_headers(request.headers, config.forward_cookies),
+    };
+    let connection_init_timeout = config.connection_init_timeout;
+
+    upgrade
+        .protocols([PROTOCOL])
+        .max_message_size(max_message_bytes)
+        .on_upgrade(move |socket| connection.serve(socket, connection_init_timeout))
+}
+
+/// Rejects upgrade requests sent by browsers from origins not allowed by the CORS configuration.
+/// Requests without an `Origin` header do not come from browsers
+fn check_origin(headers: &HeaderMap, cors: &Cors) -> Result<(), StatusCode> {
+    match headers.get(ORIGIN) {
+        Some(origin) if !cors.allows_origin(origin) => Err(StatusCode::FORBIDDEN),
+        _ => Ok(()),
+    }
+}
+
+/// Headers of the upgrade request added to the requests of the operations
+fn operation_headers(mut headers: HeaderMap, forward_cookies: bool) -> HeaderMap {
+    // those describe the upgrade request, not the operations
+    for name in [
+        http::header::CONNECTION,
+        http::header::UPGRADE,
+        http::header::SEC_WEBSOCKET_KEY,
+        http::header::SEC_WEBSOCKET_VERSION,
+        http::header::SEC_WEBSOCKET_PROTOCOL,
+        http::header::SEC_WEBSOCKET_EXTENSIONS,
+        ACCEPT_ENCODING,
+    ] {
+        headers.remove(name);
+    }
+    if !forward_cookies {
+        headers.remove(COOKIE);
+    }
+    headers
+}
+
+struct Connection<RF> {
+    service_factory: RF,
+    uri: Uri,
+    /// Headers added to the requests of the operations
+    headers: HeaderMap,
+}
+
+impl<RF> Connection<RF>
+where
+    RF: RouterFactory,
+{
+    async fn serve(mut self, socket: WebSocket, connection_init_timeout: Duration) {
+        let negotiated = socket
+            .protocol()
+            .is_some_and(|protocol| protocol == PROTOCOL);
+        let (mut sink, mut stream) = socket.split();
+        if !negotiated {
+            let _ = sink
+                .send(close(
+                    SUBPROTOCOL_NOT_ACCEPTABLE,
+                    "Subprotocol not acceptable",
+                ))
+                .await;
+            return;
+        }
+
+        // operations send their messages concurrently, through this channel
+        let (sender, mut receiver) = mpsc::channel::<Message>(32);
+        let writer = tokio::spawn(async move {
+            while let Some(message) = receiver.recv().await {
+                let closing = matches!(message, Message::Close(_));
+                if sink.send(message).await.is_err() || closing {
+                    break;
+                }
+            }
+        });
+
+        match tokio::time::timeout(connection_init_timeout, stream.next()).await {
+            Ok(Some(Ok(Message::Text(text)))) => match serde_json::from_str::<ClientMessage>(&text)
+            {
+                Ok(ClientMessage::ConnectionInit { payload }) => {
+                    if let Err(message) = self.set_headers(payload.as_ref()) {
+                        let _ = sender.send(close(INVALID_MESSAGE, &message)).await;
+                        let _ = writer.await;
+                        return;
+                    }
+                    let _ = sender
+                        .send(text_message(&ServerMessage::ConnectionAck))
+                        .await;
+                }
+                _ => {
+                    let _ = sender.send(close(UNAUTHORIZED, "Unauthorized")).await;
+                    let _ = writer.await;
+                    return;
+                }
+            },
+            Err(_) => {
+                let _ = sender
+                    .send(close(
+                        CONNECTION_INIT_TIMEOUT,
+                        "Connection initialisation timeout",
+                    ))
+                    .await;
+                let _ = writer.await;
+                return;
+            }
+            // the client left, or sent a message that is not part of the protocol
+            _ => {
+                drop(sender);
+                let _ = writer.await;
+                return;
+            }
+        }
+
+        let mut operations: HashMap<String, JoinHandle<()>> = HashMap::new();
+        while let Some(Ok(message)) = stream.next().await {
+            let text = match message {
+                Message::Text(text) => text,
+                Message::Close(_) => break,
+                // pings are answered by the websocket implementation
+                _ => continue,
+            };
+            let message = match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(message) => message,
+                Err(error) => {
+                    let _ = sender
+                        .send(close(INVALID_MESSAGE, &format!("Invalid message: {error}")))
+                        .await;
+                    break;
+                }
+            };
+            match message {
+                ClientMessage::Subscribe { id, payload } => {
+                    operations.retain(|_, operation| !operation.is_finished());
+                    if operations.contains_key(&id) {
+                        let _ = sender
+                            .send(close(
+                                SUBSCRIBER_ALREADY_EXISTS,
+                                &format!("Subscriber for {id} already exists"),
+                            ))
+                            .await;
+                        break;
+                    }
+                    let operation = tokio::spawn(execute(
+                        self.service_factory.clone(),
+                        self.request(payload),
+                        id.clone(),
+                        sender.clone(),
+                    ));
+                    operations.insert(id, operation);
+                }
+                ClientMessage::Complete { id } => {
+                    if let Some(operation) = operations.remove(&id) {
+                        operation.abort();
+                    }
+                }
+                ClientMessage::Ping { payload } => {
+                    if let Err(message) = self.set_headers(payload.as_ref()) {
+                        let _ = sender.send(close(INVALID_MESSAGE, &message)).await;
+                        break;
+                    }
+                    let _ = sender
+                        .send(text_message(&ServerMessage::Pong { payload: None }))
+                        .await;
+                }
+                ClientMessage::Pong { .. } => {}
+                ClientMessage::ConnectionInit { .. } => {
+                    let _ = sender
+                        .send(close(
+                            TOO_MANY_INITIALISATION_REQUESTS,
+                            "Too many initialisation requests",
+                        ))
+                        .await;
+                    break;
+                }
+                ClientMessage::OldStart { .. }
+                | ClientMessage::OldStop { .. }
+                | ClientMessage::ConnectionTerminate
+                | ClientMessage::CloseWebsocket => {
+                    let _ = sender
+                        .send(close(
+                            INVALID_MESSAGE,
+                            "Invalid message: unsupported message type",
+                        ))
+                        .await;
+                    break;
+                }
+            }
+        }
+
+        for operation in operations.into_values() {
+            operation.abort();
+        }
+        drop(sender);
+        let _ = writer.await;
+    }
+}
+
+impl<RF> Connection<RF> {
+    /// Adds the headers found in a `connection_init` or `ping` payload, either at the top level or
+    /// in a `headers` object
+    fn set_headers(&mut self, payload: Option<&Value>) -> Result<(), String> {
+        let fields = match payload {
+            Some(Value::Object(payload)) => match payload.get("headers") {
+                Some(Value::Object(headers)) => headers,
+                _ => payload,
+            },
+            _ => return Ok(()),
+        };
+        for (name, value) in fields.iter() {
+            let Some(value) = value.as_str() else {
+                continue;
+            };
+            let name = HeaderName::from_bytes(name.as_str().as_bytes()).map_err(|_| {
+                format!("Invalid message: '{}' is not a header name", name.as_str())
+            })?;
+            let value = HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid message: invalid value for header '{name}'"))?;
+            self.headers.insert(name, value);
+        }
+        Ok(())
+    }
+
+    fn request(&self, payload: graphql::Request) -> Result<router::Request, BoxError> {
+        let mut request = http::Request::builder()
+            .method(Method::POST)
+            .uri(self.uri.clone())
+            .body(Body::from(serde_json::to_vec(&payload)?))?;
+        *request.headers_mut() = self.headers.clone();
+        let headers = request.headers_mut();
+        headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static(APPLICATION_JSON.essence_str()),
+        );
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static(APPLICATION_JSON.essence_str()),
+        );
+        headers.remove(CONTENT_LENGTH);
+        Ok(request.into())
+    }
+}
+
+/// Runs an operation through the router pipeline, then sends its result
+async fn execute<RF>(
+    service_factory: RF,
+    request: Result<router::Request, BoxError>,
+    id: String,
+    sender: mpsc::Sender<Message>,
+) where
+    RF: RouterFactory,
+{
+    let messages = match response(service_factory, request).await {
+        Ok((true, payload)) => vec![
+            ServerMessage::Next {
+                id: id.clone(),
+                payload,
+            },
+            ServerMessage::Complete { id },
+        ],
+        // the operation was rejected before execution
+        Ok((false, payload)) => vec![ServerMessage::Error {
+            id,
+            payload: ServerError::Errors(payload.errors),
+        }],
+        Err(error) => {
+            tracing::error!(code = "INTERNAL_SERVER_ERROR", %error);
+            vec![ServerMessage::Error {
+                id,
+                payload: ServerError::Error(
+                    graphql::Error::builder()
+                        .message("internal server error")
+                        .extension_code("INTERNAL_SERVER_ERROR")
+                        .build(),
+                ),
+            }]
+        }
+    };
+    for message in messages {
+        if sender.send(text_message(&message)).await.is_err() {
+            return;
+        }
+    }
+}
+
+async fn response<RF>(
+    service_factory: RF,
+    request: Result<router::Request, BoxError>,
+) -> Result<(bool, graphql::Response), BoxError>
+where
+    RF: RouterFactory,
+{
+    let response = service_factory.create().boxed().oneshot(request?).await?;
+    let status = response.response.status();
+    let body = hyper::body::to_bytes(response.response.into_body()).await?;
+    let payload: graphql::Response = serde_json::from_slice(&body)?;
+    Ok((status.is_success(), payload))
+}
+
+fn text_message(message: &ServerMessage) -> Message {
+    Message::Text(serde_json::to_string(message).unwrap_or_default())
+}
+
+fn close(code: u16, reason: &str) -> Message {
+    Message::Close(Some(CloseFrame {
+        code,
+        reason: Cow::Owned(reason.to_string()),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    #[test]
+    fn connection_headers() {
+        let mut connection = Connection {
+            service_factory: (),
+            uri: Uri::from_static("http://localhost/graphql"),
+            headers: HeaderMap::new(),
+        };
+        connection
+            .set_headers(Some(
+                &json!({ "Authorization": "Bearer first", "retries": 3 }),
+            ))
+            .unwrap();
+        assert_eq!(connection.headers["authorization"], "Bearer first");
+
+        // credentials are refreshed by ping messages
+        connection
+            .set_headers(Some(
+                &json!({ "headers": { "authorization": "Bearer second" } }),
+            ))
+            .unwrap();
+        assert_eq!(connection.headers["authorization"], "Bearer second");
+        assert_eq!(connection.headers.len(), 1);
+
+        assert!(connection
+            .set_headers(Some(&json!({ "invalid header": "value" })))
+            .is_err());
+
+        let request = connection
+            .request(graphql::Request::builder().query("{ me }").build())
+            .unwrap();
+        assert_eq!(
+            request.router_request.headers()["authorization"],
+            "Bearer second"
+        );
+        assert_eq!(
+            request.router_request.headers()[CONTENT_TYPE],
+            "application/json"
+        );
+    }
+
+    #[test]
+    fn upgrade_origin() {
+        let cors = Cors::builder()
+            .origins(vec!["https://app.example.com".to_string()])
+            .match_origins(vec!["^https://.*\\.example\\.org$".to_string()])
+            .build();
+        let headers = |origin: Option<&'static str>| {
+            let mut headers = HeaderMap::new();
+            if let Some(origin) = origin {
+                headers.insert(ORIGIN, HeaderValue::from_static(origin));
+            }
+            headers
+        };
+
+        assert!(check_origin(&headers(None), &cors).is_ok());
+        assert!(check_origin(&headers(Some("https://app.example.com")), &cors).is_ok());
+        assert!(check_origin(&headers(Some("https://studio.example.org")), &cors).is_ok());
+        assert_eq!(
+            check_origin(&headers(Some("https://evil.example.net")), &cors),
+            Err(StatusCode::FORBIDDEN)
+        );
+        assert_eq!(
+            check_origin(&headers(Some("null")), &cors),
+            Err(StatusCode::FORBIDDEN)
+        );
+
+        let cors = Cors::builder().allow_any_origin(true).build();
+        assert!(check_origin(&headers(Some("https://evil.example.net")), &cors).is_ok());
+    }
+
+    #[test]
+    fn upgrade_cookies() {
+        let mut headers = HeaderMap::new();
+        headers.insert(COOKIE, HeaderValue::from_static("session=secret"));
+        headers.insert(
+            http::header::SEC_WEBSOCKET_KEY,
+            HeaderValue::from_static("dGhlIHNhbXBsZSBub25jZQ=="),
+        );
+        headers.insert("x-client", HeaderValue::from_static("web"));
+
+        let forwarded = operation_headers(headers.clone(), false);
+        assert!(!forwarded.contains_key(COOKIE));
+        assert!(!forwarded.contains_key(http::header::SEC_WEBSOCKET_KEY));
+        assert_eq!(forwarded["x-client"], "web");
+
+        let forwarded = operation_headers(headers, true);
+        assert_eq!(forwarded[COOKIE], "session=secret");
+    }
+}
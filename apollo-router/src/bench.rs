@@ -0,0 +1,283 @@
+//! Replays a file of operations against a router at a fixed rate and reports latencies.
+//!
+//! The operations are either sent to a running router over HTTP, or executed by an in-process
+//! router where subgraphs return empty responses, which is enough to measure the cost of query
+//! planning and execution for a given configuration.
+
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use tokio::task::JoinSet;
+use tower::ServiceExt;
+use url::Url;
+
+use crate::graphql;
+use crate::services::supergraph;
+use crate::services::SupergraphCreator;
+use crate::Configuration;
+use crate::TestHarness;
+
+/// Above this rate, the interval between requests would round down to zero
+const MAX_RPS: u32 = 1_000_000_000;
+
+/// Where the operations are sent
+pub(crate) enum Target {
+    /// A running router
+    Remote(Url),
+    /// An in-process router with mocked subgraphs
+    InProcess {
+        configuration: Arc<Configuration>,
+        schema: String,
+    },
+}
+
+#[derive(Clone)]
+enum Client {
+    Remote(reqwest::Client, Url),
+    InProcess(Arc<SupergraphCreator>),
+}
+
+struct Sample {
+    latency: Duration,
+    has_errors: bool,
+}
+
+/// Results of a benchmark run
+#[derive(Debug, PartialEq)]
+pub(crate) struct Report {
+    pub(crate) requests: usize,
+    pub(crate) errors: usize,
+    pub(crate) elapsed: Duration,
+    pub(crate) p50: Duration,
+    pub(crate) p90: Duration,
+    pub(crate) p99: Duration,
+    pub(crate) max: Duration,
+    /// Number of query plans in the in-memory cache at the end of an in-process run
+    pub(crate) query_plans: Option<usize>,
+}
+
+/// Parses an operations file, containing one GraphQL request in JSON per line
+pub(crate) fn read_operations(path: &Path) -> Result<Vec<graphql::Request>> {
+    let content = std::fs::read_to_string(path)?;
+    let operations = content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line)
+                .map_err(|e| anyhow!("invalid operation at line {}: {e}", index + 1))
+        })
+        .collect::<Result<Vec<graphql::Request>>>()?;
+
+    if operations.is_empty() {
+        return Err(anyhow!("no operations found in {}", path.display()));
+    }
+    Ok(operations)
+}
+
+/// Sends `operations` in a loop at `rps` requests per second for `duration`
+pub(crate) async fn run(
+    target: Target,
+    operations: Vec<graphql::Request>,
+    rps: u32,
+    duration: Duration,
+) -> Result<Report> {
+    if rps == 0 {
+        return Err(anyhow!("the request rate must be greater than 0"));
+    }
+    if rps > MAX_RPS {
+        return Err(anyhow!("the request rate must be at most {MAX_RPS}"));
+    }
+
+    let client = match target {
+        Target::Remote(url) => Client::Remote(reqwest::Client::new(), url),
+        Target::InProcess {
+            configuration,
+            schema,
+        } => {
+            let (_, supergraph_creator) = TestHarness::builder()
+                .configuration(configuration)
+                .schema(&schema)
+                .build_common()
+                .await
+                .map_err(|e| anyhow!("could not create the router: {e}"))?;
+            Client::InProcess(Arc::new(supergraph_creator))
+        }
+    };
+
+    let operations = Arc::new(operations);
+    let mut interval = tokio::time::interval(Duration::from_secs(1) / rps);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+    let mut tasks = JoinSet::new();
+    let start = Instant::now();
+    let mut sent = 0usize;
+    while start.elapsed() < duration {
+        interval.tick().await;
+        let client = client.clone();
+        let operation = operations[sent % operations.len()].clone();
+        tasks.spawn(async move { client.send(operation).await });
+        sent += 1;
+    }
+
+    let mut samples = Vec::with_capacity(sent);
+    while let Some(sample) = tasks.join_next().await {
+        samples.push(sample?);
+    }
+    let elapsed = start.elapsed();
+
+    let query_plans = match &client {
+        Client::InProcess(supergraph_creator) => {
+            Some(supergraph_creator.previous_cache().lock().await.len())
+        }
+        Client::Remote(..) => None,
+    };
+
+    Ok(Report::new(samples, elapsed, query_plans))
+}
+
+impl Client {
+    async fn send(self, operation: graphql::Request) -> Sample {
+        let start = Instant::now();
+        let has_errors = match self {
+            Client::Remote(client, url) => match client.post(url).json(&operation).send().await {
+                Ok(response) if response.status().is_success() => response
+                    .json::<graphql::Response>()
+                    .await
+                    .map(|response| !response.errors.is_empty())
+                    .unwrap_or(true),
+                _ => true,
+            },
+            Client::InProcess(supergraph_creator) => {
+                match supergraph::Request::fake_builder()
+                    .and_query(operation.query)
+                    .and_operation_name(operation.operation_name)
+                    .variables(operation.variables)
+                    .extensions(operation.extensions)
+                    .build()
+                {
+                    Ok(request) => match supergraph_creator.make().oneshot(request).await {
+                        Ok(mut response) => response
+                            .next_response()
+                            .await
+                            .map(|response| !response.errors.is_empty())
+                            .unwrap_or(true),
+                        Err(_) => true,
+                    },
+                    Err(_) => true,
+                }
+            }
+        };
+
+        Sample {
+            latency: start.elapsed(),
+            has_errors,
+        }
+    }
+}
+
+impl Report {
+    fn new(samples: Vec<Sample>, elapsed: Duration, query_plans: Option<usize>) -> Self {
+        let errors = samples.iter().filter(|sample| sample.has_errors).count();
+        let mut latencies: Vec<Duration> = samples.iter().map(|sample| sample.latency).collect();
+        latencies.sort();
+
+        let percentile = |p: usize| -> Duration {
+            if latencies.is_empty() {
+                return Duration::ZERO;
+            }
+            let index = (latencies.len() * p).div_ceil(100).saturating_sub(1);
+            latencies[index.min(latencies.len() - 1)]
+        };
+
+        Self {
+            requests: latencies.len(),
+            errors,
+            elapsed,
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: latencies.last().copied().unwrap_or_default(),
+            query_plans,
+        }
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "requests: {} ({} with errors) in {:.2?}, {:.1} req/s",
+            self.requests,
+            self.errors,
+            self.elapsed,
+            self.requests as f64 / self.elapsed.as_secs_f64()
+        )?;
+        write!(
+            f,
+            "latency: p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+            self.p50, self.p90, self.p99, self.max
+        )?;
+        if let Some(query_plans) = self.query_plans {
+            write!(f, "\nquery plan cache: {query_plans} entries")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let samples = (1..=100)
+            .map(|ms| Sample {
+                latency: Duration::from_millis(ms),
+                has_errors: ms % 10 == 0,
+            })
+            .collect();
+        let report = Report::new(samples, Duration::from_secs(1), None);
+
+        assert_eq!(report.requests, 100);
+        assert_eq!(report.errors, 10);
+        assert_eq!(report.p50, Duration::from_millis(50));
+        assert_eq!(report.p90, Duration::from_millis(90));
+        assert_eq!(report.p99, Duration::from_millis(99));
+        assert_eq!(report.max, Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn in_process() {
+        let operations = vec![graphql::Request::fake_builder()
+            .query("{ topProducts { upc name } }")
+            .build()];
+        let report = run(
+            Target::InProcess {
+                configuration: Default::default(),
+                schema: include_str!("testdata/supergraph.graphql").to_string(),
+            },
+            operations,
+            50,
+            Duration::from_millis(200),
+        )
+        .await
+        .unwrap();
+
+        assert!(report.requests > 0);
+        assert_eq!(report.query_plans, Some(1));
+    }
+
+    #[tokio::test]
+    async fn invalid_rate() {
+        for rps in [0, MAX_RPS + 1] {
+            let target = Target::Remote(Url::parse("http://127.0.0.1:4000").unwrap());
+            assert!(run(target, Vec::new(), rps, Duration::from_millis(10))
+                .await
+                .is_err());
+        }
+    }
+}
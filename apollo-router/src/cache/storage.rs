@@ -63,10 +63,16 @@ where
         max_capacity: NonZeroUsize,
         config: Option<RedisCache>,
         caller: &str,
-    ) -> Result<Self, BoxError> {
+    ) -> Result<Self, BoxError>
+    where
+        K: 'static,
+        V: 'static,
+    {
+        let inner = Arc::new(Mutex::new(LruCache::new(max_capacity)));
+        crate::diagnose::register_cache(caller, &inner);
         Ok(Self {
             caller: caller.to_string(),
-            inner: Arc::new(Mutex::new(LruCache::new(max_capacity))),
+            inner,
             redis: if let Some(config) = config {
                 let required_to_start = config.required_to_start;
                 match RedisCacheStorage::new(config).await {
@@ -180,6 +180,20 @@ impl Cors {
         }
     }
 
+    /// Whether requests from an origin are allowed, following the rules of the CORS layer
+    pub(crate) fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        if self.allow_any_origin {
+            return true;
+        }
+        let Ok(origin) = origin.to_str() else {
+            return false;
+        };
+        self.origins.iter().any(|allowed| allowed == origin)
+            || self.match_origins.iter().flatten().any(|regex| {
+                Regex::from_str(regex.as_str()).is_ok_and(|regex| regex.is_match(origin))
+            })
+    }
+
     // This is cribbed from the similarly named function in tower-http. The version there
     // asserts that CORS rules are useable, which results in a panic if they aren't. We
     // don't want the router to panic in such cases, so this function returns an error
@@ -11,6 +11,7 @@ use serde_json::Value;
 
 use super::ConfigurationError;
 use crate::executable::APOLLO_ROUTER_DEV_ENV;
+use crate::executable::APOLLO_ROUTER_MOCK_ENV;
 
 #[derive(buildstructor::Builder, Clone)]
 pub(crate) struct Expansion {
@@ -90,6 +91,17 @@ impl Expansion {
             Vec::new()
         };
 
+        let mock_mode = if std::env::var(APOLLO_ROUTER_MOCK_ENV).ok().as_deref() == Some("true") {
+            tracing::info!("Running in *mock* mode: generated data is served for all operations");
+            vec![Override::builder()
+                .config_path("plugins.[\"experimental.mock\"].enabled")
+                .value(true)
+                .value_type(ValueType::Bool)
+                .build()]
+        } else {
+            Vec::new()
+        };
+
         Ok(Expansion::builder()
             .and_prefix(prefix)
             .supported_modes(supported_modes)
@@ -116,6 +128,7 @@ impl Expansion {
                     .build(),
             )
             .override_configs(dev_mode_defaults)
+            .override_configs(mock_mode)
             .build())
     }
 
@@ -138,6 +151,11 @@ impl Expansion {
 
 fn dev_mode_defaults() -> Vec<Override> {
     vec![
+        Override::builder()
+            .config_path("plugins.[\"experimental.expose_null_propagation\"]")
+            .value(true)
+            .value_type(ValueType::Bool)
+            .build(),
         Override::builder()
             .config_path("plugins.[\"experimental.expose_query_plan\"]")
             .value(true)
@@ -0,0 +1,253 @@
+//! Configuration of the gRPC facade, exposing GraphQL operations as gRPC unary methods.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+use apollo_compiler::ast;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+use super::ListenAddr;
+
+/// Expose GraphQL operations as unary methods of a gRPC service
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct GrpcFacade {
+    /// Set to true to enable the gRPC listener
+    pub(crate) enabled: bool,
+    /// The socket address and port to listen on for gRPC requests, over HTTP/2 without TLS.
+    /// Defaults to 127.0.0.1:4010
+    pub(crate) listen: ListenAddr,
+    /// Protobuf package of the service
+    pub(crate) package: String,
+    /// Name of the service
+    pub(crate) service: String,
+    /// Methods of the service, each executing a GraphQL operation
+    pub(crate) methods: Vec<GrpcMethod>,
+}
+
+impl Default for GrpcFacade {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: SocketAddr::from_str("127.0.0.1:4010").unwrap().into(),
+            package: "apollo.router".to_string(),
+            service: "Operations".to_string(),
+            methods: Vec::new(),
+        }
+    }
+}
+
+/// A gRPC method executing a GraphQL operation
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct GrpcMethod {
+    /// Name of the method, like `GetUser`
+    pub(crate) name: String,
+    /// GraphQL document of the operation. The fields of the request message are generated from
+    /// the variables of the operation
+    pub(crate) query: String,
+    /// Name of the operation to execute, if the document contains several operations
+    #[serde(default)]
+    pub(crate) operation_name: Option<String>,
+}
+
+/// Protobuf type of a field of a request message
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FieldType {
+    String,
+    Int32,
+    Double,
+    Bool,
+    /// `google.protobuf.Value`, for enums, input objects, custom scalars and nested lists
+    Value,
+}
+
+/// A field of a request message, bound to the variable of the same name
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Field {
+    pub(crate) name: String,
+    pub(crate) tag: u32,
+    pub(crate) ty: FieldType,
+    pub(crate) repeated: bool,
+    /// Nullable scalars are `optional` fields, so that null can be told apart from the default
+    pub(crate) optional: bool,
+}
+
+impl GrpcFacade {
+    /// Checks the service, returning an explanation of the first problem found
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !self.package.split('.').all(is_identifier) {
+            return Err(format!("'{}' is not a valid package name", self.package));
+        }
+        if !is_identifier(&self.service) {
+            return Err(format!("'{}' is not a valid service name", self.service));
+        }
+        let mut names = HashSet::new();
+        for method in &self.methods {
+            if !is_identifier(&method.name) {
+                return Err(format!("'{}' is not a valid method name", method.name));
+            }
+            if !names.insert(&method.name) {
+                return Err(format!("method '{}' is defined twice", method.name));
+            }
+            method
+                .fields()
+                .map_err(|error| format!("method '{}': {error}", method.name))?;
+        }
+        Ok(())
+    }
+
+    /// Path of the HTTP/2 requests calling `method`
+    pub(crate) fn path(&self, method: &GrpcMethod) -> String {
+        format!("/{}.{}/{}", self.package, self.service, method.name)
+    }
+
+    /// The protobuf definition of the service
+    pub(crate) fn proto(&self) -> Result<String, String> {
+        let mut proto = format!(
+            "syntax = \"proto3\";\n\npackage {};\n\nimport \"google/protobuf/struct.proto\";\n\nservice {} {{\n",
+            self.package, self.service
+        );
+        for method in &self.methods {
+            proto.push_str(&format!(
+                "  rpc {0}({0}Request) returns ({0}Response);\n",
+                method.name
+            ));
+        }
+        proto.push_str("}\n");
+
+        for method in &self.methods {
+            proto.push_str(&format!("\nmessage {}Request {{\n", method.name));
+            for field in method.fields()? {
+                let label = if field.repeated {
+                    "repeated "
+                } else if field.optional {
+                    "optional "
+                } else {
+                    ""
+                };
+                let ty = match field.ty {
+                    FieldType::String => "string",
+                    FieldType::Int32 => "int32",
+                    FieldType::Double => "double",
+                    FieldType::Bool => "bool",
+                    FieldType::Value => "google.protobuf.Value",
+                };
+                proto.push_str(&format!("  {label}{ty} {} = {};\n", field.name, field.tag));
+            }
+            proto.push_str(&format!(
+                "}}\n\nmessage {}Response {{\n  google.protobuf.Struct data = 1;\n  google.protobuf.ListValue errors = 2;\n}}\n",
+                method.name
+            ));
+        }
+        Ok(proto)
+    }
+}
+
+impl GrpcMethod {
+    /// Fields of the request message, from the variables of the operation
+    pub(crate) fn fields(&self) -> Result<Vec<Field>, String> {
+        let document = ast::Document::parse(&self.query, "grpc_method.graphql")
+            .map_err(|e| format!("the query does not parse: {}", e.errors))?;
+        let mut operations = document
+            .definitions
+            .iter()
+            .filter_map(|definition| match definition {
+                ast::Definition::OperationDefinition(operation) => Some(operation),
+                _ => None,
+            })
+            .filter(|operation| match &self.operation_name {
+                Some(name) => operation.name.as_ref().is_some_and(|n| n == name.as_str()),
+                None => true,
+            });
+        let operation = match (operations.next(), operations.next()) {
+            (Some(operation), None) => operation,
+            (None, _) => return Err("the query does not define the operation".to_string()),
+            (Some(_), Some(_)) => {
+                return Err("the query defines several operations, set 'operation_name'".to_string())
+            }
+        };
+
+        let mut fields = Vec::new();
+        for (index, variable) in operation.variables.iter().enumerate() {
+            let (ty, repeated) = match variable.ty.as_ref() {
+                ast::Type::Named(name) | ast::Type::NonNullNamed(name) => {
+                    (field_type(name.as_str()), false)
+                }
+                ast::Type::List(item) | ast::Type::NonNullList(item) => match item.as_ref() {
+                    ast::Type::Named(name) | ast::Type::NonNullNamed(name) => {
+                        (field_type(name.as_str()), true)
+                    }
+                    _ => (FieldType::Value, false),
+                },
+            };
+            fields.push(Field {
+                name: variable.name.to_string(),
+                tag: index as u32 + 1,
+                ty,
+                repeated,
+                optional: !repeated && ty != FieldType::Value && !variable.ty.is_non_null(),
+            });
+        }
+        Ok(fields)
+    }
+}
+
+fn field_type(graphql_type: &str) -> FieldType {
+    match graphql_type {
+        "String" | "ID" => FieldType::String,
+        "Int" => FieldType::Int32,
+        "Float" => FieldType::Double,
+        "Boolean" => FieldType::Bool,
+        _ => FieldType::Value,
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn facade(query: &str) -> GrpcFacade {
+        GrpcFacade {
+            enabled: true,
+            methods: vec![GrpcMethod {
+                name: "GetUser".to_string(),
+                query: query.to_string(),
+                operation_name: None,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn generates_proto() {
+        let facade = facade(
+            "query User($id: ID!, $fields: [String!], $limit: Int, $filter: UserFilter) { user(id: $id) { name } }",
+        );
+        assert!(facade.validate().is_ok());
+        assert_eq!(
+            facade.path(&facade.methods[0]),
+            "/apollo.router.Operations/GetUser"
+        );
+        insta::assert_snapshot!(facade.proto().unwrap());
+    }
+
+    #[test]
+    fn invalid_methods() {
+        assert!(facade("query A { a } query B { b }").validate().is_err());
+        assert!(facade("query A {").validate().is_err());
+
+        let mut facade = facade("{ a }");
+        facade.methods[0].name = "get-user".to_string();
+        assert!(facade.validate().is_err());
+    }
+}
@@ -1,4 +1,5 @@
 //! Logic for loading configuration in to an object model
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
 use std::io::BufReader;
@@ -15,6 +16,7 @@ use displaydoc::Display;
 use itertools::Itertools;
 use once_cell::sync::Lazy;
 pub(crate) use persisted_queries::PersistedQueries;
+pub(crate) use persisted_queries::PersistedQueriesManifestSignature;
 #[cfg(test)]
 pub(crate) use persisted_queries::PersistedQueriesSafelist;
 use regex::Regex;
@@ -39,6 +41,8 @@ use thiserror::Error;
 use self::cors::Cors;
 use self::expansion::Expansion;
 pub(crate) use self::experimental::Discussed;
+use self::grpc::GrpcFacade;
+use self::rest::RestFacade;
 pub(crate) use self::schema::generate_config_schema;
 pub(crate) use self::schema::generate_upgrade;
 use self::subgraph::SubgraphConfiguration;
@@ -58,8 +62,10 @@ use crate::ApolloRouterError;
 pub(crate) mod cors;
 pub(crate) mod expansion;
 mod experimental;
+pub(crate) mod grpc;
 pub(crate) mod metrics;
 mod persisted_queries;
+pub(crate) mod rest;
 mod schema;
 pub(crate) mod shared;
 pub(crate) mod subgraph;
@@ -107,6 +113,9 @@ pub enum ConfigurationError {
 
     /// could not load certificate authorities: {error}
     CertificateAuthorities { error: String },
+
+    /// could not load certificate pins: {error}
+    CertificatePins { error: String },
 }
 
 /// The configuration for the router.
@@ -528,6 +537,43 @@ impl Configuration {
                 },
             );
         }
+        for route in &self.supergraph.experimental_rest.routes {
+            if let Err(error) = route.validate() {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'supergraph.experimental_rest' route",
+                    error,
+                });
+            }
+            if route.collides_with(&self.supergraph.path) {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'supergraph.experimental_rest' route",
+                    error: format!(
+                        "'{}' collides with the supergraph path '{}'",
+                        route.path, self.supergraph.path
+                    ),
+                });
+            }
+            if self.health_check.enabled
+                && self.health_check.listen == self.supergraph.listen
+                && route.collides_with(&self.health_check.path)
+            {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'supergraph.experimental_rest' route",
+                    error: format!(
+                        "'{}' collides with the health check path '{}'",
+                        route.path, self.health_check.path
+                    ),
+                });
+            }
+        }
+        if self.supergraph.experimental_grpc.enabled {
+            if let Err(error) = self.supergraph.experimental_grpc.validate() {
+                return Err(ConfigurationError::InvalidConfiguration {
+                    message: "invalid 'supergraph.experimental_grpc' service",
+                    error,
+                });
+            }
+        }
 
         // PQs.
         if self.persisted_queries.enabled {
@@ -735,6 +781,26 @@ pub(crate) struct Supergraph {
     /// Log a message if the client closes the connection before the response is sent.
     /// Default: false.
     pub(crate) experimental_log_on_broken_pipe: bool,
+
+    /// REST routes executing GraphQL operations
+    pub(crate) experimental_rest: RestFacade,
+
+    /// gRPC service executing GraphQL operations
+    pub(crate) experimental_grpc: GrpcFacade,
+
+    /// Execute operations sent over websocket connections with the graphql-ws protocol
+    pub(crate) experimental_websocket: ClientWebSocket,
+
+    /// Tell clients which errors can be retried
+    pub(crate) experimental_retryable_errors: RetryableErrors,
+
+    /// Cache of the parsing and validation results of operations
+    pub(crate) experimental_validation_cache: ValidationCache,
+
+    /// Operations up to this size in bytes are parsed and validated on the request task, larger
+    /// ones on a separate thread, to avoid the cost of switching threads for small operations.
+    /// Default: 0, every operation is parsed on a separate thread
+    pub(crate) experimental_inline_parsing_max_bytes: usize,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
@@ -773,6 +839,12 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        experimental_rest: Option<RestFacade>,
+        experimental_grpc: Option<GrpcFacade>,
+        experimental_websocket: Option<ClientWebSocket>,
+        experimental_retryable_errors: Option<RetryableErrors>,
+        experimental_validation_cache: Option<ValidationCache>,
+        experimental_inline_parsing_max_bytes: Option<usize>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(default_graphql_listen),
@@ -792,6 +864,13 @@ impl Supergraph {
             generate_query_fragments: generate_query_fragments.unwrap_or_default(),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            experimental_rest: experimental_rest.unwrap_or_default(),
+            experimental_grpc: experimental_grpc.unwrap_or_default(),
+            experimental_websocket: experimental_websocket.unwrap_or_default(),
+            experimental_retryable_errors: experimental_retryable_errors.unwrap_or_default(),
+            experimental_validation_cache: experimental_validation_cache.unwrap_or_default(),
+            experimental_inline_parsing_max_bytes: experimental_inline_parsing_max_bytes
+                .unwrap_or_default(),
         }
     }
 }
@@ -810,6 +889,12 @@ impl Supergraph {
         generate_query_fragments: Option<bool>,
         early_cancel: Option<bool>,
         experimental_log_on_broken_pipe: Option<bool>,
+        experimental_rest: Option<RestFacade>,
+        experimental_grpc: Option<GrpcFacade>,
+        experimental_websocket: Option<ClientWebSocket>,
+        experimental_retryable_errors: Option<RetryableErrors>,
+        experimental_validation_cache: Option<ValidationCache>,
+        experimental_inline_parsing_max_bytes: Option<usize>,
     ) -> Self {
         Self {
             listen: listen.unwrap_or_else(test_listen),
@@ -829,6 +914,13 @@ impl Supergraph {
             generate_query_fragments: generate_query_fragments.unwrap_or_default(),
             early_cancel: early_cancel.unwrap_or_default(),
             experimental_log_on_broken_pipe: experimental_log_on_broken_pipe.unwrap_or_default(),
+            experimental_rest: experimental_rest.unwrap_or_default(),
+            experimental_grpc: experimental_grpc.unwrap_or_default(),
+            experimental_websocket: experimental_websocket.unwrap_or_default(),
+            experimental_retryable_errors: experimental_retryable_errors.unwrap_or_default(),
+            experimental_validation_cache: experimental_validation_cache.unwrap_or_default(),
+            experimental_inline_parsing_max_bytes: experimental_inline_parsing_max_bytes
+                .unwrap_or_default(),
         }
     }
 }
@@ -856,6 +948,78 @@ impl Supergraph {
     }
 }
 
+/// Websocket connections from clients on the supergraph endpoint
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct ClientWebSocket {
+    /// Set to true to accept websocket connections using the graphql-transport-ws protocol
+    pub(crate) enabled: bool,
+
+    /// Delay after which a connection that did not send its `connection_init` message is closed
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String", default = "default_connection_init_timeout")]
+    pub(crate) connection_init_timeout: Duration,
+
+    /// Set to true to add the cookies of the upgrade request to the operations. Browsers send
+    /// cookies with websocket connections opened by any site, so they are dropped by default
+    pub(crate) forward_cookies: bool,
+}
+
+fn default_connection_init_timeout() -> Duration {
+    Duration::from_secs(10)
+}
+
+impl Default for ClientWebSocket {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            connection_init_timeout: default_connection_init_timeout(),
+            forward_cookies: false,
+        }
+    }
+}
+
+/// Classification of the errors returned to clients as retryable or not
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct RetryableErrors {
+    /// Set to true to add a `retryable` extension to the errors returned to clients
+    pub(crate) enabled: bool,
+
+    /// Delay clients should wait before retrying, set in the `retry_after_ms` extension of
+    /// retryable errors
+    #[serde(with = "humantime_serde")]
+    #[schemars(with = "String", default = "default_retry_backoff")]
+    pub(crate) backoff: Duration,
+
+    /// Whether errors with these codes are retryable, overriding the router's classification
+    pub(crate) codes: HashMap<String, bool>,
+}
+
+fn default_retry_backoff() -> Duration {
+    Duration::from_secs(1)
+}
+
+impl Default for RetryableErrors {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            backoff: default_retry_backoff(),
+            codes: HashMap::new(),
+        }
+    }
+}
+
+/// Cache of the parsing and validation results of operations, including the errors of invalid
+/// operations, by operation hash
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct ValidationCache {
+    /// Number of operations kept in the cache. Defaults to the limit of the in memory query plan
+    /// cache
+    pub(crate) limit: Option<NonZeroUsize>,
+}
+
 /// Configuration for operation limits, parser limits, HTTP limits, etc.
 #[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
 #[serde(deny_unknown_fields, default)]
@@ -919,6 +1083,17 @@ pub(crate) struct Limits {
     /// `"extensions": {"code": "MAX_ALIASES_LIMIT"}`
     pub(crate) max_aliases: Option<u32>,
 
+    /// If set, introspection operations deeper than this maximum are rejected
+    /// like operations exceeding `max_depth`. Defaults to `max_depth`.
+    ///
+    /// Introspection queries sent by tooling nest `ofType` selections deeply,
+    /// so they often need a different limit than other operations.
+    pub(crate) introspection_max_depth: Option<u32>,
+
+    /// If set, introspection operations higher than this maximum are rejected
+    /// like operations exceeding `max_height`. Defaults to `max_height`.
+    pub(crate) introspection_max_height: Option<u32>,
+
     /// If set to true (which is the default is dev mode),
     /// requests that exceed a `max_*` limit are *not* rejected.
     /// Instead they are executed normally, and a warning is logged.
@@ -944,6 +1119,8 @@ impl Default for Limits {
             max_height: None,
             max_root_fields: None,
             max_aliases: None,
+            introspection_max_depth: None,
+            introspection_max_height: None,
             warn_only: false,
             http_max_request_bytes: 2_000_000,
             parser_max_tokens: 15_000,
@@ -1049,6 +1226,11 @@ pub(crate) struct QueryPlanning {
     /// the old schema, if it determines that the schema update does not affect the corresponding query
     pub(crate) experimental_reuse_query_plans: bool,
 
+    /// Evaluate the `@skip` and `@include` conditions of operations with the values of the
+    /// request's variables before computing the query plan cache key, so that operations only
+    /// differing by their conditions share query plans
+    pub(crate) experimental_evaluate_conditions: bool,
+
     /// Set the size of a pool of workers to enable query planning parallelism.
     /// Default: 1.
     pub(crate) experimental_parallelism: AvailableParallelism,
@@ -1071,6 +1253,7 @@ impl Default for QueryPlanning {
             experimental_parallelism: Default::default(),
             experimental_paths_limit: Default::default(),
             experimental_reuse_query_plans: Default::default(),
+            experimental_evaluate_conditions: Default::default(),
             legacy_introspection_caching: default_legacy_introspection_caching(),
         }
     }
@@ -1377,6 +1560,11 @@ pub(crate) struct TlsClient {
     pub(crate) certificate_authorities: Option<String>,
     /// client certificate authentication
     pub(crate) client_authentication: Option<TlsClientAuth>,
+    /// list of SHA-256 digests of SubjectPublicKeyInfo (SPKI) structures, in hexadecimal format.
+    /// If set, the public key of the server's certificate must match one of them, in addition to
+    /// passing the usual validation. Pins set for a subgraph replace those of `all`, and an empty
+    /// list disables pinning for the subgraph
+    pub(crate) experimental_spki_pins: Option<Vec<String>>,
 }
 
 #[buildstructor::buildstructor]
@@ -1385,10 +1573,12 @@ impl TlsClient {
     pub(crate) fn new(
         certificate_authorities: Option<String>,
         client_authentication: Option<TlsClientAuth>,
+        experimental_spki_pins: Option<Vec<String>>,
     ) -> Self {
         Self {
             certificate_authorities,
             client_authentication,
+            experimental_spki_pins,
         }
     }
 }
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
@@ -20,6 +22,9 @@ pub struct PersistedQueries {
 
     /// Enables using a local copy of the persisted query manifest to safelist operations
     pub experimental_local_manifests: Option<Vec<String>>,
+
+    /// Rejects persisted query manifests that are not signed by one of the configured keys
+    pub experimental_manifest_signature: Option<PersistedQueriesManifestSignature>,
 }
 
 #[cfg(test)]
@@ -32,6 +37,7 @@ impl PersistedQueries {
         safelist: Option<PersistedQueriesSafelist>,
         experimental_prewarm_query_plan_cache: Option<bool>,
         experimental_local_manifests: Option<Vec<String>>,
+        experimental_manifest_signature: Option<PersistedQueriesManifestSignature>,
     ) -> Self {
         Self {
             enabled: enabled.unwrap_or_else(default_pq),
@@ -40,6 +46,7 @@ impl PersistedQueries {
             experimental_prewarm_query_plan_cache: experimental_prewarm_query_plan_cache
                 .unwrap_or_else(default_prewarm_query_plan_cache),
             experimental_local_manifests,
+            experimental_manifest_signature,
         }
     }
 }
@@ -55,6 +62,19 @@ pub struct PersistedQueriesSafelist {
     pub require_id: bool,
 }
 
+/// Persisted query manifest signature configuration
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub struct PersistedQueriesManifestSignature {
+    /// Path to a JSON Web Key Set file containing the keys verifying the manifest signatures
+    pub jwks: String,
+
+    /// Claims the manifest signatures must contain with these values, like the build pipeline
+    /// publishing the operations
+    #[serde(default)]
+    pub claims: HashMap<String, serde_json::Value>,
+}
+
 #[cfg(test)]
 #[buildstructor::buildstructor]
 impl PersistedQueriesSafelist {
@@ -75,6 +95,7 @@ impl Default for PersistedQueries {
             log_unknown: default_log_unknown(),
             experimental_prewarm_query_plan_cache: default_prewarm_query_plan_cache(),
             experimental_local_manifests: None,
+            experimental_manifest_signature: None,
         }
     }
 }
@@ -0,0 +1,178 @@
+//! Configuration of the REST facade, mapping HTTP routes to GraphQL operations.
+
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+
+/// Expose GraphQL operations as REST routes on the supergraph listener
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct RestFacade {
+    /// Routes mapped to GraphQL operations
+    pub(crate) routes: Vec<RestRoute>,
+}
+
+/// A REST route executing a GraphQL operation
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct RestRoute {
+    /// HTTP method of the route
+    #[serde(default)]
+    pub(crate) method: RestMethod,
+    /// Path of the route, like `/api/users/{id}`. Path parameters are bound to the variables of
+    /// the same name, like query parameters
+    pub(crate) path: String,
+    /// ID of the operation in the persisted query manifest
+    #[serde(default)]
+    pub(crate) persisted_query_id: Option<String>,
+    /// GraphQL document of the operation, when it is not in the persisted query manifest
+    #[serde(default)]
+    pub(crate) query: Option<String>,
+    /// Name of the operation to execute, if the document contains several operations
+    #[serde(default)]
+    pub(crate) operation_name: Option<String>,
+    /// Types of the variables bound to path and query parameters. Parameters are passed as strings
+    /// by default
+    #[serde(default)]
+    pub(crate) parameter_types: HashMap<String, ParameterType>,
+}
+
+/// HTTP method of a REST route
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "UPPERCASE")]
+pub(crate) enum RestMethod {
+    #[default]
+    Get,
+    Post,
+    Put,
+    Patch,
+    Delete,
+}
+
+/// Type of the variable bound to a parameter. `json` parameters are parsed as JSON
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ParameterType {
+    #[default]
+    String,
+    Int,
+    Float,
+    Boolean,
+    Json,
+}
+
+impl RestRoute {
+    /// Checks the route, returning an explanation of the first problem found
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !self.path.starts_with('/') {
+            return Err(format!("'{}' must start with '/'", self.path));
+        }
+        for segment in self.path.split('/') {
+            let parameter = segment
+                .strip_prefix('{')
+                .and_then(|segment| segment.strip_suffix('}'));
+            let valid = match parameter {
+                Some(name) => {
+                    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+                }
+                None => !segment.contains(['{', '}', ':', '*']),
+            };
+            if !valid {
+                return Err(format!(
+                    "'{}' has an invalid segment '{segment}', parameters must be whole segments like '{{id}}'",
+                    self.path
+                ));
+            }
+        }
+        match (&self.persisted_query_id, &self.query) {
+            (Some(_), None) | (None, Some(_)) => Ok(()),
+            _ => Err(format!(
+                "'{}' must set exactly one of 'persisted_query_id' and 'query'",
+                self.path
+            )),
+        }
+    }
+
+    /// Whether the route can match the same requests as another path of the listener, written in
+    /// the syntax of the supergraph path: parameters start with ':' and a '*' ends a wildcard
+    pub(crate) fn collides_with(&self, path: &str) -> bool {
+        let mut segments = self.path.split('/');
+        let mut other_segments = path.split('/');
+        loop {
+            match (segments.next(), other_segments.next()) {
+                (None, None) | (_, Some("*")) => return true,
+                (Some(segment), Some(other)) => {
+                    let matches = segment == other
+                        || segment.starts_with('{')
+                        || other.starts_with(':')
+                        || other
+                            .strip_suffix('*')
+                            .is_some_and(|prefix| segment.starts_with(prefix));
+                    if !matches {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// The path in the syntax of the HTTP router, where parameters start with ':'
+    pub(crate) fn axum_path(&self) -> String {
+        self.path
+            .split('/')
+            .map(|segment| match segment.strip_prefix('{') {
+                Some(parameter) => format!(":{}", parameter.trim_end_matches('}')),
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(path: &str) -> RestRoute {
+        RestRoute {
+            method: RestMethod::Get,
+            path: path.to_string(),
+            persisted_query_id: Some("id".to_string()),
+            query: None,
+            operation_name: None,
+            parameter_types: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn route_paths() {
+        assert!(route("/api/users/{id}").validate().is_ok());
+        assert_eq!(route("/api/users/{id}").axum_path(), "/api/users/:id");
+        assert_eq!(route("/api/users").axum_path(), "/api/users");
+        assert!(route("api/users").validate().is_err());
+        assert!(route("/api/users/user-{id}").validate().is_err());
+        assert!(route("/api/users/:id").validate().is_err());
+        assert!(route("/api/{}").validate().is_err());
+
+        let mut both = route("/api/users");
+        both.query = Some("{ users { id } }".to_string());
+        assert!(both.validate().is_err());
+    }
+
+    #[test]
+    fn route_collisions() {
+        assert!(route("/graphql").collides_with("/graphql"));
+        assert!(route("/{name}").collides_with("/graphql"));
+        assert!(route("/api/users").collides_with("/api/*"));
+        assert!(route("/api/users").collides_with("/*"));
+        assert!(route("/api/users").collides_with("/:project/users"));
+        assert!(route("/api/users").collides_with("/api/us*"));
+        assert!(!route("/api/users").collides_with("/"));
+        assert!(!route("/api/users").collides_with("/graphql"));
+        assert!(!route("/api/users").collides_with("/api/users/:id"));
+        assert!(!route("/health/{id}").collides_with("/health"));
+    }
+}
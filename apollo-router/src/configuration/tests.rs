@@ -753,6 +753,56 @@ fn test_configuration_validate_and_sanitize() {
         .is_err());
 }
 
+#[test]
+fn test_rest_route_collisions() {
+    let config = |yaml: &str| {
+        validate_yaml_configuration(yaml, Expansion::default().unwrap(), Mode::NoUpgrade)
+    };
+    let error = config(
+        r#"
+supergraph:
+  path: /api/*
+  experimental_rest:
+    routes:
+      - path: /api/users
+        query: "{ users { id } }"
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("collides with the supergraph path"));
+
+    let error = config(
+        r#"
+health_check:
+  listen: 127.0.0.1:4000
+supergraph:
+  listen: 127.0.0.1:4000
+  experimental_rest:
+    routes:
+      - path: /health
+        query: "{ users { id } }"
+"#,
+    )
+    .unwrap_err();
+    assert!(error
+        .to_string()
+        .contains("collides with the health check path"));
+
+    // the health check has its own listener by default
+    assert!(config(
+        r#"
+supergraph:
+  experimental_rest:
+    routes:
+      - path: /health
+        query: "{ users { id } }"
+"#,
+    )
+    .is_ok());
+}
+
 #[test]
 fn load_tls() {
     let mut cert_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
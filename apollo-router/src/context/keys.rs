@@ -0,0 +1,228 @@
+//! Typed keys for [`Context`](crate::Context) entries.
+//!
+//! Context entries are stored as JSON under string keys, so that Rhai scripts and coprocessors
+//! can read and write them. A [`ContextKey`] pairs such a string with the type of its value, so
+//! native plugins get the value back with the type it was inserted with.
+//!
+//! Keys used by the router itself are listed in [`RESERVED_KEYS`], and every key starting with
+//! `apollo` is reserved for the router. [`ContextKey::new`] refuses reserved names, and as it is a
+//! `const fn`, defining a key that collides with the router's is a compile time error:
+//!
+//! ```compile_fail
+//! use apollo_router::ContextKey;
+//!
+//! const COLLIDING: ContextKey<String> = ContextKey::new("operation_name");
+//! let _ = COLLIDING;
+//! ```
+
+use std::collections::HashMap;
+use std::fmt;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use serde_json_bytes::Value;
+
+use crate::query_planner::OperationKind;
+use crate::query_planner::PlanNode;
+
+/// Prefix of the keys reserved for the router
+const RESERVED_PREFIX: &str = "apollo";
+
+/// Key of the resolved operation name. This is subject to change and should not be relied on.
+pub(crate) const OPERATION_NAME: ContextKey<Option<String>> =
+    ContextKey::reserved("operation_name");
+/// Key of the resolved operation kind. This is subject to change and should not be relied on.
+pub(crate) const OPERATION_KIND: ContextKey<OperationKind> = ContextKey::reserved("operation_kind");
+/// Key to know if the response body contains at least 1 GraphQL error
+pub(crate) const CONTAINS_GRAPHQL_ERROR: ContextKey<bool> =
+    ContextKey::reserved("apollo::telemetry::contains_graphql_error");
+/// Key of the claims of a validated JWT
+pub(crate) const JWT_CLAIMS: ContextKey<Value> =
+    ContextKey::reserved("apollo_authentication::JWT::claims");
+/// Key of a value added to the query plan cache key, to separate query plans per tenant or any
+/// other criteria set by a plugin
+pub(crate) const QUERY_PLAN_CACHE_KEY: ContextKey<Value> =
+    ContextKey::reserved("apollo_query_plan_cache::key");
+/// Key of the feature flags evaluated for the request, by flag name
+pub(crate) const FEATURE_FLAGS: ContextKey<HashMap<String, bool>> =
+    ContextKey::reserved("apollo_feature_flags::flags");
+/// Key of the experiment buckets of the request, by experiment name
+pub(crate) const EXPERIMENT_BUCKETS: ContextKey<HashMap<String, String>> =
+    ContextKey::reserved("apollo_experiments::buckets");
+/// Prefix of the keys holding the bucket of a single experiment, followed by the experiment name.
+/// Those keys start with `apollo`, so they are reserved without being listed in [`RESERVED_KEYS`]
+pub(crate) const EXPERIMENT_BUCKET_PREFIX: &str = "apollo_experiments::bucket::";
+/// Key set when the query plan should be exposed in the response extensions
+pub(crate) const EXPOSE_QUERY_PLAN_ENABLED: ContextKey<bool> =
+    ContextKey::reserved("experimental::expose_query_plan.enabled");
+/// Key of the query plan exposed in the response extensions
+pub(crate) const EXPOSED_QUERY_PLAN: ContextKey<Arc<PlanNode>> =
+    ContextKey::reserved("experimental::expose_query_plan.plan");
+/// Key of the formatted query plan exposed in the response extensions
+pub(crate) const EXPOSED_FORMATTED_QUERY_PLAN: ContextKey<Option<Arc<String>>> =
+    ContextKey::reserved("experimental::expose_query_plan.formatted_plan");
+
+/// Keys of the context entries written by the router.
+///
+/// Those entries can be read by plugins, but their content is owned by the router. Keys starting
+/// with `apollo` that are not listed here are reserved too.
+pub const RESERVED_KEYS: &[&str] = &[
+    OPERATION_NAME.name(),
+    OPERATION_KIND.name(),
+    CONTAINS_GRAPHQL_ERROR.name(),
+    JWT_CLAIMS.name(),
+    QUERY_PLAN_CACHE_KEY.name(),
+    FEATURE_FLAGS.name(),
+    EXPERIMENT_BUCKETS.name(),
+    EXPOSE_QUERY_PLAN_ENABLED.name(),
+    EXPOSED_QUERY_PLAN.name(),
+    EXPOSED_FORMATTED_QUERY_PLAN.name(),
+];
+
+/// The key of a [`Context`](crate::Context) entry holding a value of type `V`.
+///
+/// Keys are meant to be declared as constants, then used with [`Context::get_typed`],
+/// [`Context::insert_typed`] and [`Context::upsert_typed`]:
+///
+/// ```
+/// use apollo_router::Context;
+/// use apollo_router::ContextKey;
+///
+/// const REQUEST_COST: ContextKey<u64> = ContextKey::new("my_plugin::request_cost");
+///
+/// let context = Context::new();
+/// context.insert_typed(&REQUEST_COST, 42).unwrap();
+/// assert_eq!(context.get_typed(&REQUEST_COST).unwrap(), Some(42));
+/// ```
+///
+/// [`Context::get_typed`]: crate::Context::get_typed
+/// [`Context::insert_typed`]: crate::Context::insert_typed
+/// [`Context::upsert_typed`]: crate::Context::upsert_typed
+pub struct ContextKey<V> {
+    name: &'static str,
+    value: PhantomData<fn() -> V>,
+}
+
+impl<V> ContextKey<V> {
+    /// Declare a key named `name`.
+    ///
+    /// # Panics
+    ///
+    /// If `name` is reserved for the router. When the key is declared as a constant, this is a
+    /// compile time error.
+    pub const fn new(name: &'static str) -> Self {
+        if is_reserved(name) {
+            panic!("context keys starting with `apollo` or used by the router are reserved");
+        }
+        Self::reserved(name)
+    }
+
+    /// Declare a key of the router, bypassing the reserved names check
+    pub(crate) const fn reserved(name: &'static str) -> Self {
+        Self {
+            name,
+            value: PhantomData,
+        }
+    }
+
+    /// The string under which the value is stored, as seen by Rhai scripts and coprocessors
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+// implemented by hand, deriving would require `V` to implement the traits
+impl<V> Clone for ContextKey<V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<V> Copy for ContextKey<V> {}
+
+impl<V> fmt::Debug for ContextKey<V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ContextKey").field(&self.name).finish()
+    }
+}
+
+impl<V> From<ContextKey<V>> for String {
+    fn from(key: ContextKey<V>) -> Self {
+        key.name.to_string()
+    }
+}
+
+impl<V> From<&ContextKey<V>> for String {
+    fn from(key: &ContextKey<V>) -> Self {
+        key.name.to_string()
+    }
+}
+
+/// Whether `name` is reserved for the router
+pub const fn is_reserved(name: &str) -> bool {
+    if starts_with(name.as_bytes(), RESERVED_PREFIX.as_bytes()) {
+        return true;
+    }
+    let mut i = 0;
+    while i < RESERVED_KEYS.len() {
+        if equals(name.as_bytes(), RESERVED_KEYS[i].as_bytes()) {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+const fn starts_with(value: &[u8], prefix: &[u8]) -> bool {
+    if value.len() < prefix.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < prefix.len() {
+        if value[i] != prefix[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+const fn equals(left: &[u8], right: &[u8]) -> bool {
+    left.len() == right.len() && starts_with(left, right)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Context;
+
+    const COUNT: ContextKey<u64> = ContextKey::new("test::count");
+
+    #[test]
+    fn reserved_names() {
+        assert!(is_reserved("operation_name"));
+        assert!(is_reserved("apollo_authentication::JWT::claims"));
+        assert!(is_reserved("apollo::anything"));
+        assert!(is_reserved("apollo_experiments::bucket::checkout"));
+        assert!(is_reserved("experimental::expose_query_plan.plan"));
+        assert!(!is_reserved("operation"));
+        assert!(!is_reserved("my_plugin::apollo"));
+        assert!(std::panic::catch_unwind(|| ContextKey::<u64>::new("operation_kind")).is_err());
+    }
+
+    #[test]
+    fn typed_access() {
+        let context = Context::new();
+        assert_eq!(context.get_typed(&COUNT).unwrap(), None);
+        context.upsert_typed(&COUNT, |count| count + 1).unwrap();
+        assert_eq!(context.insert_typed(&COUNT, 5).unwrap(), Some(1));
+        assert_eq!(context.get_typed(&COUNT).unwrap(), Some(5));
+
+        // the same entry is visible as JSON
+        assert_eq!(context.get_json_value("test::count"), Some(5.into()));
+        context
+            .insert("test::count", "not a number".to_string())
+            .unwrap();
+        assert!(context.get_typed(&COUNT).is_err());
+    }
+}
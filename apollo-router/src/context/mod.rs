@@ -23,13 +23,16 @@ use crate::json_ext::Value;
 use crate::services::layers::query_analysis::ParsedDocument;
 
 pub(crate) mod extensions;
+pub(crate) mod keys;
+
+pub use keys::ContextKey;
 
 /// The key of the resolved operation name. This is subject to change and should not be relied on.
-pub(crate) const OPERATION_NAME: &str = "operation_name";
+pub(crate) const OPERATION_NAME: &str = keys::OPERATION_NAME.name();
 /// The key of the resolved operation kind. This is subject to change and should not be relied on.
-pub(crate) const OPERATION_KIND: &str = "operation_kind";
+pub(crate) const OPERATION_KIND: &str = keys::OPERATION_KIND.name();
 /// The key to know if the response body contains at least 1 GraphQL error
-pub(crate) const CONTAINS_GRAPHQL_ERROR: &str = "apollo::telemetry::contains_graphql_error";
+pub(crate) const CONTAINS_GRAPHQL_ERROR: &str = keys::CONTAINS_GRAPHQL_ERROR.name();
 
 /// Holds [`Context`] entries.
 pub(crate) type Entries = Arc<DashMap<String, Value>>;
@@ -200,6 +203,40 @@ impl Context {
         result.map_err(|e| e.into())
     }
 
+    /// Get the value of a typed key from the context.
+    ///
+    /// Semantics are the same as [`Context::get`].
+    pub fn get_typed<V>(&self, key: &ContextKey<V>) -> Result<Option<V>, BoxError>
+    where
+        V: for<'de> serde::Deserialize<'de>,
+    {
+        self.get(key.name())
+    }
+
+    /// Insert the value of a typed key in the context.
+    ///
+    /// Semantics are the same as [`Context::insert`].
+    pub fn insert_typed<V>(&self, key: &ContextKey<V>, value: V) -> Result<Option<V>, BoxError>
+    where
+        V: for<'de> serde::Deserialize<'de> + Serialize,
+    {
+        self.insert(key.name(), value)
+    }
+
+    /// Upsert the value of a typed key in the context.
+    ///
+    /// Semantics are the same as [`Context::upsert`].
+    pub fn upsert_typed<V>(
+        &self,
+        key: &ContextKey<V>,
+        upsert: impl FnOnce(V) -> V,
+    ) -> Result<(), BoxError>
+    where
+        V: for<'de> serde::Deserialize<'de> + Serialize + Default,
+    {
+        self.upsert(key.name(), upsert)
+    }
+
     /// Upsert a JSON value in the context using the provided key and resolving
     /// function.
     ///
@@ -21,8 +21,10 @@ use crate::graphql::ErrorExtension;
 use crate::graphql::IntoGraphQLErrors;
 use crate::graphql::Location as ErrorLocation;
 use crate::graphql::Response;
+use crate::json_ext::Object;
 use crate::json_ext::Path;
 use crate::json_ext::Value;
+use crate::spec::operation_limits::ExceededLimit;
 use crate::spec::operation_limits::OperationLimits;
 use crate::spec::SpecError;
 
@@ -42,6 +44,15 @@ pub(crate) enum FetchError {
         name: String,
     },
 
+    /// invalid value for argument '{argument}': {reason}
+    ValidationConstraintViolation {
+        /// Coordinate of the argument.
+        argument: String,
+
+        /// The limit exceeded by the value.
+        reason: String,
+    },
+
     /// query could not be planned: {reason}
     ValidationPlanningError {
         /// The failure reason.
@@ -172,6 +183,7 @@ impl ErrorExtension for FetchError {
     fn extension_code(&self) -> String {
         match self {
             FetchError::ValidationInvalidTypeVariable { .. } => "VALIDATION_INVALID_TYPE_VARIABLE",
+            FetchError::ValidationConstraintViolation { .. } => "VALIDATION_CONSTRAINT_VIOLATION",
             FetchError::ValidationPlanningError { .. } => "VALIDATION_PLANNING_ERROR",
             FetchError::SubrequestMalformedResponse { .. } => "SUBREQUEST_MALFORMED_RESPONSE",
             FetchError::SubrequestUnexpectedPatchResponse { .. } => {
@@ -307,7 +319,7 @@ pub(crate) enum QueryPlannerError {
     Introspection(IntrospectionError),
 
     /// complexity limit exceeded
-    LimitExceeded(OperationLimits<bool>),
+    LimitExceeded(OperationLimits<Option<ExceededLimit>>),
 
     /// Unauthorized field or type
     Unauthorized(Vec<Path>),
@@ -359,12 +371,25 @@ impl IntoGraphQLErrors for QueryPlannerError {
                 aliases,
             }) => {
                 let mut errors = Vec::new();
-                let mut build = |exceeded, code, message| {
-                    if exceeded {
+                let mut build = |exceeded: Option<ExceededLimit>, code, message| {
+                    if let Some(exceeded) = exceeded {
+                        let mut extensions = Object::new();
+                        extensions.insert("limit.measured", exceeded.measured.into());
+                        extensions.insert("limit.max", exceeded.max.into());
+                        if let Some(path) = exceeded.path {
+                            extensions.insert(
+                                "limit.path",
+                                Value::Array(path.into_iter().map(Value::from).collect()),
+                            );
+                        }
+                        if let Some(coordinate) = exceeded.coordinate {
+                            extensions.insert("limit.coordinate", coordinate.into());
+                        }
                         errors.push(
                             Error::builder()
                                 .message(message)
                                 .extension_code(code)
+                                .extensions(extensions)
                                 .build(),
                         )
                     }
@@ -473,8 +498,8 @@ impl From<router_bridge::error::Error> for QueryPlannerError {
         QueryPlannerError::RouterBridgeError(error)
     }
 }
-impl From<OperationLimits<bool>> for QueryPlannerError {
-    fn from(error: OperationLimits<bool>) -> Self {
+impl From<OperationLimits<Option<ExceededLimit>>> for QueryPlannerError {
+    fn from(error: OperationLimits<Option<ExceededLimit>>) -> Self {
         QueryPlannerError::LimitExceeded(error)
     }
 }
@@ -7,6 +7,7 @@ use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering;
+use std::sync::Arc;
 use std::time::Duration;
 
 use anyhow::anyhow;
@@ -17,6 +18,8 @@ use clap::Args;
 use clap::CommandFactory;
 use clap::Parser;
 use clap::Subcommand;
+use http::HeaderName;
+use http::HeaderValue;
 #[cfg(any(feature = "dhat-heap", feature = "dhat-ad-hoc"))]
 use once_cell::sync::OnceCell;
 use regex::Captures;
@@ -24,28 +27,42 @@ use regex::Regex;
 use url::ParseError;
 use url::Url;
 
+use crate::bench;
+use crate::bench::read_operations;
+use crate::bench::Target;
 use crate::configuration::generate_config_schema;
 use crate::configuration::generate_upgrade;
 use crate::configuration::Discussed;
+use crate::diagnose::Bundle;
 use crate::metrics::meter_provider;
+use crate::normalize::normalize;
+use crate::normalize::DocumentReport;
 use crate::plugin::plugins;
 use crate::plugins::telemetry::reload::init_telemetry;
 use crate::router::ConfigurationSource;
 use crate::router::RouterHttpServer;
 use crate::router::SchemaSource;
 use crate::router::ShutdownSource;
+use crate::spec::Schema;
+use crate::subgraph_check;
 use crate::uplink::Endpoints;
 use crate::uplink::UplinkConfig;
+use crate::Configuration;
 use crate::LicenseSource;
 
 #[cfg(all(
     feature = "global-allocator",
+    not(feature = "mimalloc-allocator"),
     not(feature = "dhat-heap"),
     target_os = "linux"
 ))]
 #[global_allocator]
 static ALLOC: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
 
+#[cfg(all(feature = "mimalloc-allocator", not(feature = "dhat-heap")))]
+#[global_allocator]
+static ALLOC: mimalloc::MiMalloc = mimalloc::MiMalloc;
+
 // Note: the dhat-heap and dhat-ad-hoc features should not be both enabled. We name our functions
 // and variables identically to prevent this from happening.
 
@@ -60,6 +77,7 @@ pub(crate) static mut DHAT_HEAP_PROFILER: OnceCell<dhat::Profiler> = OnceCell::n
 pub(crate) static mut DHAT_AD_HOC_PROFILER: OnceCell<dhat::Profiler> = OnceCell::new();
 
 pub(crate) const APOLLO_ROUTER_DEV_ENV: &str = "APOLLO_ROUTER_DEV";
+pub(crate) const APOLLO_ROUTER_MOCK_ENV: &str = "APOLLO_ROUTER_MOCK";
 
 // Note: Constructor/Destructor functions may not play nicely with tracing, since they run after
 // main completes, so don't use tracing, use println!() and eprintln!()..
@@ -114,6 +132,95 @@ extern "C" fn drop_ad_hoc_profiler() {
 enum Commands {
     /// Configuration subcommands.
     Config(ConfigSubcommandArgs),
+
+    /// Replay operations at a fixed rate and report latencies.
+    ///
+    /// Without `--target`, the operations are executed by an in-process router using the
+    /// `--config` and `--supergraph` files, with subgraphs returning empty responses.
+    Bench(BenchArgs),
+
+    /// Subgraph subcommands.
+    Subgraph(SubgraphSubcommandArgs),
+
+    /// Print a support bundle with secrets redacted, to attach to support tickets.
+    ///
+    /// The bundle contains the `--config` file with its secrets redacted, the hash of the
+    /// `--supergraph` file, the configured plugins and a summary of the environment.
+    Diagnose(DiagnoseArgs),
+
+    /// Validate operations against the `--supergraph` and print them as the router processes them.
+    ///
+    /// For each valid operation, the report contains the document normalized by the router, its
+    /// query hash and its signature in usage reports. Fails if any operation is invalid.
+    Normalize(NormalizeArgs),
+}
+
+#[derive(Args, Debug)]
+struct NormalizeArgs {
+    /// Files containing GraphQL operation documents.
+    #[clap(value_parser, required = true)]
+    documents: Vec<PathBuf>,
+
+    /// Name of the operation to use in documents containing several operations.
+    #[clap(long)]
+    operation_name: Option<String>,
+}
+
+#[derive(Args, Debug)]
+struct DiagnoseArgs {
+    /// File the bundle is written to, instead of the standard output.
+    #[clap(long, short = 'o')]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+struct BenchArgs {
+    /// File of operations to replay, with one GraphQL request in JSON per line.
+    #[clap(value_parser)]
+    operations: PathBuf,
+
+    /// URL of a running router to send the operations to.
+    #[clap(long)]
+    target: Option<Url>,
+
+    /// Number of requests sent per second.
+    #[clap(long, default_value = "100")]
+    rps: u32,
+
+    /// Duration of the run.
+    #[clap(long, default_value = "10s", value_parser = humantime::parse_duration)]
+    duration: Duration,
+}
+
+#[derive(Args, Debug)]
+struct SubgraphSubcommandArgs {
+    /// Subcommands
+    #[clap(subcommand)]
+    command: SubgraphSubcommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum SubgraphSubcommand {
+    /// Probe a subgraph for federation compatibility and print a conformance report.
+    Check {
+        /// URL of the subgraph.
+        #[clap(value_parser)]
+        url: Url,
+
+        /// Header added to the requests sent to the subgraph, as `name: value`.
+        #[clap(long = "header", short = 'H', value_parser = parse_header)]
+        headers: Vec<(HeaderName, HeaderValue)>,
+    },
+}
+
+fn parse_header(header: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = header
+        .split_once(':')
+        .ok_or_else(|| format!("invalid header '{header}', expected 'name: value'"))?;
+    Ok((
+        HeaderName::try_from(name.trim()).map_err(|e| e.to_string())?,
+        HeaderValue::try_from(value.trim()).map_err(|e| e.to_string())?,
+    ))
 }
 
 #[derive(Args, Debug)]
@@ -186,6 +293,14 @@ pub struct Opt {
     )]
     dev: bool,
 
+    /// Serve generated data for all operations instead of calling subgraphs.
+    #[clap(
+        env = APOLLO_ROUTER_MOCK_ENV,
+        long = "mock",
+        action(ArgAction::SetTrue)
+    )]
+    mock: bool,
+
     /// Schema location relative to the project directory.
     #[clap(
         short,
@@ -247,6 +362,11 @@ pub struct Opt {
     /// Display version and exit.
     #[clap(action = ArgAction::SetTrue, long, short = 'V')]
     pub(crate) version: bool,
+
+    /// Run as the Windows service with this name, registered to start the router with this option.
+    #[cfg(windows)]
+    #[clap(long, env = "APOLLO_ROUTER_WINDOWS_SERVICE")]
+    windows_service: Option<String>,
 }
 
 // Add a filter to global log level settings so that the level only applies to the router.
@@ -324,6 +444,17 @@ pub fn main() -> Result<()> {
     #[cfg(feature = "dhat-ad-hoc")]
     create_ad_hoc_profiler();
 
+    let opt = Opt::parse();
+    #[cfg(windows)]
+    if let Some(name) = opt.windows_service.clone() {
+        return crate::service_manager::windows::run(name, opt);
+    }
+
+    runtime()?.block_on(Executable::builder().cli_args(opt).start())
+}
+
+/// Builds the Tokio runtime running the router
+pub(crate) fn runtime() -> Result<tokio::runtime::Runtime> {
     let mut builder = tokio::runtime::Builder::new_multi_thread();
     builder.enable_all();
     if let Some(nb) = std::env::var("APOLLO_ROUTER_NUM_CORES")
@@ -332,8 +463,7 @@ pub fn main() -> Result<()> {
     {
         builder.worker_threads(nb);
     }
-    let runtime = builder.build()?;
-    runtime.block_on(Executable::builder().start())
+    Ok(builder.build()?)
 }
 
 /// Entry point into creating a router executable with more customization than [`main`].
@@ -445,6 +575,21 @@ impl Executable {
                 Discussed::new().print_preview();
                 Ok(())
             }
+            Some(Commands::Bench(args)) => Self::bench(args, &opt).await,
+            Some(Commands::Subgraph(SubgraphSubcommandArgs {
+                command: SubgraphSubcommand::Check { url, headers },
+            })) => {
+                let report =
+                    subgraph_check::run(url.clone(), headers.iter().cloned().collect()).await;
+                print!("{report}");
+                if report.has_failures() {
+                    Err(anyhow!("the subgraph failed some of the checks"))
+                } else {
+                    Ok(())
+                }
+            }
+            Some(Commands::Diagnose(args)) => Self::diagnose(args, &opt),
+            Some(Commands::Normalize(args)) => Self::normalize(args, &opt),
             None => Self::inner_start(shutdown, schema, config, license, opt).await,
         };
 
@@ -459,6 +604,86 @@ impl Executable {
         result
     }
 
+    async fn bench(args: &BenchArgs, opt: &Opt) -> Result<()> {
+        let target = match &args.target {
+            Some(url) => Target::Remote(url.clone()),
+            None => {
+                let supergraph_path = opt.supergraph_path.as_ref().ok_or_else(|| {
+                    anyhow!("--supergraph is required to run the operations in process")
+                })?;
+                let configuration = match &opt.config_path {
+                    Some(config_path) => {
+                        std::fs::read_to_string(config_path)?.parse::<Configuration>()?
+                    }
+                    None => Configuration::default(),
+                };
+                Target::InProcess {
+                    configuration: Arc::new(configuration),
+                    schema: std::fs::read_to_string(supergraph_path)?,
+                }
+            }
+        };
+
+        let operations = read_operations(&args.operations)?;
+        let report = bench::run(target, operations, args.rps, args.duration).await?;
+        println!("{report}");
+        Ok(())
+    }
+
+    fn diagnose(args: &DiagnoseArgs, opt: &Opt) -> Result<()> {
+        let configuration = match &opt.config_path {
+            Some(config_path) => std::fs::read_to_string(config_path)?.parse::<Configuration>()?,
+            None => Configuration::default(),
+        };
+        let schema = opt
+            .supergraph_path
+            .as_ref()
+            .map(std::fs::read_to_string)
+            .transpose()?;
+        let bundle = serde_json::to_string_pretty(&Bundle::new(&configuration, schema.as_deref()))?;
+        match &args.output {
+            Some(output) => std::fs::write(output, bundle)?,
+            None => println!("{bundle}"),
+        }
+        Ok(())
+    }
+
+    fn normalize(args: &NormalizeArgs, opt: &Opt) -> Result<()> {
+        let supergraph_path = opt
+            .supergraph_path
+            .as_ref()
+            .ok_or_else(|| anyhow!("--supergraph is required to validate operations"))?;
+        let configuration = match &opt.config_path {
+            Some(config_path) => std::fs::read_to_string(config_path)?.parse::<Configuration>()?,
+            None => Configuration::default(),
+        };
+        let schema = Schema::parse(&std::fs::read_to_string(supergraph_path)?, &configuration)?;
+
+        let reports = args
+            .documents
+            .iter()
+            .map(|document| {
+                let query = std::fs::read_to_string(document)?;
+                Ok(DocumentReport {
+                    document: document.clone(),
+                    report: normalize(
+                        &schema,
+                        &configuration,
+                        &query,
+                        args.operation_name.as_deref(),
+                    ),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        println!("{}", serde_json::to_string_pretty(&reports)?);
+
+        if reports.iter().all(|document| document.report.is_valid()) {
+            Ok(())
+        } else {
+            Err(anyhow!("some operations are invalid"))
+        }
+    }
+
     async fn inner_start(
         shutdown: Option<ShutdownSource>,
         schema: Option<SchemaSource>,
@@ -9,14 +9,18 @@ use tower::BoxError;
 
 use crate::cache::storage::CacheStorage;
 use crate::graphql::Response;
+use crate::query_planner::fetch::QueryHash;
 use crate::query_planner::QueryPlanResult;
 
 const DEFAULT_INTROSPECTION_CACHE_CAPACITY: NonZeroUsize =
     unsafe { NonZeroUsize::new_unchecked(5) };
 
 /// A cache containing our well known introspection queries.
+///
+/// Responses are keyed by the hash of the query, which also covers the schema, so that queries
+/// differing only by their formatting share the same response.
 pub(crate) struct Introspection {
-    cache: CacheStorage<String, Response>,
+    cache: CacheStorage<QueryHash, Response>,
     planner: Arc<Planner<QueryPlanResult>>,
 }
 
@@ -38,30 +42,34 @@ impl Introspection {
     #[cfg(test)]
     pub(crate) async fn from_cache(
         planner: Arc<Planner<QueryPlanResult>>,
-        cache: HashMap<String, Response>,
+        cache: HashMap<QueryHash, Response>,
     ) -> Result<Self, BoxError> {
         let this = Self::with_capacity(planner, cache.len().try_into().unwrap()).await?;
 
-        for (query, response) in cache.into_iter() {
-            this.cache.insert(query, response).await;
+        for (hash, response) in cache.into_iter() {
+            this.cache.insert(hash, response).await;
         }
         Ok(this)
     }
 
     /// Execute an introspection and cache the response.
-    pub(crate) async fn execute(&self, query: String) -> Result<Response, IntrospectionError> {
-        if let Some(response) = self.cache.get(&query, |_| Ok(())).await {
+    pub(crate) async fn execute(
+        &self,
+        query: String,
+        hash: QueryHash,
+    ) -> Result<Response, IntrospectionError> {
+        if let Some(response) = self.cache.get(&hash, |_| Ok(())).await {
             return Ok(response);
         }
 
         // Do the introspection query and cache it
-        let response =
-            self.planner
-                .introspect(query.clone())
-                .await
-                .map_err(|_e| IntrospectionError {
-                    message: String::from("cannot find the introspection response").into(),
-                })?;
+        let response = self
+            .planner
+            .introspect(query)
+            .await
+            .map_err(|_e| IntrospectionError {
+                message: String::from("cannot find the introspection response").into(),
+            })?;
 
         let introspection_result = response.into_result().map_err(|err| IntrospectionError {
             message: format!(
@@ -76,7 +84,7 @@ impl Introspection {
 
         let response = Response::builder().data(introspection_result).build();
 
-        self.cache.insert(query, response.clone()).await;
+        self.cache.insert(hash, response.clone()).await;
 
         Ok(response)
     }
@@ -121,7 +129,8 @@ mod introspection_tests {
             .unwrap(),
         );
 
-        let cache = [(query_to_test.to_string(), expected_data.clone())]
+        let hash = QueryHash(vec![1, 2, 3]);
+        let cache = [(hash.clone(), expected_data.clone())]
             .iter()
             .cloned()
             .collect();
@@ -130,7 +139,7 @@ mod introspection_tests {
         assert_eq!(
             expected_data,
             introspection
-                .execute(query_to_test.to_string())
+                .execute(query_to_test.to_string(), hash)
                 .await
                 .unwrap()
         );
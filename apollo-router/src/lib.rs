@@ -50,14 +50,16 @@ mod json_ext;
 pub mod plugin;
 
 #[macro_use]
-pub(crate) mod metrics;
+pub mod metrics;
 
 mod apollo_studio_interop;
 pub(crate) mod axum_factory;
 mod batching;
+mod bench;
 mod cache;
 mod configuration;
 mod context;
+mod diagnose;
 mod error;
 mod executable;
 mod files;
@@ -67,16 +69,20 @@ mod http_server_factory;
 mod introspection;
 pub mod layers;
 pub(crate) mod logging;
+mod normalize;
 pub(crate) mod notification;
 mod orbiter;
 mod plugins;
 pub(crate) mod protocols;
 mod query_planner;
+mod refresh;
 mod router;
 mod router_factory;
+mod service_manager;
 pub mod services;
 pub(crate) mod spec;
 mod state_machine;
+mod subgraph_check;
 pub mod test_harness;
 pub mod tracer;
 mod uplink;
@@ -86,12 +92,18 @@ pub use crate::configuration::Configuration;
 pub use crate::configuration::ListenAddr;
 pub use crate::context::extensions::sync::ExtensionsMutex;
 pub use crate::context::extensions::Extensions;
+pub use crate::context::keys::is_reserved as is_reserved_context_key;
+pub use crate::context::keys::RESERVED_KEYS as RESERVED_CONTEXT_KEYS;
 pub use crate::context::Context;
+pub use crate::context::ContextKey;
 pub use crate::executable::main;
 pub use crate::executable::Executable;
 pub use crate::notification::Notify;
 pub use crate::router::ApolloRouterError;
 pub use crate::router::ConfigurationSource;
+pub use crate::router::EnvLicenseProvider;
+pub use crate::router::FileLicenseProvider;
+pub use crate::router::LicenseProvider;
 pub use crate::router::LicenseSource;
 pub use crate::router::RouterHttpServer;
 pub use crate::router::SchemaSource;
@@ -108,6 +120,9 @@ pub mod _private {
     // Reexports for macros
     pub use linkme;
     pub use once_cell;
+    pub use opentelemetry;
+    pub use opentelemetry_api;
+    pub use paste;
     pub use router_bridge;
     pub use serde_json;
 
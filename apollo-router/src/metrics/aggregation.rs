@@ -1,7 +1,6 @@
 use std::any::Any;
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::ops::DerefMut;
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -43,8 +42,9 @@ pub(crate) enum MeterProviderType {
     OtelDefault,
 }
 
+/// Meter provider exporting metrics to all the exporters configured in telemetry
 #[derive(Clone)]
-pub(crate) struct AggregateMeterProvider {
+pub struct AggregateMeterProvider {
     inner: Arc<Mutex<Inner>>,
 }
 
@@ -77,7 +77,8 @@ pub(crate) struct Inner {
 /// Fields are never used directly but strong references here
 /// keep weak references elsewhere upgradable.
 #[derive(From)]
-pub(crate) enum InstrumentWrapper {
+#[doc(hidden)]
+pub enum InstrumentWrapper {
     U64Counter {
         _keep_alive: Arc<Counter<u64>>,
     },
@@ -152,15 +153,18 @@ impl AggregateMeterProvider {
     }
 
     /// Create a registered instrument. This enables caching at callsites and invalidation at the meter provider via weak reference.
-    pub(crate) fn create_registered_instrument<T>(
+    #[doc(hidden)]
+    pub fn create_registered_instrument<T>(
         &self,
-        create_fn: impl Fn(&mut Inner) -> T,
+        meter_name: &'static str,
+        create_fn: impl Fn(&Meter) -> T,
     ) -> Arc<T>
     where
         Arc<T>: Into<InstrumentWrapper>,
     {
         let mut guard = self.inner.lock().expect("lock poisoned");
-        let instrument = Arc::new((create_fn)(guard.deref_mut()));
+        let meter = guard.meter(meter_name);
+        let instrument = Arc::new((create_fn)(&meter));
         guard.registered_instruments.push(instrument.clone().into());
         instrument
     }
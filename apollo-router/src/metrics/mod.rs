@@ -1,3 +1,13 @@
+//! Metrics emitted through the router's telemetry pipeline.
+//!
+//! Native plugins can emit metrics with the [`u64_counter!`](crate::u64_counter),
+//! [`f64_counter!`](crate::f64_counter), [`i64_up_down_counter!`](crate::i64_up_down_counter),
+//! [`f64_up_down_counter!`](crate::f64_up_down_counter), [`f64_histogram!`](crate::f64_histogram),
+//! [`u64_histogram!`](crate::u64_histogram) and [`i64_histogram!`](crate::i64_histogram) macros,
+//! or create their own instruments from [`meter_provider`]. These metrics are exported like the
+//! router's own metrics, and the views configured in `telemetry.exporters.metrics.common.views`
+//! apply to them, including the ones restricting their attributes to limit cardinality.
+
 #[cfg(test)]
 use std::future::Future;
 #[cfg(test)]
@@ -7,7 +17,9 @@ use std::sync::OnceLock;
 #[cfg(test)]
 use futures::FutureExt;
 
-use crate::metrics::aggregation::AggregateMeterProvider;
+pub use crate::metrics::aggregation::AggregateMeterProvider;
+#[doc(hidden)]
+pub use crate::metrics::aggregation::InstrumentWrapper;
 
 pub(crate) mod aggregation;
 pub(crate) mod filter;
@@ -478,8 +490,9 @@ pub(crate) mod test_utils {
         Gauge,
     }
 }
+/// The meter provider metrics are exported through
 #[cfg(test)]
-pub(crate) fn meter_provider() -> AggregateMeterProvider {
+pub fn meter_provider() -> AggregateMeterProvider {
     test_utils::meter_provider_and_readers().0
 }
 
@@ -488,13 +501,32 @@ pub(crate) use test_utils::collect_metrics;
 
 #[cfg(not(test))]
 static AGGREGATE_METER_PROVIDER: OnceLock<AggregateMeterProvider> = OnceLock::new();
+/// The meter provider metrics are exported through
 #[cfg(not(test))]
-pub(crate) fn meter_provider() -> AggregateMeterProvider {
+pub fn meter_provider() -> AggregateMeterProvider {
     AGGREGATE_METER_PROVIDER
         .get_or_init(Default::default)
         .clone()
 }
 
+/// Whether the metric macros cache instruments at their call site
+///
+/// There is a single test for caching callsites. Other tests do not cache because they will
+/// interfere with each other due to them using a task local meter provider to aid testing.
+#[cfg(test)]
+#[doc(hidden)]
+pub fn cache_callsite() -> bool {
+    CACHE_CALLSITE.with(|cell| cell.load(std::sync::atomic::Ordering::SeqCst))
+}
+
+// The compiler will optimize this in non test builds
+#[cfg(not(test))]
+#[doc(hidden)]
+#[inline]
+pub fn cache_callsite() -> bool {
+    true
+}
+
 #[macro_export]
 /// Get or create a u64 monotonic counter metric and add a value to it
 ///
@@ -509,31 +541,31 @@ pub(crate) fn meter_provider() -> AggregateMeterProvider {
 #[allow(unused_macros)]
 macro_rules! u64_counter {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(u64, counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(u64, counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(u64, counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(u64, counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(u64, counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(u64, counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(u64, counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(u64, counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(u64, counter, add, $name, $description, $value, $attrs);
+        $crate::metric!(u64, counter, add, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(u64, counter, add, $name, $description, $value, []);
+        $crate::metric!(u64, counter, add, $name, $description, $value, []);
     }
 }
 
@@ -547,33 +579,34 @@ macro_rules! u64_counter {
 /// * Imperfect mapping to metrics API that can only be checked at runtime.
 ///
 /// New metrics should be added using these macros.
+#[macro_export]
 #[allow(unused_macros)]
 macro_rules! f64_counter {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(f64, counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(f64, counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(f64, counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(f64, counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(f64, counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(f64, counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(f64, counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(f64, counter, add, $name, $description, $value, &attributes);
     };
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(f64, counter, add, $name, $description, $value, $attrs);
+        $crate::metric!(f64, counter, add, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(f64, counter, add, $name, $description, $value, []);
+        $crate::metric!(f64, counter, add, $name, $description, $value, []);
     }
 }
 
@@ -588,34 +621,35 @@ macro_rules! f64_counter {
 ///
 /// New metrics should be added using these macros.
 
+#[macro_export]
 #[allow(unused_macros)]
 macro_rules! i64_up_down_counter {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(i64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(i64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(i64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(i64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(i64, up_down_counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(i64, up_down_counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(i64, up_down_counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(i64, up_down_counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(i64, up_down_counter, add, $name, $description, $value, $attrs);
+        $crate::metric!(i64, up_down_counter, add, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(i64, up_down_counter, add, $name, $description, $value, []);
+        $crate::metric!(i64, up_down_counter, add, $name, $description, $value, []);
     };
 }
 
@@ -629,34 +663,35 @@ macro_rules! i64_up_down_counter {
 /// * Imperfect mapping to metrics API that can only be checked at runtime.
 ///
 /// New metrics should be added using these macros.
+#[macro_export]
 #[allow(unused_macros)]
 macro_rules! f64_up_down_counter {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(f64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(f64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(f64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(f64, up_down_counter, add, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(f64, up_down_counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(f64, up_down_counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(f64, up_down_counter, add, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(f64, up_down_counter, add, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(f64, up_down_counter, add, $name, $description, $value, $attrs);
+        $crate::metric!(f64, up_down_counter, add, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(f64, up_down_counter, add, $name, $description, $value, []);
+        $crate::metric!(f64, up_down_counter, add, $name, $description, $value, []);
     };
 }
 
@@ -670,34 +705,35 @@ macro_rules! f64_up_down_counter {
 /// * Imperfect mapping to metrics API that can only be checked at runtime.
 ///
 /// New metrics should be added using these macros.
+#[macro_export]
 #[allow(unused_macros)]
 macro_rules! f64_histogram {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(f64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(f64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(f64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(f64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(f64, histogram, record, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(f64, histogram, record, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(f64, histogram, record, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(f64, histogram, record, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(f64, histogram, record, $name, $description, $value, $attrs);
+        $crate::metric!(f64, histogram, record, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(f64, histogram, record, $name, $description, $value, []);
+        $crate::metric!(f64, histogram, record, $name, $description, $value, []);
     };
 }
 
@@ -711,34 +747,35 @@ macro_rules! f64_histogram {
 /// * Imperfect mapping to metrics API that can only be checked at runtime.
 ///
 /// New metrics should be added using these macros.
+#[macro_export]
 #[allow(unused_macros)]
 macro_rules! u64_histogram {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(u64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(u64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(u64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(u64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(u64, histogram, record, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(u64, histogram, record, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(u64, histogram, record, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(u64, histogram, record, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(u64, histogram, record, $name, $description, $value, $attrs);
+        $crate::metric!(u64, histogram, record, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(u64, histogram, record, $name, $description, $value, []);
+        $crate::metric!(u64, histogram, record, $name, $description, $value, []);
     };
 }
 
@@ -752,34 +789,35 @@ macro_rules! u64_histogram {
 /// * Imperfect mapping to metrics API that can only be checked at runtime.
 ///
 /// New metrics should be added using these macros.
+#[macro_export]
 #[allow(unused_macros)]
 macro_rules! i64_histogram {
     ($($name:ident).+, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(i64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(i64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($($name:ident).+, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(i64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(i64, histogram, record, stringify!($($name).+), $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($attr_key:literal = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
-        metric!(i64, histogram, record, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new($attr_key, $attr_value)),+];
+        $crate::metric!(i64, histogram, record, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $($($attr_key:ident).+ = $attr_value:expr),+) => {
-        let attributes = vec![$(opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
-        metric!(i64, histogram, record, $name, $description, $value, &attributes);
+        let attributes = vec![$($crate::_private::opentelemetry::KeyValue::new(stringify!($($attr_key).+), $attr_value)),+];
+        $crate::metric!(i64, histogram, record, $name, $description, $value, &attributes);
     };
 
     ($name:literal, $description:literal, $value: expr, $attrs: expr) => {
-        metric!(i64, histogram, record, $name, $description, $value, $attrs);
+        $crate::metric!(i64, histogram, record, $name, $description, $value, $attrs);
     };
 
     ($name:literal, $description:literal, $value: expr) => {
-        metric!(i64, histogram, record, $name, $description, $value, []);
+        $crate::metric!(i64, histogram, record, $name, $description, $value, []);
     };
 }
 
@@ -788,6 +826,8 @@ thread_local! {
     #[cfg(test)]
     pub(crate) static CACHE_CALLSITE: std::sync::atomic::AtomicBool = const {std::sync::atomic::AtomicBool::new(false)};
 }
+#[doc(hidden)]
+#[macro_export]
 macro_rules! metric {
     ($ty:ident, $instrument:ident, $mutation:ident, $name:expr, $description:literal, $value: expr, $attrs: expr) => {
 
@@ -799,23 +839,15 @@ macro_rules! metric {
         // The Reason a Mutex is used rather than an RwLock is that we are not holding the lock for any significant period of time and the cost of an RwLock is potentially higher.
         // If we profile and deem it's worth switching to RwLock then we can do that.
 
-        paste::paste! {
+        $crate::_private::paste::paste! {
             {
-                // There is a single test for caching callsites. Other tests do not cache because they will interfere with each other due to them using a task local meter provider to aid testing.
-                #[cfg(test)]
-                let cache_callsite = crate::metrics::CACHE_CALLSITE.with(|cell| cell.load(std::sync::atomic::Ordering::SeqCst));
-
-                // The compiler will optimize this in non test builds
-                #[cfg(not(test))]
-                let cache_callsite = true;
-
-                if cache_callsite {
-                    static INSTRUMENT_CACHE: std::sync::OnceLock<std::sync::Mutex<std::sync::Weak<opentelemetry_api::metrics::[<$instrument:camel>]<$ty>>>> = std::sync::OnceLock::new();
+                if $crate::metrics::cache_callsite() {
+                    static INSTRUMENT_CACHE: std::sync::OnceLock<std::sync::Mutex<std::sync::Weak<$crate::_private::opentelemetry_api::metrics::[<$instrument:camel>]<$ty>>>> = std::sync::OnceLock::new();
 
                     let mut instrument_guard = INSTRUMENT_CACHE
                         .get_or_init(|| {
-                            let meter_provider = crate::metrics::meter_provider();
-                            let instrument_ref = meter_provider.create_registered_instrument(|p| p.meter("apollo/router").[<$ty _ $instrument>]($name).with_description($description).init());
+                            let meter_provider = $crate::metrics::meter_provider();
+                            let instrument_ref = meter_provider.create_registered_instrument("apollo/router", |meter| meter.[<$ty _ $instrument>]($name).with_description($description).init());
                             std::sync::Mutex::new(std::sync::Arc::downgrade(&instrument_ref))
                         })
                         .lock()
@@ -826,8 +858,8 @@ macro_rules! metric {
                         instrument
                     } else {
                         // Slow path, we need to obtain the instrument again.
-                        let meter_provider = crate::metrics::meter_provider();
-                        let instrument_ref = meter_provider.create_registered_instrument(|p| p.meter("apollo/router").[<$ty _ $instrument>]($name).with_description($description).init());
+                        let meter_provider = $crate::metrics::meter_provider();
+                        let instrument_ref = meter_provider.create_registered_instrument("apollo/router", |meter| meter.[<$ty _ $instrument>]($name).with_description($description).init());
                         *instrument_guard = std::sync::Arc::downgrade(&instrument_ref);
                         // We've updated the instrument and got a strong reference to it. We can drop the mutex guard now.
                         drop(instrument_guard);
@@ -836,8 +868,8 @@ macro_rules! metric {
                     instrument.$mutation($value, &$attrs);
                 }
                 else {
-                    let meter_provider = crate::metrics::meter_provider();
-                    let meter = opentelemetry::metrics::MeterProvider::meter(&meter_provider, "apollo/router");
+                    let meter_provider = $crate::metrics::meter_provider();
+                    let meter = $crate::_private::opentelemetry::metrics::MeterProvider::meter(&meter_provider, "apollo/router");
                     let instrument = meter.[<$ty _ $instrument>]($name).with_description($description).init();
                     instrument.$mutation($value, &$attrs);
                 }
@@ -0,0 +1,152 @@
+//! Normalization of operations as the router would process them, for client build tooling.
+//!
+//! Clients can check their operations in CI against the schema that the router will serve:
+//! the report contains the validation errors the router would return, and for valid operations
+//! the document as printed by the router after parsing and validation, its query hash and its
+//! signature in usage reports.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use router_bridge::planner::ReferencedFieldsForType;
+use serde::Serialize;
+
+use crate::apollo_studio_interop::generate_usage_reporting;
+use crate::error::IntoGraphQLErrors;
+use crate::graphql;
+use crate::plugins::telemetry::config::Conf as TelemetryConfig;
+use crate::spec::Query;
+use crate::spec::Schema;
+use crate::Configuration;
+
+#[derive(Debug, Serialize)]
+pub(crate) struct Report {
+    valid: bool,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    errors: Vec<graphql::Error>,
+    /// The validated document printed by the router: formatting and comments are normalized, but
+    /// not the operation itself, unlike the signature
+    #[serde(skip_serializing_if = "Option::is_none")]
+    normalized: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    query_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    signature: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    referenced_fields_by_type: Option<HashMap<String, ReferencedFieldsForType>>,
+}
+
+impl Report {
+    pub(crate) fn is_valid(&self) -> bool {
+        self.valid
+    }
+
+    fn invalid(errors: Vec<graphql::Error>) -> Self {
+        Self {
+            valid: false,
+            errors,
+            normalized: None,
+            query_hash: None,
+            signature: None,
+            referenced_fields_by_type: None,
+        }
+    }
+}
+
+/// Report of an operation document passed on the command line
+#[derive(Debug, Serialize)]
+pub(crate) struct DocumentReport {
+    pub(crate) document: PathBuf,
+    #[serde(flatten)]
+    pub(crate) report: Report,
+}
+
+/// Parses and validates an operation like the router does, and reports how it would be processed
+pub(crate) fn normalize(
+    schema: &Schema,
+    configuration: &Configuration,
+    query: &str,
+    operation_name: Option<&str>,
+) -> Report {
+    let doc = match Query::parse_document(query, operation_name, schema, configuration) {
+        Ok(doc) => doc,
+        Err(error) => {
+            return Report::invalid(error.into_graphql_errors().unwrap_or_else(|error| {
+                vec![graphql::Error::builder()
+                    .message(error.to_string())
+                    .extension_code("GRAPHQL_VALIDATION_FAILED")
+                    .build()]
+            }))
+        }
+    };
+    if let Err(error) = doc.executable.get_operation(operation_name) {
+        return Report::invalid(vec![graphql::Error::builder()
+            .message(error.to_string())
+            .extension_code("GRAPHQL_VALIDATION_FAILED")
+            .build()]);
+    }
+
+    let usage_reporting = generate_usage_reporting(
+        &doc.executable,
+        &doc.executable,
+        &operation_name.map(ToString::to_string),
+        schema.supergraph_schema(),
+        &TelemetryConfig::signature_normalization_algorithm(configuration),
+    )
+    .result;
+
+    Report {
+        valid: true,
+        errors: Vec::new(),
+        normalized: Some(doc.executable.to_string()),
+        query_hash: Some(doc.hash.to_string()),
+        signature: Some(usage_reporting.stats_report_key),
+        referenced_fields_by_type: Some(usage_reporting.referenced_fields_by_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema() -> Schema {
+        Schema::parse(
+            include_str!("testdata/minimal_supergraph.graphql"),
+            &Default::default(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn valid_operation() {
+        let report = normalize(
+            &schema(),
+            &Default::default(),
+            "query Me {   me }",
+            Some("Me"),
+        );
+
+        assert!(report.valid);
+        assert!(report.errors.is_empty());
+        assert_eq!(report.normalized.as_deref(), Some("query Me {\n  me\n}\n"));
+        assert_eq!(report.signature.as_deref(), Some("# Me\nquery Me{me}"));
+        assert!(report.query_hash.is_some());
+        assert!(report
+            .referenced_fields_by_type
+            .unwrap()
+            .contains_key("Query"));
+    }
+
+    #[test]
+    fn invalid_operation() {
+        let schema = schema();
+        let report = normalize(&schema, &Default::default(), "{ you }", None);
+        assert!(!report.valid);
+        assert_eq!(report.errors.len(), 1);
+        assert!(report.signature.is_none());
+
+        let report = normalize(&schema, &Default::default(), "{ me }", Some("Me"));
+        assert!(!report.valid);
+        assert_eq!(report.errors.len(), 1);
+    }
+}
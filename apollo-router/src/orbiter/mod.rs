@@ -221,7 +221,7 @@ async fn send(body: UsageReport) -> Result<String, BoxError> {
         .await?)
 }
 
-fn get_os() -> String {
+pub(crate) fn get_os() -> String {
     if wsl::is_wsl() {
         "wsl"
     } else {
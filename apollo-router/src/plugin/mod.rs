@@ -17,6 +17,7 @@
 pub mod serde;
 #[macro_use]
 pub mod test;
+pub(crate) mod timing;
 
 use std::any::TypeId;
 use std::collections::HashMap;
@@ -51,6 +52,7 @@ use crate::services::execution;
 use crate::services::router;
 use crate::services::subgraph;
 use crate::services::supergraph;
+pub use crate::spec::SchemaChange;
 use crate::ListenAddr;
 
 type InstanceFactory =
@@ -406,6 +408,10 @@ pub trait Plugin: Send + Sync + 'static {
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
         MultiMap::new()
     }
+
+    /// This is invoked once the router with this plugin instance started serving requests,
+    /// if it replaced a router running with another supergraph.
+    fn schema_changed(&self, _change: &SchemaChange) {}
 }
 
 /// Plugin trait for unstable features
@@ -481,6 +487,10 @@ pub trait PluginUnstable: Send + Sync + 'static {
         MultiMap::new()
     }
 
+    /// This is invoked once the router with this plugin instance started serving requests,
+    /// if it replaced a router running with another supergraph.
+    fn schema_changed(&self, _change: &SchemaChange) {}
+
     /// test
     fn unstable_method(&self);
 }
@@ -531,6 +541,10 @@ where
         Plugin::web_endpoints(self)
     }
 
+    fn schema_changed(&self, change: &SchemaChange) {
+        Plugin::schema_changed(self, change)
+    }
+
     fn unstable_method(&self) {
         todo!()
     }
@@ -618,6 +632,10 @@ pub(crate) trait PluginPrivate: Send + Sync + 'static {
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
         MultiMap::new()
     }
+
+    /// This is invoked once the router with this plugin instance started serving requests,
+    /// if it replaced a router running with another supergraph.
+    fn schema_changed(&self, _change: &SchemaChange) {}
 }
 
 #[async_trait]
@@ -665,6 +683,10 @@ where
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
         PluginUnstable::web_endpoints(self)
     }
+
+    fn schema_changed(&self, change: &SchemaChange) {
+        PluginUnstable::schema_changed(self, change)
+    }
 }
 
 fn get_type_of<T>(_: &T) -> &'static str {
@@ -715,6 +737,9 @@ pub(crate) trait DynPlugin: Send + Sync + 'static {
     /// Return one or several `Endpoint`s and `ListenAddr` and the router will serve your custom web Endpoint(s).
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint>;
 
+    /// Notifies the plugin that the supergraph changed.
+    fn schema_changed(&self, change: &SchemaChange);
+
     /// Support downcasting
     fn as_any(&self) -> &dyn std::any::Any;
 
@@ -763,6 +788,10 @@ where
         self.web_endpoints()
     }
 
+    fn schema_changed(&self, change: &SchemaChange) {
+        self.schema_changed(change)
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -0,0 +1,288 @@
+//! Measure the time spent in each plugin.
+//!
+//! A plugin's service wraps the service of the next plugin, so the duration of its layer includes
+//! everything that runs after it. To isolate a plugin, its layer and the service it wraps are both
+//! instrumented: the request phase lasts from the call to the plugin's service until the plugin
+//! calls the next service, and the response phase from the next service's response until the
+//! plugin's response. Plugins answering without calling the next service are not measured.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+use multimap::MultiMap;
+use tower::ServiceExt;
+
+use super::DynPlugin;
+use crate::router_factory::Endpoint;
+use crate::services::execution;
+use crate::services::router;
+use crate::services::subgraph;
+use crate::services::supergraph;
+use crate::spec::SchemaChange;
+use crate::ListenAddr;
+
+/// Instants at which a request or response left a plugin layer, by plugin name
+#[derive(Clone, Default)]
+struct Marks(HashMap<Arc<str>, Instant>);
+
+fn mark(extensions: &mut http::Extensions, plugin: &Arc<str>) {
+    match extensions.get_mut::<Marks>() {
+        Some(marks) => {
+            marks.0.insert(plugin.clone(), Instant::now());
+        }
+        None => {
+            let mut marks = Marks::default();
+            marks.0.insert(plugin.clone(), Instant::now());
+            extensions.insert(marks);
+        }
+    }
+}
+
+fn take_mark(extensions: &mut http::Extensions, plugin: &Arc<str>) -> Option<Instant> {
+    extensions.get_mut::<Marks>()?.0.remove(plugin)
+}
+
+/// Requests and responses carrying the marks
+trait Marked {
+    fn extensions_mut(&mut self) -> &mut http::Extensions;
+}
+
+macro_rules! impl_marked {
+    ($ty: ty, $field: ident) => {
+        impl Marked for $ty {
+            fn extensions_mut(&mut self) -> &mut http::Extensions {
+                self.$field.extensions_mut()
+            }
+        }
+    };
+}
+
+impl_marked!(router::Request, router_request);
+impl_marked!(router::Response, response);
+impl_marked!(supergraph::Request, supergraph_request);
+impl_marked!(supergraph::Response, response);
+impl_marked!(execution::Request, supergraph_request);
+impl_marked!(execution::Response, response);
+impl_marked!(subgraph::Request, subgraph_request);
+impl_marked!(subgraph::Response, response);
+
+fn record(plugin: &Arc<str>, stage: &'static str, phase: &'static str, start: Instant) {
+    f64_histogram!(
+        "apollo.router.plugin.duration",
+        "Time spent in a plugin, excluding the services it calls",
+        start.elapsed().as_secs_f64(),
+        "plugin" = plugin.to_string(),
+        "stage" = stage,
+        "phase" = phase
+    );
+}
+
+/// Wraps the service built by a plugin, given the service it wraps
+fn timed<Req, Res>(
+    plugin: &Arc<str>,
+    stage: &'static str,
+    service: tower::util::BoxService<Req, Res, tower::BoxError>,
+    layer: impl FnOnce(
+        tower::util::BoxService<Req, Res, tower::BoxError>,
+    ) -> tower::util::BoxService<Req, Res, tower::BoxError>,
+) -> tower::util::BoxService<Req, Res, tower::BoxError>
+where
+    Req: Marked + Send + 'static,
+    Res: Marked + Send + 'static,
+{
+    let (request_plugin, response_plugin) = (plugin.clone(), plugin.clone());
+    let next = service
+        .map_request(move |mut request: Req| {
+            if let Some(start) = take_mark(request.extensions_mut(), &request_plugin) {
+                record(&request_plugin, stage, "request", start);
+            }
+            request
+        })
+        .map_response(move |mut response: Res| {
+            mark(response.extensions_mut(), &response_plugin);
+            response
+        })
+        .boxed();
+
+    let (request_plugin, response_plugin) = (plugin.clone(), plugin.clone());
+    layer(next)
+        .map_request(move |mut request: Req| {
+            mark(request.extensions_mut(), &request_plugin);
+            request
+        })
+        .map_response(move |mut response: Res| {
+            if let Some(start) = take_mark(response.extensions_mut(), &response_plugin) {
+                record(&response_plugin, stage, "response", start);
+            }
+            response
+        })
+        .boxed()
+}
+
+/// Records the time spent in the services of the wrapped plugin
+pub(crate) struct TimedPlugin {
+    name: Arc<str>,
+    inner: Box<dyn DynPlugin>,
+}
+
+impl TimedPlugin {
+    pub(crate) fn new(name: &str, inner: Box<dyn DynPlugin>) -> Self {
+        Self {
+            name: name.into(),
+            inner,
+        }
+    }
+}
+
+impl DynPlugin for TimedPlugin {
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        timed(&self.name, "router", service, |service| {
+            self.inner.router_service(service)
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        timed(&self.name, "supergraph", service, |service| {
+            self.inner.supergraph_service(service)
+        })
+    }
+
+    fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        timed(&self.name, "execution", service, |service| {
+            self.inner.execution_service(service)
+        })
+    }
+
+    fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        timed(&self.name, "subgraph", service, |service| {
+            self.inner.subgraph_service(name, service)
+        })
+    }
+
+    fn http_client_service(
+        &self,
+        name: &str,
+        service: crate::services::http::BoxService,
+    ) -> crate::services::http::BoxService {
+        self.inner.http_client_service(name, service)
+    }
+
+    fn name(&self) -> &'static str {
+        self.inner.name()
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        self.inner.web_endpoints()
+    }
+
+    fn schema_changed(&self, change: &SchemaChange) {
+        self.inner.schema_changed(change)
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self.inner.as_any()
+    }
+
+    #[cfg(test)]
+    fn as_any_mut(&mut self) -> &mut dyn std::any::Any {
+        self.inner.as_any_mut()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tower::BoxError;
+
+    use super::*;
+    use crate::metrics::FutureMetricsExt;
+    use crate::plugin::test::MockSupergraphService;
+    use crate::plugin::Plugin;
+    use crate::plugin::PluginInit;
+
+    struct Slow;
+
+    #[async_trait::async_trait]
+    impl Plugin for Slow {
+        type Config = ();
+
+        async fn new(_init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+            Ok(Slow)
+        }
+
+        fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+            service
+                .map_future(|future| async {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    future.await
+                })
+                .boxed()
+        }
+    }
+
+    #[derive(Default)]
+    struct SchemaChanges(std::sync::Mutex<Vec<String>>);
+
+    #[async_trait::async_trait]
+    impl Plugin for SchemaChanges {
+        type Config = ();
+
+        async fn new(_init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+            Ok(SchemaChanges::default())
+        }
+
+        fn schema_changed(&self, change: &SchemaChange) {
+            self.0.lock().unwrap().push(change.schema_hash.clone());
+        }
+    }
+
+    #[test]
+    fn forwards_schema_changes() {
+        let plugin = TimedPlugin::new("schema_changes", Box::<SchemaChanges>::default());
+        plugin.schema_changed(&SchemaChange {
+            schema_hash: "new".to_string(),
+            ..Default::default()
+        });
+
+        let changes = plugin.as_any().downcast_ref::<SchemaChanges>().unwrap();
+        assert_eq!(*changes.0.lock().unwrap(), vec!["new".to_string()]);
+    }
+
+    #[tokio::test]
+    async fn records_plugin_durations() {
+        async {
+            let plugin = TimedPlugin::new("slow", Box::new(Slow));
+            let mut mock_service = MockSupergraphService::new();
+            mock_service.expect_call().times(1).returning(|request| {
+                supergraph::Response::fake_builder()
+                    .context(request.context)
+                    .build()
+            });
+
+            plugin
+                .supergraph_service(mock_service.boxed())
+                .oneshot(supergraph::Request::fake_builder().build().unwrap())
+                .await
+                .unwrap();
+
+            assert_histogram_exists!(
+                "apollo.router.plugin.duration",
+                f64,
+                "plugin" = "slow",
+                "stage" = "supergraph",
+                "phase" = "request"
+            );
+            assert_histogram_exists!(
+                "apollo.router.plugin.duration",
+                f64,
+                "plugin" = "slow",
+                "stage" = "supergraph",
+                "phase" = "response"
+            );
+        }
+        .with_metrics()
+        .await;
+    }
+}
@@ -0,0 +1,79 @@
+//! Shared pieces of the token protected admin endpoints exposed by plugins.
+//!
+//! Endpoints like the log filter, profiling or diagnostics ones listen on a separate address by
+//! default and only answer requests carrying the configured token in a bearer `Authorization`
+//! header.
+
+use http::header::AUTHORIZATION;
+use http::HeaderMap;
+use http::StatusCode;
+use sha2::Digest;
+use sha2::Sha256;
+
+use crate::ListenAddr;
+
+/// Default listen address of the admin endpoints
+pub(crate) fn default_listen() -> ListenAddr {
+    ListenAddr::SocketAddr("127.0.0.1:9090".parse().expect("valid listenAddr"))
+}
+
+/// Token expected in the `Authorization: Bearer <token>` header of requests to an admin endpoint
+#[derive(Clone)]
+pub(crate) struct BearerToken {
+    /// SHA-256 digest of the token, so that the comparison does not depend on its length
+    digest: [u8; 32],
+}
+
+impl BearerToken {
+    pub(crate) fn new(token: &str) -> Self {
+        Self {
+            digest: Sha256::digest(token).into(),
+        }
+    }
+
+    /// Checks the `Authorization` header of a request, in constant time
+    pub(crate) fn check(&self, headers: &HeaderMap) -> Result<(), (StatusCode, String)> {
+        let digest: [u8; 32] = match headers
+            .get(AUTHORIZATION)
+            .and_then(|value| value.as_bytes().strip_prefix(b"Bearer "))
+        {
+            Some(token) => Sha256::digest(token).into(),
+            None => return Err((StatusCode::UNAUTHORIZED, "unauthorized".to_string())),
+        };
+        let difference = digest
+            .iter()
+            .zip(self.digest.iter())
+            .fold(0, |difference, (a, b)| difference | (a ^ b));
+        if difference == 0 {
+            Ok(())
+        } else {
+            Err((StatusCode::UNAUTHORIZED, "unauthorized".to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn headers(authorization: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static(authorization));
+        headers
+    }
+
+    #[test]
+    fn checks_the_bearer_token() {
+        let token = BearerToken::new("secret");
+        assert!(token.check(&headers("Bearer secret")).is_ok());
+        assert_eq!(
+            token.check(&headers("Bearer wrong")).unwrap_err().0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert!(token.check(&headers("Bearer secret2")).is_err());
+        assert!(token.check(&headers("secret")).is_err());
+        assert!(token.check(&HeaderMap::new()).is_err());
+    }
+}
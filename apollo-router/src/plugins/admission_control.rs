@@ -0,0 +1,300 @@
+//! Queue requests above a concurrency limit, with a bounded backlog and queue time.
+//!
+//! Requests are processed up to a number at a time, and the next ones wait in a queue. Requests
+//! arriving when the queue is full, or waiting longer than the maximum queue time, get a 503
+//! response with a `Retry-After` header, so that a burst of traffic is absorbed without letting
+//! latency grow unbounded.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
+
+use http::header::CONTENT_TYPE;
+use http::header::RETRY_AFTER;
+use http::HeaderValue;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::sync::Semaphore;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::graphql;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::router;
+
+/// Admission control configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct AdmissionControlConfig {
+    /// Set to true to queue the requests above the concurrency limit
+    enabled: bool,
+    /// Maximum number of requests processed at the same time
+    max_concurrent_requests: usize,
+    /// Maximum number of requests waiting in the queue. Requests arriving when the queue is full
+    /// are rejected
+    max_queue_depth: usize,
+    /// Maximum time a request waits in the queue before being rejected
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    max_queue_wait: Duration,
+    /// Delay sent in the `Retry-After` header of rejected requests, rounded up to the second
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    retry_after: Duration,
+}
+
+impl Default for AdmissionControlConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_concurrent_requests: 1000,
+            max_queue_depth: 1000,
+            max_queue_wait: Duration::from_secs(1),
+            retry_after: Duration::from_secs(1),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct AdmissionControl {
+    /// None when the plugin is disabled
+    queue: Option<Arc<Queue>>,
+}
+
+#[derive(Debug)]
+struct Queue {
+    permits: Arc<Semaphore>,
+    /// number of requests waiting for a permit
+    waiting: AtomicUsize,
+    max_depth: usize,
+    max_wait: Duration,
+    retry_after: HeaderValue,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rejection {
+    QueueFull,
+    WaitExceeded,
+}
+
+impl Rejection {
+    fn reason(self) -> &'static str {
+        match self {
+            Rejection::QueueFull => "queue_full",
+            Rejection::WaitExceeded => "wait_exceeded",
+        }
+    }
+}
+
+/// Counts a request as waiting in the queue until it is dropped, including when the client
+/// cancels the request
+struct Waiting<'a>(&'a Queue);
+
+impl Drop for Waiting<'_> {
+    fn drop(&mut self) {
+        self.0.waiting.fetch_sub(1, Ordering::SeqCst);
+        i64_up_down_counter!(
+            "apollo.router.admission_control.queue.depth",
+            "Number of requests waiting in the admission queue",
+            -1
+        );
+    }
+}
+
+impl Queue {
+    /// Waits for a permit to process a request
+    async fn admit(&self) -> Result<OwnedSemaphorePermit, Rejection> {
+        if let Ok(permit) = self.permits.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+        if self.waiting.fetch_add(1, Ordering::SeqCst) >= self.max_depth {
+            self.waiting.fetch_sub(1, Ordering::SeqCst);
+            return Err(Rejection::QueueFull);
+        }
+        i64_up_down_counter!(
+            "apollo.router.admission_control.queue.depth",
+            "Number of requests waiting in the admission queue",
+            1
+        );
+        let waiting = Waiting(self);
+
+        let start = Instant::now();
+        let permit =
+            tokio::time::timeout(self.max_wait, self.permits.clone().acquire_owned()).await;
+        drop(waiting);
+        f64_histogram!(
+            "apollo.router.admission_control.queue.wait",
+            "Time spent by requests in the admission queue",
+            start.elapsed().as_secs_f64()
+        );
+        match permit {
+            Ok(Ok(permit)) => Ok(permit),
+            _ => Err(Rejection::WaitExceeded),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for AdmissionControl {
+    type Config = AdmissionControlConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let config = init.config;
+        if !config.enabled {
+            return Ok(AdmissionControl { queue: None });
+        }
+        if config.max_concurrent_requests == 0 {
+            return Err("admission_control.max_concurrent_requests must be at least 1".into());
+        }
+
+        let retry_after = (config.retry_after.as_secs()
+            + u64::from(config.retry_after.subsec_nanos() > 0))
+        .to_string();
+        Ok(AdmissionControl {
+            queue: Some(Arc::new(Queue {
+                permits: Arc::new(Semaphore::new(config.max_concurrent_requests)),
+                waiting: AtomicUsize::new(0),
+                max_depth: config.max_queue_depth,
+                max_wait: config.max_queue_wait,
+                retry_after: HeaderValue::from_str(&retry_after)?,
+            })),
+        })
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        let Some(queue) = self.queue.clone() else {
+            return service;
+        };
+
+        let service = ServiceBuilder::new().buffered().service(service);
+        tower::service_fn(move |request: router::Request| {
+            let queue = queue.clone();
+            let service = service.clone();
+            async move {
+                match queue.admit().await {
+                    Ok(permit) => {
+                        let response = service.oneshot(request).await;
+                        drop(permit);
+                        response
+                    }
+                    Err(rejection) => {
+                        u64_counter!(
+                            "apollo.router.admission_control.rejected",
+                            "Number of requests rejected by admission control",
+                            1,
+                            reason = rejection.reason()
+                        );
+                        Ok(router::Response::infallible_builder()
+                            .error(
+                                graphql::Error::builder()
+                                    .message("the router is over capacity, retry later")
+                                    .extension_code("ADMISSION_QUEUE_REJECTED")
+                                    .build(),
+                            )
+                            .status_code(StatusCode::SERVICE_UNAVAILABLE)
+                            .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                            .header(RETRY_AFTER, queue.retry_after.clone())
+                            .context(request.context)
+                            .build())
+                    }
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+register_plugin!("apollo", "admission_control", AdmissionControl);
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    async fn plugin(config: serde_json::Value) -> AdmissionControl {
+        AdmissionControl::new(PluginInit::fake_new(
+            serde_json::from_value(config).unwrap(),
+            Default::default(),
+        ))
+        .await
+        .unwrap()
+    }
+
+    /// A router service taking 300ms to respond, behind admission control
+    fn service(plugin: &AdmissionControl) -> router::BoxService {
+        plugin.router_service(
+            tower::service_fn(|request: router::Request| async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                router::Response::fake_builder()
+                    .context(request.context)
+                    .build()
+            })
+            .boxed(),
+        )
+    }
+
+    async fn call(service: router::BoxService) -> (StatusCode, Option<HeaderValue>) {
+        let response = service
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        (
+            response.response.status(),
+            response.response.headers().get(RETRY_AFTER).cloned(),
+        )
+    }
+
+    #[tokio::test]
+    async fn queues_requests_until_a_slot_frees_up() {
+        let plugin = plugin(json!({
+            "enabled": true,
+            "max_concurrent_requests": 1,
+            "max_queue_wait": "1s"
+        }))
+        .await;
+
+        let first = tokio::spawn(call(service(&plugin)));
+        let second = tokio::spawn(call(service(&plugin)));
+        assert_eq!(first.await.unwrap().0, StatusCode::OK);
+        assert_eq!(second.await.unwrap().0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_over_the_queue_limits() {
+        let plugin = plugin(json!({
+            "enabled": true,
+            "max_concurrent_requests": 1,
+            "max_queue_depth": 1,
+            "max_queue_wait": "100ms",
+            "retry_after": "1500ms"
+        }))
+        .await;
+
+        let processed = tokio::spawn(call(service(&plugin)));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let queued = tokio::spawn(call(service(&plugin)));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // the queue is full
+        let (status, retry_after) = call(service(&plugin)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(retry_after.unwrap(), "2");
+
+        // the queued request waits longer than the maximum queue time
+        let (status, _) = queued.await.unwrap();
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let (status, retry_after) = processed.await.unwrap();
+        assert_eq!(status, StatusCode::OK);
+        assert!(retry_after.is_none());
+    }
+}
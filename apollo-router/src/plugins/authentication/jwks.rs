@@ -9,8 +9,8 @@ use futures::future::join_all;
 use futures::future::select;
 use futures::future::Either;
 use futures::pin_mut;
-use futures::stream::repeat;
 use futures::stream::select_all;
+use futures::stream::unfold;
 use http::header::ACCEPT;
 use jsonwebtoken::jwk::Jwk;
 use jsonwebtoken::jwk::JwkSet;
@@ -26,6 +26,10 @@ use url::Url;
 use super::Header;
 use super::CLIENT;
 use super::DEFAULT_AUTHENTICATION_NETWORK_TIMEOUT;
+use crate::refresh::RefreshSource;
+
+/// The causes of download failures are logged by [`get_jwks`]
+const JWKS_REFRESH_ERROR: &str = "could not download the JWKS";
 
 #[derive(Clone)]
 pub(super) struct JwksManager {
@@ -52,25 +56,37 @@ pub(super) struct JwkSetInfo {
 
 impl JwksManager {
     pub(super) async fn new(list: Vec<JwksConfig>) -> Result<Self, BoxError> {
-        use futures::FutureExt;
-
         let downloads = list
             .iter()
             .cloned()
-            .map(|JwksConfig { url, headers, .. }| {
-                let span = tracing::info_span!("fetch jwks", url = %url);
-                get_jwks(url.clone(), headers.clone())
-                    .map(|opt_jwks| opt_jwks.map(|jwks| (url, jwks)))
-                    .instrument(span)
+            .map(|config| {
+                let span = tracing::info_span!("fetch jwks", url = %config.url);
+                let source = RefreshSource::register("jwks", config.url.as_str());
+                async move {
+                    let jwks = get_jwks(config.url.clone(), config.headers.clone()).await;
+                    let delay = match jwks {
+                        Some(_) => source.succeeded(config.poll_interval),
+                        None => source.failed(config.poll_interval, JWKS_REFRESH_ERROR),
+                    };
+                    (config, jwks, source, delay)
+                }
+                .instrument(span)
             })
             .collect::<Vec<_>>();
 
-        let jwks_map: HashMap<_, _> = join_all(downloads).await.into_iter().flatten().collect();
+        let mut jwks_map = HashMap::new();
+        let mut sources = Vec::new();
+        for (config, jwks, source, delay) in join_all(downloads).await {
+            if let Some(jwks) = jwks {
+                jwks_map.insert(config.url.clone(), jwks);
+            }
+            sources.push((config, source, delay));
+        }
 
         let jwks_map = Arc::new(RwLock::new(jwks_map));
         let (_drop_signal, drop_receiver) = oneshot::channel::<()>();
 
-        tokio::task::spawn(poll(list.clone(), jwks_map.clone(), drop_receiver));
+        tokio::task::spawn(poll(sources, jwks_map.clone(), drop_receiver));
 
         Ok(JwksManager {
             list,
@@ -98,26 +114,35 @@ impl JwksManager {
     }
 }
 
+/// Refreshes each JWKS after the delay scheduled by its previous download
 async fn poll(
-    list: Vec<JwksConfig>,
+    sources: Vec<(JwksConfig, Arc<RefreshSource>, Duration)>,
     jwks_map: Arc<RwLock<HashMap<Url, JwkSet>>>,
     drop_receiver: oneshot::Receiver<()>,
 ) {
     use futures::stream::StreamExt;
 
-    let mut streams = select_all(list.into_iter().map(move |config| {
+    let mut streams = select_all(sources.into_iter().map(move |(config, source, delay)| {
         let jwks_map = jwks_map.clone();
-        Box::pin(
-            repeat((config, jwks_map)).then(|(config, jwks_map)| async move {
-                tokio::time::sleep(config.poll_interval).await;
+        Box::pin(unfold(delay, move |delay| {
+            let config = config.clone();
+            let source = source.clone();
+            let jwks_map = jwks_map.clone();
+            async move {
+                source.wait(delay).await;
 
-                if let Some(jwks) = get_jwks(config.url.clone(), config.headers.clone()).await {
-                    if let Ok(mut map) = jwks_map.write() {
-                        map.insert(config.url, jwks);
+                let delay = match get_jwks(config.url.clone(), config.headers.clone()).await {
+                    Some(jwks) => {
+                        if let Ok(mut map) = jwks_map.write() {
+                            map.insert(config.url, jwks);
+                        }
+                        source.succeeded(config.poll_interval)
                     }
-                }
-            }),
-        )
+                    None => source.failed(config.poll_interval, JWKS_REFRESH_ERROR),
+                };
+                Some(((), delay))
+            }
+        }))
     }));
 
     pin_mut!(drop_receiver);
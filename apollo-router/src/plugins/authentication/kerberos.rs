@@ -0,0 +1,591 @@
+//! Kerberos authentication of subgraph requests, with SPNEGO tokens sent in the
+//! `Authorization: Negotiate <token>` header.
+//!
+//! Tokens are created with the system GSSAPI library of MIT Kerberos (`libgssapi_krb5`), which
+//! requires the router to be built with the `kerberos` feature.
+
+use std::path::PathBuf;
+
+use base64::prelude::BASE64_STANDARD;
+use base64::Engine as _;
+use http::header::AUTHORIZATION;
+use http::HeaderValue;
+use http::Request;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+
+/// Configure Kerberos auth, with SPNEGO tokens.
+#[derive(Clone, JsonSchema, Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+#[cfg_attr(not(all(feature = "kerberos", unix)), allow(dead_code))]
+pub(crate) struct KerberosConfig {
+    /// The service principal of the subgraph, like `HTTP@subgraph.example.com`.
+    /// Defaults to `HTTP@<host of the subgraph URL>`.
+    #[serde(default)]
+    service_principal: Option<String>,
+    /// The client principal, like `router@EXAMPLE.COM`.
+    /// Defaults to the principal of the keytab or credential cache.
+    #[serde(default)]
+    principal: Option<String>,
+    /// Path of the keytab the client credentials are acquired from.
+    #[serde(default)]
+    keytab: Option<PathBuf>,
+    /// Path of the credential cache holding the client credentials.
+    #[serde(default)]
+    credential_cache: Option<PathBuf>,
+}
+
+impl KerberosConfig {
+    pub(super) fn check(&self) -> Result<(), BoxError> {
+        if !gssapi::is_available() {
+            return Err(
+                "Kerberos authentication requires the router to be built with the `kerberos` feature"
+                    .into(),
+            );
+        }
+        Ok(())
+    }
+
+    fn service_principal(&self, host: &str) -> String {
+        self.service_principal
+            .clone()
+            .unwrap_or_else(|| format!("HTTP@{host}"))
+    }
+
+    /// Adds a SPNEGO token for the subgraph to the request
+    pub(super) async fn sign<B>(
+        &self,
+        mut req: Request<B>,
+        subgraph_name: &str,
+    ) -> Result<Request<B>, BoxError> {
+        let host = req.uri().host().unwrap_or_default();
+        let service_principal = self.service_principal(host);
+        let config = self.clone();
+        // acquiring credentials can block on the KDC
+        let token =
+            tokio::task::spawn_blocking(move || gssapi::init_token(&config, &service_principal))
+                .await
+                .map_err(|err| err.to_string())
+                .and_then(|token| token);
+        let token = match token {
+            Ok(token) => token,
+            Err(err) => {
+                increment_failure_counter(subgraph_name);
+                let error = format!("failed to create a SPNEGO token for Kerberos: {err}");
+                tracing::error!("{}", error);
+                return Err(error.into());
+            }
+        };
+
+        req.headers_mut().insert(
+            AUTHORIZATION,
+            HeaderValue::from_str(&format!("Negotiate {}", BASE64_STANDARD.encode(token)))?,
+        );
+        increment_success_counter(subgraph_name);
+        Ok(req)
+    }
+}
+
+fn increment_success_counter(subgraph_name: &str) {
+    tracing::info!(
+        monotonic_counter.apollo.router.operations.authentication.kerberos = 1u64,
+        authentication.kerberos.failed = false,
+        subgraph.service.name = %subgraph_name,
+    );
+}
+fn increment_failure_counter(subgraph_name: &str) {
+    tracing::info!(
+        monotonic_counter.apollo.router.operations.authentication.kerberos = 1u64,
+        authentication.kerberos.failed = true,
+        subgraph.service.name = %subgraph_name,
+    );
+}
+
+#[cfg(all(feature = "kerberos", unix))]
+mod gssapi {
+    #![allow(non_camel_case_types)]
+
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::os::raw::c_char;
+    use std::os::raw::c_int;
+    use std::os::raw::c_void;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+    use std::path::PathBuf;
+    use std::ptr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use once_cell::sync::Lazy;
+    use parking_lot::Mutex;
+
+    use super::KerberosConfig;
+
+    /// 1.2.840.113554.1.2.1.4
+    const NT_HOSTBASED_SERVICE: &[u8] = b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x01\x04";
+    /// 1.2.840.113554.1.2.2.1
+    const NT_KRB5_PRINCIPAL_NAME: &[u8] = b"\x2a\x86\x48\x86\xf7\x12\x01\x02\x02\x01";
+    /// 1.3.6.1.5.5.2
+    const MECH_SPNEGO: &[u8] = b"\x2b\x06\x01\x05\x05\x02";
+
+    const GSS_C_INITIATE: c_int = 1;
+    const GSS_C_GSS_CODE: c_int = 1;
+    const GSS_C_MECH_CODE: c_int = 2;
+    const GSS_C_INDEFINITE: OM_uint32 = 0xffff_ffff;
+
+    /// Credentials are acquired again this long before they expire
+    const REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+    /// Principal, keytab and credential cache of the credentials
+    type CredentialsKey = (Option<String>, Option<PathBuf>, Option<PathBuf>);
+
+    /// Credentials acquired for each configuration, reused by the requests until they expire
+    static CREDENTIALS: Lazy<Mutex<HashMap<CredentialsKey, Arc<Credentials>>>> =
+        Lazy::new(Default::default);
+
+    // Declarations of `gssapi.h` for the few functions used here
+    type OM_uint32 = u32;
+    type gss_name_t = *mut c_void;
+    type gss_cred_id_t = *mut c_void;
+    type gss_ctx_id_t = *mut c_void;
+    type gss_OID_set = *mut c_void;
+    type gss_channel_bindings_t = *mut c_void;
+
+    #[repr(C)]
+    struct gss_OID_desc {
+        length: OM_uint32,
+        elements: *mut c_void,
+    }
+
+    #[repr(C)]
+    struct gss_buffer_desc {
+        length: usize,
+        value: *mut c_void,
+    }
+
+    // Credential store extension (`gssapi_ext.h`)
+    #[repr(C)]
+    struct KeyValueElement {
+        key: *const c_char,
+        value: *const c_char,
+    }
+
+    #[repr(C)]
+    struct KeyValueSet {
+        count: OM_uint32,
+        elements: *const KeyValueElement,
+    }
+
+    #[link(name = "gssapi_krb5")]
+    extern "C" {
+        fn gss_import_name(
+            minor_status: *mut OM_uint32,
+            input_name_buffer: *mut gss_buffer_desc,
+            input_name_type: *mut gss_OID_desc,
+            output_name: *mut gss_name_t,
+        ) -> OM_uint32;
+
+        fn gss_release_name(minor_status: *mut OM_uint32, name: *mut gss_name_t) -> OM_uint32;
+
+        fn gss_release_cred(
+            minor_status: *mut OM_uint32,
+            cred_handle: *mut gss_cred_id_t,
+        ) -> OM_uint32;
+
+        fn gss_init_sec_context(
+            minor_status: *mut OM_uint32,
+            initiator_cred_handle: gss_cred_id_t,
+            context_handle: *mut gss_ctx_id_t,
+            target_name: gss_name_t,
+            mech_type: *mut gss_OID_desc,
+            req_flags: OM_uint32,
+            time_req: OM_uint32,
+            input_chan_bindings: gss_channel_bindings_t,
+            input_token: *mut gss_buffer_desc,
+            actual_mech_type: *mut *mut gss_OID_desc,
+            output_token: *mut gss_buffer_desc,
+            ret_flags: *mut OM_uint32,
+            time_rec: *mut OM_uint32,
+        ) -> OM_uint32;
+
+        fn gss_delete_sec_context(
+            minor_status: *mut OM_uint32,
+            context_handle: *mut gss_ctx_id_t,
+            output_token: *mut gss_buffer_desc,
+        ) -> OM_uint32;
+
+        fn gss_display_status(
+            minor_status: *mut OM_uint32,
+            status_value: OM_uint32,
+            status_type: c_int,
+            mech_type: *mut gss_OID_desc,
+            message_context: *mut OM_uint32,
+            status_string: *mut gss_buffer_desc,
+        ) -> OM_uint32;
+
+        fn gss_release_buffer(
+            minor_status: *mut OM_uint32,
+            buffer: *mut gss_buffer_desc,
+        ) -> OM_uint32;
+
+        fn gss_acquire_cred_from(
+            minor_status: *mut OM_uint32,
+            desired_name: gss_name_t,
+            time_req: OM_uint32,
+            desired_mechs: gss_OID_set,
+            cred_usage: c_int,
+            cred_store: *const KeyValueSet,
+            output_cred_handle: *mut gss_cred_id_t,
+            actual_mechs: *mut gss_OID_set,
+            time_rec: *mut OM_uint32,
+        ) -> OM_uint32;
+    }
+
+    pub(super) fn is_available() -> bool {
+        true
+    }
+
+    fn oid(der: &'static [u8]) -> gss_OID_desc {
+        gss_OID_desc {
+            length: der.len() as OM_uint32,
+            elements: der.as_ptr() as *mut c_void,
+        }
+    }
+
+    struct Name(gss_name_t);
+
+    impl Drop for Name {
+        fn drop(&mut self) {
+            let mut minor = 0;
+            // SAFETY: the name was created by `gss_import_name`
+            unsafe { gss_release_name(&mut minor, &mut self.0) };
+        }
+    }
+
+    struct Credentials {
+        handle: gss_cred_id_t,
+        /// `None` when the credentials do not expire
+        expires_at: Option<Instant>,
+    }
+
+    // SAFETY: MIT Kerberos credential handles are locked internally, so they can be used by
+    // concurrent `gss_init_sec_context` calls
+    unsafe impl Send for Credentials {}
+    unsafe impl Sync for Credentials {}
+
+    impl Credentials {
+        fn expires_soon(&self) -> bool {
+            self.expires_at
+                .is_some_and(|expires_at| Instant::now() + REFRESH_MARGIN >= expires_at)
+        }
+    }
+
+    impl Drop for Credentials {
+        fn drop(&mut self) {
+            let mut minor = 0;
+            // SAFETY: the credentials were acquired by `gss_acquire_cred_from`
+            unsafe { gss_release_cred(&mut minor, &mut self.handle) };
+        }
+    }
+
+    struct SecurityContext(gss_ctx_id_t);
+
+    impl Drop for SecurityContext {
+        fn drop(&mut self) {
+            if !self.0.is_null() {
+                let mut minor = 0;
+                // SAFETY: the context was created by `gss_init_sec_context`
+                unsafe { gss_delete_sec_context(&mut minor, &mut self.0, ptr::null_mut()) };
+            }
+        }
+    }
+
+    struct Buffer(gss_buffer_desc);
+
+    impl Buffer {
+        fn empty() -> Self {
+            Buffer(gss_buffer_desc {
+                length: 0,
+                value: ptr::null_mut(),
+            })
+        }
+
+        fn to_vec(&self) -> Vec<u8> {
+            if self.0.value.is_null() {
+                return Vec::new();
+            }
+            // SAFETY: the buffer was filled by the GSSAPI library with `length` bytes
+            unsafe { std::slice::from_raw_parts(self.0.value as *const u8, self.0.length) }.to_vec()
+        }
+    }
+
+    impl Drop for Buffer {
+        fn drop(&mut self) {
+            let mut minor = 0;
+            // SAFETY: the buffer is empty or was filled by the GSSAPI library
+            unsafe { gss_release_buffer(&mut minor, &mut self.0) };
+        }
+    }
+
+    /// Messages of a status code
+    fn display_status(code: OM_uint32, status_type: c_int) -> String {
+        let mut messages = Vec::new();
+        let mut message_context = 0;
+        loop {
+            let mut minor = 0;
+            let mut buffer = Buffer::empty();
+            // SAFETY: the message buffer is released by `Buffer`
+            let major = unsafe {
+                gss_display_status(
+                    &mut minor,
+                    code,
+                    status_type,
+                    ptr::null_mut(),
+                    &mut message_context,
+                    &mut buffer.0,
+                )
+            };
+            if is_error(major) {
+                break;
+            }
+            messages.push(String::from_utf8_lossy(&buffer.to_vec()).into_owned());
+            if message_context == 0 {
+                break;
+            }
+        }
+        messages.join(", ")
+    }
+
+    fn is_error(major: OM_uint32) -> bool {
+        major & 0xffff_0000 != 0
+    }
+
+    fn check(call: &str, major: OM_uint32, minor: OM_uint32) -> Result<(), String> {
+        if is_error(major) {
+            Err(format!(
+                "{call} failed: {} ({})",
+                display_status(major, GSS_C_GSS_CODE),
+                display_status(minor, GSS_C_MECH_CODE)
+            ))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn import_name(name: &str, name_type: &'static [u8]) -> Result<Name, String> {
+        let mut minor = 0;
+        let mut name_type = oid(name_type);
+        let mut buffer = gss_buffer_desc {
+            length: name.len(),
+            value: name.as_ptr() as *mut c_void,
+        };
+        let mut output = ptr::null_mut();
+        // SAFETY: the input buffer and the OID outlive the call, which copies them
+        let major =
+            unsafe { gss_import_name(&mut minor, &mut buffer, &mut name_type, &mut output) };
+        check("gss_import_name", major, minor)?;
+        Ok(Name(output))
+    }
+
+    fn path(path: &Path) -> Result<CString, String> {
+        CString::new(path.as_os_str().as_bytes()).map_err(|e| e.to_string())
+    }
+
+    fn acquire_credentials(config: &KerberosConfig) -> Result<Credentials, String> {
+        let name = config
+            .principal
+            .as_deref()
+            .map(|principal| import_name(principal, NT_KRB5_PRINCIPAL_NAME))
+            .transpose()?;
+        let keytab = config.keytab.as_deref().map(path).transpose()?;
+        let credential_cache = config.credential_cache.as_deref().map(path).transpose()?;
+
+        let mut elements = Vec::new();
+        if let Some(keytab) = &keytab {
+            elements.push(KeyValueElement {
+                key: b"client_keytab\0".as_ptr() as *const c_char,
+                value: keytab.as_ptr(),
+            });
+        }
+        if let Some(credential_cache) = &credential_cache {
+            elements.push(KeyValueElement {
+                key: b"ccache\0".as_ptr() as *const c_char,
+                value: credential_cache.as_ptr(),
+            });
+        }
+        let store = KeyValueSet {
+            count: elements.len() as OM_uint32,
+            elements: elements.as_ptr(),
+        };
+
+        let mut minor = 0;
+        let mut credentials = ptr::null_mut();
+        let mut lifetime = 0;
+        // SAFETY: the name and the credential store strings outlive the call, and the
+        // credentials are released by `Credentials`
+        let major = unsafe {
+            gss_acquire_cred_from(
+                &mut minor,
+                name.as_ref().map(|name| name.0).unwrap_or(ptr::null_mut()),
+                0,
+                ptr::null_mut(),
+                GSS_C_INITIATE,
+                if elements.is_empty() {
+                    ptr::null()
+                } else {
+                    &store
+                },
+                &mut credentials,
+                ptr::null_mut(),
+                &mut lifetime,
+            )
+        };
+        check("gss_acquire_cred_from", major, minor)?;
+        Ok(Credentials {
+            handle: credentials,
+            expires_at: (lifetime != GSS_C_INDEFINITE)
+                .then(|| Instant::now() + Duration::from_secs(lifetime.into())),
+        })
+    }
+
+    fn credentials_key(config: &KerberosConfig) -> CredentialsKey {
+        (
+            config.principal.clone(),
+            config.keytab.clone(),
+            config.credential_cache.clone(),
+        )
+    }
+
+    /// Cached credentials of the configuration, acquired again when they are about to expire
+    fn credentials(config: &KerberosConfig) -> Result<Arc<Credentials>, String> {
+        let key = credentials_key(config);
+        // the lock is held while acquiring, so concurrent requests wait for the same credentials
+        let mut cache = CREDENTIALS.lock();
+        if let Some(credentials) = cache.get(&key) {
+            if !credentials.expires_soon() {
+                return Ok(credentials.clone());
+            }
+        }
+        let credentials = Arc::new(acquire_credentials(config)?);
+        cache.insert(key, credentials.clone());
+        Ok(credentials)
+    }
+
+    /// Removes credentials that failed, like credentials revoked before their expiration, so the
+    /// next request acquires them again
+    fn forget_credentials(config: &KerberosConfig, credentials: &Arc<Credentials>) {
+        let mut cache = CREDENTIALS.lock();
+        let key = credentials_key(config);
+        if cache
+            .get(&key)
+            .is_some_and(|cached| Arc::ptr_eq(cached, credentials))
+        {
+            cache.remove(&key);
+        }
+    }
+
+    /// Creates the initial SPNEGO token of a security context with the service
+    pub(super) fn init_token(
+        config: &KerberosConfig,
+        service_principal: &str,
+    ) -> Result<Vec<u8>, String> {
+        let credentials = credentials(config)?;
+        let target = import_name(service_principal, NT_HOSTBASED_SERVICE)?;
+        let mut mech = oid(MECH_SPNEGO);
+
+        let mut minor = 0;
+        let mut context = SecurityContext(ptr::null_mut());
+        let mut output = Buffer::empty();
+        // SAFETY: the inputs outlive the call, the context and the output token are released by
+        // `SecurityContext` and `Buffer`
+        let major = unsafe {
+            gss_init_sec_context(
+                &mut minor,
+                credentials.handle,
+                &mut context.0,
+                target.0,
+                &mut mech,
+                0,
+                0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+                ptr::null_mut(),
+                &mut output.0,
+                ptr::null_mut(),
+                ptr::null_mut(),
+            )
+        };
+        if let Err(error) = check("gss_init_sec_context", major, minor) {
+            forget_credentials(config, &credentials);
+            return Err(error);
+        }
+        Ok(output.to_vec())
+    }
+}
+
+#[cfg(not(all(feature = "kerberos", unix)))]
+mod gssapi {
+    use super::KerberosConfig;
+
+    pub(super) fn is_available() -> bool {
+        false
+    }
+
+    pub(super) fn init_token(
+        _config: &KerberosConfig,
+        _service_principal: &str,
+    ) -> Result<Vec<u8>, String> {
+        Err("the router was not built with the `kerberos` feature".to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn config(service_principal: Option<&str>) -> KerberosConfig {
+        KerberosConfig {
+            service_principal: service_principal.map(str::to_string),
+            principal: None,
+            keytab: None,
+            credential_cache: Some(PathBuf::from("/tmp/krb5cc_router")),
+        }
+    }
+
+    #[test]
+    fn service_principal() {
+        assert_eq!(
+            config(None).service_principal("products.example.com"),
+            "HTTP@products.example.com"
+        );
+        assert_eq!(
+            config(Some("HTTP@gateway.example.com")).service_principal("products.example.com"),
+            "HTTP@gateway.example.com"
+        );
+    }
+
+    #[test]
+    fn check() {
+        assert_eq!(
+            config(None).check().is_ok(),
+            cfg!(all(feature = "kerberos", unix))
+        );
+    }
+
+    #[cfg(not(feature = "kerberos"))]
+    #[tokio::test]
+    async fn sign_requires_feature() {
+        let request = Request::builder()
+            .uri("http://products.example.com/graphql")
+            .body(())
+            .unwrap();
+        let error = config(None).sign(request, "products").await.unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "failed to create a SPNEGO token for Kerberos: the router was not built with the `kerberos` feature"
+        );
+    }
+}
@@ -42,6 +42,7 @@ use self::jwks::JwksManager;
 use self::subgraph::SigningParams;
 use self::subgraph::SigningParamsConfig;
 use self::subgraph::SubgraphAuth;
+use crate::context::keys;
 use crate::graphql;
 use crate::layers::ServiceBuilderExt;
 use crate::plugin::serde::deserialize_header_name;
@@ -56,13 +57,14 @@ use crate::services::APPLICATION_JSON_HEADER_VALUE;
 use crate::Context;
 
 mod jwks;
+mod kerberos;
 pub(crate) mod subgraph;
 
 #[cfg(test)]
 mod tests;
 
 pub(crate) const AUTHENTICATION_SPAN_NAME: &str = "authentication_plugin";
-pub(crate) const APOLLO_AUTHENTICATION_JWT_CLAIMS: &str = "apollo_authentication::JWT::claims";
+pub(crate) const APOLLO_AUTHENTICATION_JWT_CLAIMS: &str = keys::JWT_CLAIMS.name();
 const HEADER_TOKEN_TRUNCATED: &str = "(truncated)";
 
 #[derive(Debug, Display, Error)]
@@ -25,6 +25,7 @@ use tower::BoxError;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 
+use super::kerberos::KerberosConfig;
 use crate::services::router::body::get_body_bytes;
 use crate::services::router::body::RouterBody;
 use crate::services::SubgraphRequest;
@@ -175,6 +176,8 @@ impl AWSSigV4Config {
 pub(crate) enum AuthConfig {
     #[serde(rename = "aws_sig_v4")]
     AWSSigV4(AWSSigV4Config),
+    #[serde(rename = "kerberos")]
+    Kerberos(KerberosConfig),
 }
 
 /// Configure subgraph authentication
@@ -197,7 +200,37 @@ pub(crate) struct SigningParams {
 }
 
 #[derive(Clone)]
-pub(crate) struct SigningParamsConfig {
+pub(crate) enum SigningParamsConfig {
+    AWSSigV4(AWSSigV4Params),
+    Kerberos(KerberosConfig),
+}
+
+impl SigningParamsConfig {
+    pub(crate) async fn sign(
+        &self,
+        req: Request<RouterBody>,
+        subgraph_name: &str,
+    ) -> Result<Request<RouterBody>, BoxError> {
+        match self {
+            Self::AWSSigV4(params) => params.sign(req, subgraph_name).await,
+            Self::Kerberos(config) => config.sign(req, subgraph_name).await,
+        }
+    }
+
+    pub(crate) async fn sign_empty(
+        &self,
+        req: Request<()>,
+        subgraph_name: &str,
+    ) -> Result<Request<()>, BoxError> {
+        match self {
+            Self::AWSSigV4(params) => params.sign_empty(req, subgraph_name).await,
+            Self::Kerberos(config) => config.sign(req, subgraph_name).await,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) struct AWSSigV4Params {
     credentials_provider: CredentialsProvider,
     region: Region,
     service_name: String,
@@ -301,8 +334,8 @@ impl ProvideCredentials for CredentialsProvider {
     }
 }
 
-impl SigningParamsConfig {
-    pub(crate) async fn sign(
+impl AWSSigV4Params {
+    async fn sign(
         &self,
         mut req: Request<RouterBody>,
         subgraph_name: &str,
@@ -342,7 +375,7 @@ impl SigningParamsConfig {
     }
 
     // This function is the same as above, except it's a new one because () doesn't implement HttpBody`
-    pub(crate) async fn sign_empty(
+    async fn sign_empty(
         &self,
         mut req: Request<()>,
         subgraph_name: &str,
@@ -430,7 +463,7 @@ pub(super) async fn make_signing_params(
     match config {
         AuthConfig::AWSSigV4(config) => {
             let credentials_provider = config.get_credentials_provider().await;
-            Ok(SigningParamsConfig {
+            Ok(SigningParamsConfig::AWSSigV4(AWSSigV4Params {
                 region: config.region(),
                 service_name: config.service_name(),
                 credentials_provider: CredentialsProvider::from_provide_credentials(
@@ -439,14 +472,18 @@ pub(super) async fn make_signing_params(
                 .await
                 .map_err(BoxError::from)?,
                 subgraph_name: subgraph_name.to_string(),
-            })
+            }))
+        }
+        AuthConfig::Kerberos(config) => {
+            config.check()?;
+            Ok(SigningParamsConfig::Kerberos(config.clone()))
         }
     }
 }
 
 /// There are three possible cases
 /// https://github.com/awslabs/aws-sdk-rust/blob/9c3168dafa4fd8885ce4e1fd41cec55ce982a33c/sdk/aws-sigv4/src/http_request/sign.rs#L264C1-L271C6
-fn get_signing_settings(signing_params: &SigningParamsConfig) -> SigningSettings {
+fn get_signing_settings(signing_params: &AWSSigV4Params) -> SigningSettings {
     let mut settings = SigningSettings::default();
     settings.payload_checksum_kind = match signing_params.service_name.as_str() {
         "appsync" | "s3" | "vpc-lattice-svcs" => PayloadChecksumKind::XAmzSha256,
@@ -513,7 +550,7 @@ mod test {
     use crate::Context;
 
     async fn test_signing_settings(service_name: &str) -> SigningSettings {
-        let params: SigningParamsConfig = make_signing_params(
+        let SigningParamsConfig::AWSSigV4(params) = make_signing_params(
             &AuthConfig::AWSSigV4(AWSSigV4Config::Hardcoded(AWSSigV4HardcodedConfig {
                 access_key_id: "id".to_string(),
                 secret_access_key: "secret".to_string(),
@@ -524,7 +561,9 @@ mod test {
             "all",
         )
         .await
-        .unwrap();
+        .unwrap() else {
+            panic!("expected AWS SigV4 signing params")
+        };
         get_signing_settings(&params)
     }
 
@@ -568,6 +607,33 @@ mod test {
         .unwrap();
     }
 
+    #[test]
+    fn test_subgraph_kerberos_config() {
+        serde_yaml::from_str::<Config>(
+            r#"
+        subgraphs:
+          products:
+            kerberos:
+              service_principal: "HTTP@products.example.com"
+              keytab: "/etc/router/router.keytab"
+        "#,
+        )
+        .unwrap();
+    }
+
+    #[cfg(not(feature = "kerberos"))]
+    #[tokio::test]
+    async fn test_kerberos_requires_feature() {
+        let config = serde_yaml::from_str::<AuthConfig>(
+            r#"
+        kerberos:
+          credential_cache: "/tmp/krb5cc_router"
+        "#,
+        )
+        .unwrap();
+        assert!(make_signing_params(&config, "products").await.is_err());
+    }
+
     #[test]
     fn test_subgraph_aws_sig_v4_hardcoded_config() {
         serde_yaml::from_str::<Config>(
@@ -65,7 +65,6 @@ pub(crate) struct CacheKeyMetadata {
 
 /// Authorization plugin
 #[derive(Clone, Debug, serde_derive_default::Default, Deserialize, JsonSchema)]
-#[allow(dead_code)]
 pub(crate) struct Conf {
     /// Reject unauthenticated requests
     #[serde(default)]
@@ -76,7 +75,6 @@ pub(crate) struct Conf {
 }
 
 #[derive(Clone, Debug, serde_derive_default::Default, Deserialize, JsonSchema)]
-#[allow(dead_code)]
 pub(crate) struct Directives {
     /// enables the `@authenticated` and `@requiresScopes` directives
     #[serde(default = "default_enable_directives")]
@@ -89,13 +87,15 @@ pub(crate) struct Directives {
     reject_unauthorized: bool,
     /// authorization errors behaviour
     #[serde(default)]
-    errors: ErrorConfig,
+    pub(crate) errors: ErrorConfig,
+    /// `@requiresScopes` matching
+    #[serde(default)]
+    scopes: ScopesConfig,
 }
 
 #[derive(
     Clone, Debug, serde_derive_default::Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema,
 )]
-#[allow(dead_code)]
 pub(crate) struct ErrorConfig {
     /// log authorization errors
     #[serde(default = "enable_log_errors")]
@@ -109,6 +109,65 @@ fn enable_log_errors() -> bool {
     true
 }
 
+#[derive(
+    Clone, Debug, serde_derive_default::Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema,
+)]
+pub(crate) struct ScopesConfig {
+    /// how the scopes of a request satisfy the scopes required by `@requiresScopes`
+    #[serde(default)]
+    pub(crate) matching: ScopeMatching,
+    /// separator between the segments of a scope, used by the `wildcard` and `hierarchical`
+    /// matching
+    #[serde(default = "default_scope_separator")]
+    pub(crate) separator: String,
+}
+
+fn default_scope_separator() -> String {
+    ":".to_string()
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ScopeMatching {
+    /// a required scope is only satisfied by the same scope
+    #[default]
+    Exact,
+    /// a scope ending with a `*` segment, like `orders:*`, also satisfies the scopes starting
+    /// with its other segments, like `orders:read`
+    Wildcard,
+    /// like `wildcard`, and a scope also satisfies the scopes below it in the hierarchy, like
+    /// `orders` for `orders:read`
+    Hierarchical,
+}
+
+impl ScopesConfig {
+    /// Whether the `granted` scope of a request satisfies a `required` scope
+    pub(crate) fn satisfies(&self, granted: &str, required: &str) -> bool {
+        if granted == required {
+            return true;
+        }
+        let separator = self.separator.as_str();
+        match self.matching {
+            ScopeMatching::Exact => false,
+            ScopeMatching::Wildcard | ScopeMatching::Hierarchical => {
+                // `*` alone is not a wildcard: it would satisfy every scope
+                let wildcard = granted
+                    .strip_suffix('*')
+                    .filter(|prefix| !prefix.is_empty() && prefix.ends_with(separator));
+                if let Some(prefix) = wildcard {
+                    if required.len() > prefix.len() && required.starts_with(prefix) {
+                        return true;
+                    }
+                }
+                self.matching == ScopeMatching::Hierarchical
+                    && required
+                        .strip_prefix(granted)
+                        .is_some_and(|rest| rest.starts_with(separator))
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub(crate) enum ErrorLocation {
@@ -136,17 +195,31 @@ pub(crate) struct AuthorizationPlugin {
 }
 
 impl AuthorizationPlugin {
-    pub(crate) fn enable_directives(
+    /// The `directives` section of the configuration of the plugin
+    pub(crate) fn directives(
         configuration: &Configuration,
-        schema: &Schema,
-    ) -> Result<bool, ServiceBuildError> {
-        let has_config = configuration
+    ) -> Result<Directives, ServiceBuildError> {
+        configuration
             .apollo_plugins
             .plugins
             .iter()
             .find(|(s, _)| s.as_str() == "authorization")
-            .and_then(|(_, v)| v.get("directives").and_then(|v| v.as_object()))
-            .and_then(|v| v.get("enabled").and_then(|v| v.as_bool()));
+            .and_then(|(_, v)| v.get("directives"))
+            .map(|v| serde_json::from_value(v.clone()))
+            .transpose()
+            .map(Option::unwrap_or_default)
+            .map_err(|e| {
+                ServiceBuildError::ServiceError(
+                    format!("invalid authorization.directives configuration: {e}").into(),
+                )
+            })
+    }
+
+    pub(crate) fn enable_directives(
+        configuration: &Configuration,
+        schema: &Schema,
+    ) -> Result<bool, ServiceBuildError> {
+        let enabled = Self::directives(configuration)?.enabled;
 
         let has_authorization_directives = schema.has_spec(
             AUTHENTICATED_SPEC_BASE_URL,
@@ -157,21 +230,7 @@ impl AuthorizationPlugin {
         ) || schema
             .has_spec(POLICY_SPEC_BASE_URL, POLICY_SPEC_VERSION_RANGE);
 
-        Ok(has_config.unwrap_or(true) && has_authorization_directives)
-    }
-
-    pub(crate) fn log_errors(configuration: &Configuration) -> ErrorConfig {
-        configuration
-            .apollo_plugins
-            .plugins
-            .iter()
-            .find(|(s, _)| s.as_str() == "authorization")
-            .and_then(|(_, v)| v.get("directives").and_then(|v| v.as_object()))
-            .and_then(|v| {
-                v.get("errors")
-                    .and_then(|v| serde_json::from_value(v.clone()).ok())
-            })
-            .unwrap_or_default()
+        Ok(enabled && has_authorization_directives)
     }
 
     pub(crate) fn query_analysis(
@@ -248,7 +307,20 @@ impl AuthorizationPlugin {
         }
     }
 
-    pub(crate) fn update_cache_key(context: &Context) {
+    pub(crate) fn scopes_config(
+        configuration: &Configuration,
+    ) -> Result<ScopesConfig, ServiceBuildError> {
+        Ok(Self::directives(configuration)?.scopes)
+    }
+
+    /// Whether the operation uses authorization directives, as found by the query analysis
+    pub(crate) fn requires_authorization(context: &Context) -> bool {
+        context.contains_key(AUTHENTICATED_KEY)
+            || context.contains_key(REQUIRED_SCOPES_KEY)
+            || context.contains_key(REQUIRED_POLICIES_KEY)
+    }
+
+    pub(crate) fn update_cache_key(context: &Context, scopes_config: &ScopesConfig) {
         let is_authenticated = context.contains_key(APOLLO_AUTHENTICATION_JWT_CLAIMS);
 
         let request_scopes = context
@@ -272,7 +344,14 @@ impl AuthorizationPlugin {
         let mut scopes = match (request_scopes, query_scopes) {
             (None, _) => vec![],
             (_, None) => vec![],
-            (Some(req), Some(query)) => req.intersection(&query).cloned().collect(),
+            // the required scopes satisfied by the request
+            (Some(req), Some(query)) => query
+                .into_iter()
+                .filter(|required| {
+                    req.iter()
+                        .any(|granted| scopes_config.satisfies(granted, required))
+                })
+                .collect(),
         };
         scopes.sort();
 
@@ -324,29 +403,12 @@ impl AuthorizationPlugin {
     }
 
     pub(crate) fn filter_query(
-        configuration: &Configuration,
+        directives: &Directives,
         key: &QueryKey,
         schema: &Schema,
     ) -> Result<Option<FilteredQuery>, QueryPlannerError> {
-        let (reject_unauthorized, dry_run) = configuration
-            .apollo_plugins
-            .plugins
-            .iter()
-            .find(|(s, _)| s.as_str() == "authorization")
-            .and_then(|(_, v)| v.get("directives").and_then(|v| v.as_object()))
-            .map(|config| {
-                (
-                    config
-                        .get("reject_unauthorized")
-                        .and_then(|v| v.as_bool())
-                        .unwrap_or(false),
-                    config
-                        .get("dry_run")
-                        .and_then(|v| v.as_bool())
-                        .unwrap_or(false),
-                )
-            })
-            .unwrap_or((false, false));
+        let reject_unauthorized = directives.reject_unauthorized;
+        let dry_run = directives.dry_run;
 
         // The filtered query will then be used
         // to generate selections for response formatting, to execute introspection and
@@ -543,6 +605,9 @@ impl Plugin for AuthorizationPlugin {
     type Config = Conf;
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        if init.config.directives.scopes.separator.is_empty() {
+            return Err("authorization.directives.scopes.separator cannot be empty".into());
+        }
         Ok(AuthorizationPlugin {
             require_authentication: init.config.require_authentication,
         })
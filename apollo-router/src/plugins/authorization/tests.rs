@@ -1157,3 +1157,134 @@ async fn cache_key_metadata() {
 
     insta::assert_json_snapshot!(response);
 }
+
+#[test]
+fn scope_matching() {
+    use crate::plugins::authorization::ScopeMatching;
+    use crate::plugins::authorization::ScopesConfig;
+
+    let exact = ScopesConfig::default();
+    assert!(exact.satisfies("orders:read", "orders:read"));
+    assert!(!exact.satisfies("orders:*", "orders:read"));
+    assert!(!exact.satisfies("orders", "orders:read"));
+
+    let wildcard = ScopesConfig {
+        matching: ScopeMatching::Wildcard,
+        ..Default::default()
+    };
+    assert!(wildcard.satisfies("orders:*", "orders:read"));
+    assert!(wildcard.satisfies("orders:*", "orders:items:write"));
+    assert!(!wildcard.satisfies("orders:*", "orders"));
+    assert!(!wildcard.satisfies("orders:*", "ordersx:read"));
+    assert!(!wildcard.satisfies("orders", "orders:read"));
+    assert!(!wildcard.satisfies("*", "orders:read"));
+
+    let hierarchical = ScopesConfig {
+        matching: ScopeMatching::Hierarchical,
+        separator: "/".to_string(),
+    };
+    assert!(hierarchical.satisfies("orders", "orders/read"));
+    assert!(hierarchical.satisfies("orders/*", "orders/read"));
+    assert!(!hierarchical.satisfies("orders", "ordersx/read"));
+    assert!(!hierarchical.satisfies("orders/read", "orders"));
+    assert!(!hierarchical.satisfies("orders", "orders:read"));
+}
+
+#[tokio::test]
+async fn filter_cache_metrics() {
+    use crate::metrics::FutureMetricsExt;
+
+    async {
+        let subgraphs = MockedSubgraphs([
+            ("user", MockSubgraph::builder().with_json(
+                serde_json::json!{{
+                    "query": "query($representations:[_Any!]!){_entities(representations:$representations){...on User{name}}}",
+                    "variables": {"representations": [{ "__typename": "User", "id":0 }],}
+                }},
+                serde_json::json! {{ "data": {"_entities":[{ "name":"Ada" }] }}},
+            ).build()),
+            ("orga", MockSubgraph::builder().with_json(
+                serde_json::json!{{"query":"{orga(id:1){id creatorUser{__typename id}}}"}},
+                serde_json::json!{{"data": {"orga": { "id": 1, "creatorUser": { "__typename": "User", "id": 0 } }}}}
+            ).build())
+        ].into_iter().collect());
+
+        let service = TestHarness::builder()
+            .configuration_json(serde_json::json!({
+                "authorization": {
+                    "directives": {
+                        "enabled": true
+                    }
+                }
+            }))
+            .unwrap()
+            .schema(AUTHENTICATED_SCHEMA)
+            .extra_plugin(subgraphs)
+            .build_router()
+            .await
+            .unwrap();
+
+        let request = |context: Context| {
+            let req = graphql::Request {
+                query: Some("query { orga(id: 1) { id creatorUser { id name phone } } }".to_string()),
+                ..Default::default()
+            };
+            router::Request {
+                context,
+                router_request: http::Request::builder()
+                    .method("POST")
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(ACCEPT, "application/json")
+                    .body(serde_json::to_vec(&req).unwrap().into())
+                    .unwrap(),
+            }
+        };
+
+        // the second request with the same claims reuses the filtered query plan
+        for _ in 0..2 {
+            service
+                .clone()
+                .oneshot(request(Context::new()))
+                .await
+                .unwrap()
+                .into_graphql_response_stream()
+                .await
+                .next()
+                .await
+                .unwrap()
+                .unwrap();
+        }
+
+        let context = Context::new();
+        context
+            .insert(
+                "apollo_authentication::JWT::claims",
+                json! {{ "scope": "user:read" }},
+            )
+            .unwrap();
+        service
+            .clone()
+            .oneshot(request(context))
+            .await
+            .unwrap()
+            .into_graphql_response_stream()
+            .await
+            .next()
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_counter!(
+            "apollo.router.authorization.filter_cache",
+            1,
+            result = "hit"
+        );
+        assert_counter!(
+            "apollo.router.authorization.filter_cache",
+            2,
+            result = "miss"
+        );
+    }
+    .with_metrics()
+    .await;
+}
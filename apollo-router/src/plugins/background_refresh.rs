@@ -0,0 +1,321 @@
+//! Report the state of the background refreshes of remote sources, like JWKS and Apollo Uplink.
+//!
+//! Each source reports the time since its last successful refresh and its consecutive failures as
+//! gauges, and the state of all the sources can be exposed on an endpoint. A `POST` request to
+//! the endpoint refreshes the sources right away, for example from a webhook called when a new
+//! supergraph schema is published.
+
+use std::task::Context;
+use std::task::Poll;
+
+use futures::future::BoxFuture;
+use http::Method;
+use http::StatusCode;
+use multimap::MultiMap;
+use opentelemetry::metrics::MeterProvider as _;
+use opentelemetry::metrics::ObservableGauge;
+use opentelemetry::KeyValue;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::metrics::meter_provider;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::refresh::RefreshSource;
+use crate::refresh::RefreshStatus;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::Body;
+use crate::ListenAddr;
+
+/// Background refresh configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct BackgroundRefreshConfig {
+    /// Endpoint listing the state of the background refreshes, and triggering refreshes
+    endpoint: RefreshEndpoint,
+}
+
+/// Background refresh endpoint configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct RefreshEndpoint {
+    /// Set to true to expose the background refresh endpoint
+    enabled: bool,
+    /// The listen address
+    listen: ListenAddr,
+    /// The path of the background refresh endpoint
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of requests to the endpoint
+    token: Option<String>,
+}
+
+impl Default for RefreshEndpoint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            path: "/debug/refresh".to_string(),
+            token: None,
+        }
+    }
+}
+
+struct BackgroundRefresh {
+    endpoint: RefreshEndpoint,
+    _gauges: [ObservableGauge<u64>; 2],
+}
+
+fn attributes(source: &RefreshSource) -> [KeyValue; 2] {
+    [
+        KeyValue::new("kind", source.kind()),
+        KeyValue::new("source", source.name().to_string()),
+    ]
+}
+
+fn gauges() -> [ObservableGauge<u64>; 2] {
+    let meter = meter_provider().meter("apollo/router");
+    [
+        meter
+            .u64_observable_gauge("apollo.router.refresh.staleness")
+            .with_description(
+                "Time in seconds since the last successful refresh of a remote source",
+            )
+            .with_callback(|observer| {
+                for source in RefreshSource::all() {
+                    observer.observe(source.staleness().as_secs(), &attributes(&source));
+                }
+            })
+            .init(),
+        meter
+            .u64_observable_gauge("apollo.router.refresh.consecutive_failures")
+            .with_description("Number of consecutive failed refreshes of a remote source")
+            .with_callback(|observer| {
+                for source in RefreshSource::all() {
+                    observer.observe(
+                        u64::from(source.consecutive_failures()),
+                        &attributes(&source),
+                    );
+                }
+            })
+            .init(),
+    ]
+}
+
+#[async_trait::async_trait]
+impl Plugin for BackgroundRefresh {
+    type Config = BackgroundRefreshConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let endpoint = init.config.endpoint;
+        if endpoint.enabled && endpoint.token.as_deref().unwrap_or_default().is_empty() {
+            return Err(
+                "background_refresh.endpoint.token is required to expose the background refresh endpoint"
+                    .into(),
+            );
+        }
+
+        Ok(BackgroundRefresh {
+            endpoint,
+            _gauges: gauges(),
+        })
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.endpoint.enabled, &self.endpoint.token) {
+            map.insert(
+                self.endpoint.listen.clone(),
+                Endpoint::from_router_service(
+                    self.endpoint.path.clone(),
+                    RefreshService {
+                        token: BearerToken::new(token),
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+#[derive(Clone)]
+struct RefreshService {
+    token: BearerToken,
+}
+
+impl RefreshService {
+    fn handle(
+        &self,
+        request: &http::Request<Body>,
+    ) -> Result<(StatusCode, Vec<RefreshStatus>), (StatusCode, String)> {
+        self.token.check(request.headers())?;
+        match *request.method() {
+            Method::GET => Ok((
+                StatusCode::OK,
+                RefreshSource::all()
+                    .iter()
+                    .map(|source| source.status())
+                    .collect(),
+            )),
+            Method::POST => {
+                // the `source` parameter restricts the refresh to the sources of a kind or name
+                let filter = request.uri().query().and_then(|query| {
+                    url::form_urlencoded::parse(query.as_bytes())
+                        .find(|(key, _)| key == "source")
+                        .map(|(_, value)| value.into_owned())
+                });
+                let sources: Vec<_> = RefreshSource::all()
+                    .into_iter()
+                    .filter(|source| {
+                        filter.as_deref().map_or(true, |filter| {
+                            source.kind() == filter || source.name() == filter
+                        })
+                    })
+                    .collect();
+                if sources.is_empty() {
+                    return Err((StatusCode::NOT_FOUND, "no matching source".to_string()));
+                }
+                for source in &sources {
+                    tracing::info!(
+                        kind = source.kind(),
+                        source = source.name(),
+                        "refresh triggered"
+                    );
+                    source.refresh_now();
+                }
+                Ok((
+                    StatusCode::ACCEPTED,
+                    sources.iter().map(|source| source.status()).collect(),
+                ))
+            }
+            _ => Err((
+                StatusCode::METHOD_NOT_ALLOWED,
+                "expected a GET or POST request".to_string(),
+            )),
+        }
+    }
+}
+
+impl Service<router::Request> for RefreshService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let result = self.handle(&req.router_request);
+        Box::pin(async move {
+            let (status, content_type, body) = match result {
+                Ok((status, sources)) => (
+                    status,
+                    "application/json",
+                    serde_json::to_vec(&serde_json::json!({ "sources": sources }))?,
+                ),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+register_plugin!("apollo", "background_refresh", BackgroundRefresh);
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn lists_the_sources() {
+        let config = serde_json::from_value(json!({ "endpoint": { "enabled": true } })).unwrap();
+        assert!(
+            BackgroundRefresh::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+
+        let source = RefreshSource::register("jwks", "https://idp.example.com/jwks.json");
+        source.failed(Duration::from_secs(60), "could not download the JWKS");
+
+        let service = RefreshService {
+            token: BearerToken::new("secret"),
+        };
+        let request = |authorization: &str| {
+            http::Request::builder()
+                .uri("http://localhost/debug/refresh")
+                .header(http::header::AUTHORIZATION, authorization)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (status, _) = service.handle(&request("Bearer wrong")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, sources) = service.handle(&request("Bearer secret")).unwrap();
+        assert_eq!(status, StatusCode::OK);
+        let sources = serde_json::to_value(sources).unwrap();
+        let status = sources
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|status| status["source"] == "https://idp.example.com/jwks.json")
+            .unwrap();
+        assert_eq!(status["kind"], "jwks");
+        assert_eq!(status["consecutive_failures"], 1);
+        assert_eq!(status["last_error"], "could not download the JWKS");
+    }
+
+    #[tokio::test]
+    async fn triggers_refreshes() {
+        let source = RefreshSource::register("uplink", "triggers_refreshes");
+        let service = RefreshService {
+            token: BearerToken::new("secret"),
+        };
+        let request = |uri: &str| {
+            http::Request::builder()
+                .method(Method::POST)
+                .uri(uri)
+                .header(http::header::AUTHORIZATION, "Bearer secret")
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let (status, _) = service
+            .handle(&request("http://localhost/debug/refresh?source=unknown"))
+            .unwrap_err();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        let (status, sources) = service
+            .handle(&request(
+                "http://localhost/debug/refresh?source=triggers_refreshes",
+            ))
+            .unwrap();
+        assert_eq!(status, StatusCode::ACCEPTED);
+        assert_eq!(sources.len(), 1);
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            source.wait(Duration::from_secs(3600)),
+        )
+        .await
+        .expect("the refresh was triggered");
+    }
+}
@@ -42,7 +42,9 @@ use crate::json_ext::Path;
 use crate::json_ext::PathElement;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
 use crate::plugins::authorization::CacheKeyMetadata;
+use crate::plugins::request_toggles::RequestToggles;
 use crate::query_planner::fetch::QueryHash;
 use crate::query_planner::OperationKind;
 use crate::services::subgraph;
@@ -97,6 +99,15 @@ pub(crate) struct Subgraph {
 
     /// Context key used to separate cache sections per user
     pub(crate) private_id: Option<String>,
+
+    /// JWT claim used to separate cache sections per user, if `private_id` is not set
+    pub(crate) private_id_claim: Option<String>,
+
+    /// maximum expiration for entries with a private scope
+    pub(crate) private_ttl: Option<Ttl>,
+
+    /// maximum size in bytes of an entry with a private scope. Larger entries are not stored
+    pub(crate) private_max_entry_size: Option<usize>,
 }
 
 /// Per subgraph configuration for entity caching
@@ -248,6 +259,11 @@ impl Plugin for EntityCache {
                 // if the top level `enabled` is true but there is no other configuration, caching is enabled for this plugin
                 .unwrap_or(true);
         let private_id = self.subgraphs.get(name).private_id.clone();
+        let private_id_claim = self.subgraphs.get(name).private_id_claim.clone();
+        let private_limits = PrivateLimits {
+            ttl: self.subgraphs.get(name).private_ttl.clone().map(|t| t.0),
+            max_entry_size: self.subgraphs.get(name).private_max_entry_size,
+        };
 
         let name = name.to_string();
 
@@ -281,6 +297,8 @@ impl Plugin for EntityCache {
                     subgraph_ttl,
                     private_queries,
                     private_id,
+                    private_id_claim,
+                    private_limits,
                     invalidation: self.invalidation.clone(),
                 })));
             tower::util::BoxService::new(inner)
@@ -336,9 +354,36 @@ struct InnerCacheService {
     subgraph_ttl: Option<Duration>,
     private_queries: Arc<RwLock<HashSet<String>>>,
     private_id: Option<String>,
+    private_id_claim: Option<String>,
+    private_limits: PrivateLimits,
     invalidation: Invalidation,
 }
 
+/// Limits applied to entries with a private scope
+#[derive(Clone, Copy, Debug, Default)]
+struct PrivateLimits {
+    ttl: Option<Duration>,
+    max_entry_size: Option<usize>,
+}
+
+impl PrivateLimits {
+    fn ttl(&self, ttl: Option<Duration>) -> Option<Duration> {
+        match (ttl, self.ttl) {
+            (Some(ttl), Some(max)) => Some(ttl.min(max)),
+            (ttl, max) => ttl.or(max),
+        }
+    }
+
+    fn allows(&self, data: &Value) -> bool {
+        match self.max_entry_size {
+            None => true,
+            Some(max) => serde_json::to_vec(data)
+                .map(|bytes| bytes.len() <= max)
+                .unwrap_or(false),
+        }
+    }
+}
+
 impl Service<subgraph::Request> for CacheService {
     type Response = subgraph::Response;
     type Error = BoxError;
@@ -378,6 +423,10 @@ impl InnerCacheService {
         {
             return self.service.call(request).await;
         }
+        // the client asked to bypass the cache with the `no_cache` request toggle
+        if RequestToggles::get(&request.context).no_cache {
+            return self.service.call(request).await;
+        }
         let query = request
             .subgraph_request
             .body()
@@ -473,6 +522,7 @@ impl InnerCacheService {
                             cache_store_root_from_response(
                                 self.storage,
                                 self.subgraph_ttl,
+                                self.private_limits,
                                 &response,
                                 cache_control,
                                 root_cache_key,
@@ -546,6 +596,7 @@ impl InnerCacheService {
                     cache_store_entities_from_response(
                         self.storage,
                         self.subgraph_ttl,
+                        self.private_limits,
                         &mut response,
                         cache_control.clone(),
                         cache_result.0,
@@ -563,15 +614,23 @@ impl InnerCacheService {
     }
 
     fn get_private_id(&self, context: &Context) -> Option<String> {
-        self.private_id.as_ref().and_then(|key| {
-            context.get_json_value(key).and_then(|value| {
-                value.as_str().map(|s| {
-                    let mut digest = Sha256::new();
-                    digest.update(s);
-                    hex::encode(digest.finalize().as_slice())
-                })
-            })
-        })
+        let id = match (&self.private_id, &self.private_id_claim) {
+            (Some(key), _) => context
+                .get_json_value(key)
+                .and_then(|value| value.as_str().map(|s| s.to_string())),
+            (None, Some(claim)) => context
+                .get_json_value(APOLLO_AUTHENTICATION_JWT_CLAIMS)
+                .and_then(|claims| match claims.as_object()?.get(claim.as_str())? {
+                    Value::String(s) => Some(s.as_str().to_string()),
+                    Value::Number(n) => Some(n.to_string()),
+                    _ => None,
+                }),
+            (None, None) => None,
+        }?;
+
+        let mut digest = Sha256::new();
+        digest.update(id);
+        Some(hex::encode(digest.finalize().as_slice()))
     }
 
     async fn handle_invalidation(
@@ -743,15 +802,22 @@ struct CacheEntry {
 async fn cache_store_root_from_response(
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
+    private_limits: PrivateLimits,
     response: &subgraph::Response,
     cache_control: CacheControl,
     cache_key: String,
 ) -> Result<(), BoxError> {
     if let Some(data) = response.response.body().data.as_ref() {
-        let ttl: Option<Duration> = cache_control
+        let mut ttl: Option<Duration> = cache_control
             .ttl()
             .map(|secs| Duration::from_secs(secs as u64))
             .or(subgraph_ttl);
+        if cache_control.private() {
+            if !private_limits.allows(data) {
+                return Ok(());
+            }
+            ttl = private_limits.ttl(ttl);
+        }
 
         if response.response.body().errors.is_empty() && cache_control.should_store() {
             let span = tracing::info_span!("cache.entity.store");
@@ -775,9 +841,11 @@ async fn cache_store_root_from_response(
     Ok(())
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn cache_store_entities_from_response(
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
+    private_limits: PrivateLimits,
     response: &mut subgraph::Response,
     cache_control: CacheControl,
     mut result_from_cache: Vec<IntermediateResult>,
@@ -809,6 +877,7 @@ async fn cache_store_entities_from_response(
             &response.response.body().errors,
             cache,
             subgraph_ttl,
+            private_limits,
             cache_control,
             &mut result_from_cache,
             update_key_private,
@@ -1070,15 +1139,19 @@ async fn insert_entities_in_result(
     errors: &[Error],
     cache: RedisCacheStorage,
     subgraph_ttl: Option<Duration>,
+    private_limits: PrivateLimits,
     cache_control: CacheControl,
     result: &mut Vec<IntermediateResult>,
     update_key_private: Option<String>,
     should_cache_private: bool,
 ) -> Result<(Vec<Value>, Vec<Error>), BoxError> {
-    let ttl: Option<Duration> = cache_control
+    let mut ttl: Option<Duration> = cache_control
         .ttl()
         .map(|secs| Duration::from_secs(secs as u64))
         .or(subgraph_ttl);
+    if cache_control.private() {
+        ttl = private_limits.ttl(ttl);
+    }
 
     let mut new_entities = Vec::new();
     let mut new_errors = Vec::new();
@@ -1138,7 +1211,11 @@ async fn insert_entities_in_result(
                     has_errors = true;
                 }
 
-                if !has_errors && cache_control.should_store() && should_cache_private {
+                if !has_errors
+                    && cache_control.should_store()
+                    && should_cache_private
+                    && (!cache_control.private() || private_limits.allows(&value))
+                {
                     to_insert.push((
                         RedisKey(key),
                         RedisValue(CacheEntry {
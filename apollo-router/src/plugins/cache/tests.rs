@@ -15,6 +15,7 @@ use tower::ServiceExt;
 use super::entity::EntityCache;
 use crate::cache::redis::RedisCacheStorage;
 use crate::plugin::test::MockSubgraph;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
 use crate::plugins::cache::entity::Subgraph;
 use crate::services::supergraph;
 use crate::Context;
@@ -401,6 +402,7 @@ async fn private() {
                 private_id: Some("sub".to_string()),
                 enabled: Some(true),
                 ttl: None,
+                ..Default::default()
             },
         ),
         (
@@ -409,6 +411,7 @@ async fn private() {
                 private_id: Some("sub".to_string()),
                 enabled: Some(true),
                 ttl: None,
+                ..Default::default()
             },
         ),
     ]
@@ -497,6 +500,135 @@ async fn private() {
     insta::assert_json_snapshot!(response);
 }
 
+#[tokio::test]
+async fn private_id_from_jwt_claim() {
+    let query = "query { currentUser { activeOrganization { id creatorUser { __typename id } } } }";
+
+    let subgraphs = MockedSubgraphs([
+        ("user", MockSubgraph::builder().with_json(
+                serde_json::json!{{"query":"{currentUser{activeOrganization{__typename id}}}"}},
+                serde_json::json!{{"data": {"currentUser": { "activeOrganization": {
+                    "__typename": "Organization",
+                    "id": "1"
+                } }}}}
+            ).with_header(CACHE_CONTROL, HeaderValue::from_static("private"))
+            .build()),
+        ("orga", MockSubgraph::builder().with_json(
+            serde_json::json!{{
+                "query": "query($representations:[_Any!]!){_entities(representations:$representations){...on Organization{creatorUser{__typename id}}}}",
+            "variables": {
+                "representations": [
+                    {
+                        "id": "1",
+                        "__typename": "Organization",
+                    }
+                ]
+            }}},
+            serde_json::json!{{"data": {
+                "_entities": [{
+                    "creatorUser": {
+                        "__typename": "User",
+                        "id": 2
+                    }
+                }]
+            }}}
+        ).with_header(CACHE_CONTROL, HeaderValue::from_static("private")).build())
+    ].into_iter().collect());
+
+    let redis_cache = RedisCacheStorage::from_mocks(Arc::new(MockStore::new()))
+        .await
+        .unwrap();
+    let subgraph = Subgraph {
+        private_id_claim: Some("sub".to_string()),
+        enabled: Some(true),
+        ..Default::default()
+    };
+    let map = [
+        ("user".to_string(), subgraph.clone()),
+        ("orga".to_string(), subgraph),
+    ]
+    .into_iter()
+    .collect();
+    let entity_cache = EntityCache::with_mocks(redis_cache.clone(), map)
+        .await
+        .unwrap();
+
+    let context_with_sub = |sub: &str| {
+        let context = Context::new();
+        context
+            .insert(
+                APOLLO_AUTHENTICATION_JWT_CLAIMS,
+                serde_json::json!({ "sub": sub }),
+            )
+            .unwrap();
+        context
+    };
+
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache.clone())
+        .extra_plugin(subgraphs)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(context_with_sub("1234"))
+        .build()
+        .unwrap();
+    let first = service
+        .oneshot(request)
+        .await
+        .unwrap()
+        .next_response()
+        .await
+        .unwrap();
+    assert!(first.errors.is_empty());
+
+    // Now testing without any mock subgraphs, the data should only come from the cache for the same subject
+    let service = TestHarness::builder()
+        .configuration_json(serde_json::json!({"include_subgraph_errors": { "all": true } }))
+        .unwrap()
+        .schema(SCHEMA)
+        .extra_plugin(entity_cache)
+        .build_supergraph()
+        .await
+        .unwrap();
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(context_with_sub("1234"))
+        .build()
+        .unwrap();
+    let cached = service
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap()
+        .next_response()
+        .await
+        .unwrap();
+    assert_eq!(cached.data, first.data);
+
+    let request = supergraph::Request::fake_builder()
+        .query(query)
+        .context(context_with_sub("5678"))
+        .build()
+        .unwrap();
+    let other = service
+        .clone()
+        .oneshot(request)
+        .await
+        .unwrap()
+        .next_response()
+        .await
+        .unwrap();
+    assert_ne!(other.data, first.data);
+}
+
 /*FIXME: reactivate test if we manage to make fred return the response to SCAN in mocks
 #[tokio::test(flavor = "multi_thread")]
 async fn invalidate() {
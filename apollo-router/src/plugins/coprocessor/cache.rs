@@ -0,0 +1,297 @@
+//! Caching of coprocessor responses for request stages.
+
+use std::num::NonZeroUsize;
+use std::time::Duration;
+use std::time::Instant;
+
+use http::HeaderMap;
+use lru::LruCache;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tower::BoxError;
+
+use crate::query_planner::fetch::QueryHash;
+use crate::services::external::Control;
+use crate::services::external::Externalizable;
+use crate::Context;
+
+const DEFAULT_CACHE_LIMIT: NonZeroUsize = unsafe { NonZeroUsize::new_unchecked(1000) };
+
+/// Caching of the coprocessor responses of a request stage
+///
+/// Only enable it if the coprocessor always returns the same response for the same key.
+/// Cached `Break` responses are returned as is, while cached `Continue` responses only
+/// apply their context changes: header and body changes are not replayed.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+pub(super) struct VerdictCacheConf {
+    /// How long a coprocessor response is reused
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    pub(super) ttl: Duration,
+    /// The request headers whose values are part of the cache key
+    #[serde(default)]
+    pub(super) headers: Vec<String>,
+    /// Whether the request body is part of the cache key
+    #[serde(default)]
+    pub(super) body: bool,
+    /// Whether the operation, from the hash of its document and its name, is part of the cache
+    /// key. Only available at the supergraph stage
+    #[serde(default)]
+    pub(super) operation: bool,
+    /// The maximum number of cached responses
+    #[serde(default = "default_cache_limit")]
+    pub(super) limit: NonZeroUsize,
+}
+
+fn default_cache_limit() -> NonZeroUsize {
+    DEFAULT_CACHE_LIMIT
+}
+
+/// Coprocessor responses of a request stage, by cache key
+#[derive(Debug)]
+pub(super) struct VerdictCache<T> {
+    ttl: Duration,
+    headers: Vec<String>,
+    body: bool,
+    operation: bool,
+    verdicts: Mutex<LruCache<String, (Instant, Externalizable<T>)>>,
+}
+
+impl<T> VerdictCache<T>
+where
+    T: Clone,
+{
+    pub(super) fn new(config: &VerdictCacheConf) -> Result<Self, BoxError> {
+        // an empty key would give the response of one request to all the others
+        if config.headers.is_empty() && !config.body && !config.operation {
+            return Err(
+                "the coprocessor cache key must use request headers, the body or the operation"
+                    .into(),
+            );
+        }
+        Ok(Self {
+            ttl: config.ttl,
+            headers: config
+                .headers
+                .iter()
+                .map(|name| name.to_ascii_lowercase())
+                .collect(),
+            body: config.body,
+            operation: config.operation,
+            verdicts: Mutex::new(LruCache::new(config.limit)),
+        })
+    }
+
+    /// Computes the cache key of a request from the configured headers, body and operation.
+    /// Returns `None` if the key uses the operation and it is not known
+    pub(super) fn key(
+        &self,
+        headers: &HeaderMap,
+        body: &[u8],
+        operation: Option<(&QueryHash, Option<&str>)>,
+    ) -> Option<String> {
+        let mut hasher = Sha256::new();
+        for name in &self.headers {
+            hasher.update(name.len().to_le_bytes());
+            hasher.update(name.as_bytes());
+            for value in headers.get_all(name.as_str()) {
+                hasher.update(value.len().to_le_bytes());
+                hasher.update(value.as_bytes());
+            }
+        }
+        if self.body {
+            hasher.update(body.len().to_le_bytes());
+            hasher.update(body);
+        }
+        if self.operation {
+            let (query_hash, operation_name) = operation?;
+            hasher.update(&query_hash.0);
+            hasher.update([0u8]);
+            hasher.update(operation_name.unwrap_or("-"));
+        }
+        Some(hex::encode(hasher.finalize()))
+    }
+
+    pub(super) fn get(&self, key: &str) -> Option<Externalizable<T>> {
+        let mut verdicts = self.verdicts.lock();
+        let (inserted_at, verdict) = verdicts.get(key)?;
+        if inserted_at.elapsed() < self.ttl {
+            return Some(verdict.clone());
+        }
+        verdicts.pop(key);
+        None
+    }
+
+    /// Caches the parts of a coprocessor response that apply to other requests with the same key
+    pub(super) fn insert(&self, key: String, output: &Externalizable<T>, context: &Context) {
+        let mut verdict = output.clone();
+        if !matches!(verdict.control, Some(Control::Break(_))) {
+            // The headers and body replace the ones of the request they were computed for
+            verdict.headers = None;
+            verdict.body = None;
+            verdict.uri = None;
+        }
+        // Only the context entries set by the coprocessor are replayed
+        verdict.context = output.context.as_ref().map(|output_context| {
+            let changes = Context::new();
+            for entry in output_context.iter() {
+                if context.get_json_value(entry.key().as_str()).as_ref() != Some(entry.value()) {
+                    changes.insert_json_value(entry.key().clone(), entry.value().clone());
+                }
+            }
+            changes
+        });
+        self.verdicts.lock().put(key, (Instant::now(), verdict));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+    use serde_json_bytes::json;
+
+    use super::*;
+    use crate::services::external::PipelineStep;
+
+    fn new_cache(headers: &[&str], body: bool) -> VerdictCache<String> {
+        VerdictCache::new(&VerdictCacheConf {
+            ttl: Duration::from_secs(60),
+            headers: headers.iter().map(ToString::to_string).collect(),
+            body,
+            operation: false,
+            limit: DEFAULT_CACHE_LIMIT,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn key_uses_configured_headers_and_body() {
+        let cache = new_cache(&["Authorization"], false);
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", HeaderValue::from_static("Bearer a"));
+        headers.insert("x-request-id", HeaderValue::from_static("1"));
+        let key = cache.key(&headers, b"{}", None);
+
+        headers.insert("x-request-id", HeaderValue::from_static("2"));
+        assert_eq!(key, cache.key(&headers, b"{\"query\":\"{ me }\"}", None));
+
+        headers.insert("authorization", HeaderValue::from_static("Bearer b"));
+        assert_ne!(key, cache.key(&headers, b"{}", None));
+
+        let cache_with_body = new_cache(&["authorization"], true);
+        assert_ne!(
+            cache_with_body.key(&headers, b"{}", None),
+            cache_with_body.key(&headers, b"{\"query\":\"{ me }\"}", None)
+        );
+    }
+
+    #[test]
+    fn key_uses_the_operation() {
+        let cache: VerdictCache<String> = VerdictCache::new(&VerdictCacheConf {
+            ttl: Duration::from_secs(60),
+            headers: Vec::new(),
+            body: false,
+            operation: true,
+            limit: DEFAULT_CACHE_LIMIT,
+        })
+        .unwrap();
+        let headers = HeaderMap::new();
+        let me = QueryHash(vec![1]);
+        let products = QueryHash(vec![2]);
+
+        let key = cache.key(&headers, b"{}", Some((&me, Some("Me"))));
+        assert!(key.is_some());
+        assert_eq!(
+            key,
+            cache.key(&headers, b"{\"variables\":{}}", Some((&me, Some("Me"))))
+        );
+        assert_ne!(
+            key,
+            cache.key(&headers, b"{}", Some((&products, Some("Me"))))
+        );
+        assert_ne!(key, cache.key(&headers, b"{}", Some((&me, None))));
+        // requests without a known operation are not cached
+        assert_eq!(cache.key(&headers, b"{}", None), None);
+    }
+
+    #[test]
+    fn key_must_not_be_empty() {
+        assert!(VerdictCache::<String>::new(&VerdictCacheConf {
+            ttl: Duration::from_secs(60),
+            headers: Vec::new(),
+            body: false,
+            operation: false,
+            limit: DEFAULT_CACHE_LIMIT,
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn continue_verdicts_only_keep_context_changes() {
+        let cache = new_cache(&["authorization"], false);
+        let context = Context::new();
+        context.insert_json_value("client", json!("web"));
+        let output_context = Context::new();
+        output_context.insert_json_value("client", json!("web"));
+        output_context.insert_json_value("user", json!("a"));
+        let output = Externalizable::router_builder()
+            .stage(PipelineStep::RouterRequest)
+            .control(Control::Continue)
+            .id("1".to_string())
+            .headers(Default::default())
+            .body("{}".to_string())
+            .context(output_context)
+            .build();
+
+        cache.insert("key".to_string(), &output, &context);
+
+        let verdict = cache.get("key").unwrap();
+        assert!(verdict.headers.is_none());
+        assert!(verdict.body.is_none());
+        let verdict_context = verdict.context.unwrap();
+        assert_eq!(verdict_context.get_json_value("user"), Some(json!("a")));
+        assert!(!verdict_context.contains_key("client"));
+    }
+
+    #[test]
+    fn break_verdicts_keep_the_response() {
+        let cache = new_cache(&["authorization"], false);
+        let output = Externalizable::router_builder()
+            .stage(PipelineStep::RouterRequest)
+            .control(Control::Break(401))
+            .id("1".to_string())
+            .body("unauthorized".to_string())
+            .build();
+
+        cache.insert("key".to_string(), &output, &Context::new());
+
+        let verdict = cache.get("key").unwrap();
+        assert_eq!(verdict.control, Some(Control::Break(401)));
+        assert_eq!(verdict.body.as_deref(), Some("unauthorized"));
+    }
+
+    #[test]
+    fn verdicts_expire() {
+        let cache: VerdictCache<String> = VerdictCache::new(&VerdictCacheConf {
+            ttl: Duration::ZERO,
+            headers: Vec::new(),
+            body: true,
+            operation: false,
+            limit: DEFAULT_CACHE_LIMIT,
+        })
+        .unwrap();
+        let output = Externalizable::router_builder()
+            .stage(PipelineStep::RouterRequest)
+            .control(Control::Continue)
+            .id("1".to_string())
+            .build();
+
+        cache.insert("key".to_string(), &output, &Context::new());
+        assert!(cache.get("key").is_none());
+    }
+}
@@ -29,18 +29,20 @@ use tower::Service;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 
+use self::cache::VerdictCache;
+use self::cache::VerdictCacheConf;
 use crate::configuration::shared::Client;
 use crate::error::Error;
 use crate::graphql;
 use crate::layers::async_checkpoint::OneShotAsyncCheckpointLayer;
 use crate::layers::ServiceBuilderExt;
-use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
+use crate::plugin::PluginPrivate;
 use crate::plugins::telemetry::config_new::conditions::Condition;
 use crate::plugins::telemetry::config_new::selectors::RouterSelector;
 use crate::plugins::telemetry::config_new::selectors::SubgraphSelector;
 use crate::plugins::traffic_shaping::Http2Config;
-use crate::register_plugin;
+use crate::register_private_plugin;
 use crate::services;
 use crate::services::external::externalize_header_map;
 use crate::services::external::Control;
@@ -55,10 +57,12 @@ use crate::services::router::body::RouterBodyConverter;
 use crate::services::subgraph;
 use crate::services::trust_dns_connector::new_async_http_connector;
 use crate::services::trust_dns_connector::AsyncHyperResolver;
+use crate::spec::SchemaChange;
 
 #[cfg(test)]
 mod test;
 
+mod cache;
 mod execution;
 mod supergraph;
 
@@ -74,7 +78,7 @@ type HTTPClientService = RouterBodyConverter<
 >;
 
 #[async_trait::async_trait]
-impl Plugin for CoprocessorPlugin<HTTPClientService> {
+impl PluginPrivate for CoprocessorPlugin<HTTPClientService> {
     type Config = Conf;
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
@@ -140,6 +144,10 @@ impl Plugin for CoprocessorPlugin<HTTPClientService> {
     fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
         self.subgraph_service(name, service)
     }
+
+    fn schema_changed(&self, change: &SchemaChange) {
+        self.schema_changed(change)
+    }
 }
 
 // This macro allows us to use it in our plugin registry!
@@ -147,7 +155,7 @@ impl Plugin for CoprocessorPlugin<HTTPClientService> {
 //
 // In order to keep the plugin names consistent,
 // we use using the `Reverse domain name notation`
-register_plugin!(
+register_private_plugin!(
     "apollo",
     "coprocessor",
     CoprocessorPlugin<HTTPClientService>
@@ -172,6 +180,8 @@ where
     http_client: C,
     configuration: Conf,
     sdl: Arc<String>,
+    router_request_cache: Option<Arc<VerdictCache<String>>>,
+    supergraph_request_cache: Option<Arc<VerdictCache<serde_json::Value>>>,
 }
 
 impl<C> CoprocessorPlugin<C>
@@ -184,10 +194,33 @@ where
     <C as tower::Service<http::Request<RouterBody>>>::Future: Send + 'static,
 {
     fn new(http_client: C, configuration: Conf, sdl: Arc<String>) -> Result<Self, BoxError> {
+        let router_request_cache = configuration
+            .router
+            .request
+            .cache
+            .as_ref()
+            .map(|config| {
+                if config.operation {
+                    return Err(BoxError::from(
+                        "the operation is not known at the router stage, it can only be part of the cache key at the supergraph stage",
+                    ));
+                }
+                VerdictCache::new(config).map(Arc::new)
+            })
+            .transpose()?;
+        let supergraph_request_cache = configuration
+            .supergraph
+            .request
+            .cache
+            .as_ref()
+            .map(|config| VerdictCache::new(config).map(Arc::new))
+            .transpose()?;
         Ok(Self {
             http_client,
             configuration,
             sdl,
+            router_request_cache,
+            supergraph_request_cache,
         })
     }
 
@@ -197,6 +230,7 @@ where
             service,
             self.configuration.url.clone(),
             self.sdl.clone(),
+            self.router_request_cache.clone(),
         )
     }
 
@@ -209,6 +243,7 @@ where
             service,
             self.configuration.url.clone(),
             self.sdl.clone(),
+            self.supergraph_request_cache.clone(),
         )
     }
 
@@ -232,6 +267,37 @@ where
             name.to_string(),
         )
     }
+
+    fn schema_changed(&self, change: &SchemaChange) {
+        if !self.configuration.schema_change.enabled {
+            return;
+        }
+        let http_client = self.http_client.clone();
+        let coprocessor_url = self.configuration.url.clone();
+        let sdl = self
+            .configuration
+            .schema_change
+            .sdl
+            .then(|| self.sdl.to_string());
+        let change = change.clone();
+
+        // Notifications must not delay the reload, and there is nothing to do if they fail
+        tokio::spawn(async move {
+            let succeeded = process_schema_change_stage(http_client, coprocessor_url, sdl, change)
+                .await
+                .map_err(|error| {
+                    tracing::error!("external extensibility: schema change stage error: {error}");
+                })
+                .is_ok();
+            u64_counter!(
+                "apollo.router.operations.coprocessor",
+                "Total operations with co-processors enabled",
+                1,
+                "coprocessor.stage" = PipelineStep::SchemaChange,
+                "coprocessor.succeeded" = succeeded
+            );
+        });
+    }
 }
 /// What information is passed to a router request/response stage
 #[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, JsonSchema)]
@@ -252,6 +318,8 @@ pub(super) struct RouterRequestConf {
     pub(super) path: bool,
     /// Send the method
     pub(super) method: bool,
+    /// Reuse the coprocessor responses for requests with the same cache key
+    pub(super) cache: Option<VerdictCacheConf>,
 }
 
 /// What information is passed to a router request/response stage
@@ -312,6 +380,16 @@ pub(super) struct SubgraphResponseConf {
     pub(super) status_code: bool,
 }
 
+/// What information is passed to the coprocessor when the supergraph changes
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, JsonSchema)]
+#[serde(default, deny_unknown_fields)]
+pub(super) struct SchemaChangeConf {
+    /// Notify the coprocessor when the router starts serving a new supergraph
+    pub(super) enabled: bool,
+    /// Send the SDL of the new supergraph
+    pub(super) sdl: bool,
+}
+
 /// Configures the externalization plugin
 #[derive(Clone, Debug, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -336,6 +414,9 @@ struct Conf {
     /// The subgraph stage request/response configuration
     #[serde(default)]
     subgraph: SubgraphStages,
+    /// The schema change notification configuration
+    #[serde(default)]
+    schema_change: SchemaChangeConf,
 }
 
 fn default_timeout() -> Duration {
@@ -358,6 +439,7 @@ impl RouterStage {
         service: router::BoxService,
         coprocessor_url: String,
         sdl: Arc<String>,
+        request_cache: Option<Arc<VerdictCache<String>>>,
     ) -> router::BoxService
     where
         C: Service<
@@ -381,6 +463,7 @@ impl RouterStage {
                 let coprocessor_url = coprocessor_url.clone();
                 let http_client = http_client.clone();
                 let sdl = sdl.clone();
+                let request_cache = request_cache.clone();
 
                 async move {
                     let mut succeeded = true;
@@ -390,6 +473,7 @@ impl RouterStage {
                         sdl,
                         request,
                         request_config,
+                        request_cache,
                     )
                     .await
                     .map_err(|error| {
@@ -614,6 +698,7 @@ async fn process_router_request_stage<C>(
     sdl: Arc<String>,
     mut request: router::Request,
     mut request_config: RouterRequestConf,
+    request_cache: Option<Arc<VerdictCache<String>>>,
 ) -> Result<ControlFlow<router::Response, router::Request>, BoxError>
 where
     C: Service<http::Request<RouterBody>, Response = http::Response<RouterBody>, Error = BoxError>
@@ -666,21 +751,47 @@ where
         .method(parts.method.to_string())
         .build();
 
-    tracing::debug!(?payload, "externalized output");
-    let guard = request.context.enter_active_request();
-    let start = Instant::now();
-    let co_processor_result = payload.call(http_client, &coprocessor_url).await;
-    let duration = start.elapsed().as_secs_f64();
-    drop(guard);
-    tracing::info!(
-        histogram.apollo.router.operations.coprocessor.duration = duration,
-        coprocessor.stage = %PipelineStep::RouterRequest,
-    );
+    let cache_key = request_cache
+        .as_ref()
+        .and_then(|cache| cache.key(&parts.headers, &bytes, None));
+    let cached_output = request_cache
+        .as_ref()
+        .zip(cache_key.as_ref())
+        .and_then(|(cache, key)| cache.get(key));
+    if request_cache.is_some() {
+        u64_counter!(
+            "apollo.router.operations.coprocessor.cache",
+            "Coprocessor responses looked up in the cache",
+            1,
+            "coprocessor.stage" = PipelineStep::RouterRequest,
+            "cache.hit" = cached_output.is_some()
+        );
+    }
 
-    tracing::debug!(?co_processor_result, "co-processor returned");
-    let mut co_processor_output = co_processor_result?;
+    let mut co_processor_output = match cached_output {
+        Some(output) => output,
+        None => {
+            tracing::debug!(?payload, "externalized output");
+            let guard = request.context.enter_active_request();
+            let start = Instant::now();
+            let co_processor_result = payload.call(http_client, &coprocessor_url).await;
+            let duration = start.elapsed().as_secs_f64();
+            drop(guard);
+            tracing::info!(
+                histogram.apollo.router.operations.coprocessor.duration = duration,
+                coprocessor.stage = %PipelineStep::RouterRequest,
+            );
 
-    validate_coprocessor_output(&co_processor_output, PipelineStep::RouterRequest)?;
+            tracing::debug!(?co_processor_result, "co-processor returned");
+            let co_processor_output = co_processor_result?;
+
+            validate_coprocessor_output(&co_processor_output, PipelineStep::RouterRequest)?;
+            if let Some((cache, key)) = request_cache.as_ref().zip(cache_key) {
+                cache.insert(key, &co_processor_output, &request.context);
+            }
+            co_processor_output
+        }
+    };
     // unwrap is safe here because validate_coprocessor_output made sure control is available
     let control = co_processor_output.control.expect("validated above; qed");
 
@@ -1224,6 +1335,41 @@ where
     Ok(response)
 }
 
+async fn process_schema_change_stage<C>(
+    http_client: C,
+    coprocessor_url: String,
+    sdl: Option<String>,
+    change: SchemaChange,
+) -> Result<(), BoxError>
+where
+    C: Service<http::Request<RouterBody>, Response = http::Response<RouterBody>, Error = BoxError>
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    <C as tower::Service<http::Request<RouterBody>>>::Future: Send + 'static,
+{
+    let payload = Externalizable::schema_change_builder()
+        .stage(PipelineStep::SchemaChange)
+        .id(change.schema_hash.clone())
+        .body(serde_json::to_value(&change)?)
+        .and_sdl(sdl)
+        .build();
+
+    tracing::debug!(?payload, "externalized output");
+    let start = Instant::now();
+    let co_processor_result = payload.call(http_client, &coprocessor_url).await;
+    let duration = start.elapsed().as_secs_f64();
+    tracing::info!(
+        histogram.apollo.router.operations.coprocessor.duration = duration,
+        coprocessor.stage = %PipelineStep::SchemaChange,
+    );
+
+    tracing::debug!(?co_processor_result, "co-processor returned");
+    let co_processor_output = co_processor_result?;
+    validate_coprocessor_output(&co_processor_output, PipelineStep::SchemaChange)
+}
+
 // -----------------------------------------------------------------------------------------
 
 fn validate_coprocessor_output<T>(
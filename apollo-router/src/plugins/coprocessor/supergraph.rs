@@ -10,14 +10,18 @@ use tower::BoxError;
 use tower::ServiceBuilder;
 use tower_service::Service;
 
+use super::cache::VerdictCache;
+use super::cache::VerdictCacheConf;
 use super::externalize_header_map;
 use super::*;
 use crate::graphql;
 use crate::layers::async_checkpoint::OneShotAsyncCheckpointLayer;
 use crate::layers::ServiceBuilderExt;
 use crate::plugins::coprocessor::EXTERNAL_SPAN_NAME;
+use crate::plugins::subscription::can_drop_event;
 use crate::plugins::telemetry::config_new::conditions::Condition;
 use crate::plugins::telemetry::config_new::selectors::SupergraphSelector;
+use crate::services::layers::query_analysis::ParsedDocument;
 use crate::services::supergraph;
 
 /// What information is passed to a router request/response stage
@@ -37,6 +41,8 @@ pub(super) struct SupergraphRequestConf {
     pub(super) sdl: bool,
     /// Send the method
     pub(super) method: bool,
+    /// Reuse the coprocessor responses for requests with the same cache key
+    pub(super) cache: Option<VerdictCacheConf>,
 }
 
 /// What information is passed to a router request/response stage
@@ -74,6 +80,7 @@ impl SupergraphStage {
         service: supergraph::BoxService,
         coprocessor_url: String,
         sdl: Arc<String>,
+        request_cache: Option<Arc<VerdictCache<serde_json::Value>>>,
     ) -> supergraph::BoxService
     where
         C: Service<
@@ -97,6 +104,7 @@ impl SupergraphStage {
                 let coprocessor_url = coprocessor_url.clone();
                 let http_client = http_client.clone();
                 let sdl = sdl.clone();
+                let request_cache = request_cache.clone();
 
                 async move {
                     let mut succeeded = true;
@@ -106,6 +114,7 @@ impl SupergraphStage {
                         sdl,
                         request,
                         request_config,
+                        request_cache,
                     )
                     .await
                     .map_err(|error| {
@@ -192,6 +201,7 @@ async fn process_supergraph_request_stage<C>(
     sdl: Arc<String>,
     mut request: supergraph::Request,
     mut request_config: SupergraphRequestConf,
+    request_cache: Option<Arc<VerdictCache<serde_json::Value>>>,
 ) -> Result<ControlFlow<supergraph::Response, supergraph::Request>, BoxError>
 where
     C: Service<http::Request<RouterBody>, Response = http::Response<RouterBody>, Error = BoxError>
@@ -239,20 +249,53 @@ where
         .and_sdl(sdl_to_send)
         .build();
 
-    tracing::debug!(?payload, "externalized output");
-    let guard = request.context.enter_active_request();
-    let start = Instant::now();
-    let co_processor_result = payload.call(http_client, &coprocessor_url).await;
-    let duration = start.elapsed().as_secs_f64();
-    drop(guard);
-    tracing::info!(
-        histogram.apollo.router.operations.coprocessor.duration = duration,
-        coprocessor.stage = %PipelineStep::SupergraphRequest,
-    );
+    let cache_key = request_cache.as_ref().and_then(|cache| {
+        let query_hash = request
+            .context
+            .extensions()
+            .with_lock(|lock| lock.get::<ParsedDocument>().map(|doc| doc.hash.clone()));
+        let operation = query_hash
+            .as_deref()
+            .map(|hash| (hash, body.operation_name.as_deref()));
+        cache.key(&parts.headers, &bytes, operation)
+    });
+    let cached_output = request_cache
+        .as_ref()
+        .zip(cache_key.as_ref())
+        .and_then(|(cache, key)| cache.get(key));
+    if request_cache.is_some() {
+        u64_counter!(
+            "apollo.router.operations.coprocessor.cache",
+            "Coprocessor responses looked up in the cache",
+            1,
+            "coprocessor.stage" = PipelineStep::SupergraphRequest,
+            "cache.hit" = cached_output.is_some()
+        );
+    }
 
-    tracing::debug!(?co_processor_result, "co-processor returned");
-    let co_processor_output = co_processor_result?;
-    validate_coprocessor_output(&co_processor_output, PipelineStep::SupergraphRequest)?;
+    let co_processor_output = match cached_output {
+        Some(output) => output,
+        None => {
+            tracing::debug!(?payload, "externalized output");
+            let guard = request.context.enter_active_request();
+            let start = Instant::now();
+            let co_processor_result = payload.call(http_client, &coprocessor_url).await;
+            let duration = start.elapsed().as_secs_f64();
+            drop(guard);
+            tracing::info!(
+                histogram.apollo.router.operations.coprocessor.duration = duration,
+                coprocessor.stage = %PipelineStep::SupergraphRequest,
+            );
+
+            tracing::debug!(?co_processor_result, "co-processor returned");
+            let co_processor_output = co_processor_result?;
+            validate_coprocessor_output(&co_processor_output, PipelineStep::SupergraphRequest)?;
+            if let Some((cache, key)) = request_cache.as_ref().zip(cache_key) {
+                cache.insert(key, &co_processor_output, &request.context);
+            }
+            co_processor_output
+        }
+    };
     // unwrap is safe here because validate_coprocessor_output made sure control is available
     let control = co_processor_output.control.expect("validated above; qed");
 
@@ -451,7 +494,7 @@ where
 
             async move {
                 if !should_be_executed {
-                    return Ok(deferred_response);
+                    return Ok(Some(deferred_response));
                 }
                 let body_to_send = response_config.body.then(|| {
                     serde_json::to_value(&deferred_response).expect("serialization will not fail")
@@ -500,23 +543,38 @@ where
                     }
                 }
 
+                // A subscription event can be dropped by breaking the control flow. The other
+                // deferred responses are always delivered, as clients need each of them to
+                // complete an incremental delivery, and so are the last event and the events
+                // with errors
+                if matches!(co_processor_output.control, Some(Control::Break(_)))
+                    && can_drop_event(&generator_map_context, &new_deferred_response)
+                {
+                    tracing::debug!("subscription event dropped by the coprocessor");
+                    return Ok(None);
+                }
+
                 // We return the deferred_response into our stream of response chunks
-                Ok(new_deferred_response)
+                Ok(Some(new_deferred_response))
             }
         })
-        .map(|res: Result<graphql::Response, BoxError>| match res {
-            Ok(response) => response,
-            Err(e) => {
-                tracing::error!("coprocessor error handling deferred supergraph response: {e}");
-                graphql::Response::builder()
-                    .error(
-                        Error::builder()
-                            .message("Internal error handling deferred response")
-                            .extension_code("INTERNAL_ERROR")
+        .filter_map(|res: Result<Option<graphql::Response>, BoxError>| {
+            future::ready(match res {
+                Ok(response) => response,
+                Err(e) => {
+                    tracing::error!("coprocessor error handling deferred supergraph response: {e}");
+                    Some(
+                        graphql::Response::builder()
+                            .error(
+                                Error::builder()
+                                    .message("Internal error handling deferred response")
+                                    .extension_code("INTERNAL_ERROR")
+                                    .build(),
+                            )
                             .build(),
                     )
-                    .build()
-            }
+                }
+            })
         });
 
     // Create our response stream which consists of our first body chained with the
@@ -542,9 +600,11 @@ mod tests {
 
     use super::super::*;
     use super::*;
+    use crate::context::keys::OPERATION_KIND;
     use crate::plugin::test::MockInternalHttpClientService;
     use crate::plugin::test::MockSupergraphService;
     use crate::plugins::telemetry::config_new::conditions::SelectorOrValue;
+    use crate::query_planner::OperationKind;
     use crate::services::router::body::get_body_bytes;
     use crate::services::supergraph;
 
@@ -603,6 +663,7 @@ mod tests {
                 body: true,
                 sdl: false,
                 method: false,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -707,6 +768,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::fake_builder().build().unwrap();
@@ -745,6 +807,7 @@ mod tests {
                 body: true,
                 sdl: false,
                 method: false,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -785,6 +848,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::fake_builder()
@@ -862,6 +926,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let crate::services::supergraph::Response { context, .. } =
@@ -973,6 +1038,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -1088,6 +1154,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder()
@@ -1206,6 +1273,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder()
@@ -1231,4 +1299,208 @@ mod tests {
             json!({ "data": { "test": 3 }, "hasNext": false }),
         );
     }
+
+    #[tokio::test]
+    async fn multi_part_drop_subscription_event() {
+        let supergraph_stage = SupergraphStage {
+            response: SupergraphResponseConf {
+                condition: Default::default(),
+                headers: false,
+                context: false,
+                body: true,
+                sdl: false,
+                status_code: false,
+            },
+            request: Default::default(),
+        };
+
+        let mut mock_supergraph_service = MockSupergraphService::new();
+
+        mock_supergraph_service
+            .expect_call()
+            .returning(|req: supergraph::Request| {
+                req.context
+                    .insert_typed(&OPERATION_KIND, OperationKind::Subscription)
+                    .unwrap();
+                Ok(supergraph::Response::fake_stream_builder()
+                    .response(graphql::Response::builder().build())
+                    .response(
+                        graphql::Response::builder()
+                            .data(json!({ "test": 1 }))
+                            .has_next(true)
+                            .build(),
+                    )
+                    .response(
+                        graphql::Response::builder()
+                            .data(json!({ "test": 2 }))
+                            .has_next(true)
+                            .build(),
+                    )
+                    .response(
+                        graphql::Response::builder()
+                            .data(json!({ "test": 3 }))
+                            .has_next(false)
+                            .build(),
+                    )
+                    .context(req.context)
+                    .build()
+                    .unwrap())
+            });
+
+        let mock_http_client =
+            mock_with_deferred_callback(move |res: http::Request<RouterBody>| {
+                Box::pin(async {
+                    let mut deserialized_response: Externalizable<serde_json::Value> =
+                        serde_json::from_slice(&get_body_bytes(res.into_body()).await.unwrap())
+                            .unwrap();
+
+                    // Drop the second event
+                    if deserialized_response.body.as_ref().unwrap()["data"]["test"] == json!(2) {
+                        deserialized_response.control = Some(Control::Break(200));
+                    }
+
+                    Ok(http::Response::builder()
+                        .body(RouterBody::from(
+                            serde_json::to_string(&deserialized_response).unwrap_or_default(),
+                        ))
+                        .unwrap())
+                })
+            });
+
+        let service = supergraph_stage.as_service(
+            mock_http_client,
+            mock_supergraph_service.boxed(),
+            "http://test".to_string(),
+            Arc::new("".to_string()),
+            None,
+        );
+
+        let request = supergraph::Request::canned_builder()
+            .query("foo")
+            .build()
+            .unwrap();
+
+        let res = service.oneshot(request).await.unwrap();
+
+        let bodies: Vec<_> = res
+            .response
+            .into_body()
+            .map(|body| serde_json::to_value(&body).unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            bodies,
+            vec![
+                json!({}),
+                json!({ "data": { "test": 1 }, "hasNext": true }),
+                json!({ "data": { "test": 3 }, "hasNext": false }),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn multi_part_keeps_last_and_failed_subscription_events() {
+        let supergraph_stage = SupergraphStage {
+            response: SupergraphResponseConf {
+                condition: Default::default(),
+                headers: false,
+                context: false,
+                body: true,
+                sdl: false,
+                status_code: false,
+            },
+            request: Default::default(),
+        };
+
+        let mut mock_supergraph_service = MockSupergraphService::new();
+
+        mock_supergraph_service
+            .expect_call()
+            .returning(|req: supergraph::Request| {
+                req.context
+                    .insert_typed(&OPERATION_KIND, OperationKind::Subscription)
+                    .unwrap();
+                Ok(supergraph::Response::fake_stream_builder()
+                    .response(graphql::Response::builder().build())
+                    .response(
+                        graphql::Response::builder()
+                            .data(json!({ "test": 1 }))
+                            .has_next(true)
+                            .build(),
+                    )
+                    .response(
+                        graphql::Response::builder()
+                            .data(json!({ "test": 2 }))
+                            .error(
+                                Error::builder()
+                                    .message("error")
+                                    .extension_code("ERROR")
+                                    .build(),
+                            )
+                            .has_next(true)
+                            .build(),
+                    )
+                    .response(
+                        graphql::Response::builder()
+                            .data(json!({ "test": 3 }))
+                            .has_next(false)
+                            .build(),
+                    )
+                    .context(req.context)
+                    .build()
+                    .unwrap())
+            });
+
+        let mock_http_client =
+            mock_with_deferred_callback(move |res: http::Request<RouterBody>| {
+                Box::pin(async {
+                    let mut deserialized_response: Externalizable<serde_json::Value> =
+                        serde_json::from_slice(&get_body_bytes(res.into_body()).await.unwrap())
+                            .unwrap();
+
+                    // Try to drop every event
+                    deserialized_response.control = Some(Control::Break(200));
+
+                    Ok(http::Response::builder()
+                        .body(RouterBody::from(
+                            serde_json::to_string(&deserialized_response).unwrap_or_default(),
+                        ))
+                        .unwrap())
+                })
+            });
+
+        let service = supergraph_stage.as_service(
+            mock_http_client,
+            mock_supergraph_service.boxed(),
+            "http://test".to_string(),
+            Arc::new("".to_string()),
+            None,
+        );
+
+        let request = supergraph::Request::canned_builder()
+            .query("foo")
+            .build()
+            .unwrap();
+
+        let res = service.oneshot(request).await.unwrap();
+
+        let bodies: Vec<_> = res
+            .response
+            .into_body()
+            .map(|body| serde_json::to_value(&body).unwrap())
+            .collect()
+            .await;
+        assert_eq!(
+            bodies,
+            vec![
+                json!({}),
+                json!({
+                    "data": { "test": 2 },
+                    "errors": [{ "message": "error", "extensions": { "code": "ERROR" } }],
+                    "hasNext": true
+                }),
+                json!({ "data": { "test": 3 }, "hasNext": false }),
+            ]
+        );
+    }
 }
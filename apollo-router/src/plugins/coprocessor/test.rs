@@ -106,6 +106,7 @@ mod tests {
                 sdl: true,
                 path: false,
                 method: false,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -141,6 +142,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -166,6 +168,7 @@ mod tests {
                 sdl: true,
                 path: false,
                 method: false,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -201,6 +204,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -226,6 +230,7 @@ mod tests {
                 sdl: true,
                 path: false,
                 method: false,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -260,6 +265,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -966,6 +972,7 @@ mod tests {
             mock_supergraph_service.boxed(),
             "http://test".to_string(),
             Arc::default(),
+            None,
         );
 
         let request = supergraph::Request::fake_builder().build().unwrap();
@@ -989,6 +996,7 @@ mod tests {
                 sdl: true,
                 path: true,
                 method: true,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -1090,6 +1098,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -1115,6 +1124,7 @@ mod tests {
                 sdl: true,
                 path: true,
                 method: true,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -1202,6 +1212,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -1220,6 +1231,7 @@ mod tests {
                 sdl: true,
                 path: true,
                 method: true,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -1328,6 +1340,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::fake_builder()
@@ -1349,6 +1362,7 @@ mod tests {
                 sdl: true,
                 path: true,
                 method: true,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -1399,6 +1413,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -1438,6 +1453,7 @@ mod tests {
                 sdl: true,
                 path: true,
                 method: true,
+                cache: None,
             },
             response: Default::default(),
         };
@@ -1478,6 +1494,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -1608,6 +1625,7 @@ mod tests {
             mock_router_service.boxed(),
             "http://test".to_string(),
             Arc::new("".to_string()),
+            None,
         );
 
         let request = supergraph::Request::canned_builder().build().unwrap();
@@ -1706,6 +1724,126 @@ mod tests {
         assert_eq!(expected, actual);
     }
 
+    #[tokio::test]
+    async fn external_plugin_router_request_cache() {
+        let request_config = RouterRequestConf {
+            headers: true,
+            cache: Some(VerdictCacheConf {
+                ttl: Duration::from_secs(60),
+                headers: vec!["authorization".to_string()],
+                body: false,
+                operation: false,
+                limit: std::num::NonZeroUsize::new(10).unwrap(),
+            }),
+            ..Default::default()
+        };
+        let cache = Arc::new(VerdictCache::new(request_config.cache.as_ref().unwrap()).unwrap());
+
+        let mut mock_http_client = MockInternalHttpClientService::new();
+        mock_http_client
+            .expect_call()
+            .times(1)
+            .returning(|_: http::Request<RouterBody>| {
+                Box::pin(async {
+                    let output = json!({
+                        "version": 1,
+                        "stage": "RouterRequest",
+                        "control": {
+                            "break": 401
+                        },
+                        "id": "1b19c05fdafc521016df33148ad63c1b",
+                        "body": "unauthorized"
+                    });
+                    Ok(http::Response::builder()
+                        .body(RouterBody::from(serde_json::to_string(&output).unwrap()))
+                        .unwrap())
+                })
+            });
+
+        // The second request is rejected from the cache, without calling the coprocessor
+        for http_client in [mock_http_client, MockInternalHttpClientService::new()] {
+            let mut request: router::Request = supergraph::Request::canned_builder()
+                .build()
+                .unwrap()
+                .try_into()
+                .unwrap();
+            request
+                .router_request
+                .headers_mut()
+                .insert("authorization", HeaderValue::from_static("Bearer invalid"));
+
+            let ControlFlow::Break(response) = process_router_request_stage(
+                http_client,
+                "http://test".to_string(),
+                Arc::default(),
+                request,
+                request_config.clone(),
+                Some(cache.clone()),
+            )
+            .await
+            .unwrap() else {
+                panic!("the request should be rejected");
+            };
+            assert_eq!(response.response.status(), StatusCode::UNAUTHORIZED);
+        }
+    }
+
+    #[tokio::test]
+    async fn external_plugin_schema_change() {
+        let mut mock_http_client = MockInternalHttpClientService::new();
+        mock_http_client
+            .expect_call()
+            .returning(|req: http::Request<RouterBody>| {
+                Box::pin(async {
+                    let body = hyper::body::to_bytes(req.into_body()).await.unwrap();
+                    let deserialized_request: Externalizable<serde_json::Value> =
+                        serde_json::from_slice(&body).unwrap();
+
+                    assert_eq!(EXTERNALIZABLE_VERSION, deserialized_request.version);
+                    assert_eq!(
+                        PipelineStep::SchemaChange.to_string(),
+                        deserialized_request.stage
+                    );
+                    assert_eq!(deserialized_request.id.as_deref(), Some("new"));
+                    assert_eq!(deserialized_request.sdl.as_deref(), Some("type Query"));
+                    assert_eq!(
+                        deserialized_request.body.unwrap(),
+                        json!({
+                            "previousSchemaHash": "old",
+                            "schemaHash": "new",
+                            "addedTypes": ["Product"],
+                            "removedTypes": [],
+                            "changedTypes": ["Query"],
+                            "addedSubgraphs": ["products"],
+                            "removedSubgraphs": []
+                        })
+                    );
+
+                    Ok(http::Response::builder()
+                        .body(RouterBody::from(body))
+                        .unwrap())
+                })
+            });
+
+        let change = SchemaChange {
+            previous_schema_hash: "old".to_string(),
+            schema_hash: "new".to_string(),
+            added_types: vec!["Product".to_string()],
+            changed_types: vec!["Query".to_string()],
+            added_subgraphs: vec!["products".to_string()],
+            ..Default::default()
+        };
+
+        process_schema_change_stage(
+            mock_http_client,
+            "http://test".to_string(),
+            Some("type Query".to_string()),
+            change,
+        )
+        .await
+        .unwrap();
+    }
+
     #[allow(clippy::type_complexity)]
     fn mock_with_callback(
         callback: fn(
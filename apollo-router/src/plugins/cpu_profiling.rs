@@ -0,0 +1,275 @@
+//! Expose an endpoint sampling the router's CPU usage in the pprof format.
+//!
+//! Each request to the endpoint samples the call stacks of the process for the requested duration,
+//! like Go's `/debug/pprof/profile`, and returns a profile that can be opened with `go tool pprof`.
+//! The profiler is linked with the `cpu-profiling` feature, enabled by default. Library users
+//! disabling the default features must enable it to use the endpoint.
+
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use http::StatusCode;
+use multimap::MultiMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::Body;
+use crate::ListenAddr;
+
+const DEFAULT_SECONDS: u64 = 30;
+const MAX_SECONDS: u64 = 300;
+
+/// CPU profiling configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct CpuProfilingConfig {
+    /// Set to true to expose the CPU profiling endpoint
+    enabled: bool,
+    /// The listen address
+    listen: ListenAddr,
+    /// The path where CPU profiles can be requested
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of profiling requests
+    token: Option<String>,
+}
+
+impl Default for CpuProfilingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            path: "/debug/pprof/profile".to_string(),
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CpuProfiling {
+    config: CpuProfilingConfig,
+}
+
+#[async_trait::async_trait]
+impl Plugin for CpuProfiling {
+    type Config = CpuProfilingConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        if init.config.enabled {
+            if !cfg!(all(feature = "cpu-profiling", unix)) {
+                return Err(
+                    "CPU profiling requires the router to be built with the `cpu-profiling` feature, on a unix platform"
+                        .into(),
+                );
+            }
+            if init.config.token.as_deref().unwrap_or_default().is_empty() {
+                return Err(
+                    "cpu_profiling.token is required to expose the profiling endpoint".into(),
+                );
+            }
+            tracing::info!(
+                "CPU profiling endpoint exposed at {}{}",
+                init.config.listen,
+                init.config.path
+            );
+        }
+
+        Ok(CpuProfiling {
+            config: init.config,
+        })
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.config.enabled, &self.config.token) {
+            map.insert(
+                self.config.listen.clone(),
+                Endpoint::from_router_service(
+                    self.config.path.clone(),
+                    CpuProfileService {
+                        token: BearerToken::new(token),
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+#[derive(Deserialize)]
+struct ProfileParameters {
+    seconds: Option<u64>,
+}
+
+#[derive(Clone)]
+struct CpuProfileService {
+    token: BearerToken,
+}
+
+impl CpuProfileService {
+    fn parse_duration(&self, request: &router::Request) -> Result<Duration, (StatusCode, String)> {
+        self.token.check(request.router_request.headers())?;
+
+        let parameters: ProfileParameters =
+            serde_urlencoded::from_str(request.router_request.uri().query().unwrap_or_default())
+                .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+        match parameters.seconds.unwrap_or(DEFAULT_SECONDS) {
+            seconds @ 1..=MAX_SECONDS => Ok(Duration::from_secs(seconds)),
+            _ => Err((
+                StatusCode::BAD_REQUEST,
+                format!("seconds must be between 1 and {MAX_SECONDS}"),
+            )),
+        }
+    }
+}
+
+impl Service<router::Request> for CpuProfileService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let duration = self.parse_duration(&req);
+        Box::pin(async move {
+            let profile = match duration {
+                Ok(duration) => tokio::task::spawn_blocking(move || profile(duration))
+                    .await?
+                    .map_err(|error| (StatusCode::INTERNAL_SERVER_ERROR, error)),
+                Err(error) => Err(error),
+            };
+            let (status, content_type, body) = match profile {
+                Ok(profile) => (StatusCode::OK, "application/octet-stream", profile),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+/// Samples the process for `duration` and returns the protobuf encoded profile
+#[cfg(all(feature = "cpu-profiling", unix))]
+fn profile(duration: Duration) -> Result<Vec<u8>, String> {
+    use prost::Message;
+
+    /// Sampling frequency, slightly off 100Hz to avoid sampling in lockstep with periodic tasks
+    const FREQUENCY: i32 = 99;
+
+    // fails if another profile is being recorded
+    let guard = pprof::ProfilerGuardBuilder::default()
+        .frequency(FREQUENCY)
+        .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+        .build()
+        .map_err(|e| format!("could not start the profiler: {e}"))?;
+    std::thread::sleep(duration);
+    let profile = guard
+        .report()
+        .build()
+        .and_then(|report| report.pprof())
+        .map_err(|e| format!("could not build the profile: {e}"))?;
+
+    let mut body = Vec::new();
+    profile
+        .encode(&mut body)
+        .map_err(|e| format!("could not encode the profile: {e}"))?;
+    Ok(body)
+}
+
+#[cfg(not(all(feature = "cpu-profiling", unix)))]
+fn profile(_duration: Duration) -> Result<Vec<u8>, String> {
+    Err("the router was not built with the `cpu-profiling` feature".to_string())
+}
+
+register_plugin!("apollo", "cpu_profiling", CpuProfiling);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn requires_token() {
+        let config = CpuProfilingConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(
+            CpuProfiling::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+    }
+
+    #[cfg(not(feature = "cpu-profiling"))]
+    #[tokio::test]
+    async fn requires_profiling_feature() {
+        let config = CpuProfilingConfig {
+            enabled: true,
+            token: Some("secret".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            CpuProfiling::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn parse_duration() {
+        let service = CpuProfileService {
+            token: BearerToken::new("secret"),
+        };
+        let request = |uri: &str, authorization: &str| {
+            router::Request::fake_builder()
+                .uri(http::Uri::try_from(uri).unwrap())
+                .header(http::header::AUTHORIZATION, authorization)
+                .build()
+                .unwrap()
+        };
+
+        assert_eq!(
+            service
+                .parse_duration(&request("http://localhost/", "Bearer wrong"))
+                .unwrap_err()
+                .0,
+            StatusCode::UNAUTHORIZED
+        );
+        assert_eq!(
+            service.parse_duration(&request("http://localhost/", "Bearer secret")),
+            Ok(Duration::from_secs(DEFAULT_SECONDS))
+        );
+        assert_eq!(
+            service.parse_duration(&request("http://localhost/?seconds=5", "Bearer secret")),
+            Ok(Duration::from_secs(5))
+        );
+        assert_eq!(
+            service
+                .parse_duration(&request("http://localhost/?seconds=0", "Bearer secret"))
+                .unwrap_err()
+                .0,
+            StatusCode::BAD_REQUEST
+        );
+    }
+}
@@ -402,6 +402,7 @@ mod test {
     use crate::plugins::test::PluginTestHarness;
     use crate::query_planner::fetch::QueryHash;
     use crate::services::execution;
+    use crate::services::layers::persisted_queries::OperationComplexity;
     use crate::services::layers::query_analysis::ParsedDocument;
     use crate::services::layers::query_analysis::ParsedDocumentInner;
     use crate::services::subgraph;
@@ -521,6 +522,51 @@ mod test {
         .await
     }
 
+    #[tokio::test]
+    async fn test_pre_computed_cost() {
+        let plugin = PluginTestHarness::<DemandControl>::builder()
+            .config(include_str!(
+                "fixtures/enforce_pre_computed_cost.router.yaml"
+            ))
+            .build()
+            .await;
+
+        // The pre-computed cost of persisted queries is used instead of estimating it from the plan
+        let ctx = context();
+        ctx.extensions().with_lock(|mut lock| {
+            lock.insert(OperationComplexity {
+                cost: Some(20.0),
+                depth: None,
+            })
+        });
+
+        let resp = plugin
+            .call_execution(
+                execution::Request::fake_builder().context(ctx).build(),
+                |req| {
+                    execution::Response::fake_builder()
+                        .context(req.context)
+                        .build()
+                        .unwrap()
+                },
+            )
+            .await
+            .unwrap();
+        let body = resp
+            .response
+            .into_body()
+            .collect::<Vec<graphql::Response>>()
+            .await;
+        assert_eq!(
+            body[0].errors[0].extensions.get("code"),
+            Some(&"COST_ESTIMATED_TOO_EXPENSIVE".into())
+        );
+        assert_eq!(
+            body[0].errors[0].extensions.get("cost.estimated"),
+            Some(&20.0.into())
+        );
+    }
+
     async fn test_on_execution(config: &'static str) -> Vec<Response> {
         let plugin = PluginTestHarness::<DemandControl>::builder()
             .config(config)
@@ -6,6 +6,7 @@ use crate::plugins::demand_control::strategy::StrategyImpl;
 use crate::plugins::demand_control::CostContext;
 use crate::plugins::demand_control::DemandControlError;
 use crate::services::execution;
+use crate::services::layers::persisted_queries::OperationComplexity;
 use crate::services::subgraph;
 
 /// This strategy will reject requests if the estimated cost of the request exceeds the maximum cost.
@@ -17,8 +18,14 @@ pub(crate) struct StaticEstimated {
 
 impl StrategyImpl for StaticEstimated {
     fn on_execution_request(&self, request: &execution::Request) -> Result<(), DemandControlError> {
-        self.cost_calculator
-            .planned(&request.query_plan)
+        // Operations from the persisted query manifest may come with a pre-computed cost
+        let pre_computed = request.context.extensions().with_lock(|lock| {
+            lock.get::<OperationComplexity>()
+                .and_then(|complexity| complexity.cost)
+        });
+        pre_computed
+            .map(Ok)
+            .unwrap_or_else(|| self.cost_calculator.planned(&request.query_plan))
             .and_then(|cost| {
                 request.context.extensions().with_lock(|mut lock| {
                     let cost_result = lock.get_or_default_mut::<CostContext>();
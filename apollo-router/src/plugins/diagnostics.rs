@@ -0,0 +1,207 @@
+//! Expose the support bundle of the running router on an endpoint.
+//!
+//! The bundle is the one printed by the `router diagnose` subcommand, with the in-memory caches
+//! and the GraphQL errors of the running router.
+
+use std::task::Context;
+use std::task::Poll;
+
+use futures::future::BoxFuture;
+use http::Method;
+use http::StatusCode;
+use multimap::MultiMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::diagnose::Bundle;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::Body;
+use crate::ListenAddr;
+
+/// Diagnostics configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct DiagnosticsConfig {
+    /// Endpoint returning the support bundle of the router
+    endpoint: DiagnosticsEndpoint,
+}
+
+/// Diagnostics endpoint configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct DiagnosticsEndpoint {
+    /// Set to true to expose the diagnostics endpoint
+    enabled: bool,
+    /// The listen address
+    listen: ListenAddr,
+    /// The path of the diagnostics endpoint
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of requests to the endpoint
+    token: Option<String>,
+}
+
+impl Default for DiagnosticsEndpoint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            path: "/debug/diagnose".to_string(),
+            token: None,
+        }
+    }
+}
+
+struct Diagnostics {
+    endpoint: DiagnosticsEndpoint,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Diagnostics {
+    type Config = DiagnosticsConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let endpoint = init.config.endpoint;
+        if endpoint.enabled && endpoint.token.as_deref().unwrap_or_default().is_empty() {
+            return Err(
+                "diagnostics.endpoint.token is required to expose the diagnostics endpoint".into(),
+            );
+        }
+
+        Ok(Diagnostics { endpoint })
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.endpoint.enabled, &self.endpoint.token) {
+            map.insert(
+                self.endpoint.listen.clone(),
+                Endpoint::from_router_service(
+                    self.endpoint.path.clone(),
+                    DiagnosticsService {
+                        token: BearerToken::new(token),
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+#[derive(Clone)]
+struct DiagnosticsService {
+    token: BearerToken,
+}
+
+impl DiagnosticsService {
+    fn handle(&self, request: &http::Request<Body>) -> Result<Bundle, (StatusCode, String)> {
+        self.token.check(request.headers())?;
+        if request.method() != Method::GET {
+            return Err((
+                StatusCode::METHOD_NOT_ALLOWED,
+                "expected a GET request".to_string(),
+            ));
+        }
+        Bundle::running().ok_or_else(|| {
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                "the router is not running yet".to_string(),
+            )
+        })
+    }
+}
+
+impl Service<router::Request> for DiagnosticsService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let result = self.handle(&req.router_request);
+        Box::pin(async move {
+            let (status, content_type, body) = match result {
+                Ok(bundle) => (
+                    StatusCode::OK,
+                    "application/json",
+                    serde_json::to_vec(&bundle)?,
+                ),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+register_plugin!("apollo", "diagnostics", Diagnostics);
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::Configuration;
+
+    #[tokio::test]
+    async fn returns_the_bundle() {
+        let config = serde_json::from_value(json!({ "endpoint": { "enabled": true } })).unwrap();
+        assert!(
+            Diagnostics::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+
+        let configuration: Configuration = "diagnostics:\n  endpoint:\n    token: secret\n"
+            .parse()
+            .unwrap();
+        crate::diagnose::running(Arc::new(configuration), Arc::new("type Query".to_string()));
+
+        let service = DiagnosticsService {
+            token: BearerToken::new("secret"),
+        };
+        let request = |authorization: &str| {
+            http::Request::builder()
+                .uri("http://localhost/debug/diagnose")
+                .header(http::header::AUTHORIZATION, authorization)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (status, _) = service.handle(&request("Bearer wrong")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let bundle =
+            serde_json::to_value(service.handle(&request("Bearer secret")).unwrap()).unwrap();
+        assert_eq!(
+            bundle["configuration"]["diagnostics"]["endpoint"]["token"],
+            "[REDACTED]"
+        );
+        assert!(bundle["plugins"]
+            .as_array()
+            .unwrap()
+            .contains(&json!("apollo.diagnostics")));
+        assert!(bundle["schema"]["hash"].is_string());
+        assert!(bundle["caches"].is_array());
+        assert!(bundle["errors"].is_array());
+    }
+}
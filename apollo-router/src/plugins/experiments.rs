@@ -0,0 +1,331 @@
+//! Assign requests to the buckets of A/B experiments.
+//!
+//! Each experiment splits the requests between weighted buckets. The assignment is sticky: it is
+//! computed from a hash of the experiment name and an identifier of the client or user, read from
+//! a request header or a JWT claim, so an identifier lands in the same bucket for every request and
+//! on every router instance. Requests without an identifier are assigned randomly.
+//!
+//! Buckets are stored in the context, where header rules, telemetry selectors, coprocessors, Rhai
+//! scripts and custom plugins can read them.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json_bytes::Value;
+use sha2::Digest;
+use sha2::Sha256;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::context::keys;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::register_plugin;
+use crate::services::supergraph;
+use crate::Context;
+
+/// Context key of the buckets of the request, as a map of experiment names to bucket names.
+/// Buckets set by coprocessors, Rhai scripts or custom plugins before this plugin runs take
+/// precedence over the configuration
+pub(crate) const EXPERIMENTS_KEY: &str = keys::EXPERIMENT_BUCKETS.name();
+
+/// Prefix of the context keys holding the bucket of each experiment as a string, for the header
+/// rules and telemetry selectors reading a single context key
+const EXPERIMENT_KEY_PREFIX: &str = keys::EXPERIMENT_BUCKET_PREFIX;
+
+/// Experiments configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct ExperimentsConfig {
+    /// Request header identifying the client or user
+    header: Option<String>,
+    /// JWT claim identifying the user, used when the request header is not set
+    claim: Option<String>,
+    /// Experiments, by name
+    experiments: HashMap<String, ExperimentConfig>,
+}
+
+/// Experiment configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct ExperimentConfig {
+    /// Buckets of the experiment. Their order is part of the assignment: reordering them moves
+    /// users between buckets
+    buckets: Vec<BucketConfig>,
+}
+
+/// Bucket of an experiment
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct BucketConfig {
+    /// The name of the bucket
+    name: String,
+    /// Share of the requests assigned to the bucket, relative to the weights of the other buckets
+    weight: u32,
+}
+
+impl ExperimentConfig {
+    fn total_weight(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.weight as u64).sum()
+    }
+
+    /// Selects the bucket of an identifier, or a random one without identifier
+    fn assign(&self, experiment: &str, id: Option<&str>) -> &str {
+        let point = match id {
+            Some(id) => sticky_point(experiment, id),
+            None => rand::random(),
+        } % self.total_weight();
+
+        let mut upper = 0;
+        self.buckets
+            .iter()
+            .find(|bucket| {
+                upper += bucket.weight as u64;
+                point < upper
+            })
+            .or(self.buckets.last())
+            .map(|bucket| bucket.name.as_str())
+            .expect("experiments have at least one bucket")
+    }
+}
+
+#[derive(Debug)]
+struct Experiments {
+    config: Arc<ExperimentsConfig>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Experiments {
+    type Config = ExperimentsConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        for (name, experiment) in &init.config.experiments {
+            if experiment.total_weight() == 0 {
+                return Err(
+                    format!("experiment {name}: the bucket weights must not all be zero").into(),
+                );
+            }
+            let mut names = HashSet::new();
+            for bucket in &experiment.buckets {
+                if !names.insert(bucket.name.as_str()) {
+                    return Err(
+                        format!("experiment {name}: duplicate bucket {}", bucket.name).into(),
+                    );
+                }
+            }
+        }
+
+        Ok(Experiments {
+            config: Arc::new(init.config),
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if self.config.experiments.is_empty() {
+            return service;
+        }
+        let config = self.config.clone();
+
+        ServiceBuilder::new()
+            .map_request(move |req: supergraph::Request| {
+                let id = identifier(&req, config.header.as_deref(), config.claim.as_deref());
+                let mut buckets: HashMap<String, String> = req
+                    .context
+                    .get(EXPERIMENTS_KEY)
+                    .unwrap_or_default()
+                    .unwrap_or_default();
+                for (name, experiment) in &config.experiments {
+                    let bucket = buckets
+                        .entry(name.clone())
+                        .or_insert_with(|| experiment.assign(name, id.as_deref()).to_string());
+                    u64_counter!(
+                        "apollo.router.operations.experiments",
+                        "Number of requests assigned to each bucket of the experiments",
+                        1,
+                        experiment = name.clone(),
+                        bucket = bucket.clone()
+                    );
+                    let _ = req
+                        .context
+                        .insert(format!("{EXPERIMENT_KEY_PREFIX}{name}"), bucket.clone());
+                }
+                let _ = req.context.insert(EXPERIMENTS_KEY, buckets);
+                req
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+/// Point of an identifier for a name, the same on every router instance
+pub(crate) fn sticky_point(name: &str, id: &str) -> u64 {
+    let digest = Sha256::new()
+        .chain_update(name)
+        .chain_update([0])
+        .chain_update(id)
+        .finalize();
+    u64::from_be_bytes(
+        digest[..8]
+            .try_into()
+            .expect("a SHA-256 digest has 32 bytes"),
+    )
+}
+
+/// Identifier of the client or user sending the request, from the header or the JWT claim
+pub(crate) fn identifier(
+    req: &supergraph::Request,
+    header: Option<&str>,
+    claim: Option<&str>,
+) -> Option<String> {
+    if let Some(header) = header {
+        let value = req
+            .supergraph_request
+            .headers()
+            .get(header)
+            .and_then(|value| value.to_str().ok())
+            .filter(|value| !value.is_empty());
+        if let Some(value) = value {
+            return Some(value.to_string());
+        }
+    }
+    claim.and_then(|claim| claim_identifier(&req.context, claim))
+}
+
+/// Identifier of the user from a string or number JWT claim
+pub(crate) fn claim_identifier(context: &Context, claim: &str) -> Option<String> {
+    match context
+        .get_json_value(APOLLO_AUTHENTICATION_JWT_CLAIMS)?
+        .get(claim)?
+    {
+        Value::String(id) => Some(id.as_str().to_string()),
+        Value::Number(id) => Some(id.to_string()),
+        _ => None,
+    }
+}
+
+register_plugin!("apollo", "experiments", Experiments);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::plugin::test::MockSupergraphService;
+
+    fn experiment(weights: &[(&str, u32)]) -> ExperimentConfig {
+        ExperimentConfig {
+            buckets: weights
+                .iter()
+                .map(|(name, weight)| BucketConfig {
+                    name: name.to_string(),
+                    weight: *weight,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn assigns_sticky_buckets() {
+        let checkout = experiment(&[("control", 50), ("treatment", 50)]);
+        let assigned: HashSet<_> = (0..100)
+            .map(|id| checkout.assign("checkout", Some(&id.to_string())))
+            .collect();
+        assert_eq!(assigned.len(), 2);
+        for id in 0..100 {
+            let id = id.to_string();
+            assert_eq!(
+                checkout.assign("checkout", Some(&id)),
+                checkout.assign("checkout", Some(&id))
+            );
+        }
+
+        let rollout = experiment(&[("control", 0), ("treatment", 1)]);
+        assert_eq!(rollout.assign("rollout", Some("user")), "treatment");
+        assert_eq!(rollout.assign("rollout", None), "treatment");
+    }
+
+    #[tokio::test]
+    async fn stores_buckets_in_the_context() {
+        let config: ExperimentsConfig = serde_json::from_value(json!({
+            "header": "x-user-id",
+            "experiments": {
+                "checkout": { "buckets": [
+                    { "name": "control", "weight": 0 },
+                    { "name": "treatment", "weight": 1 }
+                ] },
+                "pricing": { "buckets": [{ "name": "control", "weight": 1 }] }
+            }
+        }))
+        .unwrap();
+        let plugin = Experiments::new(PluginInit::fake_new(config, Default::default()))
+            .await
+            .unwrap();
+
+        let mut mock = MockSupergraphService::new();
+        mock.expect_call().returning(|req: supergraph::Request| {
+            Ok(supergraph::Response::fake_builder()
+                .context(req.context)
+                .build()
+                .unwrap())
+        });
+        let service = plugin.supergraph_service(mock.boxed());
+
+        // buckets set by a coprocessor take precedence
+        let context = Context::new();
+        context
+            .insert(EXPERIMENTS_KEY, json!({ "pricing": "treatment" }))
+            .unwrap();
+        let request = supergraph::Request::fake_builder()
+            .header("x-user-id", "user")
+            .context(context)
+            .build()
+            .unwrap();
+        let response = service.oneshot(request).await.unwrap();
+
+        let buckets: HashMap<String, String> =
+            response.context.get(EXPERIMENTS_KEY).unwrap().unwrap();
+        assert_eq!(buckets["checkout"], "treatment");
+        assert_eq!(buckets["pricing"], "treatment");
+        assert_eq!(
+            response
+                .context
+                .get::<_, String>("apollo_experiments::bucket::checkout")
+                .unwrap(),
+            Some("treatment".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_buckets() {
+        let config: ExperimentsConfig = serde_json::from_value(json!({
+            "experiments": {
+                "checkout": { "buckets": [
+                    { "name": "control", "weight": 1 },
+                    { "name": "control", "weight": 1 }
+                ] }
+            }
+        }))
+        .unwrap();
+        assert!(
+            Experiments::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+
+        let config: ExperimentsConfig = serde_json::from_value(json!({
+            "experiments": { "checkout": { "buckets": [] } }
+        }))
+        .unwrap();
+        assert!(
+            Experiments::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+    }
+}
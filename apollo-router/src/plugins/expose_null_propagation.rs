@@ -0,0 +1,330 @@
+//! Explain why fields of a response are null.
+//!
+//! When a non-nullable field resolves to null, the null propagates to its closest nullable parent.
+//! For requests with the `Apollo-Expose-Null-Propagation: true` header, the router adds to the
+//! response an extension listing the fields nulled by this propagation, the error of the
+//! non-nullable field it started from, and the subgraphs that returned errors for that field.
+
+use std::sync::Arc;
+
+use http::HeaderValue;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::json;
+use serde_json_bytes::Value;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use crate::graphql;
+use crate::json_ext::Path;
+use crate::json_ext::PathElement;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::supergraph;
+use crate::Context;
+
+const EXPOSE_NULL_PROPAGATION_HEADER_NAME: &str = "Apollo-Expose-Null-Propagation";
+const NULL_PROPAGATION_EXTENSION: &str = "apolloNullPropagation";
+
+/// Expose null propagation
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct ExposeNullPropagationConfig(
+    /// Enabled
+    bool,
+);
+
+/// Errors of the subgraph fetches of a request exposing the null propagation, with the path they
+/// apply to and the subgraph that returned them
+#[derive(Default)]
+pub(crate) struct SubgraphErrors(Vec<(Path, Arc<str>)>);
+
+/// Records the errors of a fetch, if the request exposes the null propagation
+pub(crate) fn record_subgraph_errors(
+    context: &Context,
+    service_name: &Arc<str>,
+    current_dir: &Path,
+    errors: &[graphql::Error],
+) {
+    if errors.is_empty() {
+        return;
+    }
+    context.extensions().with_lock(|mut lock| {
+        if let Some(SubgraphErrors(recorded)) = lock.get_mut::<SubgraphErrors>() {
+            recorded.extend(errors.iter().map(|error| {
+                (
+                    error.path.clone().unwrap_or_else(|| current_dir.clone()),
+                    service_name.clone(),
+                )
+            }));
+        }
+    });
+}
+
+/// Path the null of a non-nullable field propagated to: the first null value on its path
+fn propagated_to(data: &Value, origin: &Path) -> Path {
+    let mut current = data;
+    let mut path = Vec::new();
+    for element in origin.iter() {
+        let next = match (element, current) {
+            (PathElement::Key(key, _), Value::Object(object)) => object.get(key.as_str()),
+            (PathElement::Index(index), Value::Array(array)) => array.get(*index),
+            _ => None,
+        };
+        let Some(next) = next else {
+            break;
+        };
+        path.push(element.clone());
+        current = next;
+    }
+    Path(path)
+}
+
+/// Whether one of the paths starts with the other. The `@` of fetch paths matches any index
+fn overlaps(fetch_path: &Path, path: &Path) -> bool {
+    fetch_path
+        .iter()
+        .filter(|element| !matches!(element, PathElement::Fragment(_)))
+        .zip(path.iter())
+        .all(|pair| match pair {
+            (PathElement::Flatten(_), PathElement::Index(_)) => true,
+            (PathElement::Key(a, _), PathElement::Key(b, _)) => a == b,
+            (a, b) => a == b,
+        })
+}
+
+/// Describes the null propagations of a response, from the `valueCompletion` errors added when
+/// formatting it
+fn null_propagation(response: &graphql::Response, subgraph_errors: &[(Path, Arc<str>)]) -> Value {
+    let data = response.data.as_ref().unwrap_or(&Value::Null);
+    let origins = response
+        .extensions
+        .get("valueCompletion")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|error| {
+            let error = error.as_object()?;
+            let origin: Path = serde_json_bytes::from_value(error.get("path")?.clone()).ok()?;
+            Some((origin, error.get("message").cloned().unwrap_or_default()))
+        });
+
+    let propagations = origins
+        .map(|(origin, message)| {
+            let mut subgraphs: Vec<&str> = subgraph_errors
+                .iter()
+                .filter(|(path, _)| overlaps(path, &origin))
+                .map(|(_, service_name)| service_name.as_ref())
+                .collect();
+            subgraphs.sort_unstable();
+            subgraphs.dedup();
+            json!({
+                "path": serde_json_bytes::to_value(propagated_to(data, &origin)).unwrap_or_default(),
+                "origin": serde_json_bytes::to_value(&origin).unwrap_or_default(),
+                "message": message,
+                "subgraphs": subgraphs,
+            })
+        })
+        .collect();
+    Value::Array(propagations)
+}
+
+#[derive(Debug, Clone)]
+struct ExposeNullPropagation {
+    enabled: bool,
+}
+
+#[async_trait::async_trait]
+impl Plugin for ExposeNullPropagation {
+    type Config = ExposeNullPropagationConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(ExposeNullPropagation {
+            enabled: init.config.0,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if !self.enabled {
+            return service;
+        }
+
+        service
+            .map_request(|request: supergraph::Request| {
+                if request
+                    .supergraph_request
+                    .headers()
+                    .get(EXPOSE_NULL_PROPAGATION_HEADER_NAME)
+                    == Some(&HeaderValue::from_static("true"))
+                {
+                    request
+                        .context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(SubgraphErrors::default()));
+                }
+                request
+            })
+            .map_response(|response: supergraph::Response| {
+                let context = response.context.clone();
+                response.map_stream(move |mut response| {
+                    // deferred responses are not described
+                    if response.path.is_none() {
+                        let propagation = context.extensions().with_lock(|lock| {
+                            lock.get::<SubgraphErrors>()
+                                .map(|errors| null_propagation(&response, &errors.0))
+                        });
+                        if let Some(propagation) = propagation {
+                            response
+                                .extensions
+                                .insert(NULL_PROPAGATION_EXTENSION, propagation);
+                        }
+                    }
+                    response
+                })
+            })
+            .boxed()
+    }
+}
+
+register_plugin!(
+    "experimental",
+    "expose_null_propagation",
+    ExposeNullPropagation
+);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::plugin::test::MockSubgraph;
+    use crate::MockedSubgraphs;
+    use crate::TestHarness;
+
+    const SCHEMA: &str = r#"schema
+        @core(feature: "https://specs.apollo.dev/core/v0.1")
+        @core(feature: "https://specs.apollo.dev/join/v0.1")
+         {
+        query: Query
+   }
+   directive @core(feature: String!) repeatable on SCHEMA
+   directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet) on FIELD_DEFINITION
+   directive @join__type(graph: join__Graph!, key: join__FieldSet) repeatable on OBJECT | INTERFACE
+   directive @join__owner(graph: join__Graph!) on OBJECT | INTERFACE
+   directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+   scalar join__FieldSet
+   enum join__Graph {
+       USER @join__graph(name: "user", url: "http://localhost:4001/graphql")
+       ORGA @join__graph(name: "orga", url: "http://localhost:4002/graphql")
+   }
+   type Query {
+       currentUser: User @join__field(graph: USER)
+   }
+   type User
+   @join__owner(graph: USER)
+   @join__type(graph: ORGA, key: "id")
+   @join__type(graph: USER, key: "id"){
+       id: ID!
+       name: String
+       activeOrganization: Organization
+   }
+   type Organization
+   @join__owner(graph: ORGA)
+   @join__type(graph: ORGA, key: "id")
+   @join__type(graph: USER, key: "id") {
+       id: ID
+       creatorUser: User!
+       name: String
+   }"#;
+
+    async fn call(header: bool) -> graphql::Response {
+        let subgraphs = MockedSubgraphs([
+            ("user", MockSubgraph::builder().with_json(
+                serde_json::json!{{"query":"{currentUser{activeOrganization{__typename id}}}"}},
+                serde_json::json!{{"data": {"currentUser": { "activeOrganization": { "__typename": "Organization", "id": "0" } }}}}
+            ).build()),
+            ("orga", MockSubgraph::builder().with_json(
+                serde_json::json!{{
+                    "query":"query($representations:[_Any!]!){_entities(representations:$representations){...on Organization{creatorUser{__typename id}}}}",
+                    "variables": {
+                        "representations":[{"__typename": "Organization", "id":"0"}]
+                    }
+                }},
+                serde_json::json!{{"errors": [{ "message": "orga is down" }]}}
+            ).build())
+        ].into_iter().collect());
+
+        let service = TestHarness::builder()
+            .configuration_json(json!({
+                "plugins": { "experimental.expose_null_propagation": true }
+            }))
+            .unwrap()
+            .schema(SCHEMA)
+            .extra_plugin(subgraphs)
+            .build_supergraph()
+            .await
+            .unwrap();
+
+        let mut request = supergraph::Request::fake_builder().query(
+            "query { currentUser { activeOrganization { id creatorUser { __typename id } } } }",
+        );
+        if header {
+            request = request.header(EXPOSE_NULL_PROPAGATION_HEADER_NAME, "true");
+        }
+        service
+            .oneshot(request.build().unwrap())
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn exposes_null_propagation() {
+        let response = call(true).await;
+        assert_eq!(
+            response.data,
+            Some(serde_json_bytes::json!({ "currentUser": { "activeOrganization": null } }))
+        );
+        let propagations = response
+            .extensions
+            .get(NULL_PROPAGATION_EXTENSION)
+            .and_then(Value::as_array)
+            .unwrap();
+        assert_eq!(propagations.len(), 1);
+        let propagation = propagations[0].as_object().unwrap();
+        assert_eq!(
+            propagation.get("path"),
+            Some(&serde_json_bytes::json!([
+                "currentUser",
+                "activeOrganization"
+            ]))
+        );
+        // `creatorUser` is missing from the subgraph responses, the error is on its parent
+        assert_eq!(
+            propagation.get("origin"),
+            Some(&serde_json_bytes::json!([
+                "currentUser",
+                "activeOrganization"
+            ]))
+        );
+        assert!(propagation
+            .get("message")
+            .and_then(Value::as_str)
+            .unwrap()
+            .ends_with("Organization.creatorUser"));
+        assert_eq!(
+            propagation.get("subgraphs"),
+            Some(&serde_json_bytes::json!(["orga"]))
+        );
+
+        let response = call(false).await;
+        assert!(response
+            .extensions
+            .get(NULL_PROPAGATION_EXTENSION)
+            .is_none());
+    }
+}
@@ -9,18 +9,19 @@ use serde_json_bytes::json;
 use tower::BoxError;
 use tower::ServiceExt as TowerServiceExt;
 
+use crate::context::keys::EXPOSED_FORMATTED_QUERY_PLAN;
+use crate::context::keys::EXPOSED_QUERY_PLAN;
+use crate::context::keys::EXPOSE_QUERY_PLAN_ENABLED;
 use crate::layers::ServiceExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
+use crate::plugins::request_toggles::RequestToggles;
 use crate::register_plugin;
 use crate::services::execution;
 use crate::services::supergraph;
 
 const EXPOSE_QUERY_PLAN_HEADER_NAME: &str = "Apollo-Expose-Query-Plan";
 const ENABLE_EXPOSE_QUERY_PLAN_ENV: &str = "APOLLO_EXPOSE_QUERY_PLAN";
-const QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.plan";
-const FORMATTED_QUERY_PLAN_CONTEXT_KEY: &str = "experimental::expose_query_plan.formatted_plan";
-const ENABLED_CONTEXT_KEY: &str = "experimental::expose_query_plan.enabled";
 
 #[derive(Debug, Clone)]
 struct ExposeQueryPlan {
@@ -51,17 +52,17 @@ impl Plugin for ExposeQueryPlan {
             .map_request(move |req: execution::Request| {
                 if req
                     .context
-                    .get::<_, bool>(ENABLED_CONTEXT_KEY)
+                    .get_typed(&EXPOSE_QUERY_PLAN_ENABLED)
                     .ok()
                     .flatten()
                     .is_some()
                 {
                     req.context
-                        .insert(QUERY_PLAN_CONTEXT_KEY, req.query_plan.root.clone())
+                        .insert_typed(&EXPOSED_QUERY_PLAN, req.query_plan.root.clone())
                         .unwrap();
                     req.context
-                        .insert(
-                            FORMATTED_QUERY_PLAN_CONTEXT_KEY,
+                        .insert_typed(
+                            &EXPOSED_FORMATTED_QUERY_PLAN,
                             req.query_plan.formatted_query_plan.clone(),
                         )
                         .unwrap();
@@ -76,9 +77,10 @@ impl Plugin for ExposeQueryPlan {
         let conf_enabled = self.enabled;
         service
             .map_future_with_request_data(move |req: &supergraph::Request| {
-                let is_enabled = conf_enabled && req.supergraph_request.headers().get(EXPOSE_QUERY_PLAN_HEADER_NAME) == Some(&HeaderValue::from_static("true"));
+                let is_enabled = conf_enabled && (req.supergraph_request.headers().get(EXPOSE_QUERY_PLAN_HEADER_NAME) == Some(&HeaderValue::from_static("true"))
+                    || RequestToggles::get(&req.context).include_query_plan);
                 if is_enabled {
-                    req.context.insert(ENABLED_CONTEXT_KEY, true).unwrap();
+                    req.context.insert_typed(&EXPOSE_QUERY_PLAN_ENABLED, true).unwrap();
                 }
 
                 is_enabled
@@ -93,11 +95,11 @@ impl Plugin for ExposeQueryPlan {
 
                             if let Some(first) = &mut first {
                                 if let Some(plan) =
-                                    res.context.get_json_value(QUERY_PLAN_CONTEXT_KEY)
+                                    res.context.get_json_value(EXPOSED_QUERY_PLAN.name())
                                 {
                                     first
                                         .extensions
-                                        .insert("apolloQueryPlan", json!({ "object": { "kind": "QueryPlan", "node": plan }, "text": res.context.get_json_value(FORMATTED_QUERY_PLAN_CONTEXT_KEY) }));
+                                        .insert("apolloQueryPlan", json!({ "object": { "kind": "QueryPlan", "node": plan }, "text": res.context.get_json_value(EXPOSED_FORMATTED_QUERY_PLAN.name()) }));
                                 }
                             }
                             res.response = http::Response::from_parts(
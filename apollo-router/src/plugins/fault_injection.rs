@@ -0,0 +1,445 @@
+//! Inject faults in subgraph requests, to test resilience settings in staging.
+//!
+//! Faults are added latency, errors and connection resets, applied to the requests of a subgraph
+//! or of an operation. They are injected below traffic shaping, so timeouts and retries apply to
+//! them as they would to a failing subgraph. An endpoint switches the injection on and off at
+//! runtime.
+
+use std::ops::ControlFlow;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use bytes::Buf;
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use http::Method;
+use http::StatusCode;
+use multimap::MultiMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::error::FetchError;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::body::RouterBody;
+use crate::services::router::Body;
+use crate::services::subgraph;
+use crate::ListenAddr;
+
+/// Fault injection configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct FaultInjectionConfig {
+    /// Set to true to inject the configured faults
+    enabled: bool,
+    /// Whether faults are injected when the router starts. The endpoint switches it at runtime
+    active: bool,
+    /// The listen address of the endpoint
+    listen: ListenAddr,
+    /// The path of the endpoint
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of requests to the endpoint.
+    /// The endpoint is only exposed when a token is set
+    token: Option<String>,
+    /// Faults to inject. The first rule matching a subgraph request applies
+    rules: Vec<FaultRule>,
+}
+
+impl Default for FaultInjectionConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            active: true,
+            listen: default_listen(),
+            path: "/debug/fault_injection".to_string(),
+            token: None,
+            rules: Vec::new(),
+        }
+    }
+}
+
+/// Faults injected in the matching subgraph requests
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct FaultRule {
+    /// Name of the subgraph the rule applies to. The rule applies to all subgraphs if not set
+    #[serde(default)]
+    subgraph: Option<String>,
+    /// Name of the client operation the rule applies to. The rule applies to all operations if
+    /// not set
+    #[serde(default)]
+    operation_name: Option<String>,
+    #[serde(deserialize_with = "humantime_serde::deserialize", default)]
+    #[schemars(with = "String", default)]
+    /// Latency added before sending the request
+    latency: Option<Duration>,
+    /// Fraction of the requests failing with an HTTP error, between 0 and 1
+    #[serde(default)]
+    error_rate: f64,
+    /// HTTP status of the injected errors
+    #[serde(default = "default_error_status")]
+    error_status: u16,
+    /// Fraction of the requests failing as if the connection was reset, between 0 and 1
+    #[serde(default)]
+    reset_rate: f64,
+}
+
+fn default_error_status() -> u16 {
+    500
+}
+
+impl FaultRule {
+    fn matches(&self, subgraph_name: &str, request: &subgraph::Request) -> bool {
+        self.subgraph
+            .as_deref()
+            .map_or(true, |name| name == subgraph_name)
+            && self.operation_name.as_deref().map_or(true, |name| {
+                request.supergraph_request.body().operation_name.as_deref() == Some(name)
+            })
+    }
+
+    /// Waits for the latency, then fails the request if it draws an injected failure
+    async fn inject(&self, subgraph_name: &str) -> Result<(), FetchError> {
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        if rand::random::<f64>() < self.reset_rate {
+            return Err(FetchError::SubrequestHttpError {
+                status_code: None,
+                service: subgraph_name.to_string(),
+                reason: "connection reset by peer (injected fault)".to_string(),
+            });
+        }
+        if rand::random::<f64>() < self.error_rate {
+            return Err(FetchError::SubrequestHttpError {
+                status_code: Some(self.error_status),
+                service: subgraph_name.to_string(),
+                reason: format!("{}: injected fault", self.error_status),
+            });
+        }
+        Ok(())
+    }
+}
+
+struct FaultInjection {
+    config: FaultInjectionConfig,
+    rules: Arc<Vec<FaultRule>>,
+    /// Whether faults are injected, switched by the endpoint
+    active: Arc<AtomicBool>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for FaultInjection {
+    type Config = FaultInjectionConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        for rule in &init.config.rules {
+            if !(0.0..=1.0).contains(&rule.error_rate) || !(0.0..=1.0).contains(&rule.reset_rate) {
+                return Err("fault_injection rates must be between 0 and 1".into());
+            }
+            if StatusCode::from_u16(rule.error_status).is_err() {
+                return Err(format!(
+                    "fault_injection.error_status {} is not an HTTP status",
+                    rule.error_status
+                )
+                .into());
+            }
+        }
+        if init.config.enabled {
+            tracing::warn!(
+                "fault injection is enabled: subgraph requests matching {} rules will fail or be delayed",
+                init.config.rules.len()
+            );
+        }
+
+        Ok(FaultInjection {
+            rules: Arc::new(init.config.rules.clone()),
+            active: Arc::new(AtomicBool::new(init.config.active)),
+            config: init.config,
+        })
+    }
+
+    fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        if !self.config.enabled || self.rules.is_empty() {
+            return service;
+        }
+        let rules = self.rules.clone();
+        let active = self.active.clone();
+        let name = name.to_string();
+        ServiceBuilder::new()
+            .oneshot_checkpoint_async(move |request: subgraph::Request| {
+                let rule = if active.load(Ordering::Relaxed) {
+                    rules
+                        .iter()
+                        .find(|rule| rule.matches(&name, &request))
+                        .cloned()
+                } else {
+                    None
+                };
+                let name = name.clone();
+                async move {
+                    if let Some(rule) = rule {
+                        rule.inject(&name).await?;
+                    }
+                    Ok(ControlFlow::Continue(request))
+                }
+                .boxed()
+            })
+            .service(service)
+            .boxed()
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.config.enabled, &self.config.token) {
+            map.insert(
+                self.config.listen.clone(),
+                Endpoint::from_router_service(
+                    self.config.path.clone(),
+                    FaultInjectionService {
+                        token: BearerToken::new(token),
+                        active: self.active.clone(),
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+/// Body of a request switching fault injection, and of the responses of the endpoint
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct FaultInjectionStatus {
+    /// Whether faults are injected
+    active: bool,
+}
+
+#[derive(Clone)]
+struct FaultInjectionService {
+    token: BearerToken,
+    active: Arc<AtomicBool>,
+}
+
+impl FaultInjectionService {
+    async fn handle(
+        &self,
+        request: http::Request<Body>,
+    ) -> Result<FaultInjectionStatus, (StatusCode, String)> {
+        self.token.check(request.headers())?;
+
+        match *request.method() {
+            Method::GET => Ok(FaultInjectionStatus {
+                active: self.active.load(Ordering::Relaxed),
+            }),
+            Method::POST => {
+                let bytes = Into::<RouterBody>::into(request.into_body())
+                    .to_bytes()
+                    .await
+                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                let change: FaultInjectionStatus = serde_json::from_reader(bytes.reader())
+                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                self.active.store(change.active, Ordering::Relaxed);
+                tracing::info!(
+                    "fault injection {}",
+                    if change.active {
+                        "activated"
+                    } else {
+                        "deactivated"
+                    }
+                );
+                Ok(change)
+            }
+            _ => Err((
+                StatusCode::METHOD_NOT_ALLOWED,
+                "expected a GET or POST request".to_string(),
+            )),
+        }
+    }
+}
+
+impl Service<router::Request> for FaultInjectionService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move {
+            let (status, content_type, body) = match service.handle(req.router_request).await {
+                Ok(status) => (
+                    StatusCode::OK,
+                    "application/json",
+                    serde_json::to_vec(&status)?,
+                ),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+register_plugin!("experimental", "fault_injection", FaultInjection);
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::plugin::test::MockSubgraphService;
+    use crate::Context;
+
+    async fn plugin(config: serde_json::Value) -> FaultInjection {
+        FaultInjection::new(PluginInit::fake_new(
+            serde_json::from_value(config).unwrap(),
+            Default::default(),
+        ))
+        .await
+        .unwrap()
+    }
+
+    fn subgraph() -> subgraph::BoxService {
+        let mut service = MockSubgraphService::new();
+        service
+            .expect_call()
+            .returning(|request: subgraph::Request| {
+                Ok(subgraph::Response::fake_builder()
+                    .context(request.context)
+                    .build())
+            });
+        service.boxed()
+    }
+
+    fn request(operation_name: &str) -> subgraph::Request {
+        subgraph::Request::fake_builder()
+            .supergraph_request(Arc::new(
+                http::Request::builder()
+                    .body(
+                        crate::graphql::Request::builder()
+                            .query("query Me { me { name } }")
+                            .operation_name(operation_name)
+                            .build(),
+                    )
+                    .unwrap(),
+            ))
+            .context(Context::new())
+            .build()
+    }
+
+    #[tokio::test]
+    async fn injects_faults_in_matching_requests() {
+        let plugin = plugin(json!({
+            "enabled": true,
+            "rules": [
+                { "subgraph": "accounts", "operation_name": "Me", "reset_rate": 1.0 },
+                { "subgraph": "accounts", "error_rate": 1.0, "error_status": 503 }
+            ]
+        }))
+        .await;
+
+        let error = plugin
+            .subgraph_service("accounts", subgraph())
+            .oneshot(request("Me"))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("connection reset"), "{error}");
+
+        let error = plugin
+            .subgraph_service("accounts", subgraph())
+            .oneshot(request("Other"))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("503"), "{error}");
+
+        assert!(plugin
+            .subgraph_service("products", subgraph())
+            .oneshot(request("Me"))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn switches_at_runtime() {
+        let plugin = plugin(json!({
+            "enabled": true,
+            "token": "secret",
+            "rules": [{ "error_rate": 1.0 }]
+        }))
+        .await;
+        let service = FaultInjectionService {
+            token: BearerToken::new("secret"),
+            active: plugin.active.clone(),
+        };
+        let switch = |authorization: &str, active: bool| {
+            http::Request::builder()
+                .method(Method::POST)
+                .uri("http://localhost/debug/fault_injection")
+                .header(http::header::AUTHORIZATION, authorization)
+                .body(json!({ "active": active }).to_string().into())
+                .unwrap()
+        };
+
+        let (status, _) = service
+            .handle(switch("Bearer wrong", false))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(plugin
+            .subgraph_service("accounts", subgraph())
+            .oneshot(request("Me"))
+            .await
+            .is_err());
+
+        let status = service
+            .handle(switch("Bearer secret", false))
+            .await
+            .unwrap();
+        assert!(!status.active);
+        assert!(plugin
+            .subgraph_service("accounts", subgraph())
+            .oneshot(request("Me"))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_rates() {
+        let config = serde_json::from_value(json!({
+            "enabled": true,
+            "rules": [{ "error_rate": 1.5 }]
+        }))
+        .unwrap();
+        assert!(
+            FaultInjection::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+    }
+}
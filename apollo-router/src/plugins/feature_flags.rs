@@ -0,0 +1,570 @@
+//! Gate schema fields behind feature flags evaluated for each request.
+//!
+//! Fields of the supergraph can be dark launched: operations selecting them are rejected unless
+//! their flag is enabled for the request. In `hide` mode, the fields of disabled flags look like
+//! they do not exist: the error is the one of an unknown field, and they are removed from
+//! introspection responses.
+//!
+//! Flags are enabled by their configuration, a request header, a JWT claim or a rollout
+//! percentage. Rollouts are sticky: like experiment buckets, they hash the flag name with an
+//! identifier of the client or user. Coprocessors, Rhai scripts and custom plugins can evaluate
+//! flags with another provider, like an OpenFeature SDK, and set them in the context.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+use std::sync::Arc;
+
+use apollo_compiler::ast;
+use apollo_compiler::executable;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::Schema;
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json_bytes::Value;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::context::keys;
+use crate::error::Error;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::plugins::experiments::identifier;
+use crate::plugins::experiments::sticky_point;
+use crate::register_plugin;
+use crate::services::layers::query_analysis::ParsedDocument;
+use crate::services::supergraph;
+use crate::spec::query::traverse;
+use crate::Context;
+
+/// Context key of the flags evaluated by coprocessors, Rhai scripts or custom plugins, as a map
+/// of flag names to booleans. They take precedence over the configuration
+pub(crate) const FEATURE_FLAGS_KEY: &str = keys::FEATURE_FLAGS.name();
+
+/// Feature flags configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct FeatureFlagsConfig {
+    /// Handling of operations selecting fields of disabled flags
+    mode: FeatureFlagsMode,
+    /// Request header listing the flags enabled for the request, separated by commas
+    header: Option<String>,
+    /// JWT claim listing the flags enabled for the request, as an array or a string of flags
+    /// separated by spaces
+    claim: Option<String>,
+    /// Request header identifying the client or user, so percentage rollouts enable a flag for
+    /// the same clients or users on every request
+    identity_header: Option<String>,
+    /// JWT claim identifying the user, used when the identity header is not set
+    identity_claim: Option<String>,
+    /// Flags, by name
+    flags: HashMap<String, FlagConfig>,
+}
+
+/// Handling of operations selecting fields of disabled flags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+enum FeatureFlagsMode {
+    /// Reject the operation with a `FEATURE_DISABLED` error
+    #[default]
+    Reject,
+    /// Reject the operation as if the fields did not exist, and remove them from introspection
+    Hide,
+}
+
+/// Feature flag configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct FlagConfig {
+    /// Fields gated by the flag, as `Type.field` coordinates
+    fields: Vec<String>,
+    /// Enable the flag for all requests
+    enabled: bool,
+    /// Percentage of the clients or users enabling the flag, between 0 and 100. Requests
+    /// without an identifier enable it randomly
+    percentage: Option<f64>,
+}
+
+#[derive(Debug)]
+struct FeatureFlags {
+    config: Arc<FeatureFlagsConfig>,
+    schema: Arc<Valid<Schema>>,
+    /// Flags gating each field, by field coordinate
+    gates: Arc<HashMap<String, Vec<String>>>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for FeatureFlags {
+    type Config = FeatureFlagsConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let mut gates: HashMap<String, Vec<String>> = HashMap::new();
+        for (name, flag) in &init.config.flags {
+            if let Some(percentage) = flag.percentage {
+                if !(0.0..=100.0).contains(&percentage) {
+                    return Err(format!(
+                        "feature flag {name}: the percentage must be between 0 and 100"
+                    )
+                    .into());
+                }
+            }
+            for coordinate in &flag.fields {
+                let (type_name, field_name) = coordinate.split_once('.').ok_or_else(|| {
+                    format!("feature flag {name}: invalid field coordinate '{coordinate}', expected 'Type.field'")
+                })?;
+                if init
+                    .supergraph_schema
+                    .type_field(type_name, field_name)
+                    .is_err()
+                {
+                    return Err(format!(
+                        "feature flag {name}: the field {coordinate} is not defined in the supergraph"
+                    )
+                    .into());
+                }
+                gates
+                    .entry(coordinate.clone())
+                    .or_default()
+                    .push(name.clone());
+            }
+        }
+
+        Ok(FeatureFlags {
+            config: Arc::new(init.config),
+            schema: init.supergraph_schema,
+            gates: Arc::new(gates),
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        if self.gates.is_empty() {
+            return service;
+        }
+        let config = self.config.clone();
+        let schema = self.schema.clone();
+        let gates = self.gates.clone();
+        let hide = self.config.mode == FeatureFlagsMode::Hide;
+
+        ServiceBuilder::new()
+            .checkpoint(move |req: supergraph::Request| {
+                let Some(doc) = req
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<ParsedDocument>().cloned())
+                else {
+                    return Ok(ControlFlow::Continue(req));
+                };
+                let mut visitor = FieldsVisitor {
+                    schema: &schema,
+                    gates: &gates,
+                    gated_fields: Vec::new(),
+                    introspection: false,
+                };
+                let operation_name = req.supergraph_request.body().operation_name.as_deref();
+                // if this fails, the query is invalid and will fail at the query planning phase
+                let _ = traverse::document(&mut visitor, &doc.executable, operation_name);
+                if visitor.gated_fields.is_empty() && !(hide && visitor.introspection) {
+                    return Ok(ControlFlow::Continue(req));
+                }
+
+                let enabled = EnabledFlags::new(&config, &req);
+                let disabled_fields: Vec<_> = visitor
+                    .gated_fields
+                    .into_iter()
+                    .filter_map(|(type_name, field_name)| {
+                        let coordinate = format!("{type_name}.{field_name}");
+                        let flag = gates
+                            .get(&coordinate)?
+                            .iter()
+                            .find(|flag| !enabled.is_enabled(flag))?;
+                        Some((type_name, field_name, flag.clone()))
+                    })
+                    .collect();
+
+                if !disabled_fields.is_empty() {
+                    let errors = disabled_fields
+                        .into_iter()
+                        .map(|(type_name, field_name, flag)| {
+                            u64_counter!(
+                                "apollo.router.operations.feature_flags.rejected",
+                                "Number of selections of fields behind disabled feature flags",
+                                1,
+                                flag = flag.clone()
+                            );
+                            if hide {
+                                Error::builder()
+                                    .message(format!(
+                                        "Cannot query field \"{field_name}\" on type \"{type_name}\"."
+                                    ))
+                                    .extension_code("GRAPHQL_VALIDATION_FAILED")
+                                    .build()
+                            } else {
+                                Error::builder()
+                                    .message(format!(
+                                        "The field {type_name}.{field_name} is behind the disabled feature flag {flag}"
+                                    ))
+                                    .extension_code("FEATURE_DISABLED")
+                                    .extension("flag", flag)
+                                    .build()
+                            }
+                        })
+                        .collect();
+                    let res = supergraph::Response::infallible_builder()
+                        .errors(errors)
+                        .status_code(StatusCode::BAD_REQUEST)
+                        .context(req.context)
+                        .build();
+                    return Ok(ControlFlow::Break(res));
+                }
+
+                if hide && visitor.introspection {
+                    let hidden = Arc::new(hidden_fields(&gates, &enabled));
+                    let _ = req
+                        .context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(HiddenFields(hidden)));
+                }
+                Ok(ControlFlow::Continue(req))
+            })
+            .map_response(|response: supergraph::Response| {
+                let hidden = response
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<HiddenFields>().cloned());
+                match hidden {
+                    Some(HiddenFields(hidden)) if !hidden.is_empty() => {
+                        response.map_stream(move |mut response| {
+                            if let Some(data) = response.data.as_mut() {
+                                remove_hidden_fields(data, &hidden);
+                            }
+                            response
+                        })
+                    }
+                    _ => response,
+                }
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+/// Fields hidden from the introspection response, by type name
+#[derive(Clone)]
+struct HiddenFields(Arc<HashMap<String, HashSet<String>>>);
+
+/// Flags enabled for a request
+struct EnabledFlags(HashMap<String, bool>);
+
+impl EnabledFlags {
+    /// Evaluates each configured flag once for the request
+    fn new(config: &FeatureFlagsConfig, req: &supergraph::Request) -> Self {
+        let mut request = HashSet::new();
+        if let Some(header) = &config.header {
+            for value in req.supergraph_request.headers().get_all(header) {
+                if let Ok(value) = value.to_str() {
+                    request.extend(
+                        value
+                            .split(',')
+                            .map(str::trim)
+                            .filter(|flag| !flag.is_empty())
+                            .map(str::to_string),
+                    );
+                }
+            }
+        }
+        if let Some(claim) = &config.claim {
+            request.extend(claim_flags(&req.context, claim));
+        }
+        let from_context: HashMap<String, bool> = req
+            .context
+            .get(FEATURE_FLAGS_KEY)
+            .unwrap_or_default()
+            .unwrap_or_default();
+        let id = identifier(
+            req,
+            config.identity_header.as_deref(),
+            config.identity_claim.as_deref(),
+        );
+
+        EnabledFlags(
+            config
+                .flags
+                .iter()
+                .map(|(name, flag)| {
+                    let enabled = from_context.get(name).copied().unwrap_or_else(|| {
+                        request.contains(name)
+                            || flag.enabled
+                            || flag
+                                .percentage
+                                .map(|percentage| rollout(name, id.as_deref()) < percentage)
+                                .unwrap_or(false)
+                    });
+                    (name.clone(), enabled)
+                })
+                .collect(),
+        )
+    }
+
+    fn is_enabled(&self, flag: &str) -> bool {
+        self.0.get(flag).copied().unwrap_or(false)
+    }
+}
+
+/// Position of the request in the rollout of a flag, between 0 and 100. It only depends on the
+/// flag name and the identifier, so raising the percentage keeps the flag enabled for the clients
+/// or users that already had it
+fn rollout(flag: &str, id: Option<&str>) -> f64 {
+    match id {
+        Some(id) => (sticky_point(flag, id) % 10_000) as f64 / 100.0,
+        None => rand::random::<f64>() * 100.0,
+    }
+}
+
+fn claim_flags(context: &Context, claim: &str) -> Vec<String> {
+    let Some(value) = context
+        .get_json_value(APOLLO_AUTHENTICATION_JWT_CLAIMS)
+        .and_then(|claims| claims.get(claim).cloned())
+    else {
+        return Vec::new();
+    };
+    match value {
+        Value::String(flags) => flags
+            .as_str()
+            .split(' ')
+            .filter(|flag| !flag.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Value::Array(flags) => flags
+            .iter()
+            .filter_map(|flag| flag.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Fields of the flags disabled for a request, by type name
+fn hidden_fields(
+    gates: &HashMap<String, Vec<String>>,
+    enabled: &EnabledFlags,
+) -> HashMap<String, HashSet<String>> {
+    let mut hidden: HashMap<String, HashSet<String>> = HashMap::new();
+    for (coordinate, flags) in gates {
+        if flags.iter().any(|flag| !enabled.is_enabled(flag)) {
+            if let Some((type_name, field_name)) = coordinate.split_once('.') {
+                hidden
+                    .entry(type_name.to_string())
+                    .or_default()
+                    .insert(field_name.to_string());
+            }
+        }
+    }
+    hidden
+}
+
+/// Removes the hidden fields from the `fields` of the types of an introspection response
+fn remove_hidden_fields(value: &mut Value, hidden: &HashMap<String, HashSet<String>>) {
+    match value {
+        Value::Object(object) => {
+            let hidden_in_type = object
+                .get("name")
+                .and_then(|name| name.as_str())
+                .and_then(|name| hidden.get(name));
+            if let (Some(hidden_in_type), Some(Value::Array(fields))) =
+                (hidden_in_type, object.get_mut("fields"))
+            {
+                fields.retain(|field| {
+                    !field
+                        .get("name")
+                        .and_then(|name| name.as_str())
+                        .map(|name| hidden_in_type.contains(name))
+                        .unwrap_or(false)
+                });
+            }
+            for (_, value) in object.iter_mut() {
+                remove_hidden_fields(value, hidden);
+            }
+        }
+        Value::Array(values) => values
+            .iter_mut()
+            .for_each(|value| remove_hidden_fields(value, hidden)),
+        _ => {}
+    }
+}
+
+/// Collects the gated fields selected by an operation
+struct FieldsVisitor<'a> {
+    schema: &'a Schema,
+    gates: &'a HashMap<String, Vec<String>>,
+    /// type and field names
+    gated_fields: Vec<(String, String)>,
+    introspection: bool,
+}
+
+impl<'a> traverse::Visitor for FieldsVisitor<'a> {
+    fn schema(&self) -> &Schema {
+        self.schema
+    }
+
+    fn field(
+        &mut self,
+        parent_type: &str,
+        field_def: &ast::FieldDefinition,
+        node: &executable::Field,
+    ) -> Result<(), BoxError> {
+        let field_name = node.name.as_str();
+        if field_name == "__schema" || field_name == "__type" {
+            self.introspection = true;
+        }
+        let coordinate = format!("{parent_type}.{field_name}");
+        if self.gates.contains_key(&coordinate)
+            && !self
+                .gated_fields
+                .iter()
+                .any(|(t, f)| t == parent_type && f == field_name)
+        {
+            self.gated_fields
+                .push((parent_type.to_string(), field_name.to_string()));
+        }
+        traverse::field(self, field_def, node)
+    }
+}
+
+register_plugin!("apollo", "feature_flags", FeatureFlags);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::graphql;
+    use crate::TestHarness;
+
+    async fn query(mode: &str, request: supergraph::Request) -> graphql::Response {
+        let service = TestHarness::builder()
+            .configuration_json(json!({
+                "supergraph": { "introspection": true },
+                "plugins": { "experimental.mock": { "enabled": true } },
+                "feature_flags": {
+                    "mode": mode,
+                    "header": "x-feature-flags",
+                    "flags": {
+                        "new_price": { "fields": ["Product.price"] },
+                        "ga": { "fields": ["Product.upc"], "enabled": true }
+                    }
+                }
+            }))
+            .unwrap()
+            .build_supergraph()
+            .await
+            .unwrap();
+        service
+            .oneshot(request)
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap()
+    }
+
+    fn request(query: &str) -> supergraph::Request {
+        supergraph::Request::fake_builder()
+            .query(query)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn rejects_fields_of_disabled_flags() {
+        let response = query("reject", request("{ topProducts { upc price } }")).await;
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(
+            response.errors[0].extensions.get("code"),
+            Some(&"FEATURE_DISABLED".into())
+        );
+        assert_eq!(
+            response.errors[0].extensions.get("flag"),
+            Some(&"new_price".into())
+        );
+
+        let response = query("reject", request("{ topProducts { upc name } }")).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+
+    #[tokio::test]
+    async fn enables_flags_from_the_request() {
+        let response = query(
+            "reject",
+            supergraph::Request::fake_builder()
+                .query("{ topProducts { price } }")
+                .header("x-feature-flags", "beta, new_price")
+                .build()
+                .unwrap(),
+        )
+        .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+
+        let context = Context::new();
+        context
+            .insert(FEATURE_FLAGS_KEY, json!({ "new_price": true, "ga": false }))
+            .unwrap();
+        let response = query(
+            "reject",
+            supergraph::Request::fake_builder()
+                .query("{ topProducts { upc price } }")
+                .context(context)
+                .build()
+                .unwrap(),
+        )
+        .await;
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(
+            response.errors[0].extensions.get("flag"),
+            Some(&"ga".into())
+        );
+    }
+
+    #[test]
+    fn rolls_out_flags_by_identifier() {
+        for id in 0..100 {
+            let id = id.to_string();
+            assert_eq!(
+                rollout("new_price", Some(&id)),
+                rollout("new_price", Some(&id))
+            );
+        }
+        let enabled = (0..1000)
+            .filter(|id| rollout("new_price", Some(&id.to_string())) < 30.0)
+            .count();
+        assert!((200..400).contains(&enabled), "{enabled}");
+    }
+
+    #[tokio::test]
+    async fn hides_fields_of_disabled_flags() {
+        let response = query("hide", request("{ topProducts { price } }")).await;
+        assert_eq!(response.errors.len(), 1);
+        assert_eq!(
+            response.errors[0].message,
+            "Cannot query field \"price\" on type \"Product\"."
+        );
+
+        let response = query(
+            "hide",
+            request(r#"{ __type(name: "Product") { name fields { name } } }"#),
+        )
+        .await;
+        let fields = response.data.unwrap()["__type"]["fields"].clone();
+        let names: Vec<_> = fields
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| field["name"].as_str().unwrap().to_string())
+            .collect();
+        assert!(names.contains(&"upc".to_string()));
+        assert!(!names.contains(&"price".to_string()));
+    }
+}
@@ -0,0 +1,492 @@
+//! Report the fields of the schema that clients do not use.
+//!
+//! The fields present in responses are recorded as used, and a report listing the fields of the
+//! supergraph schema that were not used for a configurable duration is written to a file at a
+//! fixed interval. The report also keeps the last use of each field, so that the usage collected
+//! before a restart is restored from it.
+//!
+//! The usage is shared by the pipelines writing to the same report, so that a configuration or
+//! schema reload keeps it, including the usage recorded by the previous pipeline while it drains.
+//! The report is written one last time when no pipeline uses it anymore, like when the router
+//! stops.
+
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::Schema;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::Value;
+use tokio::sync::oneshot;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use crate::graphql::ResponseVisitor;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::supergraph;
+
+/// Report files used by the pipelines alive, by path
+static REPORT_FILES: Lazy<Mutex<HashMap<PathBuf, Weak<ReportFile>>>> = Lazy::new(Default::default);
+
+/// Field usage report configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct FieldUsageConfig {
+    /// Set to true to write a report of the fields of the schema that are not used
+    enabled: bool,
+    /// Path of the report file. The usage of the fields is restored from it on startup
+    path: PathBuf,
+    /// Fields that were not used for this long are reported as unused
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    unused_after: Duration,
+    /// Interval between two writes of the report
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    interval: Duration,
+}
+
+impl Default for FieldUsageConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            path: PathBuf::from("field_usage.json"),
+            unused_after: Duration::from_secs(30 * 24 * 60 * 60),
+            interval: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+/// Usage of a field, with times in seconds since the Unix epoch
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+struct FieldUsage {
+    /// Since when the usage of the field is recorded
+    tracked_since: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last_used: Option<u64>,
+}
+
+/// Content of the report file
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct Report {
+    generated_at: u64,
+    /// Fields not used since `generated_at - unused_after`, as `Type.field`
+    #[serde(default)]
+    unused: Vec<String>,
+    #[serde(default)]
+    fields: BTreeMap<String, FieldUsage>,
+}
+
+/// Usage of the fields of the schema, by `Type.field` name
+#[derive(Debug)]
+struct Usage {
+    fields: Mutex<HashMap<String, FieldUsage>>,
+}
+
+impl Usage {
+    /// Tracks the fields of the schema, with the usage of a previous report
+    fn new(schema: &Schema, previous: Option<Report>, now: u64) -> Self {
+        let previous = previous.map(|report| report.fields).unwrap_or_default();
+        let fields = schema_fields(schema)
+            .map(|name| {
+                let usage = previous.get(&name).copied().unwrap_or(FieldUsage {
+                    tracked_since: now,
+                    last_used: None,
+                });
+                (name, usage)
+            })
+            .collect();
+        Self {
+            fields: Mutex::new(fields),
+        }
+    }
+
+    /// Tracks the fields of a new schema, keeping the usage of the fields already tracked
+    fn update_schema(&self, schema: &Schema, now: u64) {
+        let mut fields = self.fields.lock();
+        let updated = schema_fields(schema)
+            .map(|name| {
+                let usage = fields.get(&name).copied().unwrap_or(FieldUsage {
+                    tracked_since: now,
+                    last_used: None,
+                });
+                (name, usage)
+            })
+            .collect();
+        *fields = updated;
+    }
+
+    fn record(&self, used: HashSet<String>, now: u64) {
+        let mut fields = self.fields.lock();
+        for name in used {
+            // fields that are not in the schema, like the fields of introspection types, are ignored
+            if let Some(usage) = fields.get_mut(&name) {
+                usage.last_used = Some(now);
+            }
+        }
+    }
+
+    fn report(&self, now: u64, unused_after: Duration) -> Report {
+        let fields: BTreeMap<_, _> = self
+            .fields
+            .lock()
+            .iter()
+            .map(|(name, usage)| (name.clone(), *usage))
+            .collect();
+        let unused = fields
+            .iter()
+            .filter(|(_, usage)| {
+                usage
+                    .last_used
+                    .unwrap_or(usage.tracked_since)
+                    .saturating_add(unused_after.as_secs())
+                    <= now
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        Report {
+            generated_at: now,
+            unused,
+            fields,
+        }
+    }
+}
+
+/// Usage written to a report file, shared by the pipelines using the same path
+#[derive(Debug)]
+struct ReportFile {
+    path: PathBuf,
+    usage: Usage,
+    /// From the configuration of the latest pipeline
+    unused_after: Mutex<Duration>,
+    /// Serializes the writes of the report
+    write_lock: tokio::sync::Mutex<()>,
+}
+
+impl ReportFile {
+    /// Returns the usage of the pipelines writing to the path, updated to the schema, or restores
+    /// it from the report when there is none
+    async fn open(path: &Path, schema: &Schema, unused_after: Duration) -> Arc<Self> {
+        let existing = REPORT_FILES.lock().get(path).and_then(Weak::upgrade);
+        if let Some(file) = existing {
+            file.usage.update_schema(schema, now());
+            *file.unused_after.lock() = unused_after;
+            return file;
+        }
+
+        let previous = read_report(path).await;
+        let file = Arc::new(ReportFile {
+            path: path.to_path_buf(),
+            usage: Usage::new(schema, previous, now()),
+            unused_after: Mutex::new(unused_after),
+            write_lock: tokio::sync::Mutex::new(()),
+        });
+        let mut files = REPORT_FILES.lock();
+        files.retain(|_, file| file.strong_count() > 0);
+        files.insert(path.to_path_buf(), Arc::downgrade(&file));
+        file
+    }
+
+    /// Writes the usage recorded so far, returning the number of unused fields
+    async fn write(&self) -> Result<usize, BoxError> {
+        // the report is taken once the previous write is done, so it is never older than the
+        // report on disk
+        let _guard = self.write_lock.lock().await;
+        let report = self.usage.report(now(), *self.unused_after.lock());
+        let path = self.path.clone();
+        let unused = report.unused.len();
+        tokio::task::spawn_blocking(move || write_report(&path, &report)).await??;
+        Ok(unused)
+    }
+}
+
+impl Drop for ReportFile {
+    fn drop(&mut self) {
+        let report = self.usage.report(now(), *self.unused_after.get_mut());
+        if let Err(e) = write_report(&self.path, &report) {
+            tracing::error!(
+                "could not write the field usage report {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Fields of the object and interface types of the schema that clients can select
+fn schema_fields(schema: &Schema) -> impl Iterator<Item = String> + '_ {
+    schema
+        .types
+        .iter()
+        .filter(|(_, ty)| !ty.is_built_in() && !ty.directives().has("inaccessible"))
+        .flat_map(|(type_name, ty)| {
+            let fields = match ty {
+                ExtendedType::Object(object) => Some(&object.fields),
+                ExtendedType::Interface(interface) => Some(&interface.fields),
+                _ => None,
+            };
+            fields
+                .into_iter()
+                .flatten()
+                .filter(|(_, field)| !field.directives.has("inaccessible"))
+                .map(move |(field_name, _)| format!("{type_name}.{field_name}"))
+        })
+}
+
+/// Fields present in a response. Fields selected through an interface are recorded on the
+/// interface
+#[derive(Default)]
+struct UsedFields(HashSet<String>);
+
+impl ResponseVisitor for UsedFields {
+    fn visit_field(
+        &mut self,
+        request: &apollo_compiler::ExecutableDocument,
+        ty: &apollo_compiler::executable::NamedType,
+        field: &apollo_compiler::executable::Field,
+        value: &Value,
+    ) {
+        self.0.insert(format!("{ty}.{}", field.name));
+        match value {
+            Value::Array(items) => {
+                for item in items {
+                    self.visit_list_item(request, field.ty().inner_named_type(), field, item);
+                }
+            }
+            Value::Object(children) => {
+                self.visit_selections(request, &field.selection_set, children);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+async fn read_report(path: &Path) -> Option<Report> {
+    match tokio::fs::read(path).await {
+        Ok(content) => match serde_json::from_slice(&content) {
+            Ok(report) => Some(report),
+            Err(e) => {
+                tracing::warn!(
+                    "could not parse the field usage report {}: {e}",
+                    path.display()
+                );
+                None
+            }
+        },
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::warn!(
+                "could not read the field usage report {}: {e}",
+                path.display()
+            );
+            None
+        }
+    }
+}
+
+/// Writes the report to a temporary file first, so that the report is never partially written
+fn write_report(path: &Path, report: &Report) -> Result<(), BoxError> {
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, serde_json::to_vec_pretty(report)?)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+struct FieldUsageReport {
+    /// None when the plugin is disabled
+    file: Option<Arc<ReportFile>>,
+    /// Stops the periodic writes of the report when the plugin is dropped
+    _drop_signal: Option<oneshot::Sender<()>>,
+}
+
+#[async_trait::async_trait]
+impl Plugin for FieldUsageReport {
+    type Config = FieldUsageConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let config = init.config;
+        if !config.enabled {
+            return Ok(FieldUsageReport {
+                file: None,
+                _drop_signal: None,
+            });
+        }
+
+        let file =
+            ReportFile::open(&config.path, &init.supergraph_schema, config.unused_after).await;
+
+        // the task stops when the plugin is dropped, after a configuration or schema reload
+        let (drop_signal, mut drop_receiver) = oneshot::channel::<()>();
+        let weak_file = Arc::downgrade(&file);
+        tokio::task::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval);
+            // the first tick completes immediately
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = &mut drop_receiver => break,
+                }
+                let Some(file) = weak_file.upgrade() else {
+                    break;
+                };
+                match file.write().await {
+                    Ok(unused) => {
+                        tracing::debug!("wrote the field usage report with {unused} unused fields")
+                    }
+                    Err(e) => tracing::error!(
+                        "could not write the field usage report {}: {e}",
+                        config.path.display()
+                    ),
+                }
+            }
+        });
+
+        Ok(FieldUsageReport {
+            file: Some(file),
+            _drop_signal: Some(drop_signal),
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let Some(file) = self.file.clone() else {
+            return service;
+        };
+
+        service
+            .map_response(move |response: supergraph::Response| {
+                let file = file.clone();
+                let document = response.context.unsupported_executable_document();
+                response.map_stream(move |response| {
+                    if let Some(document) = &document {
+                        let mut used = UsedFields::default();
+                        used.visit(document, &response);
+                        file.usage.record(used.0, now());
+                    }
+                    response
+                })
+            })
+            .boxed()
+    }
+}
+
+register_plugin!("apollo", "field_usage", FieldUsageReport);
+
+#[cfg(test)]
+mod test {
+    use apollo_compiler::ExecutableDocument;
+
+    use super::*;
+    use crate::graphql;
+
+    const SCHEMA: &str = r#"
+        type Query {
+            me: User
+            legacy: String
+        }
+        type User {
+            id: ID!
+            name: String
+        }
+    "#;
+
+    #[test]
+    fn reports_unused_fields() {
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        let previous = Report {
+            generated_at: 0,
+            unused: Vec::new(),
+            fields: [(
+                "User.name".to_string(),
+                FieldUsage {
+                    tracked_since: 0,
+                    last_used: Some(100),
+                },
+            )]
+            .into_iter()
+            .collect(),
+        };
+        let usage = Usage::new(&schema, Some(previous), 1_000);
+
+        let document =
+            ExecutableDocument::parse_and_validate(&schema, "{ me { id } }", "query.graphql")
+                .unwrap();
+        let response = graphql::Response::builder()
+            .data(serde_json_bytes::json!({ "me": { "id": "1" } }))
+            .build();
+        let mut used = UsedFields::default();
+        used.visit(&document, &response);
+        usage.record(used.0, 2_000);
+
+        let report = usage.report(2_000, Duration::from_secs(500));
+        assert_eq!(report.fields.len(), 4);
+        assert_eq!(
+            report.fields["Query.me"],
+            FieldUsage {
+                tracked_since: 1_000,
+                last_used: Some(2_000),
+            }
+        );
+        // `Query.legacy` is tracked since 1000, and `User.name` was last used at 100
+        assert_eq!(report.unused, vec!["Query.legacy", "User.name"]);
+
+        let report = usage.report(2_000, Duration::from_secs(1_500));
+        assert_eq!(report.unused, vec!["User.name"]);
+    }
+
+    #[tokio::test]
+    async fn keeps_usage_across_reloads() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("field_usage.json");
+        let schema = Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        let unused_after = Duration::from_secs(60);
+
+        let previous = ReportFile::open(&path, &schema, unused_after).await;
+        previous
+            .usage
+            .record(["Query.legacy".to_string()].into_iter().collect(), now());
+
+        // the new pipeline shares the usage of the previous one, still draining
+        let reloaded = Schema::parse_and_validate(
+            "type Query { legacy: String added: String }",
+            "schema.graphql",
+        )
+        .unwrap();
+        let current = ReportFile::open(&path, &reloaded, unused_after).await;
+        assert!(Arc::ptr_eq(&previous, &current));
+        previous
+            .usage
+            .record(["Query.added".to_string()].into_iter().collect(), now());
+        drop(previous);
+        assert!(!path.exists());
+
+        // the report is written when the last pipeline using it is dropped
+        drop(current);
+        let report = read_report(&path).await.unwrap();
+        assert_eq!(report.fields.len(), 2);
+        assert!(report.fields["Query.legacy"].last_used.is_some());
+        assert!(report.fields["Query.added"].last_used.is_some());
+        assert!(report.unused.is_empty());
+    }
+}
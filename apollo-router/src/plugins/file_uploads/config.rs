@@ -1,9 +1,10 @@
 use bytesize::ByteSize;
 use schemars::JsonSchema;
 use serde::Deserialize;
+use serde::Deserializer;
 
 /// Request limits for a multipart request
-#[derive(Debug, Clone, Copy, Deserialize, JsonSchema)]
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
 pub(crate) struct MultipartRequestLimits {
     /// The maximum amount of files allowed for a single query (default: 4)
@@ -13,6 +14,16 @@ pub(crate) struct MultipartRequestLimits {
     #[serde(deserialize_with = "bytesize::ByteSize::deserialize")]
     #[schemars(with = "String")]
     pub(crate) max_file_size: ByteSize,
+
+    /// The maximum size of all files of a single query, in bytes (default: no limit)
+    #[serde(default, deserialize_with = "deserialize_optional_byte_size")]
+    #[schemars(with = "Option<String>")]
+    pub(crate) max_total_size: Option<ByteSize>,
+
+    /// The content types accepted for files, such as `image/png` or `image/*`. Files without a
+    /// content type are rejected if this list is not empty (default: all content types)
+    #[serde(default)]
+    pub(crate) allowed_content_types: Vec<String>,
 }
 
 impl Default for MultipartRequestLimits {
@@ -20,10 +31,38 @@ impl Default for MultipartRequestLimits {
         Self {
             max_files: 5,
             max_file_size: ByteSize::mb(1),
+            max_total_size: None,
+            allowed_content_types: Vec::new(),
         }
     }
 }
 
+impl MultipartRequestLimits {
+    pub(crate) fn allows_content_type(&self, content_type: Option<&str>) -> bool {
+        if self.allowed_content_types.is_empty() {
+            return true;
+        }
+        let Some(content_type) = content_type else {
+            return false;
+        };
+        self.allowed_content_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(allowed_type) => content_type
+                    .split_once('/')
+                    .is_some_and(|(ty, _)| ty.eq_ignore_ascii_case(allowed_type)),
+                None => allowed.eq_ignore_ascii_case(content_type),
+            })
+    }
+}
+
+fn deserialize_optional_byte_size<'de, D>(deserializer: D) -> Result<Option<ByteSize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    ByteSize::deserialize(deserializer).map(Some)
+}
+
 /// Supported mode for a multipart request
 #[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields, rename_all = "lowercase")]
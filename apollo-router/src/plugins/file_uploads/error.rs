@@ -52,6 +52,15 @@ pub(super) enum FileUploadError {
     #[error("Exceeded the limit of {limit} on {filename} file.")]
     MaxFileSizeLimitExceeded { limit: ByteSize, filename: String },
 
+    #[error("Exceeded the limit of {0} on the total size of files in a single request.")]
+    MaxTotalSizeLimitExceeded(ByteSize),
+
+    #[error("Content type of {filename} file is not allowed: {content_type}.")]
+    ContentTypeNotAllowed {
+        content_type: String,
+        filename: String,
+    },
+
     #[error("{0}")]
     HyperBodyErrorWrapper(#[from] hyper::Error),
 }
@@ -67,6 +76,12 @@ impl From<FileUploadError> for graphql::Error {
                 FileUploadError::MaxFileSizeLimitExceeded { .. } => {
                     "FILE_UPLOADS_LIMITS_MAX_FILE_SIZE_EXCEEDED".to_string()
                 }
+                FileUploadError::MaxTotalSizeLimitExceeded(_) => {
+                    "FILE_UPLOADS_LIMITS_MAX_TOTAL_SIZE_EXCEEDED".to_string()
+                }
+                FileUploadError::ContentTypeNotAllowed { .. } => {
+                    "FILE_UPLOADS_LIMITS_CONTENT_TYPE_NOT_ALLOWED".to_string()
+                }
                 _ => "FILE_UPLOADS_OPERATION_CANNOT_STREAM".to_string(),
             })
             .build()
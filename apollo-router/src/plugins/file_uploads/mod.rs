@@ -66,9 +66,10 @@ impl PluginPrivate for FileUploadsPlugin {
         if !self.enabled {
             return service;
         }
-        let limits = self.limits;
+        let limits = self.limits.clone();
         ServiceBuilder::new()
             .oneshot_checkpoint_async(move |req: router::Request| {
+                let limits = limits.clone();
                 async move {
                     let context = req.context.clone();
                     Ok(match router_layer(req, limits).await {
@@ -38,8 +38,11 @@ struct MultipartRequestState {
     limits: MultipartRequestLimits,
     read_files_counter: usize,
     file_sizes: Vec<usize>,
+    total_file_size: usize,
     max_files_exceeded: bool,
     max_files_size_exceeded: bool,
+    max_total_size_exceeded: bool,
+    content_type_not_allowed: bool,
 }
 
 impl Drop for MultipartRequestState {
@@ -49,7 +52,9 @@ impl Drop for MultipartRequestState {
             "file uploads",
             1,
             "file_uploads.limits.max_file_size.exceeded" = self.max_files_size_exceeded,
-            "file_uploads.limits.max_files.exceeded" = self.max_files_exceeded
+            "file_uploads.limits.max_files.exceeded" = self.max_files_exceeded,
+            "file_uploads.limits.max_total_size.exceeded" = self.max_total_size_exceeded,
+            "file_uploads.limits.content_type.not_allowed" = self.content_type_not_allowed
         );
 
         for file_size in &self.file_sizes {
@@ -64,6 +69,11 @@ impl Drop for MultipartRequestState {
             "number of files per request",
             self.read_files_counter as u64
         );
+        u64_histogram!(
+            "apollo.router.operations.file_uploads.total_size",
+            "total size of uploaded files per request",
+            self.total_file_size as u64
+        );
     }
 }
 
@@ -84,8 +94,11 @@ impl MultipartRequest {
                 limits,
                 read_files_counter: 0,
                 file_sizes: Vec::new(),
+                total_file_size: 0,
                 max_files_exceeded: false,
                 max_files_size_exceeded: false,
+                max_total_size_exceeded: false,
+                content_type_not_allowed: false,
             })),
         }
     }
@@ -187,17 +200,26 @@ where
                 }
                 Poll::Ready(Some(Ok(bytes))) => {
                     self.current_field_bytes += bytes.len();
+                    self.state.total_file_size += bytes.len();
                     let limit = self.state.limits.max_file_size;
                     if self.current_field_bytes > (limit.as_u64() as usize) {
                         self.current_field = None;
                         self.state.max_files_size_exceeded = true;
-                        Poll::Ready(Some(Err(FileUploadError::MaxFileSizeLimitExceeded {
+                        return Poll::Ready(Some(Err(FileUploadError::MaxFileSizeLimitExceeded {
                             limit,
                             filename,
-                        })))
-                    } else {
-                        Poll::Ready(Some(Ok(bytes)))
+                        })));
+                    }
+                    if let Some(limit) = self.state.limits.max_total_size {
+                        if self.state.total_file_size > (limit.as_u64() as usize) {
+                            self.current_field = None;
+                            self.state.max_total_size_exceeded = true;
+                            return Poll::Ready(Some(Err(
+                                FileUploadError::MaxTotalSizeLimitExceeded(limit),
+                            )));
+                        }
                     }
+                    Poll::Ready(Some(Ok(bytes)))
                 }
                 Poll::Ready(Some(Err(e))) => {
                     Poll::Ready(Some(Err(FileUploadError::InvalidMultipartRequest(e))))
@@ -243,6 +265,22 @@ where
 
                         if let Some(name) = field.name() {
                             if self.file_names.remove(name) {
+                                let content_type =
+                                    field.content_type().map(|mime| mime.essence_str());
+                                if !self.state.limits.allows_content_type(content_type) {
+                                    self.state.content_type_not_allowed = true;
+                                    return Poll::Ready(Some(Err(
+                                        FileUploadError::ContentTypeNotAllowed {
+                                            content_type: content_type
+                                                .unwrap_or("unknown")
+                                                .to_owned(),
+                                            filename: format!(
+                                                "'{}'",
+                                                field.file_name().unwrap_or(name)
+                                            ),
+                                        },
+                                    )));
+                                }
                                 let prefix = (self.file_prefix_fn)(field.headers());
                                 self.current_field = Some(field);
                                 return Poll::Ready(Some(Ok(prefix)));
@@ -5,6 +5,7 @@ use std::task::Context;
 use std::task::Poll;
 
 use access_json::JSONQuery;
+use bytesize::ByteSize;
 use http::header::HeaderName;
 use http::header::ACCEPT;
 use http::header::ACCEPT_ENCODING;
@@ -36,6 +37,7 @@ use crate::plugin::serde::deserialize_json_query;
 use crate::plugin::serde::deserialize_option_header_name;
 use crate::plugin::serde::deserialize_option_header_value;
 use crate::plugin::serde::deserialize_regex;
+use crate::plugin::serde::deserialize_vec_header_name;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
 use crate::register_plugin;
@@ -178,6 +180,26 @@ enum Propagate {
         #[serde(deserialize_with = "deserialize_regex")]
         matching: Regex,
     },
+    /// Propagate all headers except a deny list
+    All {
+        /// Propagate all the headers that are not reserved or denied
+        all: PropagateAll,
+    },
+}
+
+#[derive(Clone, JsonSchema, Default, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+/// Propagate all headers
+struct PropagateAll {
+    /// The names of the headers that are not propagated
+    #[schemars(with = "Vec<String>")]
+    #[serde(deserialize_with = "deserialize_vec_header_name")]
+    except: Vec<HeaderName>,
+
+    /// The maximum size of the propagated headers, counting the names and values of each header
+    /// propagated by this rule. Headers that would exceed it are not propagated (default: no limit)
+    #[schemars(with = "Option<String>")]
+    max_size: Option<ByteSize>,
 }
 
 /// Configuration for header propagation
@@ -437,6 +459,42 @@ impl<S> HeadersService<S> {
                         already_propagated.insert(name.as_str());
                     }
                 }
+                Operation::Propagate(Propagate::All { all }) => {
+                    let headers = req.subgraph_request.headers_mut();
+                    let mut size = 0;
+                    for name in req.supergraph_request.headers().keys() {
+                        if self.reserved_headers.contains(name)
+                            || all.except.contains(name)
+                            || already_propagated.contains(name.as_str())
+                        {
+                            continue;
+                        }
+                        let values = req.supergraph_request.headers().get_all(name);
+                        let header_size: usize = values
+                            .iter()
+                            .map(|value| name.as_str().len() + value.len())
+                            .sum();
+                        if let Some(max_size) = all.max_size {
+                            if (size + header_size) as u64 > max_size.as_u64() {
+                                tracing::debug!(
+                                    "header '{name}' is not propagated because it exceeds the max_size of {max_size}"
+                                );
+                                u64_counter!(
+                                    "apollo.router.operations.headers.propagation.truncated",
+                                    "Number of headers that were not propagated to a subgraph because they exceed the max_size",
+                                    1,
+                                    subgraph = req.subgraph_name.clone().unwrap_or_default()
+                                );
+                                continue;
+                            }
+                        }
+                        size += header_size;
+                        for value in values {
+                            headers.append(name, value.clone());
+                        }
+                        already_propagated.insert(name.as_str());
+                    }
+                }
             }
         }
     }
@@ -566,6 +624,19 @@ mod test {
         "#,
         )
         .unwrap();
+
+        serde_yaml::from_str::<Config>(
+            r#"
+        all:
+            request:
+                - propagate:
+                    all:
+                        except:
+                            - "authorization"
+                        max_size: "8KiB"
+        "#,
+        )
+        .unwrap();
     }
 
     #[tokio::test]
@@ -711,6 +782,63 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn test_propagate_all_except() -> Result<(), BoxError> {
+        let mut mock = MockSubgraphService::new();
+        mock.expect_call()
+            .times(1)
+            .withf(|request| {
+                request.assert_headers(vec![
+                    ("aa", "vaa"),
+                    ("ab", "vab"),
+                    ("ac", "vac"),
+                    ("db", "vdb"),
+                    ("db", "vdb"),
+                    ("db", "vdb2"),
+                ])
+            })
+            .returning(example_response);
+
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Propagate(Propagate::All {
+            all: PropagateAll {
+                except: vec!["da".try_into()?],
+                max_size: None,
+            },
+        })]))
+        .layer(mock);
+
+        service.ready().await?.call(example_request()).await?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_propagate_all_max_size() -> Result<(), BoxError> {
+        let mut mock = MockSubgraphService::new();
+        mock.expect_call()
+            .times(1)
+            .withf(|request| {
+                request.assert_headers(vec![
+                    ("aa", "vaa"),
+                    ("ab", "vab"),
+                    ("ac", "vac"),
+                    ("da", "vda"),
+                ])
+            })
+            .returning(example_response);
+
+        // "da: vda" fits in the budget, the three values of "db" don't
+        let mut service = HeadersLayer::new(Arc::new(vec![Operation::Propagate(Propagate::All {
+            all: PropagateAll {
+                except: Vec::new(),
+                max_size: Some(ByteSize::b(10)),
+            },
+        })]))
+        .layer(mock);
+
+        service.ready().await?.call(example_request()).await?;
+        Ok(())
+    }
+
     #[tokio::test]
     async fn test_propagate_exact() -> Result<(), BoxError> {
         let mut mock = MockSubgraphService::new();
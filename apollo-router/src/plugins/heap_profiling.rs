@@ -0,0 +1,301 @@
+//! Expose an endpoint dumping jemalloc heap profiles on demand.
+//!
+//! The router must be built with the `jemalloc-profiling` feature, without the
+//! `mimalloc-allocator` feature, and started with profiling active (`_RJEM_MALLOC_CONF=prof:true`). Each request to the endpoint writes a profile with
+//! `prof.dump` and returns it, so it can be analyzed with `jeprof`.
+
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::task::Context;
+use std::task::Poll;
+
+use futures::future::BoxFuture;
+use http::StatusCode;
+use multimap::MultiMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::Body;
+use crate::ListenAddr;
+
+/// Heap profiling configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct HeapProfilingConfig {
+    /// Set to true to expose the heap profiling endpoint
+    enabled: bool,
+    /// The listen address
+    listen: ListenAddr,
+    /// The path where heap profiles can be downloaded
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of requests to the endpoint
+    token: Option<String>,
+}
+
+impl Default for HeapProfilingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            path: "/debug/heap".to_string(),
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct HeapProfiling {
+    config: HeapProfilingConfig,
+}
+
+#[async_trait::async_trait]
+impl Plugin for HeapProfiling {
+    type Config = HeapProfilingConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        if init.config.enabled {
+            if init.config.token.as_deref().unwrap_or_default().is_empty() {
+                return Err(
+                    "heap_profiling.token is required to expose the heap profiling endpoint".into(),
+                );
+            }
+            if !jemalloc::is_available() {
+                return Err(
+                    "heap profiling requires the router to be built with the `jemalloc-profiling` feature and jemalloc as its allocator"
+                        .into(),
+                );
+            }
+            if !jemalloc::is_active() {
+                tracing::warn!(
+                    "jemalloc profiling is not active, heap profiles will not be available. Start the router with `_RJEM_MALLOC_CONF=prof:true` to activate it"
+                );
+            }
+            tracing::info!(
+                "Heap profiling endpoint exposed at {}{}",
+                init.config.listen,
+                init.config.path
+            );
+        }
+
+        Ok(HeapProfiling {
+            config: init.config,
+        })
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.config.enabled, &self.config.token) {
+            map.insert(
+                self.config.listen.clone(),
+                Endpoint::from_router_service(
+                    self.config.path.clone(),
+                    HeapProfileService {
+                        token: BearerToken::new(token),
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+#[derive(Clone)]
+struct HeapProfileService {
+    token: BearerToken,
+}
+
+impl Service<router::Request> for HeapProfileService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let authorized = self.token.check(req.router_request.headers());
+        Box::pin(async move {
+            let profile = match authorized {
+                Ok(()) => tokio::task::spawn_blocking(dump_heap_profile)
+                    .await?
+                    .map_err(|error| {
+                        tracing::error!("could not dump the heap profile: {error}");
+                        (StatusCode::INTERNAL_SERVER_ERROR, error)
+                    }),
+                Err(error) => Err(error),
+            };
+            let (status, content_type, body) = match profile {
+                Ok(profile) => (StatusCode::OK, "application/octet-stream", profile),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+/// Writes a heap profile to a temporary file and returns its content
+fn dump_heap_profile() -> Result<Vec<u8>, String> {
+    static DUMPS: AtomicUsize = AtomicUsize::new(0);
+
+    let path = std::env::temp_dir().join(format!(
+        "router-heap-{}-{}.prof",
+        std::process::id(),
+        DUMPS.fetch_add(1, Ordering::Relaxed)
+    ));
+    jemalloc::dump(&path)?;
+    let profile = std::fs::read(&path).map_err(|e| e.to_string());
+    let _ = std::fs::remove_file(&path);
+    profile
+}
+
+#[cfg(all(
+    feature = "jemalloc-profiling",
+    not(feature = "mimalloc-allocator"),
+    not(feature = "dhat-heap"),
+    target_os = "linux"
+))]
+mod jemalloc {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub(super) fn is_available() -> bool {
+        true
+    }
+
+    /// Whether the process was started with `prof:true`
+    pub(super) fn is_active() -> bool {
+        let mut active = false;
+        let mut len = std::mem::size_of::<bool>();
+        // SAFETY: `opt.prof` is a bool, read into a value of the right size
+        let result = unsafe {
+            tikv_jemalloc_sys::mallctl(
+                b"opt.prof\0".as_ptr() as *const libc::c_char,
+                &mut active as *mut bool as *mut libc::c_void,
+                &mut len,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        result == 0 && active
+    }
+
+    pub(super) fn dump(path: &Path) -> Result<(), String> {
+        let path = CString::new(path.as_os_str().as_bytes()).map_err(|e| e.to_string())?;
+        let mut path_ptr = path.as_ptr();
+        // SAFETY: `prof.dump` takes a pointer to a nul terminated file name outliving the call
+        let result = unsafe {
+            tikv_jemalloc_sys::mallctl(
+                b"prof.dump\0".as_ptr() as *const libc::c_char,
+                std::ptr::null_mut(),
+                std::ptr::null_mut(),
+                &mut path_ptr as *mut *const libc::c_char as *mut libc::c_void,
+                std::mem::size_of::<*const libc::c_char>(),
+            )
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(format!(
+                "jemalloc returned error code {result}, is profiling active (`_RJEM_MALLOC_CONF=prof:true`)?"
+            ))
+        }
+    }
+}
+
+#[cfg(not(all(
+    feature = "jemalloc-profiling",
+    not(feature = "mimalloc-allocator"),
+    not(feature = "dhat-heap"),
+    target_os = "linux"
+)))]
+mod jemalloc {
+    use std::path::Path;
+
+    pub(super) fn is_available() -> bool {
+        false
+    }
+
+    pub(super) fn is_active() -> bool {
+        false
+    }
+
+    pub(super) fn dump(_path: &Path) -> Result<(), String> {
+        Err("the router was not built with the `jemalloc-profiling` feature and jemalloc as its allocator".to_string())
+    }
+}
+
+register_plugin!("apollo", "heap_profiling", HeapProfiling);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn no_endpoint_when_disabled() {
+        let plugin = HeapProfiling::new(PluginInit::fake_new(
+            HeapProfilingConfig::default(),
+            Default::default(),
+        ))
+        .await
+        .unwrap();
+        assert!(plugin.web_endpoints().is_empty());
+    }
+
+    #[tokio::test]
+    async fn requires_token() {
+        let config = HeapProfilingConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(
+            HeapProfiling::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+
+        let mut service = HeapProfileService {
+            token: BearerToken::new("secret"),
+        };
+        let request = router::Request::fake_builder()
+            .header(http::header::AUTHORIZATION, "Bearer wrong")
+            .build()
+            .unwrap();
+        let response = service.call(request).await.unwrap();
+        assert_eq!(response.response.status(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[cfg(not(feature = "jemalloc-profiling"))]
+    #[tokio::test]
+    async fn requires_profiling_feature() {
+        let config = HeapProfilingConfig {
+            enabled: true,
+            token: Some("secret".to_string()),
+            ..Default::default()
+        };
+        assert!(
+            HeapProfiling::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+    }
+}
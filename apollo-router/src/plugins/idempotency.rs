@@ -0,0 +1,610 @@
+//! Replay the response of mutations retried with the same idempotency key.
+//!
+//! When a client sends a mutation with an idempotency key header, the router keeps its response
+//! for a while. A retry of the same operation with the same key gets that response back instead
+//! of executing the mutation again, so that subgraphs do not apply its side effects twice.
+//!
+//! Keys are scoped to the user or client sending the request, identified by a JWT claim or a
+//! request header, so that a key cannot replay the response of another user. Requests without an
+//! identity share a single scope: an anonymous client can get the response of another anonymous
+//! client that sent the same key, operation and variables. A key reused for a different operation
+//! or different variables is rejected.
+//!
+//! Once a mutation started executing, it runs to completion even if the client disconnects, so
+//! that its retry is answered with its response instead of executing it again.
+
+use std::num::NonZeroUsize;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::ready;
+use futures::stream::once;
+use futures::StreamExt;
+use http::HeaderMap;
+use http::HeaderName;
+use http::StatusCode;
+use lru::LruCache;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json_bytes::Value;
+use sha2::Digest;
+use sha2::Sha256;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+use tracing::Instrument;
+
+use crate::batching::BatchQuery;
+use crate::graphql;
+use crate::json_ext::Object;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::experiments::claim_identifier;
+use crate::query_planner::fetch::QueryHash;
+use crate::query_planner::OperationKind;
+use crate::register_plugin;
+use crate::services::execution;
+use crate::services::layers::query_analysis::ParsedDocument;
+use crate::Context;
+
+/// Idempotency configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct IdempotencyConfig {
+    /// Set to true to replay the response of mutations retried with the same idempotency key
+    enabled: bool,
+    /// Request header containing the idempotency key
+    header: String,
+    /// JWT claim identifying the user, keys are scoped to each user
+    claim: String,
+    /// Request header identifying the client, used when the request does not have the JWT claim
+    identity_header: Option<String>,
+    /// How long the response of a mutation is kept
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    window: Duration,
+    /// Maximum number of responses kept
+    capacity: NonZeroUsize,
+}
+
+impl Default for IdempotencyConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            header: "idempotency-key".to_string(),
+            claim: "sub".to_string(),
+            identity_header: None,
+            window: Duration::from_secs(60),
+            capacity: NonZeroUsize::new(10_000).expect("not zero; qed"),
+        }
+    }
+}
+
+/// Idempotency keys are scoped to the user or client sending the request
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct IdempotencyKey {
+    key: String,
+    identity: Option<String>,
+}
+
+/// A mutation is only replayed for the same operation and variables
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Payload {
+    query_hash: Arc<QueryHash>,
+    operation_name: Option<String>,
+    /// SHA-256 digest of the variables, independent of the order of their fields
+    variables: [u8; 32],
+}
+
+enum Entry {
+    /// The first request with this key is executing
+    InFlight { payload: Payload },
+    Completed {
+        payload: Payload,
+        expires: Instant,
+        response: StoredResponse,
+    },
+}
+
+impl Entry {
+    fn payload(&self) -> &Payload {
+        match self {
+            Entry::InFlight { payload } | Entry::Completed { payload, .. } => payload,
+        }
+    }
+}
+
+/// State of an idempotency key when a request using it is received
+enum Existing {
+    InFlight,
+    Reused,
+    Completed(StoredResponse),
+}
+
+#[derive(Clone)]
+struct StoredResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: graphql::Response,
+}
+
+impl StoredResponse {
+    fn into_response(self, context: Context) -> execution::Response {
+        let mut response = http::Response::new(once(ready(self.body)).boxed());
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+
+        execution::Response::new_from_response(response, context)
+    }
+}
+
+type Responses = Arc<Mutex<LruCache<IdempotencyKey, Entry>>>;
+
+/// Removes the in flight entry of a mutation if its execution fails, so that it can be retried
+struct InFlight {
+    responses: Responses,
+    key: Option<(IdempotencyKey, Payload)>,
+}
+
+impl InFlight {
+    fn complete(mut self, expires: Instant, response: StoredResponse) {
+        if let Some((key, payload)) = self.key.take() {
+            self.responses.lock().expect("lock poisoned").put(
+                key,
+                Entry::Completed {
+                    payload,
+                    expires,
+                    response,
+                },
+            );
+        }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some((key, _)) = self.key.take() {
+            self.responses.lock().expect("lock poisoned").pop(&key);
+        }
+    }
+}
+
+/// Where the identity scoping idempotency keys is read from
+#[derive(Clone)]
+struct Identity {
+    claim: String,
+    header: Option<HeaderName>,
+}
+
+impl Identity {
+    fn of(&self, request: &execution::Request) -> Option<String> {
+        claim_identifier(&request.context, &self.claim).or_else(|| {
+            request
+                .supergraph_request
+                .headers()
+                .get(self.header.as_ref()?)?
+                .to_str()
+                .ok()
+                .filter(|value| !value.is_empty())
+                .map(str::to_string)
+        })
+    }
+}
+
+impl IdempotencyKey {
+    fn new(
+        header: &HeaderName,
+        identity: &Identity,
+        request: &execution::Request,
+    ) -> Option<(Self, Payload)> {
+        let key = request
+            .supergraph_request
+            .headers()
+            .get(header)?
+            .to_str()
+            .ok()?
+            .to_string();
+
+        // batched requests coordinate their subgraph fetches, they must all execute
+        if request
+            .context
+            .extensions()
+            .with_lock(|lock| lock.contains_key::<BatchQuery>())
+        {
+            return None;
+        }
+
+        // only mutations returning a single response are replayed
+        let body = request.supergraph_request.body();
+        let operation_name = body.operation_name.as_deref();
+        let is_mutation = request
+            .query_plan
+            .query
+            .operation(operation_name)
+            .map(|operation| operation.kind() == &OperationKind::Mutation)
+            .unwrap_or(false);
+        if !is_mutation
+            || request
+                .query_plan
+                .is_deferred(operation_name, &body.variables)
+        {
+            return None;
+        }
+
+        let query_hash = request
+            .context
+            .extensions()
+            .with_lock(|lock| lock.get::<ParsedDocument>().map(|doc| doc.hash.clone()))?;
+        let mut variables = Sha256::new();
+        hash_object(&mut variables, &body.variables);
+        Some((
+            Self {
+                key,
+                identity: identity.of(request),
+            },
+            Payload {
+                query_hash,
+                operation_name: body.operation_name.clone(),
+                variables: variables.finalize().into(),
+            },
+        ))
+    }
+}
+
+/// Hashes a JSON object with its fields sorted by name, so that the same variables sent in a
+/// different order have the same digest
+fn hash_object(hasher: &mut Sha256, object: &Object) {
+    let mut fields: Vec<_> = object.iter().collect();
+    fields.sort_by(|(left, _), (right, _)| left.as_str().cmp(right.as_str()));
+    hasher.update((fields.len() as u64).to_be_bytes());
+    for (name, value) in fields {
+        hash_str(hasher, name.as_str());
+        hash_value(hasher, value);
+    }
+}
+
+fn hash_value(hasher: &mut Sha256, value: &Value) {
+    match value {
+        Value::Null => hasher.update([0u8]),
+        Value::Bool(value) => hasher.update([1u8, *value as u8]),
+        Value::Number(value) => {
+            hasher.update([2u8]);
+            hash_str(hasher, &value.to_string());
+        }
+        Value::String(value) => {
+            hasher.update([3u8]);
+            hash_str(hasher, value.as_str());
+        }
+        Value::Array(values) => {
+            hasher.update([4u8]);
+            hasher.update((values.len() as u64).to_be_bytes());
+            for value in values {
+                hash_value(hasher, value);
+            }
+        }
+        Value::Object(object) => {
+            hasher.update([5u8]);
+            hash_object(hasher, object);
+        }
+    }
+}
+
+/// Strings are length prefixed, so that consecutive strings cannot be confused
+fn hash_str(hasher: &mut Sha256, value: &str) {
+    hasher.update((value.len() as u64).to_be_bytes());
+    hasher.update(value);
+}
+
+struct Idempotency {
+    /// None when the plugin is disabled
+    responses: Option<Responses>,
+    header: HeaderName,
+    identity: Identity,
+    window: Duration,
+}
+
+#[async_trait::async_trait]
+impl Plugin for Idempotency {
+    type Config = IdempotencyConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let config = init.config;
+        Ok(Idempotency {
+            responses: config
+                .enabled
+                .then(|| Arc::new(Mutex::new(LruCache::new(config.capacity)))),
+            header: HeaderName::try_from(config.header.as_str())?,
+            identity: Identity {
+                claim: config.claim,
+                header: config
+                    .identity_header
+                    .as_deref()
+                    .map(HeaderName::try_from)
+                    .transpose()?,
+            },
+            window: config.window,
+        })
+    }
+
+    fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        let Some(responses) = self.responses.clone() else {
+            return service;
+        };
+        let header = self.header.clone();
+        let identity = self.identity.clone();
+        let window = self.window;
+
+        let service = ServiceBuilder::new().buffered().service(service);
+        tower::service_fn(move |request: execution::Request| {
+            let key = IdempotencyKey::new(&header, &identity, &request);
+            let responses = responses.clone();
+            let service = service.clone();
+            async move {
+                let Some((key, payload)) = key else {
+                    return service.oneshot(request).await;
+                };
+
+                let existing = {
+                    let mut locked = responses.lock().expect("lock poisoned");
+                    let existing = match locked.get(&key) {
+                        Some(Entry::Completed { expires, .. }) if *expires <= Instant::now() => {
+                            None
+                        }
+                        Some(entry) if entry.payload() != &payload => Some(Existing::Reused),
+                        Some(Entry::InFlight { .. }) => Some(Existing::InFlight),
+                        Some(Entry::Completed { response, .. }) => {
+                            Some(Existing::Completed(response.clone()))
+                        }
+                        None => None,
+                    };
+                    if existing.is_none() {
+                        locked.put(
+                            key.clone(),
+                            Entry::InFlight {
+                                payload: payload.clone(),
+                            },
+                        );
+                    }
+                    existing
+                };
+                match existing {
+                    Some(Existing::Completed(response)) => {
+                        u64_counter!(
+                            "apollo.router.operations.idempotency.replayed",
+                            "Number of mutations answered with the response of a previous request with the same idempotency key",
+                            1
+                        );
+                        return Ok(response.into_response(request.context));
+                    }
+                    Some(Existing::Reused) => {
+                        return execution::Response::error_builder()
+                            .error(
+                                graphql::Error::builder()
+                                    .message("the idempotency key was used for a different request")
+                                    .extension_code("IDEMPOTENCY_KEY_REUSED")
+                                    .build(),
+                            )
+                            .status_code(StatusCode::UNPROCESSABLE_ENTITY)
+                            .context(request.context)
+                            .build();
+                    }
+                    Some(Existing::InFlight) => {
+                        return execution::Response::error_builder()
+                            .error(
+                                graphql::Error::builder()
+                                    .message("a request with the same idempotency key is in progress")
+                                    .extension_code("IDEMPOTENCY_KEY_IN_USE")
+                                    .build(),
+                            )
+                            .status_code(StatusCode::CONFLICT)
+                            .context(request.context)
+                            .build();
+                    }
+                    None => {}
+                }
+
+                let in_flight = InFlight {
+                    responses,
+                    key: Some((key, payload)),
+                };
+                // the mutation is executed in its own task, so that it completes and its response
+                // is stored even if the client disconnects before the end
+                let execution = tokio::spawn(
+                    async move {
+                        let response = service.oneshot(request).await?;
+                        let (parts, mut stream) = response.response.into_parts();
+                        let body = stream.next().await.unwrap_or_default();
+                        let stored = StoredResponse {
+                            status: parts.status,
+                            headers: parts.headers,
+                            body,
+                        };
+                        in_flight.complete(Instant::now() + window, stored.clone());
+                        Ok::<_, BoxError>((stored, response.context))
+                    }
+                    .in_current_span(),
+                );
+                let (stored, context) = execution.await??;
+
+                Ok(stored.into_response(context))
+            }
+        })
+        .boxed()
+    }
+}
+
+register_plugin!("apollo", "idempotency", Idempotency);
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+
+    use serde_json::json;
+    use tower::Service;
+
+    use super::*;
+    use crate::services::subgraph;
+    use crate::services::supergraph;
+    use crate::TestHarness;
+
+    async fn counting_service(
+        config: serde_json::Value,
+        delay: Duration,
+    ) -> (supergraph::BoxCloneService, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let service = TestHarness::builder()
+            .configuration_json(json!({ "idempotency": config }))
+            .unwrap()
+            .subgraph_hook(move |_, _| {
+                let counted = counted.clone();
+                tower::service_fn(move |request: subgraph::Request| {
+                    counted.fetch_add(1, Ordering::SeqCst);
+                    async move {
+                        tokio::time::sleep(delay).await;
+                        Ok(subgraph::Response::fake_builder()
+                            .data(serde_json_bytes::json!({ "createProduct": { "upc": "1" } }))
+                            .context(request.context)
+                            .build())
+                    }
+                })
+                .boxed()
+            })
+            .build_supergraph()
+            .await
+            .unwrap();
+        (service, calls)
+    }
+
+    fn mutation_request(
+        headers: &[(&'static str, &'static str)],
+        upc: &str,
+    ) -> supergraph::Request {
+        let mut request = supergraph::Request::fake_builder()
+            .query(r#"mutation($upc: ID!) { createProduct(upc: $upc) { upc } }"#)
+            .variable("upc", upc)
+            .build()
+            .unwrap();
+        for &(name, value) in headers {
+            request.supergraph_request.headers_mut().insert(
+                HeaderName::from_static(name),
+                http::HeaderValue::from_static(value),
+            );
+        }
+        request
+    }
+
+    async fn mutation(
+        service: &mut supergraph::BoxCloneService,
+        headers: &[(&'static str, &'static str)],
+        upc: &str,
+    ) -> (StatusCode, graphql::Response) {
+        let request = mutation_request(headers, upc);
+        let mut response = service.ready().await.unwrap().call(request).await.unwrap();
+        let status = response.response.status();
+        (status, response.next_response().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn replays_mutations_with_the_same_key() {
+        let (mut service, calls) =
+            counting_service(json!({ "enabled": true, "window": "10s" }), Duration::ZERO).await;
+
+        for key in ["a", "a", "b"] {
+            let (status, response) = mutation(&mut service, &[("idempotency-key", key)], "1").await;
+            assert_eq!(status, StatusCode::OK);
+            assert_eq!(
+                response.data,
+                Some(serde_json_bytes::json!({ "createProduct": { "upc": "1" } }))
+            );
+        }
+        // the second mutation was replayed
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn scopes_keys_to_the_identity() {
+        let (mut service, calls) = counting_service(
+            json!({
+                "enabled": true,
+                "identity_header": "x-user-id"
+            }),
+            Duration::ZERO,
+        )
+        .await;
+
+        for user in ["alice", "bob", "alice"] {
+            let headers = [("idempotency-key", "a"), ("x-user-id", user)];
+            let (status, _) = mutation(&mut service, &headers, "1").await;
+            assert_eq!(status, StatusCode::OK);
+        }
+        // the key of alice does not replay her response to bob
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn rejects_keys_reused_for_another_request() {
+        let (mut service, calls) =
+            counting_service(json!({ "enabled": true }), Duration::ZERO).await;
+
+        let (status, _) = mutation(&mut service, &[("idempotency-key", "a")], "1").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, response) = mutation(&mut service, &[("idempotency-key", "a")], "2").await;
+        assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            response.errors[0].extensions.get("code"),
+            Some(&serde_json_bytes::json!("IDEMPOTENCY_KEY_REUSED"))
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn completes_mutations_of_disconnected_clients() {
+        let (mut service, calls) =
+            counting_service(json!({ "enabled": true }), Duration::from_millis(100)).await;
+
+        // the client disconnects while the mutation is executing
+        let request = mutation_request(&[("idempotency-key", "a")], "1");
+        let response = service.ready().await.unwrap().call(request);
+        let executing = async {
+            while calls.load(Ordering::SeqCst) == 0 {
+                tokio::time::sleep(Duration::from_millis(1)).await;
+            }
+        };
+        tokio::select! {
+            _ = response => panic!("the mutation should still be executing"),
+            _ = executing => {}
+        }
+        tokio::time::sleep(Duration::from_millis(200)).await;
+
+        // its retry gets the response of the first execution
+        let (status, response) = mutation(&mut service, &[("idempotency-key", "a")], "1").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            response.data,
+            Some(serde_json_bytes::json!({ "createProduct": { "upc": "1" } }))
+        );
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn hashes_variables_in_any_order() {
+        let digest = |variables: serde_json_bytes::Value| {
+            let mut hasher = Sha256::new();
+            hash_object(&mut hasher, variables.as_object().unwrap());
+            hasher.finalize()
+        };
+        assert_eq!(
+            digest(serde_json_bytes::json!({ "a": 1, "b": { "c": [true], "d": null } })),
+            digest(serde_json_bytes::json!({ "b": { "d": null, "c": [true] }, "a": 1 }))
+        );
+        assert_ne!(
+            digest(serde_json_bytes::json!({ "a": 1 })),
+            digest(serde_json_bytes::json!({ "a": "1" }))
+        );
+    }
+}
@@ -0,0 +1,348 @@
+//! Expose an endpoint changing the log filter at runtime.
+//!
+//! Directives posted to the endpoint (like `apollo_router::plugins::telemetry=debug`) are added to
+//! the log filter the router was started with, then removed after a delay, so a debug logging
+//! session does not require a restart and cannot be left running by mistake.
+
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+
+use bytes::Buf;
+use futures::future::BoxFuture;
+use http::Method;
+use http::StatusCode;
+use multimap::MultiMap;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tower::BoxError;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::plugins::telemetry::reload::log_filter;
+use crate::plugins::telemetry::reload::reload_log_filter;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::body::RouterBody;
+use crate::services::router::Body;
+use crate::ListenAddr;
+
+/// Incremented on each change of the log filter, so that a revert only applies to the change that
+/// scheduled it
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Log filter endpoint configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct LogFilterConfig {
+    /// Set to true to expose the log filter endpoint
+    enabled: bool,
+    /// The listen address
+    listen: ListenAddr,
+    /// The path of the log filter endpoint
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of requests to the endpoint
+    token: Option<String>,
+    /// How long a log filter change lasts when the request does not specify a duration
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    default_duration: Duration,
+    /// Maximum duration of a log filter change
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    max_duration: Duration,
+}
+
+impl Default for LogFilterConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            path: "/debug/log_filter".to_string(),
+            token: None,
+            default_duration: Duration::from_secs(5 * 60),
+            max_duration: Duration::from_secs(60 * 60),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LogFilter {
+    config: LogFilterConfig,
+}
+
+#[async_trait::async_trait]
+impl Plugin for LogFilter {
+    type Config = LogFilterConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        if init.config.enabled {
+            if init.config.token.as_deref().unwrap_or_default().is_empty() {
+                return Err(
+                    "log_filter.token is required to expose the log filter endpoint".into(),
+                );
+            }
+            if init.config.default_duration > init.config.max_duration {
+                return Err(
+                    "log_filter.default_duration cannot exceed log_filter.max_duration".into(),
+                );
+            }
+            tracing::info!(
+                "Log filter endpoint exposed at {}{}",
+                init.config.listen,
+                init.config.path
+            );
+        }
+
+        Ok(LogFilter {
+            config: init.config,
+        })
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.config.enabled, &self.config.token) {
+            map.insert(
+                self.config.listen.clone(),
+                Endpoint::from_router_service(
+                    self.config.path.clone(),
+                    LogFilterService {
+                        token: BearerToken::new(token),
+                        default_duration: self.config.default_duration,
+                        max_duration: self.config.max_duration,
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+/// Body of a request changing the log filter
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LogFilterChange {
+    /// Directives added to the log filter, in the `RUST_LOG` format
+    directives: String,
+    /// How long the change lasts
+    #[serde(default, with = "humantime_serde")]
+    duration: Option<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+struct LogFilterStatus {
+    /// Directives of the log filter now in use
+    filter: String,
+    /// Seconds before the change is reverted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    revert_in_seconds: Option<u64>,
+}
+
+#[derive(Clone)]
+struct LogFilterService {
+    token: BearerToken,
+    default_duration: Duration,
+    max_duration: Duration,
+}
+
+impl LogFilterService {
+    async fn handle(
+        &self,
+        request: http::Request<Body>,
+    ) -> Result<LogFilterStatus, (StatusCode, String)> {
+        self.token.check(request.headers())?;
+
+        match *request.method() {
+            Method::GET => log_filter()
+                .map(|filter| LogFilterStatus {
+                    filter,
+                    revert_in_seconds: None,
+                })
+                .ok_or_else(|| {
+                    (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        "the log filter is not available".to_string(),
+                    )
+                }),
+            Method::POST => {
+                let bytes = Into::<RouterBody>::into(request.into_body())
+                    .to_bytes()
+                    .await
+                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                let change: LogFilterChange = serde_json::from_reader(bytes.reader())
+                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                let duration = change.duration.unwrap_or(self.default_duration);
+                if duration > self.max_duration {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        format!(
+                            "the duration cannot exceed {}",
+                            humantime::format_duration(self.max_duration)
+                        ),
+                    ));
+                }
+
+                let filter = reload_log_filter(Some(&change.directives))
+                    .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?;
+                let generation = GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+                tracing::info!(
+                    "log filter set to '{filter}' for {}",
+                    humantime::format_duration(duration)
+                );
+                tokio::task::spawn(async move {
+                    tokio::time::sleep(duration).await;
+                    if GENERATION.load(Ordering::SeqCst) == generation {
+                        revert();
+                    }
+                });
+
+                Ok(LogFilterStatus {
+                    filter,
+                    revert_in_seconds: Some(duration.as_secs()),
+                })
+            }
+            Method::DELETE => {
+                GENERATION.fetch_add(1, Ordering::SeqCst);
+                revert()
+                    .map(|filter| LogFilterStatus {
+                        filter,
+                        revert_in_seconds: None,
+                    })
+                    .ok_or_else(|| {
+                        (
+                            StatusCode::INTERNAL_SERVER_ERROR,
+                            "the log filter could not be restored".to_string(),
+                        )
+                    })
+            }
+            _ => Err((
+                StatusCode::METHOD_NOT_ALLOWED,
+                "expected a GET, POST or DELETE request".to_string(),
+            )),
+        }
+    }
+}
+
+/// Restores the log filter the router was started with
+fn revert() -> Option<String> {
+    match reload_log_filter(None) {
+        Ok(filter) => {
+            tracing::info!("log filter restored to '{filter}'");
+            Some(filter)
+        }
+        Err(e) => {
+            tracing::error!("could not restore the log filter: {e}");
+            None
+        }
+    }
+}
+
+impl Service<router::Request> for LogFilterService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let service = self.clone();
+        Box::pin(async move {
+            let (status, content_type, body) = match service.handle(req.router_request).await {
+                Ok(status) => (
+                    StatusCode::OK,
+                    "application/json",
+                    serde_json::to_vec(&status)?,
+                ),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+register_plugin!("apollo", "log_filter", LogFilter);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn service() -> LogFilterService {
+        LogFilterService {
+            token: BearerToken::new("secret"),
+            default_duration: Duration::from_secs(60),
+            max_duration: Duration::from_secs(600),
+        }
+    }
+
+    fn request(method: Method, authorization: &str, body: &str) -> http::Request<Body> {
+        http::Request::builder()
+            .method(method)
+            .uri("http://localhost/debug/log_filter")
+            .header(http::header::AUTHORIZATION, authorization)
+            .body(body.to_string().into())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn requires_token() {
+        let config = LogFilterConfig {
+            enabled: true,
+            ..Default::default()
+        };
+        assert!(
+            LogFilter::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+
+        let (status, _) = service()
+            .handle(request(Method::GET, "Bearer wrong", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_changes() {
+        let (status, _) = service()
+            .handle(request(Method::POST, "Bearer secret", "{}"))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, error) = service()
+            .handle(request(
+                Method::POST,
+                "Bearer secret",
+                r#"{"directives": "apollo_router=debug", "duration": "1h"}"#,
+            ))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(error, "the duration cannot exceed 10m");
+
+        let (status, _) = service()
+            .handle(request(Method::PUT, "Bearer secret", ""))
+            .await
+            .unwrap_err();
+        assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+    }
+}
@@ -0,0 +1,265 @@
+//! Reject new requests when the router's memory usage gets close to its limit.
+//!
+//! The resident memory of the process is sampled at a fixed interval. Once it crosses a
+//! percentage of the limit, new requests get a 503 response until the usage goes back down,
+//! so the router can shed load before it is killed for running out of memory.
+
+use std::ops::ControlFlow;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+
+use http::header::CONTENT_TYPE;
+use http::HeaderValue;
+use http::StatusCode;
+use opentelemetry::metrics::MeterProvider as _;
+use opentelemetry::metrics::ObservableGauge;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::graphql;
+use crate::layers::ServiceBuilderExt;
+use crate::metrics::meter_provider;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::router;
+
+const CGROUP_V2_LIMIT: &str = "/sys/fs/cgroup/memory.max";
+const CGROUP_V1_LIMIT: &str = "/sys/fs/cgroup/memory/memory.limit_in_bytes";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Memory limit configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct MemoryLimitConfig {
+    /// Reject new requests when the memory usage is over the threshold
+    enabled: bool,
+    /// Memory limit of the router process in bytes. Defaults to the limit of the container's cgroup
+    limit: Option<u64>,
+    /// Percentage of the limit above which new requests are rejected
+    threshold_percent: u8,
+    /// Interval between two measures of the memory usage
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String", default = "default_interval")]
+    interval: Duration,
+}
+
+fn default_interval() -> String {
+    humantime::format_duration(DEFAULT_INTERVAL).to_string()
+}
+
+impl Default for MemoryLimitConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            limit: None,
+            threshold_percent: 90,
+            interval: DEFAULT_INTERVAL,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct MemoryLimit {
+    /// usage above which requests are rejected, in bytes. Zero when the plugin is disabled
+    threshold: u64,
+    usage: Arc<AtomicU64>,
+    _gauge: Option<ObservableGauge<u64>>,
+}
+
+fn usage_gauge(usage: Weak<AtomicU64>) -> ObservableGauge<u64> {
+    meter_provider()
+        .meter("apollo/router")
+        .u64_observable_gauge("apollo.router.memory_limit.usage")
+        .with_description("Resident memory of the router process in bytes")
+        .with_callback(move |observer| {
+            if let Some(usage) = usage.upgrade() {
+                observer.observe(usage.load(Ordering::Relaxed), &[]);
+            }
+        })
+        .init()
+}
+
+#[async_trait::async_trait]
+impl Plugin for MemoryLimit {
+    type Config = MemoryLimitConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let usage = Arc::new(AtomicU64::new(0));
+        let config = init.config;
+        if !config.enabled {
+            return Ok(MemoryLimit {
+                threshold: 0,
+                usage,
+                _gauge: None,
+            });
+        }
+
+        if config.threshold_percent == 0 || config.threshold_percent > 100 {
+            return Err("memory_limit.threshold_percent must be between 1 and 100".into());
+        }
+        if resident_memory().is_none() {
+            return Err("memory usage cannot be measured on this platform".into());
+        }
+        let limit = config
+            .limit
+            .or_else(cgroup_memory_limit)
+            .ok_or("no memory limit configured and no cgroup memory limit found")?;
+        let threshold = limit / 100 * config.threshold_percent as u64;
+
+        // the task stops when the plugin is dropped, after a configuration reload
+        let weak_usage = Arc::downgrade(&usage);
+        let gauge = usage_gauge(weak_usage.clone());
+        tokio::task::spawn(async move {
+            let mut interval = tokio::time::interval(config.interval);
+            let mut over_threshold = false;
+            loop {
+                interval.tick().await;
+                let Some(usage) = weak_usage.upgrade() else {
+                    break;
+                };
+                let current = resident_memory().unwrap_or_default();
+                usage.store(current, Ordering::Relaxed);
+
+                // only log when the threshold is crossed, the gauge reports every measure
+                if (current >= threshold) != over_threshold {
+                    over_threshold = current >= threshold;
+                    if over_threshold {
+                        tracing::warn!(
+                            usage = current,
+                            limit = limit,
+                            threshold = threshold,
+                            "memory usage is over the threshold, new requests are rejected"
+                        );
+                    } else {
+                        tracing::info!(
+                            usage = current,
+                            limit = limit,
+                            threshold = threshold,
+                            "memory usage is back under the threshold, new requests are accepted"
+                        );
+                    }
+                }
+            }
+        });
+
+        Ok(MemoryLimit {
+            threshold,
+            usage,
+            _gauge: Some(gauge),
+        })
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        if self.threshold == 0 {
+            return service;
+        }
+
+        let threshold = self.threshold;
+        let usage = self.usage.clone();
+        ServiceBuilder::new()
+            .checkpoint(move |request: router::Request| {
+                if usage.load(Ordering::Relaxed) < threshold {
+                    return Ok(ControlFlow::Continue(request));
+                }
+
+                u64_counter!(
+                    "apollo.router.memory_limit.rejected",
+                    "Number of requests rejected because the memory usage was over the limit",
+                    1
+                );
+                let response = router::Response::infallible_builder()
+                    .error(
+                        graphql::Error::builder()
+                            .message("the router is over its memory limit")
+                            .extension_code("MEMORY_LIMIT_EXCEEDED")
+                            .build(),
+                    )
+                    .status_code(StatusCode::SERVICE_UNAVAILABLE)
+                    .header(CONTENT_TYPE, HeaderValue::from_static("application/json"))
+                    .context(request.context)
+                    .build();
+                Ok(ControlFlow::Break(response))
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+/// Resident memory of the process in bytes
+#[cfg(target_os = "linux")]
+fn resident_memory() -> Option<u64> {
+    let statm = std::fs::read_to_string("/proc/self/statm").ok()?;
+    let resident_pages: u64 = statm.split_whitespace().nth(1)?.parse().ok()?;
+    // SAFETY: sysconf has no preconditions
+    let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    Some(resident_pages * u64::try_from(page_size).ok()?)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_memory() -> Option<u64> {
+    None
+}
+
+fn cgroup_memory_limit() -> Option<u64> {
+    [CGROUP_V2_LIMIT, CGROUP_V1_LIMIT]
+        .iter()
+        .find_map(|path| parse_cgroup_limit(&std::fs::read_to_string(path).ok()?))
+}
+
+fn parse_cgroup_limit(content: &str) -> Option<u64> {
+    let limit: u64 = content.trim().parse().ok()?;
+    // cgroup v1 reports a huge page aligned value when there is no limit
+    (limit < u64::MAX / 2).then_some(limit)
+}
+
+register_plugin!("apollo", "memory_limit", MemoryLimit);
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::plugin::test::MockRouterService;
+
+    #[test]
+    fn cgroup_limits() {
+        assert_eq!(parse_cgroup_limit("1073741824\n"), Some(1073741824));
+        assert_eq!(parse_cgroup_limit("max\n"), None);
+        assert_eq!(parse_cgroup_limit("9223372036854771712\n"), None);
+    }
+
+    #[tokio::test]
+    async fn rejects_requests_over_threshold() {
+        let plugin = MemoryLimit {
+            threshold: 100,
+            usage: Arc::new(AtomicU64::new(50)),
+            _gauge: None,
+        };
+        let mut mock_service = MockRouterService::new();
+        mock_service.expect_call().times(1).returning(|request| {
+            router::Response::fake_builder()
+                .context(request.context)
+                .build()
+        });
+        let service = plugin.router_service(mock_service.boxed());
+        let response = service
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.response.status(), StatusCode::OK);
+
+        plugin.usage.store(150, Ordering::Relaxed);
+        let mock_service = MockRouterService::new();
+        let service = plugin.router_service(mock_service.boxed());
+        let response = service
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
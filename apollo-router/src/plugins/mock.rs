@@ -0,0 +1,440 @@
+//! Serve generated data for all operations, without calling subgraphs.
+//!
+//! Values are derived from a hash of the seed and of their path in the response, so an operation
+//! gets the same data on every request, and a field gets the same value in every operation.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use apollo_compiler::executable::Selection;
+use apollo_compiler::executable::SelectionSet;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::schema::Type;
+use apollo_compiler::validation::Valid;
+use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Schema;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::ByteString;
+use serde_json_bytes::Map;
+use serde_json_bytes::Value;
+use tower::BoxError;
+use tower::ServiceExt;
+
+use crate::graphql;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::execution;
+use crate::services::layers::query_analysis::ParsedDocument;
+
+/// Serve generated data instead of calling subgraphs
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct MockConfig {
+    /// Set to true to serve generated data for all operations. The `--mock` command line flag
+    /// enables it
+    enabled: bool,
+    /// Seed of the generated values. An operation gets the same data for a given seed
+    seed: u64,
+    /// Number of items in generated lists
+    list_length: usize,
+    /// Generators of the values of types, by type name. Other types get generated values
+    types: HashMap<String, TypeGenerator>,
+}
+
+impl Default for MockConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seed: 0,
+            list_length: 2,
+            types: HashMap::new(),
+        }
+    }
+}
+
+/// Generator of the values of a type
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[serde(deny_unknown_fields)]
+struct TypeGenerator {
+    /// Values to pick from. For object types, the values are the response objects
+    values: Vec<serde_json::Value>,
+}
+
+struct Mock {
+    enabled: bool,
+    generator: Arc<Generator>,
+}
+
+register_plugin!("experimental", "mock", Mock);
+
+#[async_trait::async_trait]
+impl Plugin for Mock {
+    type Config = MockConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        if init.config.enabled {
+            tracing::warn!(
+                "mock mode is enabled: the router serves generated data and does not call subgraphs"
+            );
+        }
+        let mut types = HashMap::new();
+        for (name, generator) in init.config.types {
+            if generator.values.is_empty() {
+                return Err(format!("the generator of type '{name}' has no values").into());
+            }
+            types.insert(
+                name,
+                generator
+                    .values
+                    .into_iter()
+                    .map(serde_json_bytes::Value::from)
+                    .collect(),
+            );
+        }
+        Ok(Mock {
+            enabled: init.config.enabled,
+            generator: Arc::new(Generator {
+                schema: init.supergraph_schema.clone(),
+                seed: init.config.seed,
+                list_length: init.config.list_length,
+                types,
+            }),
+        })
+    }
+
+    fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        if !self.enabled {
+            return service;
+        }
+        let generator = self.generator.clone();
+        tower::service_fn(move |request: execution::Request| {
+            let generator = generator.clone();
+            async move {
+                let document = request
+                    .context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<ParsedDocument>().cloned());
+                let operation_name = request.supergraph_request.body().operation_name.as_deref();
+                match document
+                    .ok_or_else(|| "the operation was not parsed".to_string())
+                    .and_then(|document| generator.response(&document.executable, operation_name))
+                {
+                    Ok(data) => execution::Response::builder()
+                        .data(data)
+                        .context(request.context)
+                        .build(),
+                    Err(message) => execution::Response::builder()
+                        .error(
+                            graphql::Error::builder()
+                                .message(message)
+                                .extension_code("MOCK_ERROR")
+                                .build(),
+                        )
+                        .context(request.context)
+                        .build(),
+                }
+            }
+        })
+        .boxed()
+    }
+}
+
+struct Generator {
+    schema: Arc<Valid<Schema>>,
+    seed: u64,
+    list_length: usize,
+    /// Values to pick from, by type name
+    types: HashMap<String, Vec<Value>>,
+}
+
+impl Generator {
+    fn response(
+        &self,
+        document: &Valid<ExecutableDocument>,
+        operation_name: Option<&str>,
+    ) -> Result<Value, String> {
+        let operation = document
+            .get_operation(operation_name)
+            .map_err(|_| "the operation was not found in the document".to_string())?;
+        let mut data = Map::new();
+        self.object(
+            document,
+            &operation.selection_set,
+            operation.selection_set.ty.as_str(),
+            "",
+            &mut data,
+        );
+        Ok(Value::Object(data))
+    }
+
+    /// Fills `object`, of the concrete type `ty`, with the fields of `selection_set`
+    fn object(
+        &self,
+        document: &ExecutableDocument,
+        selection_set: &SelectionSet,
+        ty: &str,
+        path: &str,
+        object: &mut Map<ByteString, Value>,
+    ) {
+        for selection in &selection_set.selections {
+            match selection {
+                Selection::Field(field) => {
+                    let key = field.response_key().as_str();
+                    let path = format!("{path}.{key}");
+                    if field.name == "__typename" {
+                        object.insert(ByteString::from(key), Value::from(ty));
+                        continue;
+                    }
+                    // a field selected several times has its sub-selections merged
+                    match object.get_mut(key) {
+                        Some(existing) => {
+                            self.merge(document, field.ty(), &field.selection_set, &path, existing)
+                        }
+                        None => {
+                            let value = self.value(
+                                document,
+                                field.ty(),
+                                field.name.as_str(),
+                                &field.selection_set,
+                                &path,
+                            );
+                            object.insert(ByteString::from(key), value);
+                        }
+                    }
+                }
+                Selection::FragmentSpread(spread) => {
+                    if let Some(fragment) = document.fragments.get(&spread.fragment_name) {
+                        if self.applies(fragment.type_condition().as_str(), ty) {
+                            self.object(document, &fragment.selection_set, ty, path, object);
+                        }
+                    }
+                }
+                Selection::InlineFragment(inline) => {
+                    let applies = inline
+                        .type_condition
+                        .as_ref()
+                        .map_or(true, |condition| self.applies(condition.as_str(), ty));
+                    if applies {
+                        self.object(document, &inline.selection_set, ty, path, object);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds the fields of `selection_set` to a value generated for another selection of the field
+    fn merge(
+        &self,
+        document: &ExecutableDocument,
+        ty: &Type,
+        selection_set: &SelectionSet,
+        path: &str,
+        value: &mut Value,
+    ) {
+        match (ty, value) {
+            (Type::List(item) | Type::NonNullList(item), Value::Array(items)) => {
+                for (index, value) in items.iter_mut().enumerate() {
+                    self.merge(
+                        document,
+                        item,
+                        selection_set,
+                        &format!("{path}.{index}"),
+                        value,
+                    );
+                }
+            }
+            (_, Value::Object(object)) => {
+                let concrete = object
+                    .get("__typename")
+                    .and_then(|typename| typename.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.concrete_type(ty.inner_named_type(), path));
+                self.object(document, selection_set, &concrete, path, object);
+            }
+            _ => {}
+        }
+    }
+
+    fn value(
+        &self,
+        document: &ExecutableDocument,
+        ty: &Type,
+        field_name: &str,
+        selection_set: &SelectionSet,
+        path: &str,
+    ) -> Value {
+        let name = match ty {
+            Type::Named(name) | Type::NonNullNamed(name) => name.as_str(),
+            Type::List(item) | Type::NonNullList(item) => {
+                return Value::Array(
+                    (0..self.list_length)
+                        .map(|index| {
+                            self.value(
+                                document,
+                                item,
+                                field_name,
+                                selection_set,
+                                &format!("{path}.{index}"),
+                            )
+                        })
+                        .collect(),
+                )
+            }
+        };
+        let hash = self.hash(path);
+
+        if let Some(values) = self.types.get(name) {
+            return values[(hash % values.len() as u64) as usize].clone();
+        }
+        match self.schema.types.get(name) {
+            Some(ExtendedType::Object(_) | ExtendedType::Interface(_) | ExtendedType::Union(_)) => {
+                let concrete = self.concrete_type(name, path);
+                let mut object = Map::new();
+                self.object(document, selection_set, &concrete, path, &mut object);
+                Value::Object(object)
+            }
+            Some(ExtendedType::Enum(enum_type)) if !enum_type.values.is_empty() => {
+                let index = (hash % enum_type.values.len() as u64) as usize;
+                enum_type
+                    .values
+                    .keys()
+                    .nth(index)
+                    .map(|value| Value::from(value.as_str()))
+                    .unwrap_or_default()
+            }
+            _ => match name {
+                "Int" => Value::from((hash % 100) as i64),
+                "Float" => Value::from((hash % 10_000) as f64 / 100.0),
+                "Boolean" => Value::from(hash % 2 == 0),
+                "ID" => Value::from(format!("{:x}", hash % 0x1_0000_0000)),
+                "String" => Value::from(format!("{field_name} {}", hash % 1000)),
+                // custom scalars without a generator
+                _ => Value::from(format!("{name} {}", hash % 1000)),
+            },
+        }
+    }
+
+    /// Picks the object type of a value of type `name`
+    fn concrete_type(&self, name: &str, path: &str) -> String {
+        let possible_types: Vec<&str> = match self.schema.types.get(name) {
+            Some(ExtendedType::Union(union_type)) => union_type
+                .members
+                .iter()
+                .map(|member| member.as_str())
+                .collect(),
+            Some(ExtendedType::Interface(_)) => self
+                .schema
+                .types
+                .iter()
+                .filter_map(|(object_name, ty)| match ty {
+                    ExtendedType::Object(object)
+                        if object
+                            .implements_interfaces
+                            .iter()
+                            .any(|interface| interface.as_str() == name) =>
+                    {
+                        Some(object_name.as_str())
+                    }
+                    _ => None,
+                })
+                .collect(),
+            _ => return name.to_string(),
+        };
+        if possible_types.is_empty() {
+            return name.to_string();
+        }
+        let index = (self.hash(path) % possible_types.len() as u64) as usize;
+        possible_types[index].to_string()
+    }
+
+    /// Whether a fragment on `condition` applies to a value of the object type `ty`
+    fn applies(&self, condition: &str, ty: &str) -> bool {
+        condition == ty
+            || match self.schema.types.get(condition) {
+                Some(ExtendedType::Union(union_type)) => union_type
+                    .members
+                    .iter()
+                    .any(|member| member.as_str() == ty),
+                Some(ExtendedType::Interface(_)) => {
+                    matches!(self.schema.types.get(ty), Some(ExtendedType::Object(object))
+                        if object.implements_interfaces.iter().any(|interface| interface.as_str() == condition))
+                }
+                _ => false,
+            }
+    }
+
+    /// FNV-1a hash of the seed and path, stable across router versions
+    fn hash(&self, path: &str) -> u64 {
+        self.seed
+            .to_le_bytes()
+            .iter()
+            .chain(path.as_bytes())
+            .fold(0xcbf29ce484222325, |hash, byte| {
+                (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use crate::graphql;
+    use crate::services::supergraph;
+    use crate::TestHarness;
+
+    async fn query(configuration: serde_json::Value, query: &str) -> graphql::Response {
+        let service = TestHarness::builder()
+            .configuration_json(configuration)
+            .unwrap()
+            .build_supergraph()
+            .await
+            .unwrap();
+        let request = supergraph::Request::fake_builder()
+            .query(query)
+            .build()
+            .unwrap();
+        service
+            .oneshot(request)
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn generates_data() {
+        let configuration = json!({
+            "plugins": {
+                "experimental.mock": {
+                    "enabled": true,
+                    "seed": 42,
+                    "list_length": 3,
+                    "types": { "Boolean": { "values": [true] } }
+                }
+            }
+        });
+        let operation = "{ topProducts { upc name price inStock ...on Product { name reviews { id } } } me { __typename id } }";
+
+        let response = query(configuration.clone(), operation).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        let data = response.data.clone().unwrap();
+        let products = data["topProducts"].as_array().unwrap();
+        assert_eq!(products.len(), 3);
+        for product in products {
+            assert!(product["upc"].is_string());
+            assert!(product["price"].is_i64());
+            assert_eq!(product["inStock"], true.into());
+            assert_eq!(product["reviews"].as_array().unwrap().len(), 3);
+        }
+        assert_eq!(data["me"]["__typename"], "User".into());
+
+        // the data only depends on the seed and the operation
+        assert_eq!(query(configuration, operation).await.data, response.data);
+    }
+}
@@ -20,21 +20,39 @@ macro_rules! schemar_fn {
     };
 }
 
+mod admin_endpoint;
+mod admission_control;
 pub(crate) mod authentication;
 pub(crate) mod authorization;
+mod background_refresh;
 pub(crate) mod cache;
 mod coprocessor;
+mod cpu_profiling;
 pub(crate) mod csrf;
 mod demand_control;
+mod diagnostics;
+mod experiments;
+pub(crate) mod expose_null_propagation;
 mod expose_query_plan;
+mod fault_injection;
+mod feature_flags;
+mod field_usage;
 pub(crate) mod file_uploads;
 mod forbid_mutations;
 mod headers;
+mod heap_profiling;
+mod idempotency;
 mod include_subgraph_errors;
+mod log_filter;
+mod memory_limit;
+mod mock;
 pub(crate) mod override_url;
+mod pipeline_generations;
 pub(crate) mod progressive_override;
 mod record_replay;
+pub(crate) mod request_toggles;
 pub(crate) mod rhai;
+mod subgraph_failures;
 pub(crate) mod subscription;
 pub(crate) mod telemetry;
 #[cfg(test)]
@@ -1,12 +1,20 @@
 //! Allows subgraph URLs to be overridden.
 
 use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use apollo_compiler::validation::Valid;
+use apollo_compiler::Schema;
+use arc_swap::ArcSwap;
+use futures::StreamExt;
 use http::Uri;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use serde::Serialize;
+use tokio::task::JoinHandle;
 use tower::BoxError;
 use tower::ServiceExt;
 
@@ -16,9 +24,10 @@ use crate::register_plugin;
 use crate::services::subgraph;
 use crate::services::SubgraphRequest;
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 struct OverrideSubgraphUrl {
-    urls: HashMap<String, Uri>,
+    urls: Arc<ArcSwap<HashMap<String, Uri>>>,
+    watcher: Option<JoinHandle<()>>,
 }
 
 /// Subgraph URL mappings
@@ -26,6 +35,12 @@ struct OverrideSubgraphUrl {
 #[serde(deny_unknown_fields)]
 #[serde(untagged)]
 enum Conf {
+    /// Subgraph URL mappings loaded from a file
+    File {
+        /// Path of a YAML or JSON file mapping subgraph names to URLs. Every subgraph must be
+        /// declared in the supergraph schema. The file is reloaded when it changes
+        experimental_file: PathBuf,
+    },
     /// Subgraph URL mappings
     Mapping(HashMap<String, String>),
 }
@@ -35,28 +50,42 @@ impl Plugin for OverrideSubgraphUrl {
     type Config = Conf;
 
     async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
-        let Conf::Mapping(urls) = init.config;
-        Ok(OverrideSubgraphUrl {
-            urls: urls
-                .into_iter()
-                .map(|(k, url)| {
-                    #[cfg(unix)]
-                    // there is no standard for unix socket URLs apparently
-                    if let Some(path) = url.strip_prefix("unix://") {
-                        // there is no specified format for unix socket URLs (cf https://github.com/whatwg/url/issues/577)
-                        // so a unix:// URL will not be parsed by http::Uri
-                        // To fix that, hyperlocal came up with its own Uri type that can be converted to http::Uri.
-                        // It hides the socket path in a hex encoded authority that the unix socket connector will
-                        // know how to decode
-                        Ok((k, hyperlocal::Uri::new(path, "/").into()))
-                    } else {
-                        Uri::from_str(&url).map(|url| (k, url))
+        match init.config {
+            Conf::Mapping(urls) => Ok(OverrideSubgraphUrl {
+                urls: Arc::new(ArcSwap::from_pointee(parse_urls(urls)?)),
+                watcher: None,
+            }),
+            Conf::File { experimental_file } => {
+                let subgraphs = subgraph_names(&init.supergraph_schema);
+                let urls = Arc::new(ArcSwap::from_pointee(load_urls(
+                    &experimental_file,
+                    &subgraphs,
+                )?));
+                let watched_urls = urls.clone();
+                let watcher = tokio::spawn(async move {
+                    // the first event only signals that the watch started
+                    let mut changes = crate::files::watch(&experimental_file).skip(1);
+                    while changes.next().await.is_some() {
+                        match load_urls(&experimental_file, &subgraphs) {
+                            Ok(urls) => {
+                                tracing::info!(
+                                    "reloaded subgraph URL overrides from {}",
+                                    experimental_file.display()
+                                );
+                                watched_urls.store(Arc::new(urls));
+                            }
+                            Err(error) => tracing::error!(
+                                "could not reload subgraph URL overrides, keeping the previous ones: {error}"
+                            ),
+                        }
                     }
-                    #[cfg(not(unix))]
-                    Uri::from_str(&url).map(|url| (k, url))
+                });
+                Ok(OverrideSubgraphUrl {
+                    urls,
+                    watcher: Some(watcher),
                 })
-                .collect::<Result<_, _>>()?,
-        })
+            }
+        }
     }
 
     fn subgraph_service(
@@ -64,11 +93,12 @@ impl Plugin for OverrideSubgraphUrl {
         subgraph_name: &str,
         service: subgraph::BoxService,
     ) -> subgraph::BoxService {
-        let new_url = self.urls.get(subgraph_name).cloned();
+        let urls = self.urls.clone();
+        let subgraph_name = subgraph_name.to_string();
         service
             .map_request(move |mut req: SubgraphRequest| {
-                if let Some(new_url) = new_url.clone() {
-                    *req.subgraph_request.uri_mut() = new_url;
+                if let Some(new_url) = urls.load().get(&subgraph_name) {
+                    *req.subgraph_request.uri_mut() = new_url.clone();
                 }
 
                 req
@@ -77,6 +107,75 @@ impl Plugin for OverrideSubgraphUrl {
     }
 }
 
+impl Drop for OverrideSubgraphUrl {
+    fn drop(&mut self) {
+        if let Some(watcher) = self.watcher.take() {
+            watcher.abort();
+        }
+    }
+}
+
+fn parse_urls(urls: HashMap<String, String>) -> Result<HashMap<String, Uri>, BoxError> {
+    Ok(urls
+        .into_iter()
+        .map(|(k, url)| {
+            #[cfg(unix)]
+            // there is no standard for unix socket URLs apparently
+            if let Some(path) = url.strip_prefix("unix://") {
+                // there is no specified format for unix socket URLs (cf https://github.com/whatwg/url/issues/577)
+                // so a unix:// URL will not be parsed by http::Uri
+                // To fix that, hyperlocal came up with its own Uri type that can be converted to http::Uri.
+                // It hides the socket path in a hex encoded authority that the unix socket connector will
+                // know how to decode
+                Ok((k, hyperlocal::Uri::new(path, "/").into()))
+            } else {
+                Uri::from_str(&url).map(|url| (k, url))
+            }
+            #[cfg(not(unix))]
+            Uri::from_str(&url).map(|url| (k, url))
+        })
+        .collect::<Result<_, _>>()?)
+}
+
+/// Reads a mapping file, rejecting subgraphs that are not in the supergraph schema
+fn load_urls(path: &Path, subgraphs: &[String]) -> Result<HashMap<String, Uri>, BoxError> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read {}: {e}", path.display()))?;
+    let urls: HashMap<String, String> = serde_yaml::from_str(&content)
+        .map_err(|e| format!("could not parse {}: {e}", path.display()))?;
+
+    let mut unknown: Vec<&str> = urls
+        .keys()
+        .filter(|name| !subgraphs.contains(name))
+        .map(|name| name.as_str())
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort_unstable();
+        return Err(format!(
+            "{} overrides the URLs of subgraphs that are not in the supergraph: {}",
+            path.display(),
+            unknown.join(", ")
+        )
+        .into());
+    }
+
+    parse_urls(urls)
+}
+
+/// Names of the subgraphs declared in the `join__Graph` enum of the supergraph
+fn subgraph_names(schema: &Valid<Schema>) -> Vec<String> {
+    schema
+        .get_enum("join__Graph")
+        .into_iter()
+        .flat_map(|join_enum| join_enum.values.values())
+        .filter_map(|value| {
+            let join_directive = value.directives.get("join__graph")?;
+            let name = join_directive.argument_by_name("name")?.as_str()?;
+            Some(name.to_string())
+        })
+        .collect()
+}
+
 register_plugin!("apollo", "override_subgraph_url", OverrideSubgraphUrl);
 
 #[cfg(test)]
@@ -89,6 +188,7 @@ mod tests {
     use tower::Service;
     use tower::ServiceExt;
 
+    use super::*;
     use crate::plugin::test::MockSubgraphService;
     use crate::plugin::DynPlugin;
     use crate::services::SubgraphRequest;
@@ -138,4 +238,69 @@ mod tests {
             .await
             .unwrap();
     }
+
+    fn supergraph() -> Arc<Valid<Schema>> {
+        Arc::new(
+            Schema::parse_and_validate(
+                include_str!("../testdata/minimal_supergraph.graphql"),
+                "supergraph.graphql",
+            )
+            .unwrap(),
+        )
+    }
+
+    async fn file_plugin(path: PathBuf) -> Result<OverrideSubgraphUrl, BoxError> {
+        OverrideSubgraphUrl::new(
+            PluginInit::fake_builder()
+                .config(Conf::File {
+                    experimental_file: path,
+                })
+                .supergraph_schema(supergraph())
+                .build(),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn mapping_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("urls.yaml");
+        std::fs::write(&path, "accounts: http://localhost:8001\n").unwrap();
+
+        let plugin = file_plugin(path.clone()).await.unwrap();
+        assert_eq!(
+            plugin.urls.load().get("accounts"),
+            Some(&Uri::from_str("http://localhost:8001").unwrap())
+        );
+
+        std::fs::write(&path, "accounts: http://localhost:8002\n").unwrap();
+        for _ in 0..50 {
+            if plugin.urls.load().get("accounts")
+                == Some(&Uri::from_str("http://localhost:8002").unwrap())
+            {
+                return;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        }
+        panic!("the mapping file was not reloaded");
+    }
+
+    #[tokio::test]
+    async fn mapping_file_with_unknown_subgraphs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("urls.yaml");
+        std::fs::write(
+            &path,
+            "accounts: http://localhost:8001\nreviews: http://localhost:8002\nproducts: http://localhost:8003\n",
+        )
+        .unwrap();
+
+        let error = file_plugin(path).await.unwrap_err().to_string();
+        assert!(
+            error.ends_with(
+                "overrides the URLs of subgraphs that are not in the supergraph: products, reviews"
+            ),
+            "{error}"
+        );
+    }
 }
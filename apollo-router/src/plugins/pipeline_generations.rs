@@ -0,0 +1,524 @@
+//! Track the generations of the router pipeline across configuration and schema reloads.
+//!
+//! Each reload creates a new pipeline, and requests in flight keep using the pipeline they
+//! started on. Long-lived requests like subscriptions can keep a replaced pipeline alive
+//! indefinitely, so this reports the pipelines still draining with their request counts, and can
+//! close their remaining requests after a hard drain timeout.
+
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use http::Method;
+use http::StatusCode;
+use hyper::body::HttpBody;
+use multimap::MultiMap;
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio_util::sync::CancellationToken;
+use tower::BoxError;
+use tower::ServiceExt;
+use tower_service::Service;
+
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::admin_endpoint::default_listen;
+use crate::plugins::admin_endpoint::BearerToken;
+use crate::register_plugin;
+use crate::router_factory::Endpoint;
+use crate::services::router;
+use crate::services::router::Body;
+use crate::ListenAddr;
+
+/// Generations of the pipeline that were created and are not dropped yet, in creation order
+static GENERATIONS: Lazy<Mutex<Vec<Weak<Generation>>>> = Lazy::new(Default::default);
+static NEXT_GENERATION: AtomicU64 = AtomicU64::new(1);
+
+/// Pipeline generations configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct PipelineGenerationsConfig {
+    /// Maximum time a pipeline replaced by a reload keeps serving its requests. Its remaining
+    /// streamed responses, like subscriptions, are then closed. Not limited by default
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "Option<String>")]
+    hard_drain_timeout: Option<Duration>,
+    /// Endpoint listing the pipeline generations
+    endpoint: GenerationsEndpoint,
+}
+
+/// Pipeline generations endpoint configuration
+#[derive(Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct GenerationsEndpoint {
+    /// Set to true to expose the pipeline generations endpoint
+    enabled: bool,
+    /// The listen address
+    listen: ListenAddr,
+    /// The path of the pipeline generations endpoint
+    path: String,
+    /// Token expected in the `Authorization: Bearer <token>` header of requests to the endpoint
+    token: Option<String>,
+}
+
+impl Default for GenerationsEndpoint {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            path: "/debug/pipelines".to_string(),
+            token: None,
+        }
+    }
+}
+
+/// A pipeline created by a reload, alive until its last request finishes
+#[derive(Debug)]
+struct Generation {
+    id: u64,
+    created_at: Instant,
+    /// requests in flight, including streamed responses
+    requests: AtomicI64,
+    /// set once the generation served its first request
+    serving: AtomicBool,
+    /// set when a newer generation started serving requests
+    retired_at: Mutex<Option<Instant>>,
+    hard_drain_timeout: Option<Duration>,
+    /// cancelled when the hard drain timeout expires
+    hard_drain: CancellationToken,
+}
+
+impl Generation {
+    fn register(hard_drain_timeout: Option<Duration>) -> Arc<Self> {
+        let generation = Arc::new(Generation {
+            id: NEXT_GENERATION.fetch_add(1, Ordering::Relaxed),
+            created_at: Instant::now(),
+            requests: AtomicI64::new(0),
+            serving: AtomicBool::new(false),
+            retired_at: Mutex::new(None),
+            hard_drain_timeout,
+            hard_drain: CancellationToken::new(),
+        });
+        let mut generations = GENERATIONS.lock();
+        generations.retain(|generation| generation.strong_count() > 0);
+        generations.push(Arc::downgrade(&generation));
+        i64_up_down_counter!(
+            "apollo.router.pipeline.generations",
+            "Number of pipeline generations alive",
+            1,
+            state = "current"
+        );
+        generation
+    }
+
+    /// Generations alive, in creation order
+    fn all() -> Vec<Arc<Generation>> {
+        GENERATIONS
+            .lock()
+            .iter()
+            .filter_map(Weak::upgrade)
+            .collect()
+    }
+
+    fn state(&self) -> &'static str {
+        if self.retired_at.lock().is_some() {
+            "draining"
+        } else if self.serving.load(Ordering::Relaxed) {
+            "current"
+        } else {
+            "starting"
+        }
+    }
+
+    /// Marks the generation as serving, which retires the previous ones
+    fn start_serving(&self) {
+        if self.serving.swap(true, Ordering::Relaxed) {
+            return;
+        }
+        for generation in Generation::all() {
+            if generation.id < self.id {
+                generation.retire();
+            }
+        }
+    }
+
+    /// Adds to the requests in flight, counted by state of the generation
+    fn add_requests(&self, delta: i64) {
+        // the lock keeps the state from changing until the request is counted
+        let retired_at = self.retired_at.lock();
+        self.requests.fetch_add(delta, Ordering::Relaxed);
+        let state = if retired_at.is_some() {
+            "draining"
+        } else {
+            "current"
+        };
+        i64_up_down_counter!(
+            "apollo.router.pipeline.requests",
+            "Number of requests in flight by pipeline generation state",
+            delta,
+            state = state
+        );
+    }
+
+    fn retire(self: &Arc<Self>) {
+        {
+            let mut retired_at = self.retired_at.lock();
+            if retired_at.is_some() {
+                return;
+            }
+            *retired_at = Some(Instant::now());
+            // the requests in flight are draining too
+            let requests = self.requests.load(Ordering::Relaxed);
+            i64_up_down_counter!(
+                "apollo.router.pipeline.requests",
+                "Number of requests in flight by pipeline generation state",
+                -requests,
+                state = "current"
+            );
+            i64_up_down_counter!(
+                "apollo.router.pipeline.requests",
+                "Number of requests in flight by pipeline generation state",
+                requests,
+                state = "draining"
+            );
+        }
+        i64_up_down_counter!(
+            "apollo.router.pipeline.generations",
+            "Number of pipeline generations alive",
+            -1,
+            state = "current"
+        );
+        i64_up_down_counter!(
+            "apollo.router.pipeline.generations",
+            "Number of pipeline generations alive",
+            1,
+            state = "draining"
+        );
+        tracing::info!(
+            generation = self.id,
+            requests = self.requests.load(Ordering::Relaxed),
+            "pipeline generation replaced, draining its requests"
+        );
+
+        if let Some(timeout) = self.hard_drain_timeout {
+            let generation = Arc::downgrade(self);
+            tokio::task::spawn(async move {
+                tokio::time::sleep(timeout).await;
+                if let Some(generation) = generation.upgrade() {
+                    tracing::warn!(
+                        generation = generation.id,
+                        requests = generation.requests.load(Ordering::Relaxed),
+                        "hard drain timeout of the pipeline generation expired, closing its requests"
+                    );
+                    generation.hard_drain.cancel();
+                }
+            });
+        }
+    }
+}
+
+impl Drop for Generation {
+    fn drop(&mut self) {
+        let state = if self.retired_at.lock().is_some() {
+            tracing::info!(generation = self.id, "pipeline generation drained");
+            "draining"
+        } else {
+            "current"
+        };
+        i64_up_down_counter!(
+            "apollo.router.pipeline.generations",
+            "Number of pipeline generations alive",
+            -1,
+            state = state
+        );
+    }
+}
+
+/// Counts a request in its generation until it is dropped
+struct RequestGuard(Arc<Generation>);
+
+impl RequestGuard {
+    fn new(generation: Arc<Generation>) -> Self {
+        generation.add_requests(1);
+        RequestGuard(generation)
+    }
+
+    /// Keeps the request counted until the end of a streamed response, which is closed when the
+    /// hard drain timeout expires
+    fn wrap(self, body: Body) -> Body {
+        let hard_drain = self.0.hard_drain.clone();
+        Body::wrap_stream(
+            body.take_until(hard_drain.cancelled_owned())
+                .map(move |chunk| {
+                    let _guard = &self;
+                    chunk
+                }),
+        )
+    }
+}
+
+impl Drop for RequestGuard {
+    fn drop(&mut self) {
+        self.0.add_requests(-1);
+    }
+}
+
+#[derive(Debug)]
+struct PipelineGenerations {
+    generation: Arc<Generation>,
+    endpoint: GenerationsEndpoint,
+}
+
+#[async_trait::async_trait]
+impl Plugin for PipelineGenerations {
+    type Config = PipelineGenerationsConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        let endpoint = init.config.endpoint;
+        if endpoint.enabled && endpoint.token.as_deref().unwrap_or_default().is_empty() {
+            return Err(
+                "pipeline_generations.endpoint.token is required to expose the pipeline generations endpoint"
+                    .into(),
+            );
+        }
+
+        Ok(PipelineGenerations {
+            generation: Generation::register(init.config.hard_drain_timeout),
+            endpoint,
+        })
+    }
+
+    fn router_service(&self, service: router::BoxService) -> router::BoxService {
+        let generation = self.generation.clone();
+        service
+            .map_future(move |future| {
+                generation.start_serving();
+                let guard = RequestGuard::new(generation.clone());
+                async move {
+                    let mut response = future.await?;
+                    // responses of a known size are sent right away, streams are counted
+                    // until their end
+                    if response.response.body().size_hint().exact().is_none() {
+                        response.response = response.response.map(|body| guard.wrap(body));
+                    }
+                    Ok(response)
+                }
+            })
+            .boxed()
+    }
+
+    fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint> {
+        let mut map = MultiMap::new();
+        if let (true, Some(token)) = (self.endpoint.enabled, &self.endpoint.token) {
+            map.insert(
+                self.endpoint.listen.clone(),
+                Endpoint::from_router_service(
+                    self.endpoint.path.clone(),
+                    GenerationsService {
+                        token: BearerToken::new(token),
+                    }
+                    .boxed(),
+                ),
+            );
+        }
+        map
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct GenerationStatus {
+    id: u64,
+    /// `starting` until the generation serves its first request, then `current` until a newer
+    /// generation replaces it, then `draining`
+    state: &'static str,
+    requests: i64,
+    age_seconds: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    draining_seconds: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hard_drain_in_seconds: Option<u64>,
+}
+
+impl From<&Generation> for GenerationStatus {
+    fn from(generation: &Generation) -> Self {
+        let retired_at = *generation.retired_at.lock();
+        let draining = retired_at.map(|retired_at| retired_at.elapsed());
+        GenerationStatus {
+            id: generation.id,
+            state: generation.state(),
+            requests: generation.requests.load(Ordering::Relaxed),
+            age_seconds: generation.created_at.elapsed().as_secs(),
+            draining_seconds: draining.map(|draining| draining.as_secs()),
+            hard_drain_in_seconds: draining
+                .zip(generation.hard_drain_timeout)
+                .map(|(draining, timeout)| timeout.saturating_sub(draining).as_secs()),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct GenerationsService {
+    token: BearerToken,
+}
+
+impl GenerationsService {
+    fn handle(
+        &self,
+        request: &http::Request<Body>,
+    ) -> Result<Vec<GenerationStatus>, (StatusCode, String)> {
+        self.token.check(request.headers())?;
+        if request.method() != Method::GET {
+            return Err((
+                StatusCode::METHOD_NOT_ALLOWED,
+                "expected a GET request".to_string(),
+            ));
+        }
+        Ok(Generation::all()
+            .iter()
+            .map(|generation| GenerationStatus::from(generation.as_ref()))
+            .collect())
+    }
+}
+
+impl Service<router::Request> for GenerationsService {
+    type Response = router::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Ok(()).into()
+    }
+
+    fn call(&mut self, req: router::Request) -> Self::Future {
+        let result = self.handle(&req.router_request);
+        Box::pin(async move {
+            let (status, content_type, body) = match result {
+                Ok(generations) => (
+                    StatusCode::OK,
+                    "application/json",
+                    serde_json::to_vec(&serde_json::json!({ "generations": generations }))?,
+                ),
+                Err((status, error)) => (status, "text/plain", error.into_bytes()),
+            };
+            Ok(router::Response {
+                response: http::Response::builder()
+                    .status(status)
+                    .header(http::header::CONTENT_TYPE, content_type)
+                    .body::<Body>(body.into())
+                    .map_err(BoxError::from)?,
+                context: req.context,
+            })
+        })
+    }
+}
+
+register_plugin!("apollo", "pipeline_generations", PipelineGenerations);
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+
+    async fn plugin(config: serde_json::Value) -> PipelineGenerations {
+        PipelineGenerations::new(PluginInit::fake_new(
+            serde_json::from_value(config).unwrap(),
+            Default::default(),
+        ))
+        .await
+        .unwrap()
+    }
+
+    /// Calls a router service streaming its response until the returned sender is dropped
+    async fn stream(plugin: &PipelineGenerations) -> (hyper::body::Sender, Body) {
+        let (sender, body) = Body::channel();
+        let body = Arc::new(Mutex::new(Some(body)));
+        let service = plugin.router_service(
+            tower::service_fn(move |request: router::Request| {
+                let body = body.lock().take().unwrap();
+                async move {
+                    Ok::<_, BoxError>(router::Response {
+                        response: http::Response::new(body),
+                        context: request.context,
+                    })
+                }
+            })
+            .boxed(),
+        );
+        let response = service
+            .oneshot(router::Request::fake_builder().build().unwrap())
+            .await
+            .unwrap();
+        (sender, response.response.into_body())
+    }
+
+    #[tokio::test]
+    async fn tracks_draining_generations() {
+        let old = plugin(json!({})).await;
+        let (sender, body) = stream(&old).await;
+        assert_eq!(old.generation.requests.load(Ordering::Relaxed), 1);
+
+        let new = plugin(json!({})).await;
+        let (_new_sender, _new_body) = stream(&new).await;
+        assert_eq!(old.generation.state(), "draining");
+        assert!(Generation::all()
+            .iter()
+            .any(|generation| generation.id == old.generation.id));
+
+        // the old generation is drained once its stream ends
+        drop(sender);
+        hyper::body::to_bytes(body).await.unwrap();
+        assert_eq!(old.generation.requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn closes_streams_after_the_hard_drain_timeout() {
+        let old = plugin(json!({ "hard_drain_timeout": "50ms" })).await;
+        let (_sender, body) = stream(&old).await;
+
+        let new = plugin(json!({})).await;
+        let (_new_sender, _new_body) = stream(&new).await;
+
+        tokio::time::timeout(Duration::from_secs(5), hyper::body::to_bytes(body))
+            .await
+            .expect("the stream should be closed by the hard drain")
+            .unwrap();
+        assert_eq!(old.generation.requests.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn endpoint_requires_token() {
+        let config = serde_json::from_value(json!({ "endpoint": { "enabled": true } })).unwrap();
+        assert!(
+            PipelineGenerations::new(PluginInit::fake_new(config, Default::default()))
+                .await
+                .is_err()
+        );
+
+        let service = GenerationsService {
+            token: BearerToken::new("secret"),
+        };
+        let request = |authorization: &str| {
+            http::Request::builder()
+                .uri("http://localhost/debug/pipelines")
+                .header(http::header::AUTHORIZATION, authorization)
+                .body(Body::empty())
+                .unwrap()
+        };
+        let (status, _) = service.handle(&request("Bearer wrong")).unwrap_err();
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+        assert!(service.handle(&request("Bearer secret")).is_ok());
+    }
+}
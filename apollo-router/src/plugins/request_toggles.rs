@@ -0,0 +1,329 @@
+//! Per-request toggles sent by clients in the `router` extension of GraphQL requests.
+//!
+//! Clients can ask for debugging or caching behaviours for a single request, like
+//! `{"extensions": {"router": {"include_query_plan": true}}}`. The extension is validated against
+//! a strict schema, each toggle has to be enabled in the configuration, and can require an
+//! authenticated request with some JWT scopes.
+
+use std::collections::HashSet;
+use std::ops::ControlFlow;
+
+use http::StatusCode;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde_json_bytes::json;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::error::Error;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
+use crate::register_plugin;
+use crate::services::supergraph;
+use crate::tracer::TraceId;
+use crate::Context;
+
+/// Name of the request and response extension
+const ROUTER_EXTENSION: &str = "router";
+
+/// Request toggles configuration
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct RequestTogglesConfig {
+    /// Add the query plan to the response extensions. Requires `experimental.expose_query_plan`
+    include_query_plan: ToggleConfig,
+    /// Add the trace ID of the request to the response extensions
+    trace: ToggleConfig,
+    /// Bypass the entity cache
+    no_cache: ToggleConfig,
+}
+
+/// Requirements of a request toggle
+#[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct ToggleConfig {
+    /// Set to true to accept the toggle from clients
+    enabled: bool,
+    /// Only accept the toggle in authenticated requests
+    authenticated: bool,
+    /// JWT scopes required to use the toggle. Requests must have all of them
+    scopes: Vec<String>,
+}
+
+/// Toggles enabled for a request, stored in the context extensions
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct RequestToggles {
+    pub(crate) include_query_plan: bool,
+    pub(crate) trace: bool,
+    pub(crate) no_cache: bool,
+}
+
+/// Trace ID of a request using the `trace` toggle
+#[derive(Clone)]
+struct TracedRequest(TraceId);
+
+impl RequestToggles {
+    /// Gets the toggles of a request, if it had any
+    pub(crate) fn get(context: &Context) -> Self {
+        context
+            .extensions()
+            .with_lock(|lock| lock.get::<RequestToggles>().copied())
+            .unwrap_or_default()
+    }
+
+    fn enabled(&self) -> impl Iterator<Item = &'static str> {
+        [
+            ("include_query_plan", self.include_query_plan),
+            ("trace", self.trace),
+            ("no_cache", self.no_cache),
+        ]
+        .into_iter()
+        .filter_map(|(name, enabled)| enabled.then_some(name))
+    }
+}
+
+impl RequestTogglesConfig {
+    fn toggle(&self, name: &str) -> &ToggleConfig {
+        match name {
+            "include_query_plan" => &self.include_query_plan,
+            "trace" => &self.trace,
+            _ => &self.no_cache,
+        }
+    }
+}
+
+impl ToggleConfig {
+    fn allows(&self, context: &Context) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        if !self.authenticated && self.scopes.is_empty() {
+            return true;
+        }
+        let Some(claims) = context.get_json_value(APOLLO_AUTHENTICATION_JWT_CLAIMS) else {
+            return false;
+        };
+        let granted: HashSet<&str> = claims
+            .get("scope")
+            .and_then(|scope| scope.as_str())
+            .map(|scope| scope.split(' ').collect())
+            .unwrap_or_default();
+        self.scopes
+            .iter()
+            .all(|scope| granted.contains(scope.as_str()))
+    }
+}
+
+#[derive(Debug)]
+struct RequestTogglesPlugin {
+    config: RequestTogglesConfig,
+}
+
+#[async_trait::async_trait]
+impl Plugin for RequestTogglesPlugin {
+    type Config = RequestTogglesConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(RequestTogglesPlugin {
+            config: init.config,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let config = self.config.clone();
+
+        ServiceBuilder::new()
+            .checkpoint(move |req: supergraph::Request| {
+                let Some(extension) = req
+                    .supergraph_request
+                    .body()
+                    .extensions
+                    .get(ROUTER_EXTENSION)
+                else {
+                    return Ok(ControlFlow::Continue(req));
+                };
+
+                let toggles: RequestToggles = match serde_json_bytes::from_value(extension.clone())
+                {
+                    Ok(toggles) => toggles,
+                    Err(err) => {
+                        let res = supergraph::Response::infallible_builder()
+                            .error(
+                                Error::builder()
+                                    .message(format!("invalid router extension: {err}"))
+                                    .extension_code("INVALID_ROUTER_EXTENSION")
+                                    .build(),
+                            )
+                            .status_code(StatusCode::BAD_REQUEST)
+                            .context(req.context)
+                            .build();
+                        return Ok(ControlFlow::Break(res));
+                    }
+                };
+
+                let forbidden: Vec<_> = toggles
+                    .enabled()
+                    .filter(|name| !config.toggle(name).allows(&req.context))
+                    .collect();
+                if !forbidden.is_empty() {
+                    let errors = forbidden
+                        .into_iter()
+                        .map(|name| {
+                            u64_counter!(
+                                "apollo.router.operations.request_toggles.rejected",
+                                "Number of requests using request toggles they are not allowed to",
+                                1,
+                                toggle = name
+                            );
+                            Error::builder()
+                                .message(format!(
+                                    "the request is not allowed to use the router extension toggle {name}"
+                                ))
+                                .extension_code("ROUTER_EXTENSION_FORBIDDEN")
+                                .extension("toggle", name)
+                                .build()
+                        })
+                        .collect();
+                    let res = supergraph::Response::infallible_builder()
+                        .errors(errors)
+                        .status_code(StatusCode::FORBIDDEN)
+                        .context(req.context)
+                        .build();
+                    return Ok(ControlFlow::Break(res));
+                }
+
+                // the sampling decision was made by the router span, the trace ID is only exposed
+                // for sampled requests
+                let trace_id = toggles.trace.then(TraceId::maybe_new).flatten();
+                let _ = req.context.extensions().with_lock(|mut lock| {
+                    lock.insert(toggles);
+                    if let Some(trace_id) = trace_id {
+                        lock.insert(TracedRequest(trace_id));
+                    }
+                });
+                Ok(ControlFlow::Continue(req))
+            })
+            .map_first_graphql_response(|context, parts, mut response| {
+                let trace_id = context
+                    .extensions()
+                    .with_lock(|lock| lock.get::<TracedRequest>().cloned());
+                if let Some(TracedRequest(trace_id)) = trace_id {
+                    response.extensions.insert(
+                        ROUTER_EXTENSION,
+                        json!({ "trace_id": trace_id.to_string() }),
+                    );
+                }
+                (parts, response)
+            })
+            .service(service)
+            .boxed()
+    }
+}
+
+register_plugin!("apollo", "request_toggles", RequestTogglesPlugin);
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::graphql;
+    use crate::TestHarness;
+
+    async fn query(request: supergraph::Request) -> graphql::Response {
+        let service = TestHarness::builder()
+            .configuration_json(json!({
+                "plugins": { "experimental.expose_query_plan": true },
+                "request_toggles": {
+                    "include_query_plan": { "enabled": true },
+                    "no_cache": { "enabled": true, "scopes": ["cache:bypass"] }
+                }
+            }))
+            .unwrap()
+            .build_supergraph()
+            .await
+            .unwrap();
+        service
+            .oneshot(request)
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap()
+    }
+
+    fn request(router: serde_json::Value) -> supergraph::Request {
+        supergraph::Request::fake_builder()
+            .query("{ topProducts { upc name } }")
+            .extension(ROUTER_EXTENSION, router)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn includes_the_query_plan() {
+        let response = query(request(json!({ "include_query_plan": true }))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert!(response.extensions.contains_key("apolloQueryPlan"));
+
+        let response = query(request(json!({ "include_query_plan": false }))).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert!(!response.extensions.contains_key("apolloQueryPlan"));
+    }
+
+    #[tokio::test]
+    async fn rejects_invalid_extensions() {
+        let response = query(request(json!({ "include_query_plan": "yes" }))).await;
+        assert_eq!(
+            response.errors[0].extensions.get("code"),
+            Some(&"INVALID_ROUTER_EXTENSION".into())
+        );
+
+        let response = query(request(json!({ "unknown": true }))).await;
+        assert_eq!(
+            response.errors[0].extensions.get("code"),
+            Some(&"INVALID_ROUTER_EXTENSION".into())
+        );
+    }
+
+    #[tokio::test]
+    async fn rejects_forbidden_toggles() {
+        let response = query(request(json!({ "trace": true, "no_cache": true }))).await;
+        assert_eq!(response.errors.len(), 2);
+        assert_eq!(
+            response.errors[0].extensions.get("code"),
+            Some(&"ROUTER_EXTENSION_FORBIDDEN".into())
+        );
+        assert_eq!(
+            response.errors[0].extensions.get("toggle"),
+            Some(&"trace".into())
+        );
+        assert_eq!(
+            response.errors[1].extensions.get("toggle"),
+            Some(&"no_cache".into())
+        );
+
+        let context = Context::new();
+        context
+            .insert(
+                APOLLO_AUTHENTICATION_JWT_CLAIMS,
+                json!({ "scope": "profile cache:bypass" }),
+            )
+            .unwrap();
+        let response = query(
+            supergraph::Request::fake_builder()
+                .query("{ topProducts { upc name } }")
+                .extension(ROUTER_EXTENSION, json!({ "no_cache": true }))
+                .context(context)
+                .build()
+                .unwrap(),
+        )
+        .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+    }
+}
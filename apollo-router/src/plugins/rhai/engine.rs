@@ -46,8 +46,11 @@ use crate::graphql::Response;
 use crate::http_ext;
 use crate::plugins::authentication::APOLLO_AUTHENTICATION_JWT_CLAIMS;
 use crate::plugins::cache::entity::CONTEXT_CACHE_KEY;
+use crate::plugins::subscription::can_drop_event;
+use crate::plugins::subscription::is_subscription;
 use crate::plugins::subscription::SUBSCRIPTION_WS_CUSTOM_CONNECTION_PARAMS;
 use crate::query_planner::APOLLO_OPERATION_ID;
+use crate::query_planner::CONTEXT_CACHE_KEY as QUERY_PLAN_CACHE_KEY;
 use crate::Context;
 
 const CANNOT_ACCESS_HEADERS_ON_A_DEFERRED_RESPONSE: &str =
@@ -56,6 +59,11 @@ const CANNOT_ACCESS_HEADERS_ON_A_DEFERRED_RESPONSE: &str =
 const CANNOT_ACCESS_STATUS_CODE_ON_A_DEFERRED_RESPONSE: &str =
     "cannot access status_code on a deferred response";
 
+const CANNOT_DROP_A_DEFERRED_RESPONSE: &str =
+    "cannot drop a deferred response which is not a subscription event";
+const CANNOT_DROP_A_LAST_OR_FAILED_EVENT: &str =
+    "cannot drop the last subscription event or a subscription event with errors";
+
 const CANNOT_GET_ENVIRONMENT_VARIABLE: &str = "environment variable not found";
 
 pub(super) trait OptionDance<T> {
@@ -751,6 +759,22 @@ mod router_plugin {
         false
     }
 
+    #[rhai_fn(name = "drop_event", return_raw)]
+    pub(crate) fn supergraph_deferred_response_drop_event(
+        obj: &mut SharedMut<supergraph::DeferredResponse>,
+    ) -> Result<(), Box<EvalAltResult>> {
+        obj.with_mut(|response| {
+            if !is_subscription(&response.context) {
+                return Err(CANNOT_DROP_A_DEFERRED_RESPONSE.into());
+            }
+            if !can_drop_event(&response.context, &response.response) {
+                return Err(CANNOT_DROP_A_LAST_OR_FAILED_EVENT.into());
+            }
+            response.dropped = true;
+            Ok(())
+        })
+    }
+
     #[rhai_fn(get = "headers", pure, return_raw)]
     pub(crate) fn get_originating_headers_execution_response(
         obj: &mut SharedMut<execution::FirstResponse>,
@@ -1311,6 +1335,8 @@ pub(crate) struct RhaiSupergraphResponse {
 pub(crate) struct RhaiSupergraphDeferredResponse {
     pub(crate) context: Context,
     pub(crate) response: Response,
+    /// Set by `drop_event()` to drop a subscription event instead of sending it to the client
+    pub(crate) dropped: bool,
 }
 
 #[derive(Default)]
@@ -1323,6 +1349,8 @@ pub(crate) struct RhaiExecutionResponse {
 pub(crate) struct RhaiExecutionDeferredResponse {
     pub(crate) context: Context,
     pub(crate) response: Response,
+    /// Subscription events are only dropped from the supergraph service
+    pub(crate) dropped: bool,
 }
 
 macro_rules! if_subgraph {
@@ -1776,6 +1804,10 @@ impl Rhai {
             SUBSCRIPTION_WS_CUSTOM_CONNECTION_PARAMS.to_string().into(),
         );
         global_variables.insert("APOLLO_ENTITY_CACHE_KEY".into(), CONTEXT_CACHE_KEY.into());
+        global_variables.insert(
+            "APOLLO_QUERY_PLAN_CACHE_KEY".into(),
+            QUERY_PLAN_CACHE_KEY.into(),
+        );
         global_variables.insert("APOLLO_OPERATION_ID".into(), APOLLO_OPERATION_ID.into());
 
         let shared_globals = Arc::new(global_variables);
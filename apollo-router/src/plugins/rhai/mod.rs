@@ -45,6 +45,7 @@ use crate::layers::ServiceBuilderExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
 use crate::plugins::rhai::engine::OptionDance;
+use crate::plugins::subscription::can_drop_event;
 use crate::register_plugin;
 
 mod engine;
@@ -56,6 +57,14 @@ mod router;
 mod subgraph;
 mod supergraph;
 
+/// The service callbacks of a script, with the number of parameters the router calls them with
+const SERVICE_FUNCTIONS: [(&str, usize); 4] = [
+    ("router_service", 1),
+    ("supergraph_service", 1),
+    ("execution_service", 1),
+    ("subgraph_service", 2),
+];
+
 struct EngineBlock {
     ast: AST,
     engine: Arc<Engine>,
@@ -88,6 +97,23 @@ impl EngineBlock {
         // defined in scripts into scope.
         engine.run_ast_with_scope(&mut scope, &ast)?;
 
+        // Reject service callbacks that the router could not call
+        for (name, arity) in SERVICE_FUNCTIONS {
+            let mut definitions = ast
+                .iter_functions()
+                .filter(|function| function.name == name)
+                .peekable();
+            if definitions.peek().is_some()
+                && !definitions.any(|function| function.params.len() == arity)
+            {
+                return Err(format!(
+                    "in Rhai script {}: function {name} must take {arity} parameter(s)",
+                    main.display()
+                )
+                .into());
+            }
+        }
+
         Ok(EngineBlock {
             ast,
             engine,
@@ -115,6 +141,13 @@ pub(crate) struct Conf {
     scripts: Option<PathBuf>,
     /// The main entry point for Rhai script evaluation
     main: Option<String>,
+    /// Reload the scripts when they change (default: true)
+    #[serde(default = "default_hot_reload")]
+    hot_reload: bool,
+}
+
+fn default_hot_reload() -> bool {
+    true
 }
 
 #[async_trait::async_trait]
@@ -144,9 +177,16 @@ impl Plugin for Rhai {
             main,
             sdl,
         )?));
-        let watched_block = block.clone();
-
         let park_flag = Arc::new(AtomicBool::new(false));
+        if !init.config.hot_reload {
+            return Ok(Self {
+                block,
+                park_flag,
+                watcher_handle: None,
+            });
+        }
+
+        let watched_block = block.clone();
         let watching_flag = park_flag.clone();
 
         let watcher_handle = std::thread::spawn(move || {
@@ -657,6 +697,7 @@ macro_rules! gen_map_deferred_response {
                             let response = $base::DeferredResponse {
                                 context,
                                 response: deferred_response,
+                                dropped: false,
                             };
                             let shared_response = Shared::new(Mutex::new(Some(response)));
 
@@ -681,8 +722,13 @@ macro_rules! gen_map_deferred_response {
 
                             let mut guard = shared_response.lock().unwrap();
                             let response_opt = guard.take();
-                            let $base::DeferredResponse { response, .. } =
+                            let $base::DeferredResponse { context, response, dropped } =
                                 response_opt.unwrap();
+                            // the script can add errors to the event after dropping it
+                            if dropped && can_drop_event(&context, &response) {
+                                tracing::debug!("subscription event dropped by the rhai script");
+                                return None;
+                            }
                             Some(response)
                         }
                     });
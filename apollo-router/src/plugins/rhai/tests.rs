@@ -22,6 +22,7 @@ use super::process_error;
 use super::subgraph;
 use super::PathBuf;
 use super::Rhai;
+use crate::context::keys::OPERATION_KIND;
 use crate::graphql;
 use crate::graphql::Error;
 use crate::graphql::Request;
@@ -35,6 +36,7 @@ use crate::plugins::rhai::engine::RhaiRouterChunkedResponse;
 use crate::plugins::rhai::engine::RhaiRouterResponse;
 use crate::plugins::rhai::engine::RhaiSupergraphDeferredResponse;
 use crate::plugins::rhai::engine::RhaiSupergraphResponse;
+use crate::query_planner::OperationKind;
 use crate::services::ExecutionRequest;
 use crate::services::SubgraphRequest;
 use crate::services::SupergraphRequest;
@@ -407,6 +409,59 @@ async fn it_can_process_supergraph_deferred_response() {
         .expect("test failed");
 }
 
+#[tokio::test]
+async fn it_can_drop_supergraph_subscription_event() {
+    let response = RhaiSupergraphDeferredResponse::default();
+    response
+        .context
+        .insert_typed(&OPERATION_KIND, OperationKind::Subscription)
+        .unwrap();
+    call_rhai_function_with_arg("drop_supergraph_deferred_response", response)
+        .await
+        .expect("test failed");
+}
+
+#[tokio::test]
+async fn it_cannot_drop_supergraph_deferred_response() {
+    let response = RhaiSupergraphDeferredResponse::default();
+    let error = call_rhai_function_with_arg("drop_supergraph_deferred_response", response)
+        .await
+        .expect_err("must fail");
+    assert!(error
+        .to_string()
+        .contains("cannot drop a deferred response which is not a subscription event"));
+}
+
+#[tokio::test]
+async fn it_cannot_drop_last_or_failed_subscription_events() {
+    let last = graphql::Response::builder().has_next(false).build();
+    let failed = graphql::Response::builder()
+        .error(
+            Error::builder()
+                .message("error")
+                .extension_code("ERROR")
+                .build(),
+        )
+        .has_next(true)
+        .build();
+    for event in [last, failed] {
+        let response = RhaiSupergraphDeferredResponse {
+            response: event,
+            ..Default::default()
+        };
+        response
+            .context
+            .insert_typed(&OPERATION_KIND, OperationKind::Subscription)
+            .unwrap();
+        let error = call_rhai_function_with_arg("drop_supergraph_deferred_response", response)
+            .await
+            .expect_err("must fail");
+        assert!(error.to_string().contains(
+            "cannot drop the last subscription event or a subscription event with errors"
+        ));
+    }
+}
+
 #[tokio::test]
 async fn it_can_process_execution_response() {
     let response = RhaiExecutionResponse::default();
@@ -695,6 +750,45 @@ async fn it_mentions_source_when_syntax_error_occurs() {
     assert!(err.to_string().contains("syntax_errors.rhai"));
 }
 
+#[tokio::test]
+async fn it_rejects_service_functions_with_wrong_parameters() {
+    let err: BoxError = crate::plugin::plugins()
+        .find(|factory| factory.name == "apollo.rhai")
+        .expect("Plugin not found")
+        .create_instance_without_schema(
+            &Value::from_str(
+                r#"{"scripts":"tests/fixtures", "main":"invalid_service_function.rhai"}"#,
+            )
+            .unwrap(),
+        )
+        .await
+        .err()
+        .unwrap();
+
+    assert!(err
+        .to_string()
+        .contains("function subgraph_service must take 2 parameter(s)"));
+}
+
+#[tokio::test]
+async fn it_does_not_watch_scripts_without_hot_reload() {
+    let dyn_plugin: Box<dyn DynPlugin> = crate::plugin::plugins()
+        .find(|factory| factory.name == "apollo.rhai")
+        .expect("Plugin not found")
+        .create_instance_without_schema(
+            &Value::from_str(
+                r#"{"scripts":"tests/fixtures", "main":"test.rhai", "hot_reload": false}"#,
+            )
+            .unwrap(),
+        )
+        .await
+        .unwrap();
+
+    let it: &dyn std::any::Any = dyn_plugin.as_any();
+    let rhai_instance: &Rhai = it.downcast_ref::<Rhai>().expect("downcast");
+    assert!(rhai_instance.watcher_handle.is_none());
+}
+
 #[test]
 #[should_panic(
     expected = "can use env: ErrorRuntime(\"could not expand variable: THIS_SHOULD_NOT_EXIST, environment variable not found\", none)"
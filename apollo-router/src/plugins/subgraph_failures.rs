@@ -0,0 +1,308 @@
+//! Per-subgraph policy for failed fetches.
+//!
+//! By default, when a subgraph fetch fails, the fields it should have provided are set to null
+//! and an error is added to the response. Depending on the subgraph, the router can instead fail
+//! the whole request, or set the fields to null without reporting an error, to keep optional
+//! parts of the response from polluting the errors returned to clients.
+
+use std::collections::HashMap;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use schemars::JsonSchema;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::json;
+use serde_json_bytes::Value;
+use tower::BoxError;
+use tower::ServiceBuilder;
+use tower::ServiceExt;
+
+use crate::json_ext::Object;
+use crate::layers::ServiceBuilderExt;
+use crate::plugin::Plugin;
+use crate::plugin::PluginInit;
+use crate::register_plugin;
+use crate::services::subgraph;
+use crate::services::supergraph;
+use crate::Context;
+
+register_plugin!("apollo", "subgraph_failures", SubgraphFailures);
+
+/// Configuration of the handling of failed subgraph fetches
+#[derive(Clone, Debug, JsonSchema, Default, Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields, default)]
+struct SubgraphFailuresConfig {
+    /// Policy applied to all subgraphs
+    all: FailurePolicy,
+
+    /// Policy applied to specific subgraphs
+    subgraphs: HashMap<String, FailurePolicy>,
+}
+
+/// What to do when a subgraph fetch fails
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum FailurePolicy {
+    /// Fail the whole request: the response data is null
+    FailRequest,
+    /// Set the fields provided by the subgraph to null, and add an error to the response
+    #[default]
+    NullAndError,
+    /// Set the fields provided by the subgraph to null, without adding an error to the response
+    NullSilently,
+}
+
+/// Marks a request in which a subgraph configured with `fail_request` failed
+#[derive(Clone, Copy)]
+struct FailedRequest;
+
+struct SubgraphFailures {
+    config: SubgraphFailuresConfig,
+}
+
+impl SubgraphFailures {
+    fn policy(&self, name: &str) -> FailurePolicy {
+        *self.config.subgraphs.get(name).unwrap_or(&self.config.all)
+    }
+}
+
+/// A fetch failed if the subgraph could not be called, or if it returned errors without data
+fn is_failure(response: &Result<subgraph::Response, BoxError>) -> bool {
+    match response {
+        Ok(response) => {
+            let body = response.response.body();
+            !body.errors.is_empty() && body.data.as_ref().map_or(true, Value::is_null)
+        }
+        Err(_) => true,
+    }
+}
+
+#[async_trait::async_trait]
+impl Plugin for SubgraphFailures {
+    type Config = SubgraphFailuresConfig;
+
+    async fn new(init: PluginInit<Self::Config>) -> Result<Self, BoxError> {
+        Ok(SubgraphFailures {
+            config: init.config,
+        })
+    }
+
+    fn supergraph_service(&self, service: supergraph::BoxService) -> supergraph::BoxService {
+        let fail_request = self.config.all == FailurePolicy::FailRequest
+            || self
+                .config
+                .subgraphs
+                .values()
+                .any(|policy| *policy == FailurePolicy::FailRequest);
+        if !fail_request {
+            return service;
+        }
+
+        service
+            .map_response(|response: supergraph::Response| {
+                let context = response.context.clone();
+                response.map_stream(move |mut response| {
+                    let failed = context
+                        .extensions()
+                        .with_lock(|lock| lock.contains_key::<FailedRequest>());
+                    if failed {
+                        response.data = Some(Value::Null);
+                        response.incremental.clear();
+                    }
+                    response
+                })
+            })
+            .boxed()
+    }
+
+    fn subgraph_service(&self, name: &str, service: subgraph::BoxService) -> subgraph::BoxService {
+        let policy = self.policy(name);
+        if policy == FailurePolicy::NullAndError {
+            return service;
+        }
+
+        let subgraph_name = name.to_string();
+        ServiceBuilder::new()
+            .map_future_with_request_data(
+                |request: &subgraph::Request| {
+                    let entities = request
+                        .subgraph_request
+                        .body()
+                        .variables
+                        .contains_key("representations");
+                    (request.context.clone(), entities)
+                },
+                move |(context, entities): (Context, bool), future: BoxFuture<'static, _>| {
+                    let subgraph_name = subgraph_name.clone();
+                    async move {
+                        let response = future.await;
+                        if !is_failure(&response) {
+                            return response;
+                        }
+                        u64_counter!(
+                            "apollo.router.operations.subgraph_failures",
+                            "Number of failed subgraph fetches handled by a failure policy",
+                            1,
+                            subgraph = subgraph_name.clone(),
+                            policy = match policy {
+                                FailurePolicy::FailRequest => "fail_request",
+                                _ => "null_silently",
+                            }
+                        );
+                        match policy {
+                            FailurePolicy::FailRequest => {
+                                context
+                                    .extensions()
+                                    .with_lock(|mut lock| lock.insert(FailedRequest));
+                                response
+                            }
+                            _ => {
+                                tracing::debug!(
+                                    "ignoring the failure of subgraph({subgraph_name})"
+                                );
+                                // An empty list of entities, so that the fetch node does not
+                                // warn about a missing `_entities` key
+                                let data = entities.then(|| json!({ "_entities": [] }));
+                                Ok(subgraph::Response::builder()
+                                    .and_data(data)
+                                    .context(context)
+                                    .subgraph_name(subgraph_name)
+                                    .extensions(Object::default())
+                                    .build())
+                            }
+                        }
+                    }
+                    .boxed()
+                },
+            )
+            .service(service)
+            .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::*;
+    use crate::graphql;
+    use crate::plugin::test::MockSubgraph;
+    use crate::MockedSubgraphs;
+    use crate::TestHarness;
+
+    const SCHEMA: &str = r#"schema
+        @core(feature: "https://specs.apollo.dev/core/v0.1")
+        @core(feature: "https://specs.apollo.dev/join/v0.1")
+         {
+        query: Query
+   }
+   directive @core(feature: String!) repeatable on SCHEMA
+   directive @join__field(graph: join__Graph, requires: join__FieldSet, provides: join__FieldSet) on FIELD_DEFINITION
+   directive @join__type(graph: join__Graph!, key: join__FieldSet) repeatable on OBJECT | INTERFACE
+   directive @join__owner(graph: join__Graph!) on OBJECT | INTERFACE
+   directive @join__graph(name: String!, url: String!) on ENUM_VALUE
+   scalar join__FieldSet
+   enum join__Graph {
+       USER @join__graph(name: "user", url: "http://localhost:4001/graphql")
+       ORGA @join__graph(name: "orga", url: "http://localhost:4002/graphql")
+   }
+   type Query {
+       currentUser: User @join__field(graph: USER)
+   }
+   type User
+   @join__owner(graph: USER)
+   @join__type(graph: ORGA, key: "id")
+   @join__type(graph: USER, key: "id"){
+       id: ID!
+       name: String
+       activeOrganization: Organization
+   }
+   type Organization
+   @join__owner(graph: ORGA)
+   @join__type(graph: ORGA, key: "id")
+   @join__type(graph: USER, key: "id") {
+       id: ID
+       creatorUser: User
+       name: String
+   }"#;
+
+    async fn call(config: serde_json::Value) -> graphql::Response {
+        let subgraphs = MockedSubgraphs([
+            ("user", MockSubgraph::builder().with_json(
+                serde_json::json!{{"query":"{currentUser{activeOrganization{__typename id}}}"}},
+                serde_json::json!{{"data": {"currentUser": { "activeOrganization": { "__typename": "Organization", "id": "0" } }}}}
+            ).build()),
+            ("orga", MockSubgraph::builder().with_json(
+                serde_json::json!{{
+                    "query":"query($representations:[_Any!]!){_entities(representations:$representations){...on Organization{creatorUser{__typename id}}}}",
+                    "variables": {
+                        "representations":[{"__typename": "Organization", "id":"0"}]
+                    }
+                }},
+                serde_json::json!{{"errors": [{ "message": "orga is down" }]}}
+            ).build())
+        ].into_iter().collect());
+
+        let service = TestHarness::builder()
+            .configuration_json(json!({
+                "include_subgraph_errors": { "all": true },
+                "subgraph_failures": config
+            }))
+            .unwrap()
+            .schema(SCHEMA)
+            .extra_plugin(subgraphs)
+            .build_supergraph()
+            .await
+            .unwrap();
+
+        let request = supergraph::Request::fake_builder()
+            .query(
+                "query { currentUser { activeOrganization { id creatorUser { __typename id } } } }",
+            )
+            .build()
+            .unwrap();
+        service
+            .oneshot(request)
+            .await
+            .unwrap()
+            .next_response()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn nulls_fields_and_adds_errors_by_default() {
+        let response = call(json!({})).await;
+        assert_eq!(
+            response.data,
+            Some(serde_json_bytes::json!({
+                "currentUser": { "activeOrganization": { "id": "0", "creatorUser": null } }
+            }))
+        );
+        assert_eq!(response.errors.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn nulls_fields_silently() {
+        let response = call(json!({ "subgraphs": { "orga": "null_silently" } })).await;
+        assert_eq!(
+            response.data,
+            Some(serde_json_bytes::json!({
+                "currentUser": { "activeOrganization": { "id": "0", "creatorUser": null } }
+            }))
+        );
+        assert!(response.errors.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fails_the_request() {
+        let response = call(json!({
+            "all": "null_silently",
+            "subgraphs": { "orga": "fail_request" }
+        }))
+        .await;
+        assert_eq!(response.data, Some(Value::Null));
+        assert_eq!(response.errors.len(), 1);
+    }
+}
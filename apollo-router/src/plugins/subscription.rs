@@ -26,6 +26,7 @@ use tower::ServiceExt;
 use tracing_futures::Instrument;
 use uuid::Uuid;
 
+use crate::context::keys::OPERATION_KIND;
 use crate::context::Context;
 use crate::graphql;
 use crate::graphql::Response;
@@ -688,6 +689,23 @@ pub(crate) fn create_verifier(sub_id: &str) -> Result<String, BoxError> {
     Ok(verifier)
 }
 
+/// Returns true if the request of this context executes a subscription. Response hooks use it
+/// to know if a response after the primary one is a subscription event, which can be dropped,
+/// or an incremental delivery payload, which cannot
+pub(crate) fn is_subscription(context: &Context) -> bool {
+    matches!(
+        context.get_typed(&OPERATION_KIND),
+        Ok(Some(OperationKind::Subscription))
+    )
+}
+
+/// Returns true if a response hook can drop this response: it must be a subscription event, and
+/// the last event, which tells the client the subscription ended, and the events with errors are
+/// always delivered
+pub(crate) fn can_drop_event(context: &Context, response: &graphql::Response) -> bool {
+    is_subscription(context) && response.has_next != Some(false) && response.errors.is_empty()
+}
+
 fn ensure_id_consistency(
     context: &Context,
     id_from_path: &str,
@@ -1,5 +1,6 @@
 //! Configuration for the telemetry plugin.
 use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::collections::HashSet;
 
 use axum::headers::HeaderName;
@@ -19,6 +20,7 @@ use serde::Deserialize;
 use serde::Serialize;
 
 use super::metrics::MetricsAttributesConf;
+use super::propagation::PropagationFormat;
 use super::*;
 use crate::plugin::serde::deserialize_option_header_name;
 use crate::plugins::telemetry::metrics;
@@ -211,6 +213,10 @@ pub(crate) struct Tracing {
     pub(crate) common: TracingCommon,
     /// OpenTelemetry native exporter configuration
     pub(crate) otlp: otlp::Config,
+    /// OpenTelemetry native exporters for specific subgraphs, keyed by subgraph name.
+    /// Spans tagged with one of these subgraph names are sent to the subgraph's exporter
+    /// instead of the `otlp` exporter.
+    pub(crate) otlp_subgraphs: HashMap<String, otlp::Config>,
     /// Jaeger exporter configuration
     pub(crate) jaeger: tracing::jaeger::Config,
     /// Zipkin exporter configuration
@@ -292,6 +298,13 @@ pub(crate) struct Propagation {
     pub(crate) zipkin: bool,
     /// Propagate AWS X-Ray
     pub(crate) aws_xray: bool,
+    /// Formats used to extract the trace context from incoming requests, in priority order: the
+    /// first format present on a request is used. When set, this replaces the flags above for
+    /// extraction.
+    pub(crate) extract: Vec<PropagationFormat>,
+    /// Formats used to inject the trace context into subgraph requests. Defaults to the `extract`
+    /// formats.
+    pub(crate) inject: Vec<PropagationFormat>,
 }
 
 #[derive(Clone, Debug, Deserialize, JsonSchema, Default)]
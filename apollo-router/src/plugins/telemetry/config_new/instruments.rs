@@ -84,6 +84,16 @@ pub(crate) struct InstrumentsConfig {
         CacheInstrumentsConfig,
         Instrument<CacheAttributes, SubgraphSelector, SubgraphValue>,
     >,
+    /// Plugin instruments
+    pub(crate) plugin: PluginInstrumentsConfig,
+}
+
+#[derive(Clone, Deserialize, JsonSchema, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct PluginInstrumentsConfig {
+    /// Histogram of the time spent in each plugin, by stage and by phase (request or response)
+    #[serde(rename = "apollo.router.plugin.duration")]
+    pub(crate) duration: bool,
 }
 
 impl InstrumentsConfig {
@@ -73,11 +73,14 @@ use self::config_new::instruments::SubgraphInstruments;
 use self::config_new::spans::Spans;
 use self::metrics::apollo::studio::SingleTypeStat;
 use self::metrics::AttributesForwardConf;
+use self::propagation::PrioritizedPropagator;
+use self::propagation::PropagationFormat;
 use self::reload::reload_fmt;
 pub(crate) use self::span_factory::SpanMode;
 use self::tracing::apollo_telemetry::APOLLO_PRIVATE_DURATION_NS;
 use self::tracing::apollo_telemetry::CLIENT_NAME_KEY;
 use self::tracing::apollo_telemetry::CLIENT_VERSION_KEY;
+use self::tracing::otlp::OtlpTracing;
 use crate::apollo_studio_interop::ExtendedReferenceStats;
 use crate::apollo_studio_interop::ReferencedEnums;
 use crate::context::CONTAINS_GRAPHQL_ERROR;
@@ -162,6 +165,7 @@ pub(crate) mod metrics;
 /// Opentelemetry utils
 pub(crate) mod otel;
 mod otlp;
+pub(crate) mod propagation;
 pub(crate) mod reload;
 mod resource;
 mod span_factory;
@@ -853,35 +857,79 @@ impl Telemetry {
         let tracing = &config.exporters.tracing;
 
         let mut propagators: Vec<Box<dyn TextMapPropagator + Send + Sync + 'static>> = Vec::new();
-        // TLDR the jaeger propagator MUST BE the first one because the version of opentelemetry_jaeger is buggy.
-        // It overrides the current span context with an empty one if it doesn't find the corresponding headers.
-        // Waiting for the >=0.16.1 release
-        if propagation.jaeger || tracing.jaeger.enabled() {
-            propagators.push(Box::<opentelemetry_jaeger::Propagator>::default());
+        if !propagation.extract.is_empty() || !propagation.inject.is_empty() {
+            let extract = if propagation.extract.is_empty() {
+                Self::enabled_propagation_formats(config)
+            } else {
+                propagation.extract.clone()
+            };
+            let inject = if propagation.inject.is_empty() {
+                extract.clone()
+            } else {
+                propagation.inject.clone()
+            };
+            propagators.push(Box::new(PrioritizedPropagator::new(&extract, &inject)));
+            if propagation.baggage {
+                propagators
+                    .push(Box::<opentelemetry::sdk::propagation::BaggagePropagator>::default());
+            }
+        } else {
+            // TLDR the jaeger propagator MUST BE the first one because the version of opentelemetry_jaeger is buggy.
+            // It overrides the current span context with an empty one if it doesn't find the corresponding headers.
+            // Waiting for the >=0.16.1 release
+            if propagation.jaeger || tracing.jaeger.enabled() {
+                propagators.push(Box::<opentelemetry_jaeger::Propagator>::default());
+            }
+            if propagation.baggage {
+                propagators
+                    .push(Box::<opentelemetry::sdk::propagation::BaggagePropagator>::default());
+            }
+            if propagation.trace_context || tracing.otlp.enabled {
+                propagators.push(
+                    Box::<opentelemetry::sdk::propagation::TraceContextPropagator>::default(),
+                );
+            }
+            if propagation.zipkin || tracing.zipkin.enabled {
+                propagators.push(Box::<opentelemetry_zipkin::Propagator>::default());
+            }
+            if propagation.datadog || tracing.datadog.enabled() {
+                propagators.push(Box::<tracing::datadog_exporter::DatadogPropagator>::default());
+            }
+            if propagation.aws_xray {
+                propagators.push(Box::<opentelemetry_aws::XrayPropagator>::default());
+            }
         }
-        if propagation.baggage {
-            propagators.push(Box::<opentelemetry::sdk::propagation::BaggagePropagator>::default());
+        if let Some(from_request_header) = &propagation.request.header_name {
+            propagators.push(Box::new(CustomTraceIdPropagator::new(
+                from_request_header.to_string(),
+            )));
         }
+
+        TextMapCompositePropagator::new(propagators)
+    }
+
+    /// Propagation formats enabled through the boolean flags or the configured exporters
+    fn enabled_propagation_formats(config: &config::Conf) -> Vec<PropagationFormat> {
+        let propagation = &config.exporters.tracing.propagation;
+        let tracing = &config.exporters.tracing;
+
+        let mut formats = Vec::new();
         if propagation.trace_context || tracing.otlp.enabled {
-            propagators
-                .push(Box::<opentelemetry::sdk::propagation::TraceContextPropagator>::default());
+            formats.push(PropagationFormat::TraceContext);
+        }
+        if propagation.jaeger || tracing.jaeger.enabled() {
+            formats.push(PropagationFormat::Jaeger);
         }
         if propagation.zipkin || tracing.zipkin.enabled {
-            propagators.push(Box::<opentelemetry_zipkin::Propagator>::default());
+            formats.push(PropagationFormat::ZipkinB3Multi);
         }
         if propagation.datadog || tracing.datadog.enabled() {
-            propagators.push(Box::<tracing::datadog_exporter::DatadogPropagator>::default());
+            formats.push(PropagationFormat::Datadog);
         }
         if propagation.aws_xray {
-            propagators.push(Box::<opentelemetry_aws::XrayPropagator>::default());
+            formats.push(PropagationFormat::AwsXray);
         }
-        if let Some(from_request_header) = &propagation.request.header_name {
-            propagators.push(Box::new(CustomTraceIdPropagator::new(
-                from_request_header.to_string(),
-            )));
-        }
-
-        TextMapCompositePropagator::new(propagators)
+        formats
     }
 
     fn create_tracer_provider(
@@ -901,13 +949,17 @@ impl Telemetry {
         builder = setup_tracing(builder, &tracing_config.jaeger, &common, spans_config)?;
         builder = setup_tracing(builder, &tracing_config.zipkin, &common, spans_config)?;
         builder = setup_tracing(builder, &tracing_config.datadog, &common, spans_config)?;
-        builder = setup_tracing(builder, &tracing_config.otlp, &common, spans_config)?;
+        let otlp = OtlpTracing {
+            default: &tracing_config.otlp,
+            subgraphs: &tracing_config.otlp_subgraphs,
+        };
+        builder = setup_tracing(builder, &otlp, &common, spans_config)?;
         builder = setup_tracing(builder, &config.apollo, &common, spans_config)?;
 
         if !tracing_config.jaeger.enabled()
             && !tracing_config.zipkin.enabled()
             && !tracing_config.datadog.enabled()
-            && !TracingConfigurator::enabled(&tracing_config.otlp)
+            && !TracingConfigurator::enabled(&otlp)
             && !TracingConfigurator::enabled(&config.apollo)
         {
             sampler = SamplerOption::Always(Sampler::AlwaysOff);
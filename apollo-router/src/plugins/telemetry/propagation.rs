@@ -0,0 +1,212 @@
+//! Trace propagation with distinct formats for extraction and injection.
+//!
+//! The composite propagator from the OpenTelemetry SDK runs every configured format for both
+//! extraction and injection, and the last format that finds a trace context wins. When a tracing
+//! estate is mid-migration between formats, operators need to control which incoming header takes
+//! precedence and which headers are sent to subgraphs, independently.
+use opentelemetry::propagation::text_map_propagator::FieldIter;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::propagation::Injector;
+use opentelemetry::propagation::TextMapPropagator;
+use opentelemetry::trace::TraceContextExt;
+use opentelemetry::Context;
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+use crate::plugins::telemetry::tracing::datadog_exporter::DatadogPropagator;
+
+/// A trace context propagation format
+#[derive(Clone, Copy, Debug, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(deny_unknown_fields, rename_all = "snake_case")]
+pub(crate) enum PropagationFormat {
+    /// W3C trace context https://www.w3.org/TR/trace-context/
+    TraceContext,
+    /// Jaeger (`uber-trace-id` header)
+    Jaeger,
+    /// Zipkin B3 single header (`b3`). Extraction accepts both B3 encodings.
+    ZipkinB3Single,
+    /// Zipkin B3 multiple headers (`X-B3-TraceId`, `X-B3-SpanId`, ...). Extraction accepts both B3 encodings.
+    ZipkinB3Multi,
+    /// Datadog
+    Datadog,
+    /// AWS X-Ray
+    AwsXray,
+}
+
+impl PropagationFormat {
+    fn propagator(self) -> Box<dyn TextMapPropagator + Send + Sync + 'static> {
+        match self {
+            PropagationFormat::TraceContext => {
+                Box::<opentelemetry::sdk::propagation::TraceContextPropagator>::default()
+            }
+            PropagationFormat::Jaeger => Box::<opentelemetry_jaeger::Propagator>::default(),
+            PropagationFormat::ZipkinB3Single => {
+                Box::new(opentelemetry_zipkin::Propagator::with_encoding(
+                    opentelemetry_zipkin::B3Encoding::SingleHeader,
+                ))
+            }
+            PropagationFormat::ZipkinB3Multi => {
+                Box::new(opentelemetry_zipkin::Propagator::with_encoding(
+                    opentelemetry_zipkin::B3Encoding::MultipleHeader,
+                ))
+            }
+            PropagationFormat::Datadog => Box::<DatadogPropagator>::default(),
+            PropagationFormat::AwsXray => Box::<opentelemetry_aws::XrayPropagator>::default(),
+        }
+    }
+}
+
+/// Extracts the trace context using the first format, in priority order, that is present on
+/// the request, and injects it using a separate list of formats.
+#[derive(Debug)]
+pub(crate) struct PrioritizedPropagator {
+    extractors: Vec<Box<dyn TextMapPropagator + Send + Sync + 'static>>,
+    injectors: Vec<Box<dyn TextMapPropagator + Send + Sync + 'static>>,
+    fields: Vec<String>,
+}
+
+impl PrioritizedPropagator {
+    pub(crate) fn new(extract: &[PropagationFormat], inject: &[PropagationFormat]) -> Self {
+        let extractors: Vec<_> = extract.iter().map(|format| format.propagator()).collect();
+        let injectors: Vec<_> = inject.iter().map(|format| format.propagator()).collect();
+        let mut fields = Vec::new();
+        for propagator in extractors.iter().chain(injectors.iter()) {
+            for field in propagator.fields() {
+                if !fields.iter().any(|existing| existing == field) {
+                    fields.push(field.to_string());
+                }
+            }
+        }
+        Self {
+            extractors,
+            injectors,
+            fields,
+        }
+    }
+}
+
+impl TextMapPropagator for PrioritizedPropagator {
+    fn inject_context(&self, cx: &Context, injector: &mut dyn Injector) {
+        for propagator in &self.injectors {
+            propagator.inject_context(cx, injector);
+        }
+    }
+
+    fn extract_with_context(&self, cx: &Context, extractor: &dyn Extractor) -> Context {
+        for propagator in &self.extractors {
+            let extracted = propagator.extract_with_context(cx, extractor);
+            let span_context = extracted.span().span_context().clone();
+            if span_context.is_valid() && &span_context != cx.span().span_context() {
+                return extracted;
+            }
+        }
+        cx.clone()
+    }
+
+    fn fields(&self) -> FieldIter<'_> {
+        FieldIter::new(self.fields.as_slice())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use opentelemetry::trace::SpanContext;
+    use opentelemetry::trace::SpanId;
+    use opentelemetry::trace::TraceFlags;
+    use opentelemetry::trace::TraceId;
+    use opentelemetry::trace::TraceState;
+
+    use super::*;
+
+    const W3C_TRACE_ID: &str = "0af7651916cd43dd8448eb211c80319c";
+    const B3_TRACE_ID: &str = "80f198ee56343ba864fe8b2a57d3eff7";
+
+    fn headers() -> HashMap<String, String> {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "traceparent".to_string(),
+            format!("00-{W3C_TRACE_ID}-b7ad6b7169203331-01"),
+        );
+        headers.insert(
+            "b3".to_string(),
+            format!("{B3_TRACE_ID}-e457b5a2e4d86bd1-1"),
+        );
+        headers
+    }
+
+    fn extracted_trace_id(propagator: &PrioritizedPropagator) -> TraceId {
+        propagator
+            .extract(&headers())
+            .span()
+            .span_context()
+            .trace_id()
+    }
+
+    #[test]
+    fn first_format_present_wins() {
+        let propagator = PrioritizedPropagator::new(
+            &[
+                PropagationFormat::ZipkinB3Multi,
+                PropagationFormat::TraceContext,
+            ],
+            &[],
+        );
+        assert_eq!(
+            extracted_trace_id(&propagator),
+            TraceId::from_hex(B3_TRACE_ID).unwrap()
+        );
+
+        let propagator = PrioritizedPropagator::new(
+            &[
+                PropagationFormat::TraceContext,
+                PropagationFormat::ZipkinB3Multi,
+            ],
+            &[],
+        );
+        assert_eq!(
+            extracted_trace_id(&propagator),
+            TraceId::from_hex(W3C_TRACE_ID).unwrap()
+        );
+    }
+
+    #[test]
+    fn missing_formats_are_skipped() {
+        let propagator = PrioritizedPropagator::new(
+            &[PropagationFormat::Jaeger, PropagationFormat::ZipkinB3Single],
+            &[],
+        );
+        assert_eq!(
+            extracted_trace_id(&propagator),
+            TraceId::from_hex(B3_TRACE_ID).unwrap()
+        );
+
+        let propagator = PrioritizedPropagator::new(&[PropagationFormat::Jaeger], &[]);
+        assert!(!propagator
+            .extract(&headers())
+            .span()
+            .span_context()
+            .is_valid());
+    }
+
+    #[test]
+    fn injects_only_configured_formats() {
+        let propagator = PrioritizedPropagator::new(
+            &[PropagationFormat::ZipkinB3Multi],
+            &[PropagationFormat::TraceContext],
+        );
+        let cx = Context::new().with_remote_span_context(SpanContext::new(
+            TraceId::from_hex(W3C_TRACE_ID).unwrap(),
+            SpanId::from_hex("b7ad6b7169203331").unwrap(),
+            TraceFlags::SAMPLED,
+            true,
+            TraceState::default(),
+        ));
+        let mut injected = HashMap::new();
+        propagator.inject_context(&cx, &mut injected);
+        assert!(injected.contains_key("traceparent"));
+        assert!(!injected.contains_key("x-b3-traceid"));
+        assert!(!injected.contains_key("b3"));
+    }
+}
@@ -53,6 +53,15 @@ static FMT_LAYER_HANDLE: OnceCell<
     Handle<Box<dyn Layer<LayeredTracer> + Send + Sync>, LayeredTracer>,
 > = OnceCell::new();
 
+/// Reloads the log filter of the registry, along with the directives it was started with
+struct LogFilterHandle {
+    default: String,
+    current: std::sync::Mutex<String>,
+    reload: Box<dyn Fn(EnvFilter) -> Result<(), tracing_subscriber::reload::Error> + Send + Sync>,
+}
+
+static LOG_FILTER_HANDLE: OnceCell<LogFilterHandle> = OnceCell::new();
+
 pub(super) static SPAN_SAMPLING_RATE: AtomicU64 = AtomicU64::new(0);
 
 pub(super) static METRICS_LAYER: OnceCell<MetricsLayer> = OnceCell::new();
@@ -96,6 +105,8 @@ pub(crate) fn init_telemetry(log_level: &str) -> Result<()> {
             // manually filter salsa logs because some of them run at the INFO level https://github.com/salsa-rs/salsa/issues/425
             let log_level = format!("{log_level},salsa=error");
             tracing::debug!("Running the router with log level set to {log_level}");
+            let (filter_layer, filter_handle) =
+                tracing_subscriber::reload::Layer::new(EnvFilter::try_new(&log_level)?);
             // Env filter is separate because of https://github.com/tokio-rs/tracing/issues/1629
             // the tracing registry is only created once
             tracing_subscriber::registry()
@@ -104,8 +115,13 @@ pub(crate) fn init_telemetry(log_level: &str) -> Result<()> {
                 .with(opentelemetry_layer)
                 .with(fmt_layer)
                 .with(metrics_layer.clone())
-                .with(EnvFilter::try_new(log_level)?)
+                .with(filter_layer)
                 .try_init()?;
+            let _ = LOG_FILTER_HANDLE.set(LogFilterHandle {
+                current: std::sync::Mutex::new(log_level.clone()),
+                default: log_level,
+                reload: Box::new(move |filter| filter_handle.reload(filter)),
+            });
 
             Ok(hot_tracer)
         })
@@ -123,6 +139,28 @@ pub(super) fn reload_fmt(layer: Box<dyn Layer<LayeredTracer> + Send + Sync>) {
     }
 }
 
+/// Adds `directives` to the log filter the router was started with, or restores it if `None`.
+/// Returns the directives of the new filter
+pub(crate) fn reload_log_filter(directives: Option<&str>) -> Result<String, BoxError> {
+    let handle = LOG_FILTER_HANDLE
+        .get()
+        .ok_or("the log filter cannot be changed")?;
+    let filter = match directives {
+        Some(directives) => format!("{},{directives}", handle.default),
+        None => handle.default.clone(),
+    };
+    (handle.reload)(EnvFilter::try_new(&filter)?)?;
+    *handle.current.lock().expect("lock poisoned") = filter.clone();
+    Ok(filter)
+}
+
+/// Directives of the current log filter
+pub(crate) fn log_filter() -> Option<String> {
+    LOG_FILTER_HANDLE
+        .get()
+        .map(|handle| handle.current.lock().expect("lock poisoned").clone())
+}
+
 pub(crate) fn apollo_opentelemetry_initialized() -> bool {
     OPENTELEMETRY_TRACER_HANDLE.get().is_some()
 }
@@ -1,18 +1,31 @@
 //! Configuration for Otlp tracing.
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::result::Result;
 
+use futures::future::BoxFuture;
+use opentelemetry::sdk::export::trace::ExportResult;
+use opentelemetry::sdk::export::trace::SpanData;
+use opentelemetry::sdk::export::trace::SpanExporter;
 use opentelemetry::sdk::trace::BatchSpanProcessor;
 use opentelemetry::sdk::trace::Builder;
+use opentelemetry::Key;
 use opentelemetry_otlp::SpanExporterBuilder;
 use tower::BoxError;
 
 use crate::plugins::telemetry::config::TracingCommon;
 use crate::plugins::telemetry::config_new::spans::Spans;
+use crate::plugins::telemetry::otlp;
 use crate::plugins::telemetry::otlp::TelemetryDataKind;
 use crate::plugins::telemetry::tracing::SpanProcessorExt;
 use crate::plugins::telemetry::tracing::TracingConfigurator;
 
-impl TracingConfigurator for super::super::otlp::Config {
+const SUBGRAPH_NAME_ATTRIBUTES: [Key; 2] = [
+    Key::from_static_str("apollo.subgraph.name"),
+    Key::from_static_str("subgraph.name"),
+];
+
+impl TracingConfigurator for otlp::Config {
     fn enabled(&self) -> bool {
         self.enabled
     }
@@ -37,3 +50,226 @@ impl TracingConfigurator for super::super::otlp::Config {
         ))
     }
 }
+
+/// Otlp tracing where spans of some subgraphs are sent to their own endpoint.
+pub(crate) struct OtlpTracing<'a> {
+    pub(crate) default: &'a otlp::Config,
+    pub(crate) subgraphs: &'a HashMap<String, otlp::Config>,
+}
+
+impl OtlpTracing<'_> {
+    fn enabled_subgraphs(&self) -> impl Iterator<Item = (&String, &otlp::Config)> {
+        self.subgraphs.iter().filter(|(_, config)| config.enabled)
+    }
+}
+
+impl TracingConfigurator for OtlpTracing<'_> {
+    fn enabled(&self) -> bool {
+        self.default.enabled || self.enabled_subgraphs().next().is_some()
+    }
+
+    fn apply(
+        &self,
+        builder: Builder,
+        common: &TracingCommon,
+        spans_config: &Spans,
+    ) -> Result<Builder, BoxError> {
+        if self.enabled_subgraphs().next().is_none() {
+            return self.default.apply(builder, common, spans_config);
+        }
+
+        let default = if self.default.enabled {
+            let exporter: SpanExporterBuilder = self.default.exporter(TelemetryDataKind::Traces)?;
+            Some(exporter.build_span_exporter()?)
+        } else {
+            None
+        };
+        let mut subgraphs = HashMap::new();
+        for (subgraph_name, config) in self.enabled_subgraphs() {
+            tracing::info!("Configuring Otlp tracing for subgraph {subgraph_name}");
+            let exporter: SpanExporterBuilder = config.exporter(TelemetryDataKind::Traces)?;
+            subgraphs.insert(subgraph_name.clone(), exporter.build_span_exporter()?);
+        }
+
+        tracing::info!(
+            "Configuring Otlp tracing with subgraph routing: {}",
+            self.default.batch_processor
+        );
+        Ok(builder.with_span_processor(
+            BatchSpanProcessor::builder(
+                SubgraphRoutingSpanExporter { default, subgraphs },
+                opentelemetry::runtime::Tokio,
+            )
+            .with_batch_config(self.default.batch_processor.clone().into())
+            .build()
+            .filtered(),
+        ))
+    }
+}
+
+/// Sends spans tagged with a subgraph name to that subgraph's exporter, if it has one,
+/// and every other span to the default exporter.
+#[derive(Debug)]
+struct SubgraphRoutingSpanExporter<E> {
+    default: Option<E>,
+    subgraphs: HashMap<String, E>,
+}
+
+impl<E> SubgraphRoutingSpanExporter<E> {
+    fn route<'a>(&self, span: &'a SpanData) -> Option<Cow<'a, str>> {
+        SUBGRAPH_NAME_ATTRIBUTES
+            .iter()
+            .find_map(|key| span.attributes.get(key))
+            .map(|value| value.as_str())
+            .filter(|subgraph_name| self.subgraphs.contains_key(subgraph_name.as_ref()))
+    }
+}
+
+impl<E: SpanExporter> SpanExporter for SubgraphRoutingSpanExporter<E> {
+    fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+        let mut default_batch = Vec::new();
+        let mut subgraph_batches: HashMap<String, Vec<SpanData>> = HashMap::new();
+        for span in batch {
+            match self.route(&span).map(|name| name.into_owned()) {
+                Some(subgraph_name) => subgraph_batches
+                    .entry(subgraph_name)
+                    .or_default()
+                    .push(span),
+                None => default_batch.push(span),
+            }
+        }
+
+        let mut exports = Vec::new();
+        if let Some(default) = &mut self.default {
+            if !default_batch.is_empty() {
+                exports.push(default.export(default_batch));
+            }
+        }
+        for (subgraph_name, exporter) in &mut self.subgraphs {
+            if let Some(batch) = subgraph_batches.remove(subgraph_name) {
+                exports.push(exporter.export(batch));
+            }
+        }
+
+        Box::pin(async move {
+            futures::future::join_all(exports)
+                .await
+                .into_iter()
+                .collect::<ExportResult>()
+        })
+    }
+
+    fn shutdown(&mut self) {
+        if let Some(default) = &mut self.default {
+            default.shutdown();
+        }
+        for exporter in self.subgraphs.values_mut() {
+            exporter.shutdown();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use opentelemetry::sdk::export::trace::SpanData;
+    use opentelemetry::sdk::trace::EvictedHashMap;
+    use opentelemetry::sdk::trace::EvictedQueue;
+    use opentelemetry::sdk::Resource;
+    use opentelemetry::trace::SpanContext;
+    use opentelemetry::trace::SpanKind;
+    use opentelemetry::trace::Status;
+    use opentelemetry::KeyValue;
+
+    use super::*;
+
+    #[derive(Debug, Default, Clone)]
+    struct RecordingExporter(Arc<Mutex<Vec<String>>>);
+
+    impl SpanExporter for RecordingExporter {
+        fn export(&mut self, batch: Vec<SpanData>) -> BoxFuture<'static, ExportResult> {
+            self.0
+                .lock()
+                .unwrap()
+                .extend(batch.into_iter().map(|span| span.name.to_string()));
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn span(name: &'static str, subgraph_name: Option<&'static str>) -> SpanData {
+        let mut attributes = EvictedHashMap::new(10, 10);
+        if let Some(subgraph_name) = subgraph_name {
+            attributes.insert(KeyValue::new("apollo.subgraph.name", subgraph_name));
+        }
+        SpanData {
+            span_context: SpanContext::empty_context(),
+            parent_span_id: opentelemetry::trace::SpanId::INVALID,
+            span_kind: SpanKind::Internal,
+            name: name.into(),
+            start_time: std::time::SystemTime::now(),
+            end_time: std::time::SystemTime::now(),
+            attributes,
+            events: EvictedQueue::new(10),
+            links: EvictedQueue::new(10),
+            status: Status::Unset,
+            resource: Cow::Owned(Resource::empty()),
+            instrumentation_lib: Default::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn routes_subgraph_spans() {
+        let default = RecordingExporter::default();
+        let products = RecordingExporter::default();
+        let mut exporter = SubgraphRoutingSpanExporter {
+            default: Some(default.clone()),
+            subgraphs: [("products".to_string(), products.clone())]
+                .into_iter()
+                .collect(),
+        };
+
+        exporter
+            .export(vec![
+                span("router", None),
+                span("subgraph products", Some("products")),
+                span("subgraph reviews", Some("reviews")),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *default.0.lock().unwrap(),
+            vec!["router".to_string(), "subgraph reviews".to_string()]
+        );
+        assert_eq!(
+            *products.0.lock().unwrap(),
+            vec!["subgraph products".to_string()]
+        );
+    }
+
+    #[tokio::test]
+    async fn drops_unrouted_spans_without_default() {
+        let products = RecordingExporter::default();
+        let mut exporter = SubgraphRoutingSpanExporter {
+            default: None,
+            subgraphs: [("products".to_string(), products.clone())]
+                .into_iter()
+                .collect(),
+        };
+
+        exporter
+            .export(vec![
+                span("router", None),
+                span("subgraph products", Some("products")),
+            ])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            *products.0.lock().unwrap(),
+            vec!["subgraph products".to_string()]
+        );
+    }
+}
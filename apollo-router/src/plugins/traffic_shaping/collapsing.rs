@@ -0,0 +1,260 @@
+//! Collapse identical client queries in flight. Implemented as a tower Layer.
+//!
+//! Identical queries (same query, operation name, variables, authorization status, `Authorization`
+//! and `Cookie` headers and configured header values) arriving while one of them is executing share
+//! the result of that execution.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::task::Poll;
+
+use futures::future::ready;
+use futures::future::BoxFuture;
+use futures::lock::Mutex;
+use futures::stream::once;
+use futures::StreamExt;
+use http::header::AUTHORIZATION;
+use http::header::COOKIE;
+use http::HeaderMap;
+use http::HeaderName;
+use http::HeaderValue;
+use http::StatusCode;
+use tokio::sync::broadcast::Sender;
+use tokio::sync::broadcast::{self};
+use tokio::sync::oneshot;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+
+use crate::batching::BatchQuery;
+use crate::graphql;
+use crate::plugins::authorization::CacheKeyMetadata;
+use crate::query_planner::OperationKind;
+use crate::services::execution;
+use crate::Context;
+
+type WaitMap = Arc<Mutex<HashMap<CollapsingKey, Sender<Result<CollapsedResponse, String>>>>>;
+
+#[derive(Clone)]
+pub(crate) struct RequestCollapsingLayer {
+    headers: Arc<Vec<HeaderName>>,
+    wait_map: WaitMap,
+}
+
+/// Headers identifying the user, always part of the key so that users never share a response
+const IDENTITY_HEADERS: [HeaderName; 2] = [AUTHORIZATION, COOKIE];
+
+impl RequestCollapsingLayer {
+    pub(crate) fn new(mut headers: Vec<HeaderName>) -> Self {
+        for name in IDENTITY_HEADERS {
+            if !headers.contains(&name) {
+                headers.push(name);
+            }
+        }
+        Self {
+            headers: Arc::new(headers),
+            wait_map: Default::default(),
+        }
+    }
+}
+
+impl Layer<execution::BoxService> for RequestCollapsingLayer {
+    type Service = RequestCollapsingService;
+
+    fn layer(&self, service: execution::BoxService) -> Self::Service {
+        RequestCollapsingService(Some(InnerRequestCollapsingService {
+            service,
+            headers: self.headers.clone(),
+            wait_map: self.wait_map.clone(),
+        }))
+    }
+}
+
+/// Two requests with equal keys produce the same response
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct CollapsingKey {
+    query: Option<String>,
+    operation_name: Option<String>,
+    variables: String,
+    headers: Vec<Option<HeaderValue>>,
+    authorization: CacheKeyMetadata,
+}
+
+impl CollapsingKey {
+    fn new(request: &execution::Request, headers: &[HeaderName]) -> Self {
+        let body = request.supergraph_request.body();
+        Self {
+            query: body.query.clone(),
+            operation_name: body.operation_name.clone(),
+            variables: serde_json::to_string(&body.variables)
+                .expect("serialization should not fail"),
+            headers: headers
+                .iter()
+                .map(|name| request.supergraph_request.headers().get(name).cloned())
+                .collect(),
+            authorization: request
+                .context
+                .extensions()
+                .with_lock(|lock| lock.get::<CacheKeyMetadata>().cloned())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CollapsedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: graphql::Response,
+}
+
+impl CollapsedResponse {
+    async fn from_response(response: execution::Response) -> (Self, Context) {
+        let (parts, mut stream) = response.response.into_parts();
+        // only queries returning a single response are collapsed
+        let body = stream.next().await.unwrap_or_default();
+
+        (
+            Self {
+                status: parts.status,
+                headers: parts.headers,
+                body,
+            },
+            response.context,
+        )
+    }
+
+    fn into_response(self, context: Context) -> execution::Response {
+        let mut response = http::Response::new(once(ready(self.body)).boxed());
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+
+        execution::Response::new_from_response(response, context)
+    }
+}
+
+pub(crate) struct RequestCollapsingService(Option<InnerRequestCollapsingService>);
+
+struct InnerRequestCollapsingService {
+    service: execution::BoxService,
+    headers: Arc<Vec<HeaderName>>,
+    wait_map: WaitMap,
+}
+
+impl Service<execution::Request> for RequestCollapsingService {
+    type Response = execution::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
+        match &mut self.0 {
+            Some(s) => s.service.poll_ready(cx),
+            None => panic!("service should have been called only once"),
+        }
+    }
+
+    fn call(&mut self, request: execution::Request) -> Self::Future {
+        match self.0.take() {
+            None => panic!("service should have been called only once"),
+            Some(s) => Box::pin(s.call_inner(request)),
+        }
+    }
+}
+
+impl InnerRequestCollapsingService {
+    async fn call_inner(
+        mut self,
+        request: execution::Request,
+    ) -> Result<execution::Response, BoxError> {
+        if !is_collapsible(&request) {
+            return self.service.call(request).await;
+        }
+
+        let cache_key = CollapsingKey::new(&request, &self.headers);
+        loop {
+            let mut locked_wait_map = self.wait_map.lock().await;
+            match locked_wait_map.get_mut(&cache_key) {
+                Some(waiter) => {
+                    // Register interest in key
+                    let mut receiver = waiter.subscribe();
+                    drop(locked_wait_map);
+
+                    match receiver.recv().await {
+                        Ok(value) => {
+                            u64_counter!(
+                                "apollo.router.operations.collapsed",
+                                "Number of requests that shared the execution of an identical concurrent request",
+                                1
+                            );
+                            return value
+                                .map(|response| response.into_response(request.context))
+                                .map_err(|e| e.into());
+                        }
+                        // there was an issue with the broadcast channel, retry
+                        Err(_) => continue,
+                    }
+                }
+                None => {
+                    let (tx, _rx) = broadcast::channel(1);
+
+                    locked_wait_map.insert(cache_key.clone(), tx.clone());
+                    drop(locked_wait_map);
+
+                    let res = {
+                        // when _drop_signal is dropped, either by getting out of the block or by
+                        // cancellation, the drop_sentinel future will return with Err(), then we
+                        // remove the entry from the wait map
+                        let (_drop_signal, drop_sentinel) = oneshot::channel::<()>();
+                        let wait_map = self.wait_map.clone();
+                        tokio::task::spawn(async move {
+                            let _ = drop_sentinel.await;
+                            let mut locked_wait_map = wait_map.lock().await;
+                            locked_wait_map.remove(&cache_key);
+                        });
+
+                        match self.service.call(request).await {
+                            Ok(response) => Ok(CollapsedResponse::from_response(response).await),
+                            Err(e) => Err(e),
+                        }
+                    };
+
+                    // Let our waiters know
+                    let broadcast_value = res
+                        .as_ref()
+                        .map(|(response, _)| response.clone())
+                        .map_err(|e| e.to_string());
+                    // ignore the error we get if there are no waiters
+                    let _ = tx.send(broadcast_value);
+
+                    return res.map(|(response, context)| response.into_response(context));
+                }
+            }
+        }
+    }
+}
+
+/// Only queries returning a single response can share their execution
+fn is_collapsible(request: &execution::Request) -> bool {
+    // batched requests coordinate their subgraph fetches, they must all execute
+    if request
+        .context
+        .extensions()
+        .with_lock(|lock| lock.contains_key::<BatchQuery>())
+    {
+        return false;
+    }
+
+    let body = request.supergraph_request.body();
+    let operation_name = body.operation_name.as_deref();
+    let is_query = request
+        .query_plan
+        .query
+        .operation(operation_name)
+        .map(|operation| operation.kind() == &OperationKind::Query)
+        .unwrap_or(false);
+
+    is_query
+        && !request
+            .query_plan
+            .is_deferred(operation_name, &body.variables)
+}
@@ -2,13 +2,16 @@
 //!
 //! Currently includes:
 //! * Query deduplication
+//! * Client request collapsing
 //! * Timeout
 //! * Compression
 //! * Rate limiting
 //!
+mod collapsing;
 mod deduplication;
 pub(crate) mod rate;
 mod retry;
+mod retry_after;
 pub(crate) mod timeout;
 
 use std::collections::HashMap;
@@ -19,20 +22,26 @@ use std::time::Duration;
 use futures::future::BoxFuture;
 use futures::FutureExt;
 use http::header::CONTENT_ENCODING;
+use http::HeaderName;
 use http::HeaderValue;
 use http::StatusCode;
 use schemars::JsonSchema;
 use serde::Deserialize;
 use tower::util::Either;
 use tower::BoxError;
+use tower::Layer;
 use tower::Service;
 use tower::ServiceBuilder;
 use tower::ServiceExt;
 
+use self::collapsing::RequestCollapsingLayer;
 use self::deduplication::QueryDeduplicationLayer;
 use self::rate::RateLimitLayer;
 use self::rate::RateLimited;
 pub(crate) use self::retry::RetryPolicy;
+use self::retry_after::BackingOff;
+use self::retry_after::RetryAfterLayer;
+pub(crate) use self::retry_after::RETRY_AFTER_EXTENSION;
 use self::timeout::Elapsed;
 use self::timeout::TimeoutLayer;
 use crate::error::ConfigurationError;
@@ -41,6 +50,7 @@ use crate::layers::ServiceBuilderExt;
 use crate::plugin::Plugin;
 use crate::plugin::PluginInit;
 use crate::register_plugin;
+use crate::services::execution;
 use crate::services::http::service::Compression;
 use crate::services::subgraph;
 use crate::services::supergraph;
@@ -72,6 +82,10 @@ struct Shaping {
     experimental_retry: Option<RetryConfig>,
     /// Enable HTTP2 for subgraphs
     experimental_http2: Option<Http2Config>,
+    /// Follow redirects returned by subgraphs. Redirects are not followed by default
+    redirects: Option<RedirectPolicy>,
+    /// Back off from subgraphs answering with a 429 or 503 status and a `Retry-After` header
+    experimental_retry_after: Option<RetryAfterConf>,
 }
 
 #[derive(PartialEq, Default, Debug, Clone, Deserialize, JsonSchema)]
@@ -86,6 +100,51 @@ pub(crate) enum Http2Config {
     Http2Only,
 }
 
+/// Redirect policy for subgraph requests
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+pub(crate) struct RedirectPolicy {
+    /// Maximum number of redirects followed for a single request
+    pub(crate) max_hops: u8,
+    /// URL schemes that a redirect can lead to
+    pub(crate) allowed_schemes: Vec<String>,
+    /// Hosts that a redirect can lead to, in addition to the host of the subgraph
+    pub(crate) allowed_hosts: Vec<String>,
+    /// Keep the method and body of the request on 301 and 302 redirects instead of switching to
+    /// GET. 307 and 308 redirects always keep them, 303 redirects always switch to GET
+    pub(crate) preserve_method: bool,
+}
+
+impl Default for RedirectPolicy {
+    fn default() -> Self {
+        Self {
+            max_hops: 3,
+            allowed_schemes: vec!["http".to_string(), "https".to_string()],
+            allowed_hosts: Vec::new(),
+            preserve_method: true,
+        }
+    }
+}
+
+impl RedirectPolicy {
+    /// Whether a redirect can lead to `uri`, for a subgraph hosted on `subgraph_host`
+    pub(crate) fn allows(&self, subgraph_host: &str, uri: &http::Uri) -> bool {
+        let scheme_allowed = uri.scheme_str().is_some_and(|scheme| {
+            self.allowed_schemes
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(scheme))
+        });
+        let host = uri.host().unwrap_or_default();
+        let host_allowed = host.eq_ignore_ascii_case(subgraph_host)
+            || self
+                .allowed_hosts
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(host));
+
+        scheme_allowed && host_allowed
+    }
+}
+
 impl Merge for Shaping {
     fn merge(&self, fallback: Option<&Self>) -> Self {
         match fallback {
@@ -109,6 +168,16 @@ impl Merge for Shaping {
                     .as_ref()
                     .or(fallback.experimental_http2.as_ref())
                     .cloned(),
+                redirects: self
+                    .redirects
+                    .as_ref()
+                    .or(fallback.redirects.as_ref())
+                    .cloned(),
+                experimental_retry_after: self
+                    .experimental_retry_after
+                    .as_ref()
+                    .or(fallback.experimental_retry_after.as_ref())
+                    .cloned(),
             },
         }
     }
@@ -150,6 +219,28 @@ impl Merge for RetryConfig {
     }
 }
 
+/// Retry-After configuration
+#[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct RetryAfterConf {
+    /// Maximum delay the router backs off for, whatever the delay asked by the subgraph
+    #[serde(deserialize_with = "humantime_serde::deserialize")]
+    #[schemars(with = "String")]
+    max_delay: Duration,
+    /// Number of requests sent to the subgraph at the same time while backing off. The other
+    /// requests fail right away
+    max_concurrent_requests: usize,
+}
+
+impl Default for RetryAfterConf {
+    fn default() -> Self {
+        Self {
+            max_delay: Duration::from_secs(60),
+            max_concurrent_requests: 1,
+        }
+    }
+}
+
 // this is a wrapper struct to add subgraph specific options over Shaping
 #[derive(PartialEq, Debug, Clone, Deserialize, JsonSchema)]
 #[serde(deny_unknown_fields)]
@@ -178,6 +269,18 @@ struct RouterShaping {
     #[schemars(with = "String", default)]
     /// Enable timeout for incoming requests
     timeout: Option<Duration>,
+    /// Collapse identical queries received concurrently into a single execution
+    deduplicate_query: Option<RouterDeduplication>,
+}
+
+#[derive(PartialEq, Debug, Clone, Default, Deserialize, JsonSchema)]
+#[serde(deny_unknown_fields, default)]
+struct RouterDeduplication {
+    /// Enable collapsing of identical concurrent queries
+    enabled: bool,
+    /// Request headers that must have the same values for two queries to be collapsed, in
+    /// addition to `authorization` and `cookie`, which are always compared
+    headers: Vec<String>,
 }
 
 #[derive(Debug, Clone, Default, Deserialize, JsonSchema)]
@@ -225,6 +328,8 @@ pub(crate) struct TrafficShaping {
     config: Config,
     rate_limit_router: Option<RateLimitLayer>,
     rate_limit_subgraphs: Mutex<HashMap<String, RateLimitLayer>>,
+    retry_after_subgraphs: Mutex<HashMap<String, RetryAfterLayer>>,
+    request_collapsing: Option<RequestCollapsingLayer>,
 }
 
 #[async_trait::async_trait]
@@ -255,14 +360,46 @@ impl Plugin for TrafficShaping {
             })
             .transpose()?;
 
+        let request_collapsing = init
+            .config
+            .router
+            .as_ref()
+            .and_then(|r| r.deduplicate_query.as_ref())
+            .filter(|deduplication| deduplication.enabled)
+            .map(|deduplication| {
+                deduplication
+                    .headers
+                    .iter()
+                    .map(|name| {
+                        HeaderName::try_from(name.as_str()).map_err(|e| {
+                            ConfigurationError::InvalidConfiguration {
+                                message: "bad configuration for traffic_shaping plugin",
+                                error: format!("invalid header name '{name}': {e}"),
+                            }
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(RequestCollapsingLayer::new)
+            })
+            .transpose()?;
+
         {
             Ok(Self {
                 config: init.config,
                 rate_limit_router,
                 rate_limit_subgraphs: Mutex::new(HashMap::new()),
+                retry_after_subgraphs: Mutex::new(HashMap::new()),
+                request_collapsing,
             })
         }
     }
+
+    fn execution_service(&self, service: execution::BoxService) -> execution::BoxService {
+        match &self.request_collapsing {
+            Some(layer) => layer.layer(service).boxed(),
+            None => service,
+        }
+    }
 }
 
 pub(crate) type TrafficShapingSubgraphFuture<S> = Either<
@@ -382,6 +519,25 @@ impl TrafficShaping {
                         .clone()
                 });
 
+            let retry_after =
+                config
+                    .shaping
+                    .experimental_retry_after
+                    .as_ref()
+                    .map(|retry_after_conf| {
+                        self.retry_after_subgraphs
+                            .lock()
+                            .unwrap()
+                            .entry(name.to_string())
+                            .or_insert_with(|| {
+                                RetryAfterLayer::new(
+                                    retry_after_conf.max_delay,
+                                    retry_after_conf.max_concurrent_requests,
+                                )
+                            })
+                            .clone()
+                    });
+
             let retry = config.shaping.experimental_retry.as_ref().map(|config| {
                 let retry_policy = RetryPolicy::new(
                     config.ttl,
@@ -418,6 +574,14 @@ impl TrafficShaping {
                                             .context(ctx)
                                             .build()
                                     }
+                                    Err(error) if error.is::<BackingOff>() => {
+                                        let backing_off = error.downcast::<BackingOff>().expect("checked above; qed");
+                                        subgraph::Response::error_builder()
+                                            .status_code(StatusCode::TOO_MANY_REQUESTS)
+                                            .error::<graphql::Error>((*backing_off).into())
+                                            .context(ctx)
+                                            .build()
+                                    }
                                     _ => response,
                                 }
                             }.boxed()
@@ -428,6 +592,7 @@ impl TrafficShaping {
                         .timeout
                         .unwrap_or(DEFAULT_TIMEOUT),
                     ))
+                    .option_layer(retry_after)
                     .option_layer(retry)
                     .option_layer(rate_limit)
                 .service(service)
@@ -452,6 +617,15 @@ impl TrafficShaping {
         .and_then(|config| config.shaping.experimental_http2)
         .unwrap_or(Http2Config::Enable)
     }
+
+    pub(crate) fn subgraph_redirect_policy(&self, service_name: &str) -> Option<RedirectPolicy> {
+        Self::merge_config(
+            self.config.all.as_ref(),
+            self.config.subgraphs.get(service_name),
+        )
+        .and_then(|config| config.shaping.redirects)
+        .filter(|policy| policy.max_hops > 0)
+    }
 }
 
 register_plugin!("apollo", "traffic_shaping", TrafficShaping);
@@ -459,6 +633,8 @@ register_plugin!("apollo", "traffic_shaping", TrafficShaping);
 #[cfg(test)]
 mod test {
     use std::num::NonZeroUsize;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
     use std::sync::Arc;
 
     use bytes::Bytes;
@@ -485,6 +661,7 @@ mod test {
     use crate::services::SupergraphRequest;
     use crate::services::SupergraphResponse;
     use crate::Configuration;
+    use crate::TestHarness;
 
     static EXPECTED_RESPONSE: Lazy<Bytes> = Lazy::new(|| {
         Bytes::from_static(r#"{"data":{"topProducts":[{"upc":"1","name":"Table","reviews":[{"id":"1","product":{"name":"Table"},"author":{"id":"1","name":"Ada Lovelace"}},{"id":"4","product":{"name":"Table"},"author":{"id":"2","name":"Alan Turing"}}]},{"upc":"2","name":"Couch","reviews":[{"id":"2","product":{"name":"Couch"},"author":{"id":"1","name":"Ada Lovelace"}}]}]}}"#.as_bytes())
@@ -772,6 +949,48 @@ mod test {
         assert!(shaping_config.enable_subgraph_http2("this_doesnt_exist") == Http2Config::Disable);
     }
 
+    #[tokio::test]
+    async fn test_subgraph_redirect_policy() {
+        let config = serde_yaml::from_str::<Config>(
+            r#"
+        all:
+          redirects:
+            allowed_hosts: [cdn.example.com]
+        subgraphs:
+          products:
+            redirects:
+              max_hops: 0
+        "#,
+        )
+        .unwrap();
+
+        let shaping_config = TrafficShaping::new(PluginInit::fake_builder().config(config).build())
+            .await
+            .unwrap();
+
+        assert!(shaping_config
+            .subgraph_redirect_policy("products")
+            .is_none());
+        let policy = shaping_config.subgraph_redirect_policy("reviews").unwrap();
+        assert_eq!(policy.max_hops, 3);
+        assert!(policy.allows(
+            "reviews.example.com",
+            &"https://reviews.example.com/v2".parse().unwrap()
+        ));
+        assert!(policy.allows(
+            "reviews.example.com",
+            &"http://cdn.example.com/".parse().unwrap()
+        ));
+        assert!(!policy.allows(
+            "reviews.example.com",
+            &"http://169.254.169.254/".parse().unwrap()
+        ));
+        assert!(!policy.allows(
+            "reviews.example.com",
+            &"ftp://reviews.example.com/".parse().unwrap()
+        ));
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn it_rate_limit_subgraph_requests() {
         let config = serde_yaml::from_str::<serde_json::Value>(
@@ -926,4 +1145,84 @@ mod test {
             .errors
             .is_empty());
     }
+
+    async fn collapsed_executions(tenants: [&'static str; 2]) -> usize {
+        collapsed_executions_with_header("x-tenant", tenants).await
+    }
+
+    async fn collapsed_executions_with_header(
+        header: &'static str,
+        values: [&'static str; 2],
+    ) -> usize {
+        let executions = Arc::new(AtomicUsize::new(0));
+        let counter = executions.clone();
+        let service = TestHarness::builder()
+            .configuration_json(serde_json::json!({
+                "traffic_shaping": {
+                    "router": {
+                        "deduplicate_query": {
+                            "enabled": true,
+                            "headers": ["x-tenant"]
+                        }
+                    }
+                }
+            }))
+            .unwrap()
+            .execution_hook(move |service| {
+                let counter = counter.clone();
+                ServiceBuilder::new()
+                    .map_request(move |request: execution::Request| {
+                        counter.fetch_add(1, Ordering::SeqCst);
+                        request
+                    })
+                    .map_future(|future| async move {
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        future.await
+                    })
+                    .service(service)
+                    .boxed()
+            })
+            .build_supergraph()
+            .await
+            .unwrap();
+
+        let request = |value: &str| {
+            SupergraphRequest::fake_builder()
+                .query("{ topProducts { upc name } }")
+                .header(header, value)
+                .build()
+                .unwrap()
+        };
+        let (first, second) = tokio::join!(
+            service.clone().oneshot(request(values[0])),
+            service.clone().oneshot(request(values[1]))
+        );
+        let first = first.unwrap().next_response().await.unwrap();
+        let second = second.unwrap().next_response().await.unwrap();
+        assert_eq!(first, second);
+
+        executions.load(Ordering::SeqCst)
+    }
+
+    #[tokio::test]
+    async fn it_collapses_identical_concurrent_queries() {
+        assert_eq!(collapsed_executions(["a", "a"]).await, 1);
+    }
+
+    #[tokio::test]
+    async fn it_does_not_collapse_queries_with_different_headers() {
+        assert_eq!(collapsed_executions(["a", "b"]).await, 2);
+    }
+
+    #[tokio::test]
+    async fn it_does_not_collapse_queries_of_different_users() {
+        assert_eq!(
+            collapsed_executions_with_header("authorization", ["Bearer a", "Bearer b"]).await,
+            2
+        );
+        assert_eq!(
+            collapsed_executions_with_header("cookie", ["session=a", "session=b"]).await,
+            2
+        );
+    }
 }
@@ -0,0 +1,255 @@
+//! Back off from subgraphs asking for it with a `Retry-After` header
+//!
+//! When a subgraph answers with a 429 or 503 status and a `Retry-After` header, the router sends
+//! it only a few requests at a time until the delay expires. The other requests fail right away
+//! instead of adding to the load of the subgraph.
+
+use std::error;
+use std::fmt;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::Context;
+use std::task::Poll;
+use std::time::Duration;
+use std::time::Instant;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use http::header::RETRY_AFTER;
+use http::StatusCode;
+use tokio::sync::Semaphore;
+use tower::BoxError;
+use tower::Layer;
+use tower::Service;
+
+use crate::graphql;
+use crate::services::subgraph;
+
+pub(crate) const RETRY_AFTER_EXTENSION: &str = "retry_after_ms";
+
+/// The subgraph asked the router to back off.
+#[derive(Debug)]
+pub(crate) struct BackingOff {
+    remaining: Duration,
+}
+
+impl fmt::Display for BackingOff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad("the subgraph asked to retry later")
+    }
+}
+
+impl From<BackingOff> for graphql::Error {
+    fn from(backing_off: BackingOff) -> Self {
+        graphql::Error::builder()
+            .message(String::from("The subgraph asked to retry later"))
+            .extension_code("SUBREQUEST_BACKING_OFF")
+            .extension(RETRY_AFTER_EXTENSION, millis(backing_off.remaining))
+            .build()
+    }
+}
+
+impl error::Error for BackingOff {}
+
+fn millis(duration: Duration) -> u64 {
+    u64::try_from(duration.as_millis()).unwrap_or(u64::MAX)
+}
+
+#[derive(Debug)]
+struct State {
+    /// End of the current back off
+    until: Mutex<Option<Instant>>,
+    /// Requests allowed during a back off
+    permits: Arc<Semaphore>,
+    max_delay: Duration,
+}
+
+impl State {
+    fn remaining(&self) -> Option<Duration> {
+        let mut until = self.until.lock().expect("lock poisoned");
+        match *until {
+            Some(instant) => {
+                let remaining = instant.checked_duration_since(Instant::now());
+                if remaining.is_none() {
+                    *until = None;
+                }
+                remaining
+            }
+            None => None,
+        }
+    }
+
+    fn back_off(&self, delay: Duration) {
+        let instant = Instant::now() + delay;
+        let mut until = self.until.lock().expect("lock poisoned");
+        if until.map_or(true, |until| until < instant) {
+            *until = Some(instant);
+        }
+    }
+}
+
+/// Delay requested by a 429 or 503 response. Only delays in seconds are supported, not HTTP
+/// dates
+fn retry_after(response: &subgraph::Response) -> Option<Duration> {
+    let status = response.response.status();
+    if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+        return None;
+    }
+    let seconds = response
+        .response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+/// Limits the requests sent to a subgraph while it asks the router to back off
+#[derive(Debug, Clone)]
+pub(crate) struct RetryAfterLayer {
+    state: Arc<State>,
+}
+
+impl RetryAfterLayer {
+    pub(crate) fn new(max_delay: Duration, max_concurrent_requests: usize) -> Self {
+        Self {
+            state: Arc::new(State {
+                until: Mutex::new(None),
+                permits: Arc::new(Semaphore::new(max_concurrent_requests)),
+                max_delay,
+            }),
+        }
+    }
+}
+
+impl<S> Layer<S> for RetryAfterLayer {
+    type Service = RetryAfter<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RetryAfter {
+            inner,
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct RetryAfter<S> {
+    inner: S,
+    state: Arc<State>,
+}
+
+impl<S> Service<subgraph::Request> for RetryAfter<S>
+where
+    S: Service<subgraph::Request, Response = subgraph::Response>,
+    S::Error: Into<BoxError>,
+    S::Future: Send + 'static,
+{
+    type Response = subgraph::Response;
+    type Error = BoxError;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, request: subgraph::Request) -> Self::Future {
+        let permit = match self.state.remaining() {
+            Some(remaining) => match self.state.permits.clone().try_acquire_owned() {
+                Ok(permit) => Some(permit),
+                Err(_) => {
+                    return futures::future::ready(Err(BackingOff { remaining }.into())).boxed()
+                }
+            },
+            None => None,
+        };
+
+        let state = self.state.clone();
+        let future = self.inner.call(request);
+        async move {
+            let mut response = future.await.map_err(Into::into)?;
+            drop(permit);
+
+            if let Some(delay) = retry_after(&response) {
+                let delay = delay.min(state.max_delay);
+                tracing::debug!(
+                    "subgraph({}) asked to retry after {delay:?}",
+                    response.subgraph_name.as_deref().unwrap_or_default()
+                );
+                state.back_off(delay);
+                for error in response.response.body_mut().errors.iter_mut() {
+                    error
+                        .extensions
+                        .insert(RETRY_AFTER_EXTENSION, millis(delay).into());
+                }
+            }
+            Ok(response)
+        }
+        .boxed()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn service(layer: &RetryAfterLayer) -> RetryAfter<subgraph::BoxService> {
+        layer.layer(
+            tower::service_fn(|request: subgraph::Request| async move {
+                let response = http::Response::builder()
+                    .status(StatusCode::TOO_MANY_REQUESTS)
+                    .header(RETRY_AFTER, "120")
+                    .body(
+                        graphql::Response::builder()
+                            .error(graphql::Error::builder().message("slow down").build())
+                            .build(),
+                    )
+                    .unwrap();
+                Ok(subgraph::Response::new_from_response(
+                    response,
+                    request.context,
+                    "products".to_string(),
+                ))
+            })
+            .boxed(),
+        )
+    }
+
+    #[tokio::test]
+    async fn backs_off_after_retry_after() {
+        let layer = RetryAfterLayer::new(Duration::from_secs(60), 1);
+
+        let response = service(&layer)
+            .oneshot(subgraph::Request::fake_builder().build())
+            .await
+            .unwrap();
+        // the delay is capped
+        assert_eq!(
+            response.response.body().errors[0]
+                .extensions
+                .get(RETRY_AFTER_EXTENSION),
+            Some(&60_000.into())
+        );
+
+        // the permit of a request in flight is held until it completes
+        let permit = layer.state.permits.clone().try_acquire_owned().unwrap();
+        let error = service(&layer)
+            .oneshot(subgraph::Request::fake_builder().build())
+            .await
+            .unwrap_err();
+        let backing_off = error.downcast::<BackingOff>().unwrap();
+        assert!(backing_off.remaining <= Duration::from_secs(60));
+        drop(permit);
+
+        // one request at a time is still sent during the back off
+        assert!(service(&layer)
+            .oneshot(subgraph::Request::fake_builder().build())
+            .await
+            .is_ok());
+    }
+}
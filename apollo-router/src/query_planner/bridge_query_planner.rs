@@ -42,6 +42,7 @@ use crate::json_ext::Path;
 use crate::metrics::meter_provider;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::authorization::CacheKeyMetadata;
+use crate::plugins::authorization::Directives;
 use crate::plugins::authorization::UnauthorizedPaths;
 use crate::plugins::progressive_override::LABELS_TO_OVERRIDE_KEY;
 use crate::plugins::telemetry::config::ApolloSignatureNormalizationAlgorithm;
@@ -55,6 +56,7 @@ use crate::services::layers::query_analysis::ParsedDocumentInner;
 use crate::services::QueryPlannerContent;
 use crate::services::QueryPlannerRequest;
 use crate::services::QueryPlannerResponse;
+use crate::spec::interning::Interner;
 use crate::spec::operation_limits::OperationLimits;
 use crate::spec::query::change::QueryHashVisitor;
 use crate::spec::Query;
@@ -76,6 +78,7 @@ pub(crate) struct BridgeQueryPlanner {
     introspection: Option<Arc<Introspection>>,
     configuration: Arc<Configuration>,
     enable_authorization_directives: bool,
+    authorization_directives: Directives,
     _federation_instrument: ObservableGauge<u64>,
     signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm,
 }
@@ -297,10 +300,13 @@ impl PlannerMode {
 
     async fn subgraphs(
         &self,
+        schema: &Schema,
     ) -> Result<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>, ServiceBuildError> {
         let js = match self {
             PlannerMode::Js(js) => js,
             PlannerMode::Both { js, .. } => js,
+            // the schemas are not interned: their types and fields are shared with the schemas of
+            // the native query planner, and interning would copy each of them
             PlannerMode::Rust { rust, .. } => {
                 return Ok(rust
                     .subgraph_schemas()
@@ -309,13 +315,17 @@ impl PlannerMode {
                     .collect())
             }
         };
+        // the subgraph schemas share their names and descriptions with the supergraph schema
+        let interner = Interner::new(schema.supergraph_schema());
         js.subgraphs()
             .await?
             .into_iter()
             .map(|(name, schema_str)| {
-                let schema = apollo_compiler::Schema::parse_and_validate(schema_str, "")
-                    .map_err(|errors| SchemaError::Validate(errors.into()))?;
-                Ok((name, Arc::new(schema)))
+                let mut schema = apollo_compiler::Schema::parse_and_validate(schema_str, "")
+                    .map_err(|errors| SchemaError::Validate(errors.into()))?
+                    .into_inner();
+                interner.intern(&mut schema);
+                Ok((name, Arc::new(Valid::assume_valid(schema))))
             })
             .collect()
     }
@@ -330,7 +340,7 @@ impl BridgeQueryPlanner {
         let schema = Schema::parse(&schema, &configuration)?;
         let planner = PlannerMode::new(&schema, &configuration, old_planner).await?;
 
-        let subgraph_schemas = Arc::new(planner.subgraphs().await?);
+        let subgraph_schemas = Arc::new(planner.subgraphs(&schema).await?);
 
         let introspection = if configuration.supergraph.introspection {
             Some(Arc::new(
@@ -347,6 +357,7 @@ impl BridgeQueryPlanner {
 
         let enable_authorization_directives =
             AuthorizationPlugin::enable_directives(&configuration, &schema)?;
+        let authorization_directives = AuthorizationPlugin::directives(&configuration)?;
         let federation_instrument = federation_version_instrument(schema.federation_version());
         let signature_normalization_algorithm =
             TelemetryConfig::signature_normalization_algorithm(&configuration);
@@ -357,6 +368,7 @@ impl BridgeQueryPlanner {
             subgraph_schemas,
             introspection,
             enable_authorization_directives,
+            authorization_directives,
             configuration,
             _federation_instrument: federation_instrument,
             signature_normalization_algorithm,
@@ -411,7 +423,7 @@ impl BridgeQueryPlanner {
             filtered_query: None,
             unauthorized: UnauthorizedPaths {
                 paths: vec![],
-                errors: AuthorizationPlugin::log_errors(&self.configuration),
+                errors: self.authorization_directives.errors.clone(),
             },
             subselections,
             defer_stats,
@@ -420,11 +432,15 @@ impl BridgeQueryPlanner {
         })
     }
 
-    async fn introspection(&self, query: String) -> Result<QueryPlannerContent, QueryPlannerError> {
+    async fn introspection(
+        &self,
+        query: String,
+        hash: QueryHash,
+    ) -> Result<QueryPlannerContent, QueryPlannerError> {
         match self.introspection.as_ref() {
             Some(introspection) => {
                 let response = introspection
-                    .execute(query)
+                    .execute(query, hash)
                     .await
                     .map_err(QueryPlannerError::Introspection)?;
 
@@ -754,8 +770,14 @@ impl BridgeQueryPlanner {
         mut key: QueryKey,
         mut doc: ParsedDocument,
     ) -> Result<QueryPlannerContent, QueryPlannerError> {
+        // introspection responses are computed from the original query, even if it is filtered
+        let original_hash = (*doc.hash).clone();
         let filter_res = if self.enable_authorization_directives {
-            match AuthorizationPlugin::filter_query(&self.configuration, &key, &self.schema) {
+            match AuthorizationPlugin::filter_query(
+                &self.authorization_directives,
+                &key,
+                &self.schema,
+            ) {
                 Err(QueryPlannerError::Unauthorized(unauthorized_paths)) => {
                     let response = graphql::Response::builder()
                         .data(Object::new())
@@ -846,7 +868,7 @@ impl BridgeQueryPlanner {
                     response: Box::new(graphql::Response::builder().data(data).build()),
                 });
             } else {
-                return self.introspection(key.original_query).await;
+                return self.introspection(key.original_query, original_hash).await;
             }
         }
 
@@ -1017,6 +1039,80 @@ mod tests {
         .await;
     }
 
+    #[test(tokio::test)]
+    async fn subgraph_schemas_share_names_with_the_supergraph() {
+        let planner = BridgeQueryPlanner::new(EXAMPLE_SCHEMA.to_string(), Default::default(), None)
+            .await
+            .unwrap();
+        let schema = planner.schema();
+        let supergraph = schema.supergraph_schema();
+        let subgraph_schemas = planner.subgraph_schemas();
+        assert!(!subgraph_schemas.is_empty());
+
+        for subgraph in subgraph_schemas.values() {
+            for (type_name, ty) in &subgraph.types {
+                let Some((supergraph_name, supergraph_ty)) =
+                    supergraph.types.get_key_value(type_name)
+                else {
+                    continue;
+                };
+                assert!(std::ptr::eq(
+                    type_name.as_str().as_ptr(),
+                    supergraph_name.as_str().as_ptr()
+                ));
+                assert!(std::ptr::eq(
+                    ty.name().as_str().as_ptr(),
+                    supergraph_ty.name().as_str().as_ptr()
+                ));
+            }
+        }
+    }
+
+    #[test(tokio::test)]
+    async fn native_subgraph_schemas_are_not_copied() {
+        use apollo_compiler::schema::ExtendedType;
+
+        fn same_node(left: &ExtendedType, right: &ExtendedType) -> bool {
+            match (left, right) {
+                (ExtendedType::Scalar(left), ExtendedType::Scalar(right)) => left.ptr_eq(right),
+                (ExtendedType::Object(left), ExtendedType::Object(right)) => left.ptr_eq(right),
+                (ExtendedType::Interface(left), ExtendedType::Interface(right)) => {
+                    left.ptr_eq(right)
+                }
+                (ExtendedType::Union(left), ExtendedType::Union(right)) => left.ptr_eq(right),
+                (ExtendedType::Enum(left), ExtendedType::Enum(right)) => left.ptr_eq(right),
+                (ExtendedType::InputObject(left), ExtendedType::InputObject(right)) => {
+                    left.ptr_eq(right)
+                }
+                _ => false,
+            }
+        }
+
+        let configuration = Configuration {
+            experimental_query_planner_mode: QueryPlannerMode::New,
+            ..Default::default()
+        };
+        let planner = BridgeQueryPlanner::new(
+            include_str!("../testdata/minimal_fed2_supergraph.graphql").into(),
+            Arc::new(configuration),
+            None,
+        )
+        .await
+        .unwrap();
+        let PlannerMode::Rust { rust, .. } = &planner.planner else {
+            panic!("expected the native query planner");
+        };
+        let subgraph_schemas = planner.subgraph_schemas();
+        assert!(!subgraph_schemas.is_empty());
+
+        for (name, schema) in rust.subgraph_schemas().iter() {
+            let subgraph = subgraph_schemas.get(&name.to_string()).unwrap();
+            for (type_name, ty) in &schema.schema().types {
+                assert!(same_node(ty, &subgraph.types[type_name]));
+            }
+        }
+    }
+
     #[test(tokio::test)]
     async fn empty_query_plan_should_be_a_planner_error() {
         let schema = Schema::parse(EXAMPLE_SCHEMA, &Default::default()).unwrap();
@@ -26,17 +26,21 @@ use tracing::Instrument;
 use super::fetch::QueryHash;
 use crate::cache::storage::InMemoryCache;
 use crate::cache::DeduplicatingCache;
+use crate::context::keys;
 use crate::error::CacheResolverError;
 use crate::error::QueryPlannerError;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::authorization::CacheKeyMetadata;
+use crate::plugins::authorization::ScopesConfig;
 use crate::plugins::progressive_override::LABELS_TO_OVERRIDE_KEY;
 use crate::plugins::telemetry::utils::Timer;
 use crate::query_planner::fetch::SubgraphSchemas;
 use crate::query_planner::labeler::add_defer_labels;
 use crate::query_planner::BridgeQueryPlannerPool;
+use crate::query_planner::QueryPlan;
 use crate::query_planner::QueryPlanResult;
 use crate::services::layers::persisted_queries::PersistedQueryLayer;
+use crate::services::layers::query_analysis::OriginalUsageReporting;
 use crate::services::layers::query_analysis::ParsedDocument;
 use crate::services::layers::query_analysis::QueryAnalysisLayer;
 use crate::services::query_planner;
@@ -53,6 +57,9 @@ pub(crate) type Plugins = IndexMap<String, Box<dyn QueryPlannerPlugin>>;
 pub(crate) type InMemoryCachePlanner =
     InMemoryCache<CachingQueryKey, Result<QueryPlannerContent, Arc<QueryPlannerError>>>;
 pub(crate) const APOLLO_OPERATION_ID: &str = "apollo_operation_id";
+/// Context entry whose value is added to the query plan cache key, to separate query plans
+/// per tenant or any other criteria set by a plugin
+pub(crate) const CONTEXT_CACHE_KEY: &str = keys::QUERY_PLAN_CACHE_KEY.name();
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize)]
 pub(crate) enum ConfigMode {
@@ -76,9 +83,11 @@ pub(crate) struct CachingQueryPlanner<T: Clone> {
     subgraph_schemas: Arc<HashMap<String, Arc<Valid<apollo_compiler::Schema>>>>,
     plugins: Arc<Plugins>,
     enable_authorization_directives: bool,
+    authorization_scopes: ScopesConfig,
     config_mode: ConfigMode,
     introspection: bool,
     legacy_introspection_caching: bool,
+    evaluate_conditions: bool,
 }
 
 fn init_query_plan_from_redis(
@@ -141,12 +150,17 @@ where
             subgraph_schemas,
             plugins: Arc::new(plugins),
             enable_authorization_directives,
+            authorization_scopes: AuthorizationPlugin::scopes_config(configuration)?,
             config_mode,
             introspection: configuration.supergraph.introspection,
             legacy_introspection_caching: configuration
                 .supergraph
                 .query_planning
                 .legacy_introspection_caching,
+            evaluate_conditions: configuration
+                .supergraph
+                .query_planning
+                .experimental_evaluate_conditions,
         })
     }
 
@@ -194,6 +208,7 @@ where
                                 hash,
                                 metadata,
                                 plan_options,
+                                extra_key,
                                 config_mode: _,
                                 schema_id: _,
                                 introspection: _,
@@ -205,6 +220,7 @@ where
                             hash: Some(hash.clone()),
                             metadata: metadata.clone(),
                             plan_options: plan_options.clone(),
+                            extra_key: extra_key.clone(),
                             config_mode: self.config_mode.clone(),
                             introspection: self.introspection,
                         },
@@ -247,6 +263,7 @@ where
                         hash: None,
                         metadata: CacheKeyMetadata::default(),
                         plan_options: PlanOptions::default(),
+                        extra_key: None,
                         config_mode: self.config_mode.clone(),
                         introspection: self.introspection,
                     });
@@ -264,6 +281,7 @@ where
             hash,
             metadata,
             plan_options,
+            extra_key,
             config_mode: _,
             introspection: _,
         } in all_cache_keys
@@ -284,6 +302,7 @@ where
                 schema_id: Arc::clone(&self.schema.schema_id),
                 metadata,
                 plan_options,
+                extra_key,
                 config_mode: self.config_mode.clone(),
                 introspection: self.introspection,
             };
@@ -441,7 +460,7 @@ where
         request: query_planner::CachingRequest,
     ) -> Result<<T as tower::Service<QueryPlannerRequest>>::Response, CacheResolverError> {
         if self.enable_authorization_directives {
-            AuthorizationPlugin::update_cache_key(&request.context);
+            AuthorizationPlugin::update_cache_key(&request.context, &self.authorization_scopes);
         }
 
         let plan_options = PlanOptions {
@@ -474,13 +493,31 @@ where
             .with_lock(|lock| lock.get::<CacheKeyMetadata>().cloned())
             .unwrap_or_default();
 
+        let extra_key = request
+            .context
+            .get_json_value(CONTEXT_CACHE_KEY)
+            .map(|value| {
+                let mut hasher = Sha256::new();
+                hasher.update(serde_json::to_vec(&value).expect("serialization should not fail"));
+                hex::encode(hasher.finalize())
+            });
+
+        // when conditions are evaluated, the parsed document is the normalized operation, and
+        // operations normalized to the same document share their cache entry
+        let mut query = if self.evaluate_conditions {
+            doc.ast.to_string()
+        } else {
+            request.query.clone()
+        };
+
         let caching_key = CachingQueryKey {
-            query: request.query.clone(),
+            query: query.clone(),
             operation: request.operation_name.to_owned(),
             hash: doc.hash.clone(),
             schema_id: Arc::clone(&self.schema.schema_id),
             metadata,
             plan_options,
+            extra_key,
             config_mode: self.config_mode.clone(),
             introspection: self.introspection,
         };
@@ -492,11 +529,23 @@ where
                 init_query_plan_from_redis(&self.subgraph_schemas, v)
             })
             .await;
+        // operations are filtered while they are planned, so a cached plan reuses the filtering
+        // result of the same operation with the same claims
+        if self.enable_authorization_directives
+            && AuthorizationPlugin::requires_authorization(&context)
+        {
+            u64_counter!(
+                "apollo.router.authorization.filter_cache",
+                "Number of operations using authorization directives, by whether their filtering result was reused from the query plan cache",
+                1,
+                result = if entry.is_first() { "miss" } else { "hit" }
+            );
+        }
         if entry.is_first() {
             let query_planner::CachingRequest {
-                mut query,
                 operation_name,
                 context,
+                ..
             } = request;
 
             let schema = self.schema.api_schema();
@@ -539,9 +588,7 @@ where
 
                             // This will be overridden when running in ApolloMetricsGenerationMode::New mode
                             if let Some(QueryPlannerContent::Plan { plan, .. }) = &content {
-                                context.extensions().with_lock(|mut lock| {
-                                    lock.insert::<Arc<UsageReporting>>(plan.usage_reporting.clone())
-                                });
+                                insert_usage_reporting(&context, plan);
                             }
                             Ok(QueryPlannerResponse {
                                 content,
@@ -576,9 +623,7 @@ where
             match res {
                 Ok(content) => {
                     if let QueryPlannerContent::Plan { plan, .. } = &content {
-                        context.extensions().with_lock(|mut lock| {
-                            lock.insert::<Arc<UsageReporting>>(plan.usage_reporting.clone())
-                        });
+                        insert_usage_reporting(&context, plan);
                     }
 
                     Ok(QueryPlannerResponse::builder()
@@ -613,6 +658,18 @@ where
     }
 }
 
+/// When the operation's conditions were evaluated before query planning, the plan was computed
+/// from the normalized operation, and the operation sent by the client is reported instead
+fn insert_usage_reporting(context: &Context, plan: &QueryPlan) {
+    context.extensions().with_lock(|mut lock| {
+        let usage_reporting = match lock.get::<OriginalUsageReporting>() {
+            Some(original) => original.0.clone(),
+            None => plan.usage_reporting.clone(),
+        };
+        lock.insert::<Arc<UsageReporting>>(usage_reporting)
+    });
+}
+
 fn stats_report_key_hash(stats_report_key: &str) -> String {
     let mut hasher = sha1::Sha1::new();
     hasher.update(stats_report_key.as_bytes());
@@ -628,6 +685,8 @@ pub(crate) struct CachingQueryKey {
     pub(crate) hash: Arc<QueryHash>,
     pub(crate) metadata: CacheKeyMetadata,
     pub(crate) plan_options: PlanOptions,
+    /// hash of the `apollo_query_plan_cache::key` context entry
+    pub(crate) extra_key: Option<String>,
     pub(crate) config_mode: ConfigMode,
     pub(crate) introspection: bool,
 }
@@ -651,6 +710,9 @@ impl std::fmt::Display for CachingQueryKey {
             .update(serde_json::to_vec(&self.config_mode).expect("serialization should not fail"));
         hasher.update(&*self.schema_id);
         hasher.update([self.introspection as u8]);
+        if let Some(extra_key) = &self.extra_key {
+            hasher.update(extra_key);
+        }
         let metadata = hex::encode(hasher.finalize());
 
         write!(
@@ -668,6 +730,7 @@ impl Hash for CachingQueryKey {
         self.operation.hash(state);
         self.metadata.hash(state);
         self.plan_options.hash(state);
+        self.extra_key.hash(state);
         self.config_mode.hash(state);
         self.introspection.hash(state);
     }
@@ -680,6 +743,7 @@ pub(crate) struct WarmUpCachingQueryKey {
     pub(crate) hash: Option<Arc<QueryHash>>,
     pub(crate) metadata: CacheKeyMetadata,
     pub(crate) plan_options: PlanOptions,
+    pub(crate) extra_key: Option<String>,
     pub(crate) config_mode: ConfigMode,
     pub(crate) introspection: bool,
 }
@@ -695,7 +759,6 @@ mod tests {
     use super::*;
     use crate::error::PlanErrors;
     use crate::json_ext::Object;
-    use crate::query_planner::QueryPlan;
     use crate::spec::Query;
     use crate::spec::Schema;
     use crate::Configuration;
@@ -899,6 +962,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn context_entry_changes_cache_key() {
+        let key = CachingQueryKey {
+            query: "{ me { id } }".to_string(),
+            schema_id: Arc::new("schema".to_string()),
+            operation: None,
+            hash: Arc::new(QueryHash(vec![1, 2, 3])),
+            metadata: CacheKeyMetadata::default(),
+            plan_options: PlanOptions::default(),
+            extra_key: None,
+            config_mode: ConfigMode::Js(Arc::new(QueryPlannerConfig::default())),
+            introspection: false,
+        };
+        let tenant_a = CachingQueryKey {
+            extra_key: Some("a".to_string()),
+            ..key.clone()
+        };
+        let tenant_b = CachingQueryKey {
+            extra_key: Some("b".to_string()),
+            ..key.clone()
+        };
+
+        assert_ne!(key.to_string(), tenant_a.to_string());
+        assert_ne!(tenant_a.to_string(), tenant_b.to_string());
+        assert_eq!(tenant_a.to_string(), tenant_a.clone().to_string());
+        assert_ne!(tenant_a, tenant_b);
+    }
+
     #[test(tokio::test)]
     async fn test_introspection_cache() {
         let mut delegate = MockMyQueryPlanner::new();
@@ -22,6 +22,7 @@ use crate::json_ext::Object;
 use crate::json_ext::Path;
 use crate::json_ext::Value;
 use crate::json_ext::ValueExt;
+use crate::plugins::expose_null_propagation::record_subgraph_errors;
 use crate::plugins::subscription::SubscriptionConfig;
 use crate::query_planner::FlattenNode;
 use crate::query_planner::Primary;
@@ -243,6 +244,12 @@ impl PlanNode {
                                 "apollo_private.sent_time_offset" = fetch_time_offset
                             ))
                             .await;
+                        record_subgraph_errors(
+                            parameters.context,
+                            &fetch_node.service_name,
+                            current_dir,
+                            &e,
+                        );
                         value = v;
                         errors = e;
                     }
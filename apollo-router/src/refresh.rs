@@ -0,0 +1,235 @@
+//! Scheduling of the background refreshes of remote sources, like JWKS and Apollo Uplink.
+//!
+//! A source is refreshed ahead of the end of its refresh interval, at a random point of the last
+//! part of the interval, so that routers started together do not all refresh at the same time.
+//! Failed refreshes are retried with an exponential backoff, bounded by the refresh interval.
+//! The state of every source is kept in a registry, reported by the `background_refresh` plugin,
+//! which can also trigger an immediate refresh of a source.
+
+use std::fmt::Display;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+use std::time::Instant;
+
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use rand::Rng;
+use serde::Serialize;
+use tokio::sync::Notify;
+
+/// Sources that were registered and are not dropped yet, in registration order
+static SOURCES: Lazy<Mutex<Vec<Weak<RefreshSource>>>> = Lazy::new(Default::default);
+
+/// Part of the refresh interval at the end of which the next refresh happens
+const REFRESH_AHEAD: f64 = 0.2;
+/// Delay before retrying the first failed refresh, doubled on every consecutive failure
+const MIN_BACKOFF: Duration = Duration::from_secs(1);
+
+/// A remote source refreshed in the background
+#[derive(Debug)]
+pub(crate) struct RefreshSource {
+    kind: &'static str,
+    name: String,
+    registered_at: Instant,
+    state: Mutex<RefreshState>,
+    /// Wakes up the source waiting for its next refresh
+    trigger: Notify,
+}
+
+#[derive(Debug, Default)]
+struct RefreshState {
+    last_success: Option<Instant>,
+    last_error: Option<String>,
+    consecutive_failures: u32,
+    next_refresh: Option<Instant>,
+}
+
+impl RefreshSource {
+    /// Registers a source, reported until the returned value is dropped
+    pub(crate) fn register(kind: &'static str, name: impl Into<String>) -> Arc<Self> {
+        let source = Arc::new(RefreshSource {
+            kind,
+            name: name.into(),
+            registered_at: Instant::now(),
+            state: Mutex::new(RefreshState::default()),
+            trigger: Notify::new(),
+        });
+        let mut sources = SOURCES.lock();
+        sources.retain(|source| source.strong_count() > 0);
+        sources.push(Arc::downgrade(&source));
+        source
+    }
+
+    /// Sources alive, in registration order
+    pub(crate) fn all() -> Vec<Arc<RefreshSource>> {
+        SOURCES.lock().iter().filter_map(Weak::upgrade).collect()
+    }
+
+    pub(crate) fn kind(&self) -> &'static str {
+        self.kind
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Waits for the delay until the next refresh, or until a refresh is triggered
+    pub(crate) async fn wait(&self, delay: Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(delay) => {}
+            _ = self.trigger.notified() => {}
+        }
+    }
+
+    /// Refreshes the source now. If the source is refreshing, it refreshes again right after
+    pub(crate) fn refresh_now(&self) {
+        self.trigger.notify_one();
+    }
+
+    /// Records a successful refresh, and returns the delay until the next one
+    pub(crate) fn succeeded(&self, interval: Duration) -> Duration {
+        let delay = refresh_ahead(interval);
+        let mut state = self.state.lock();
+        state.last_success = Some(Instant::now());
+        state.last_error = None;
+        state.consecutive_failures = 0;
+        state.next_refresh = Some(Instant::now() + delay);
+        delay
+    }
+
+    /// Records a failed refresh, and returns the delay until it is retried
+    pub(crate) fn failed(&self, interval: Duration, error: impl Display) -> Duration {
+        let mut state = self.state.lock();
+        state.consecutive_failures = state.consecutive_failures.saturating_add(1);
+        state.last_error = Some(error.to_string());
+        let delay = backoff(state.consecutive_failures, interval);
+        state.next_refresh = Some(Instant::now() + delay);
+        drop(state);
+
+        u64_counter!(
+            "apollo.router.refresh.failures",
+            "Number of failed refreshes of remote sources",
+            1,
+            kind = self.kind,
+            source = self.name.clone()
+        );
+        f64_histogram!(
+            "apollo.router.refresh.backoff",
+            "Delay before retrying a failed refresh of a remote source",
+            delay.as_secs_f64(),
+            kind = self.kind,
+            source = self.name.clone()
+        );
+        delay
+    }
+
+    /// Time since the last successful refresh, or since the registration if there was none
+    pub(crate) fn staleness(&self) -> Duration {
+        self.state
+            .lock()
+            .last_success
+            .unwrap_or(self.registered_at)
+            .elapsed()
+    }
+
+    pub(crate) fn consecutive_failures(&self) -> u32 {
+        self.state.lock().consecutive_failures
+    }
+
+    pub(crate) fn status(&self) -> RefreshStatus {
+        let state = self.state.lock();
+        RefreshStatus {
+            kind: self.kind,
+            source: self.name.clone(),
+            last_success_seconds_ago: state.last_success.map(|at| at.elapsed().as_secs()),
+            next_refresh_in_seconds: state
+                .next_refresh
+                .map(|at| at.saturating_duration_since(Instant::now()).as_secs()),
+            consecutive_failures: state.consecutive_failures,
+            last_error: state.last_error.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct RefreshStatus {
+    kind: &'static str,
+    source: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_success_seconds_ago: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    next_refresh_in_seconds: Option<u64>,
+    consecutive_failures: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_error: Option<String>,
+}
+
+/// Delay until the next refresh, in the last part of the interval
+fn refresh_ahead(interval: Duration) -> Duration {
+    interval.mul_f64(1.0 - REFRESH_AHEAD * rand::thread_rng().gen::<f64>())
+}
+
+/// Delay until a failed refresh is retried, between half and all of the exponential backoff
+fn backoff(consecutive_failures: u32, interval: Duration) -> Duration {
+    let backoff = MIN_BACKOFF
+        .saturating_mul(2u32.saturating_pow(consecutive_failures.saturating_sub(1)))
+        .min(interval);
+    backoff.mul_f64(1.0 - 0.5 * rand::thread_rng().gen::<f64>())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn refreshes_ahead_of_the_interval() {
+        let interval = Duration::from_secs(100);
+        for _ in 0..100 {
+            let delay = refresh_ahead(interval);
+            assert!(delay >= Duration::from_secs(80) && delay <= interval);
+        }
+    }
+
+    #[test]
+    fn backs_off_exponentially_up_to_the_interval() {
+        let interval = Duration::from_secs(10);
+        for _ in 0..100 {
+            let delay = backoff(1, interval);
+            assert!(delay >= Duration::from_millis(500) && delay <= Duration::from_secs(1));
+            let delay = backoff(3, interval);
+            assert!(delay >= Duration::from_secs(2) && delay <= Duration::from_secs(4));
+            let delay = backoff(30, interval);
+            assert!(delay >= Duration::from_secs(5) && delay <= interval);
+        }
+        assert_eq!(backoff(1, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn tracks_failures() {
+        let source = RefreshSource::register("test", "tracks_failures");
+        source.failed(Duration::from_secs(10), "timeout");
+        source.failed(Duration::from_secs(10), "timeout");
+        assert_eq!(source.consecutive_failures(), 2);
+        assert_eq!(source.status().last_error.as_deref(), Some("timeout"));
+
+        source.succeeded(Duration::from_secs(10));
+        assert_eq!(source.consecutive_failures(), 0);
+        assert!(source.status().last_error.is_none());
+        assert!(RefreshSource::all()
+            .iter()
+            .any(|registered| registered.name() == "tracks_failures"));
+    }
+
+    #[tokio::test]
+    async fn refreshes_when_triggered() {
+        let source = RefreshSource::register("test", "refreshes_when_triggered");
+        source.refresh_now();
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            source.wait(Duration::from_secs(3600)),
+        )
+        .await
+        .expect("the refresh was triggered");
+    }
+}
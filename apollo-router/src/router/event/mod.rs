@@ -8,6 +8,9 @@ use std::fmt::Debug;
 use std::fmt::Formatter;
 
 pub use configuration::ConfigurationSource;
+pub use license::EnvLicenseProvider;
+pub use license::FileLicenseProvider;
+pub use license::LicenseProvider;
 pub use license::LicenseSource;
 pub(crate) use reload::ReloadSource;
 pub use schema::SchemaSource;
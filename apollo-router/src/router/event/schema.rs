@@ -1,5 +1,6 @@
 use std::path::PathBuf;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::time::Duration;
 
 use derivative::Derivative;
@@ -8,6 +9,7 @@ use derive_more::From;
 use futures::prelude::*;
 use url::Url;
 
+use crate::refresh::RefreshSource;
 use crate::router::Event;
 use crate::router::Event::NoMoreSchema;
 use crate::router::Event::UpdateSchema;
@@ -186,12 +188,14 @@ enum FetcherError {
 
 // Encapsulates fetching the schema from the first viable url.
 // It will try each url in order until it finds one that works.
-// On the second and subsequent calls it will wait for the period before making the call.
+// On the second and subsequent calls it will wait for the period before making the call,
+// unless a refresh is triggered.
 struct Fetcher {
     client: reqwest::Client,
     urls: Vec<Url>,
     period: Duration,
     first_call: bool,
+    source: Arc<RefreshSource>,
 }
 
 impl Fetcher {
@@ -202,6 +206,10 @@ impl Fetcher {
                 .timeout(Duration::from_secs(10))
                 .build()
                 .map_err(FetcherError::InitializationError)?,
+            source: RefreshSource::register(
+                "supergraph_urls",
+                urls.iter().map(Url::as_str).collect::<Vec<_>>().join(", "),
+            ),
             urls,
             period,
             first_call: true,
@@ -210,7 +218,7 @@ impl Fetcher {
     async fn fetch_supergraph_from_first_viable_url(&mut self) -> Option<Event> {
         // If this is not the first call then we need to wait for the period before trying again.
         if !self.first_call {
-            tokio::time::sleep(self.period).await;
+            self.source.wait(self.period).await;
         }
         self.first_call = false;
 
@@ -222,7 +230,10 @@ impl Fetcher {
                 .await
             {
                 Ok(res) if res.status().is_success() => match res.text().await {
-                    Ok(schema) => return Some(UpdateSchema(schema)),
+                    Ok(schema) => {
+                        self.source.succeeded(self.period);
+                        return Some(UpdateSchema(schema));
+                    }
                     Err(err) => {
                         tracing::warn!(
                             url.full = %url,
@@ -244,6 +255,10 @@ impl Fetcher {
             }
         }
         tracing::error!("failed to fetch supergraph schema from all urls");
+        self.source.failed(
+            self.period,
+            "failed to fetch supergraph schema from all urls",
+        );
         None
     }
 }
@@ -12,6 +12,9 @@ use std::task::Poll;
 pub use error::ApolloRouterError;
 pub use event::ConfigurationSource;
 pub(crate) use event::Event;
+pub use event::EnvLicenseProvider;
+pub use event::FileLicenseProvider;
+pub use event::LicenseProvider;
 pub use event::LicenseSource;
 pub(crate) use event::ReloadSource;
 pub use event::SchemaSource;
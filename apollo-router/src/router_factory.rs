@@ -22,6 +22,7 @@ use crate::configuration::Configuration;
 use crate::configuration::ConfigurationError;
 use crate::configuration::TlsClient;
 use crate::configuration::APOLLO_PLUGIN_PREFIX;
+use crate::plugin::timing::TimedPlugin;
 use crate::plugin::DynPlugin;
 use crate::plugin::Handler;
 use crate::plugin::PluginFactory;
@@ -127,6 +128,9 @@ pub(crate) trait RouterFactory:
     type Future: Send;
 
     fn web_endpoints(&self) -> MultiMap<ListenAddr, Endpoint>;
+
+    /// Notifies the plugins once this factory's routers replaced the ones of the previous factory
+    fn activated(&self, _previous: &Self) {}
 }
 
 /// Factory for creating a RouterFactory
@@ -446,7 +450,8 @@ pub(crate) async fn create_subgraph_services(
             configuration,
             &tls_root_store,
             shaping.enable_subgraph_http2(name),
-        )?;
+        )?
+        .with_redirect_policy(shaping.subgraph_redirect_policy(name));
 
         let http_service_factory =
             HttpClientServiceFactory::new(Arc::new(http_service), plugins.clone());
@@ -474,6 +479,23 @@ impl TlsClient {
             .as_deref()
             .map(create_certificate_store)
     }
+
+    pub(crate) fn create_spki_pins(&self) -> Option<Result<Vec<[u8; 32]>, ConfigurationError>> {
+        self.experimental_spki_pins
+            .as_ref()
+            .map(|pins| pins.iter().map(|pin| parse_spki_pin(pin)).collect())
+    }
+}
+
+/// Parses the hexadecimal SHA-256 digest of a SubjectPublicKeyInfo, as printed by
+/// `openssl x509 -noout -pubkey | openssl pkey -pubin -outform der | openssl dgst -sha256`
+fn parse_spki_pin(pin: &str) -> Result<[u8; 32], ConfigurationError> {
+    let digits: String = pin.chars().filter(|c| *c != ':').collect();
+    let mut digest = [0; 32];
+    hex::decode_to_slice(digits, &mut digest).map_err(|e| ConfigurationError::CertificatePins {
+        error: format!("could not parse the SPKI pin '{pin}': {e}"),
+    })?;
+    Ok(digest)
 }
 
 pub(crate) fn create_certificate_store(
@@ -552,6 +574,13 @@ pub(crate) async fn create_plugins(
     let supergraph_schema = Arc::new(schema.supergraph_schema().clone());
     let mut apollo_plugins_config = configuration.apollo_plugins.clone().plugins;
     let user_plugins_config = configuration.plugins.clone().plugins.unwrap_or_default();
+    let plugin_timing = apollo_plugins_config
+        .get("telemetry")
+        .and_then(|telemetry| {
+            telemetry.pointer("/instrumentation/instruments/plugin/apollo.router.plugin.duration")
+        })
+        .and_then(Value::as_bool)
+        .unwrap_or_default();
     let extra = extra_plugins.unwrap_or_default();
     let plugin_registry = &*crate::plugin::PLUGINS;
     let apollo_telemetry_plugin_mandatory = apollo_opentelemetry_initialized();
@@ -682,8 +711,18 @@ pub(crate) async fn create_plugins(
             }
         }
     }
+    add_mandatory_apollo_plugin!("pipeline_generations");
+    add_mandatory_apollo_plugin!("background_refresh");
+    add_mandatory_apollo_plugin!("diagnostics");
+    add_optional_apollo_plugin!("memory_limit");
+    add_optional_apollo_plugin!("admission_control");
+    add_optional_apollo_plugin!("heap_profiling");
+    add_optional_apollo_plugin!("cpu_profiling");
+    add_optional_apollo_plugin!("log_filter");
+    add_optional_apollo_plugin!("subgraph_failures");
     add_mandatory_apollo_plugin!("traffic_shaping");
     add_optional_apollo_plugin!("forbid_mutations");
+    add_optional_apollo_plugin!("idempotency");
     add_optional_apollo_plugin!("subscription");
     add_optional_apollo_plugin!("override_subgraph_url");
     add_optional_apollo_plugin!("authorization");
@@ -691,6 +730,10 @@ pub(crate) async fn create_plugins(
     add_optional_apollo_plugin!("preview_file_uploads");
     add_optional_apollo_plugin!("preview_entity_cache");
     add_mandatory_apollo_plugin!("progressive_override");
+    add_optional_apollo_plugin!("feature_flags");
+    add_optional_apollo_plugin!("experiments");
+    add_optional_apollo_plugin!("request_toggles");
+    add_optional_apollo_plugin!("field_usage");
 
     // This relative ordering is documented in `docs/source/customizations/native.mdx`:
     add_optional_apollo_plugin!("rhai");
@@ -729,6 +772,14 @@ pub(crate) async fn create_plugins(
             "there were {} configuration errors",
             errors.len()
         )))
+    } else if plugin_timing {
+        Ok(plugin_instances
+            .into_iter()
+            .map(|(name, plugin)| {
+                let plugin: Box<dyn DynPlugin> = Box::new(TimedPlugin::new(&name, plugin));
+                (name, plugin)
+            })
+            .collect())
     } else {
         Ok(plugin_instances)
     }
@@ -0,0 +1,31 @@
+//! Integration with the service manager running the router.
+//!
+//! On Linux, systemd is notified of the readiness of the router through the `sd_notify` protocol,
+//! and receives watchdog pings while the router is live, so that it can restart a router that
+//! stopped responding. On Windows, the router can run as a Windows service, which reports its
+//! state to the service control manager and shuts down gracefully when the service is stopped.
+//!
+//! The state is reported by the HTTP server factory, following the health check of the router.
+
+#[cfg(target_os = "linux")]
+mod systemd;
+#[cfg(windows)]
+pub(crate) mod windows;
+
+/// The router is live: its event loop started
+pub(crate) fn live(live: bool) {
+    #[cfg(target_os = "linux")]
+    systemd::watchdog(live);
+    #[cfg(not(target_os = "linux"))]
+    let _ = live;
+}
+
+/// The router is ready to serve requests, or stopped being ready because it is shutting down
+pub(crate) fn ready(ready: bool) {
+    #[cfg(target_os = "linux")]
+    systemd::ready(ready);
+    #[cfg(windows)]
+    windows::ready(ready);
+    #[cfg(not(any(target_os = "linux", windows)))]
+    let _ = ready;
+}
@@ -0,0 +1,126 @@
+//! Notifications sent to systemd through the `sd_notify` protocol.
+//!
+//! Messages are datagrams sent to the unix socket in the `NOTIFY_SOCKET` environment variable,
+//! set by systemd for services of `Type=notify`. Watchdog pings are sent at half of the interval
+//! in the `WATCHDOG_USEC` environment variable, set when the service has a `WatchdogSec`.
+//! See <https://www.freedesktop.org/software/systemd/man/latest/sd_notify.html>.
+
+use std::ffi::OsStr;
+use std::io;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::net::SocketAddr;
+use std::os::unix::net::UnixDatagram;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use tokio::task::JoinHandle;
+
+const NOTIFY_SOCKET: &str = "NOTIFY_SOCKET";
+const WATCHDOG_USEC: &str = "WATCHDOG_USEC";
+const WATCHDOG_PID: &str = "WATCHDOG_PID";
+
+/// Task sending the watchdog pings, while the router is live
+static WATCHDOG: Mutex<Option<JoinHandle<()>>> = parking_lot::const_mutex(None);
+
+pub(super) fn ready(ready: bool) {
+    let state = if ready {
+        format!(
+            "READY=1\nSTATUS=Serving requests\nMAINPID={}",
+            std::process::id()
+        )
+    } else {
+        "STOPPING=1\nSTATUS=Shutting down".to_string()
+    };
+    send(&state);
+}
+
+pub(super) fn watchdog(live: bool) {
+    let mut watchdog = WATCHDOG.lock();
+    if let Some(task) = watchdog.take() {
+        task.abort();
+    }
+    if !live {
+        return;
+    }
+    let Some(interval) = watchdog_interval(
+        std::env::var(WATCHDOG_USEC).ok().as_deref(),
+        std::env::var(WATCHDOG_PID).ok().as_deref(),
+    ) else {
+        return;
+    };
+    tracing::debug!("sending systemd watchdog pings every {interval:?}");
+    // The pings are sent from the runtime serving requests: if it stops making progress, systemd
+    // stops receiving pings and restarts the router.
+    *watchdog = Some(tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            send("WATCHDOG=1");
+        }
+    }));
+}
+
+/// Half of the watchdog timeout, if the watchdog is enabled for this process
+fn watchdog_interval(usec: Option<&str>, pid: Option<&str>) -> Option<Duration> {
+    if let Some(pid) = pid {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec = usec?.parse::<u64>().ok().filter(|usec| *usec > 0)?;
+    Some(Duration::from_micros(usec) / 2)
+}
+
+fn send(state: &str) {
+    let Some(path) = std::env::var_os(NOTIFY_SOCKET) else {
+        return;
+    };
+    if let Err(err) = send_to(&path, state) {
+        tracing::warn!("could not notify systemd: {err}");
+    }
+}
+
+fn send_to(path: &OsStr, state: &str) -> io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    // Socket paths starting with '@' are in the abstract namespace
+    let address = match path.as_bytes().strip_prefix(b"@") {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(path)?,
+    };
+    socket.send_to_addr(state.as_bytes(), &address)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sends_notifications() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify.sock");
+        let listener = UnixDatagram::bind(&path).unwrap();
+
+        send_to(path.as_os_str(), "READY=1").unwrap();
+        let mut buffer = [0; 64];
+        let len = listener.recv(&mut buffer).unwrap();
+        assert_eq!(&buffer[..len], b"READY=1");
+    }
+
+    #[test]
+    fn watchdog_interval_is_half_of_the_timeout() {
+        let pid = std::process::id().to_string();
+        assert_eq!(
+            watchdog_interval(Some("30000000"), None),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(
+            watchdog_interval(Some("30000000"), Some(&pid)),
+            Some(Duration::from_secs(15))
+        );
+        assert_eq!(watchdog_interval(Some("30000000"), Some("0")), None);
+        assert_eq!(watchdog_interval(Some("0"), None), None);
+        assert_eq!(watchdog_interval(None, None), None);
+    }
+}
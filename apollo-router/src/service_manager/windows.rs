@@ -0,0 +1,213 @@
+//! Running the router as a Windows service.
+//!
+//! The service control dispatcher takes over the main thread, and calls the service main function
+//! on another thread, where the router runs until the service is stopped. The state of the router
+//! is reported to the service control manager: the service is running once the router is ready,
+//! and stopping while the router shuts down gracefully.
+//! See <https://learn.microsoft.com/en-us/windows/win32/services/service-programs>.
+
+use std::ffi::OsString;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use anyhow::Result;
+use once_cell::sync::OnceCell;
+use parking_lot::Mutex;
+use tokio::sync::oneshot;
+use windows_service::define_windows_service;
+use windows_service::service::ServiceControl;
+use windows_service::service::ServiceControlAccept;
+use windows_service::service::ServiceExitCode;
+use windows_service::service::ServiceState;
+use windows_service::service::ServiceStatus;
+use windows_service::service::ServiceType;
+use windows_service::service_control_handler;
+use windows_service::service_control_handler::ServiceControlHandlerResult;
+use windows_service::service_control_handler::ServiceStatusHandle;
+use windows_service::service_dispatcher;
+
+use crate::executable::Opt;
+use crate::router::ShutdownSource;
+use crate::Executable;
+
+/// Time the service control manager waits for a state change before considering it failed
+const WAIT_HINT: Duration = Duration::from_secs(60);
+
+/// Name and options of the service, taken by the service main function
+static SERVICE: Mutex<Option<(String, Opt)>> = parking_lot::const_mutex(None);
+/// Result of the service main function
+static RESULT: Mutex<Option<Result<()>>> = parking_lot::const_mutex(None);
+static STATUS_HANDLE: OnceCell<ServiceStatusHandle> = OnceCell::new();
+
+/// Runs the router as a Windows service, until the service is stopped
+pub(crate) fn run(name: String, opt: Opt) -> Result<()> {
+    *SERVICE.lock() = Some((name.clone(), opt));
+    service_dispatcher::start(&name, ffi_service_main)
+        .map_err(|err| anyhow!("could not start the {name} Windows service: {err}"))?;
+    RESULT.lock().take().unwrap_or(Ok(()))
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    let result = run_service();
+    set_status(
+        ServiceState::Stopped,
+        match result {
+            Ok(_) => ServiceExitCode::NO_ERROR,
+            Err(_) => ServiceExitCode::ServiceSpecific(1),
+        },
+    );
+    *RESULT.lock() = Some(result);
+}
+
+fn run_service() -> Result<()> {
+    let (name, opt) = SERVICE
+        .lock()
+        .take()
+        .ok_or_else(|| anyhow!("the Windows service was already started"))?;
+
+    let (shutdown_sender, shutdown_receiver) = oneshot::channel();
+    let shutdown_sender = Mutex::new(Some(shutdown_sender));
+    let status_handle = service_control_handler::register(&name, move |control| {
+        handle_control(control, &shutdown_sender)
+    })
+    .map_err(|err| anyhow!("could not register the service control handler: {err}"))?;
+    let _ = STATUS_HANDLE.set(status_handle);
+    set_status(ServiceState::StartPending, ServiceExitCode::NO_ERROR);
+
+    crate::executable::runtime()?.block_on(
+        Executable::builder()
+            .cli_args(opt)
+            .shutdown(ShutdownSource::Custom(Box::pin(async move {
+                let _ = shutdown_receiver.await;
+            })))
+            .start(),
+    )
+}
+
+/// Triggers the shutdown of the router when the service is stopped
+fn handle_control(
+    control: ServiceControl,
+    shutdown_sender: &Mutex<Option<oneshot::Sender<()>>>,
+) -> ServiceControlHandlerResult {
+    match control {
+        ServiceControl::Stop | ServiceControl::Shutdown | ServiceControl::Preshutdown => {
+            if let Some(sender) = shutdown_sender.lock().take() {
+                let _ = sender.send(());
+            }
+            ServiceControlHandlerResult::NoError
+        }
+        ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+        _ => ServiceControlHandlerResult::NotImplemented,
+    }
+}
+
+pub(super) fn ready(ready: bool) {
+    let state = if ready {
+        ServiceState::Running
+    } else {
+        ServiceState::StopPending
+    };
+    set_status(state, ServiceExitCode::NO_ERROR);
+}
+
+fn set_status(state: ServiceState, exit_code: ServiceExitCode) {
+    let Some(status_handle) = STATUS_HANDLE.get() else {
+        return;
+    };
+    if let Err(err) = status_handle.set_service_status(status(state, exit_code)) {
+        tracing::warn!("could not report the state of the Windows service: {err}");
+    }
+}
+
+/// Status reported to the service control manager: stop controls are only accepted while the
+/// router is running
+fn status(state: ServiceState, exit_code: ServiceExitCode) -> ServiceStatus {
+    let (controls_accepted, wait_hint) = match state {
+        ServiceState::Running => (
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            Duration::ZERO,
+        ),
+        ServiceState::StartPending | ServiceState::StopPending => {
+            (ServiceControlAccept::empty(), WAIT_HINT)
+        }
+        _ => (ServiceControlAccept::empty(), Duration::ZERO),
+    };
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted,
+        exit_code,
+        checkpoint: 0,
+        wait_hint,
+        process_id: None,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn stop_controls_trigger_the_shutdown() {
+        for control in [
+            ServiceControl::Stop,
+            ServiceControl::Shutdown,
+            ServiceControl::Preshutdown,
+        ] {
+            let (sender, mut receiver) = oneshot::channel();
+            let sender = Mutex::new(Some(sender));
+            assert!(matches!(
+                handle_control(control, &sender),
+                ServiceControlHandlerResult::NoError
+            ));
+            assert!(receiver.try_recv().is_ok());
+            // later stop controls are acknowledged while the router shuts down
+            assert!(matches!(
+                handle_control(control, &sender),
+                ServiceControlHandlerResult::NoError
+            ));
+        }
+    }
+
+    #[test]
+    fn other_controls_do_not_trigger_the_shutdown() {
+        let (sender, mut receiver) = oneshot::channel();
+        let sender = Mutex::new(Some(sender));
+        assert!(matches!(
+            handle_control(ServiceControl::Interrogate, &sender),
+            ServiceControlHandlerResult::NoError
+        ));
+        assert!(matches!(
+            handle_control(ServiceControl::Pause, &sender),
+            ServiceControlHandlerResult::NotImplemented
+        ));
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn stop_is_only_accepted_while_running() {
+        let running = status(ServiceState::Running, ServiceExitCode::NO_ERROR);
+        assert_eq!(
+            running.controls_accepted,
+            ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN
+        );
+        assert_eq!(running.wait_hint, Duration::ZERO);
+
+        for state in [ServiceState::StartPending, ServiceState::StopPending] {
+            let pending = status(state, ServiceExitCode::NO_ERROR);
+            assert_eq!(pending.controls_accepted, ServiceControlAccept::empty());
+            assert_eq!(pending.wait_hint, WAIT_HINT);
+        }
+
+        let stopped = status(ServiceState::Stopped, ServiceExitCode::ServiceSpecific(1));
+        assert_eq!(stopped.controls_accepted, ServiceControlAccept::empty());
+        assert_eq!(stopped.exit_code, ServiceExitCode::ServiceSpecific(1));
+    }
+
+    #[test]
+    fn service_main_requires_the_options() {
+        assert!(run_service().is_err());
+    }
+}
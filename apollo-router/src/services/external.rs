@@ -42,6 +42,7 @@ pub(crate) enum PipelineStep {
     ExecutionResponse,
     SubgraphRequest,
     SubgraphResponse,
+    SchemaChange,
 }
 
 impl From<PipelineStep> for opentelemetry::Value {
@@ -266,6 +267,36 @@ where
         }
     }
 
+    #[builder(visibility = "pub(crate)")]
+    /// This is the constructor (or builder) to use when constructing a schema change
+    /// `Externalizable`.
+    ///
+    fn schema_change_new(
+        stage: PipelineStep,
+        id: String,
+        body: Option<T>,
+        sdl: Option<String>,
+    ) -> Self {
+        assert!(matches!(stage, PipelineStep::SchemaChange));
+        Externalizable {
+            version: EXTERNALIZABLE_VERSION,
+            stage: stage.to_string(),
+            control: None,
+            id: Some(id),
+            headers: None,
+            body,
+            context: None,
+            status_code: None,
+            sdl,
+            uri: None,
+            path: None,
+            method: None,
+            service_name: None,
+            has_next: None,
+            query_plan: None,
+        }
+    }
+
     pub(crate) async fn call<C>(self, mut client: C, uri: &str) -> Result<Self, BoxError>
     where
         C: Service<
@@ -380,4 +411,21 @@ mod test {
             .id(String::default())
             .build();
     }
+
+    #[test]
+    fn it_will_build_schema_change_externalizable_correctly() {
+        Externalizable::<String>::schema_change_builder()
+            .stage(PipelineStep::SchemaChange)
+            .id(String::default())
+            .build();
+    }
+
+    #[test]
+    #[should_panic]
+    fn it_will_not_build_schema_change_externalizable_incorrectly() {
+        Externalizable::<String>::schema_change_builder()
+            .stage(PipelineStep::RouterRequest)
+            .id(String::default())
+            .build();
+    }
 }
@@ -2,6 +2,7 @@ use std::fmt::Display;
 use std::sync::Arc;
 use std::task::Poll;
 use std::time::Duration;
+use std::time::SystemTime;
 
 use ::serde::Deserialize;
 use bytes::Bytes;
@@ -10,18 +11,35 @@ use futures::Stream;
 use futures::TryFutureExt;
 use global::get_text_map_propagator;
 use http::header::ACCEPT_ENCODING;
+use http::header::AUTHORIZATION;
 use http::header::CONTENT_ENCODING;
+use http::header::CONTENT_LENGTH;
+use http::header::CONTENT_TYPE;
+use http::header::COOKIE;
+use http::header::HOST;
+use http::header::LOCATION;
+use http::header::PROXY_AUTHORIZATION;
+use http::uri::Scheme;
 use http::HeaderValue;
+use http::Method;
 use http::Request;
+use http::StatusCode;
 use hyper::client::HttpConnector;
 use hyper_rustls::HttpsConnector;
 #[cfg(unix)]
 use hyperlocal::UnixConnector;
 use opentelemetry::global;
 use pin_project_lite::pin_project;
+use rustls::client::ServerCertVerified;
+use rustls::client::ServerCertVerifier;
+use rustls::client::WebPkiVerifier;
+use rustls::Certificate;
 use rustls::ClientConfig;
 use rustls::RootCertStore;
+use rustls::ServerName;
 use schemars::JsonSchema;
+use sha2::Digest;
+use sha2::Sha256;
 use tower::util::Either;
 use tower::BoxError;
 use tower::Service;
@@ -43,6 +61,7 @@ use crate::plugins::telemetry::reload::prepare_context;
 use crate::plugins::telemetry::LOGGING_DISPLAY_BODY;
 use crate::plugins::telemetry::LOGGING_DISPLAY_HEADERS;
 use crate::plugins::traffic_shaping::Http2Config;
+use crate::plugins::traffic_shaping::RedirectPolicy;
 use crate::services::router::body::RouterBody;
 use crate::services::trust_dns_connector::new_async_http_connector;
 use crate::services::trust_dns_connector::AsyncHyperResolver;
@@ -96,6 +115,7 @@ pub(crate) struct HttpClientService {
     #[cfg(unix)]
     unix_client: UnixHTTPClient,
     service: Arc<String>,
+    redirect_policy: Option<Arc<RedirectPolicy>>,
 }
 
 impl HttpClientService {
@@ -128,8 +148,19 @@ impl HttpClientService {
                 .all
                 .client_authentication
                 .as_ref());
+        // pins of a subgraph replace the global ones, even when empty
+        let spki_pins = configuration
+            .tls
+            .subgraph
+            .subgraphs
+            .get(&name)
+            .and_then(|tls| tls.create_spki_pins())
+            .or_else(|| configuration.tls.subgraph.all.create_spki_pins())
+            .transpose()?
+            .unwrap_or_default();
 
-        let tls_client_config = generate_tls_client_config(tls_cert_store, client_cert_config)?;
+        let tls_client_config =
+            generate_tls_client_config(tls_cert_store, client_cert_config, spki_pins)?;
 
         HttpClientService::new(name, http2, tls_client_config)
     }
@@ -168,9 +199,16 @@ impl HttpClientService {
                 .layer(DecompressionLayer::new())
                 .service(hyper::Client::builder().build(UnixConnector)),
             service: Arc::new(service.into()),
+            redirect_policy: None,
         })
     }
 
+    /// Follow the redirects allowed by `policy`, instead of returning redirect responses
+    pub(crate) fn with_redirect_policy(mut self, policy: Option<RedirectPolicy>) -> Self {
+        self.redirect_policy = policy.map(Arc::new);
+        self
+    }
+
     pub(crate) fn native_roots_store() -> RootCertStore {
         let mut roots = rustls::RootCertStore::empty();
         let mut valid_count = 0;
@@ -201,21 +239,71 @@ impl HttpClientService {
 pub(crate) fn generate_tls_client_config(
     tls_cert_store: RootCertStore,
     client_cert_config: Option<&TlsClientAuth>,
+    spki_pins: Vec<[u8; 32]>,
 ) -> Result<rustls::ClientConfig, BoxError> {
-    let tls_builder = rustls::ClientConfig::builder().with_safe_defaults();
+    let verifier = WebPkiVerifier::new(tls_cert_store, None);
+    let verifier: Arc<dyn ServerCertVerifier> = if spki_pins.is_empty() {
+        Arc::new(verifier)
+    } else {
+        Arc::new(PinnedKeyVerifier {
+            verifier,
+            spki_pins,
+        })
+    };
+    let tls_builder = rustls::ClientConfig::builder()
+        .with_safe_defaults()
+        .with_custom_certificate_verifier(verifier);
     Ok(match client_cert_config {
-        Some(client_auth_config) => tls_builder
-            .with_root_certificates(tls_cert_store)
-            .with_client_auth_cert(
-                client_auth_config.certificate_chain.clone(),
-                client_auth_config.key.clone(),
-            )?,
-        None => tls_builder
-            .with_root_certificates(tls_cert_store)
-            .with_no_client_auth(),
+        Some(client_auth_config) => tls_builder.with_client_auth_cert(
+            client_auth_config.certificate_chain.clone(),
+            client_auth_config.key.clone(),
+        )?,
+        None => tls_builder.with_no_client_auth(),
     })
 }
 
+/// Validates server certificates like the default verifier, then requires the public key of the
+/// server's own certificate to match a pinned SHA-256 digest of its SubjectPublicKeyInfo. Pinning
+/// the key rather than the certificate keeps the pins valid when the certificate is renewed with
+/// the same key. The other certificates presented by the server are not matched: a server can
+/// append any certificate to its chain
+struct PinnedKeyVerifier {
+    verifier: WebPkiVerifier,
+    spki_pins: Vec<[u8; 32]>,
+}
+
+impl ServerCertVerifier for PinnedKeyVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &Certificate,
+        intermediates: &[Certificate],
+        server_name: &ServerName,
+        scts: &mut dyn Iterator<Item = &[u8]>,
+        ocsp_response: &[u8],
+        now: SystemTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        let verified = self.verifier.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            scts,
+            ocsp_response,
+            now,
+        )?;
+        let (_, certificate) = x509_parser::parse_x509_certificate(&end_entity.0).map_err(|e| {
+            rustls::Error::General(format!("could not parse the server certificate: {e}"))
+        })?;
+        let digest: [u8; 32] = Sha256::digest(certificate.public_key().raw).into();
+        if self.spki_pins.contains(&digest) {
+            Ok(verified)
+        } else {
+            Err(rustls::Error::General(
+                "the public key of the server certificate does not match any pin".to_string(),
+            ))
+        }
+    }
+}
+
 impl tower::Service<HttpRequest> for HttpClientService {
     type Response = HttpResponse;
     type Error = BoxError;
@@ -255,6 +343,7 @@ impl tower::Service<HttpRequest> for HttpClientService {
         let client = self.http_client.clone();
 
         let service_name = self.service.clone();
+        let redirect_policy = self.redirect_policy.clone();
 
         let path = schema_uri.path();
 
@@ -315,9 +404,24 @@ impl tower::Service<HttpRequest> for HttpClientService {
                 tracing::info!(http.request.body = ?http_request.body(), apollo.subgraph.name = %service_name, "Request body to subgraph {service_name:?}");
             }
 
-            let http_response = do_fetch(client, &context, &service_name, http_request)
-                .instrument(http_req_span)
-                .await?;
+            let http_response = match redirect_policy {
+                None => {
+                    do_fetch(client, &context, &service_name, http_request)
+                        .instrument(http_req_span)
+                        .await?
+                }
+                Some(policy) => {
+                    do_fetch_following_redirects(
+                        client,
+                        &context,
+                        &service_name,
+                        http_request,
+                        &policy,
+                    )
+                    .instrument(http_req_span)
+                    .await?
+                }
+            };
 
             // Print out the debug for the response
             if display_headers {
@@ -357,6 +461,110 @@ async fn do_fetch(
     ))
 }
 
+/// Sends the request, then follows the redirects allowed by `policy`
+async fn do_fetch_following_redirects(
+    client: MixedClient,
+    context: &Context,
+    service_name: &str,
+    request: Request<RouterBody>,
+    policy: &RedirectPolicy,
+) -> Result<http::Response<RouterBody>, FetchError> {
+    let redirect_error = |reason: String| FetchError::SubrequestHttpError {
+        status_code: None,
+        service: service_name.to_string(),
+        reason,
+    };
+
+    let (parts, body) = request.into_parts();
+    // the body is kept in memory to send it again after a redirect
+    let mut body = body
+        .to_bytes()
+        .await
+        .map_err(|err| redirect_error(format!("cannot read the request body: {err}")))?;
+    let subgraph_host = parts.uri.host().unwrap_or_default().to_string();
+    let mut method = parts.method;
+    let mut uri = parts.uri;
+    let mut headers = parts.headers;
+    let mut hops = 0;
+
+    loop {
+        let mut request = http::Request::new(RouterBody::from(body.clone()));
+        *request.method_mut() = method.clone();
+        *request.uri_mut() = uri.clone();
+        *request.version_mut() = parts.version;
+        *request.headers_mut() = headers.clone();
+        let response = do_fetch(client.clone(), context, service_name, request).await?;
+
+        let status = response.status();
+        let location = match status {
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => response.headers().get(LOCATION),
+            _ => None,
+        };
+        let Some(location) = location else {
+            return Ok(response);
+        };
+
+        if hops == policy.max_hops {
+            return Err(redirect_error(format!(
+                "too many redirects, the limit is {}",
+                policy.max_hops
+            )));
+        }
+        hops += 1;
+
+        let next = location
+            .to_str()
+            .ok()
+            .and_then(|location| url::Url::parse(&uri.to_string()).ok()?.join(location).ok())
+            .and_then(|next| next.as_str().parse::<http::Uri>().ok())
+            .ok_or_else(|| redirect_error(format!("invalid redirect location {location:?}")))?;
+        if !policy.allows(&subgraph_host, &next) {
+            return Err(redirect_error(format!(
+                "redirect to {next} is not allowed by the redirect policy"
+            )));
+        }
+        if uri.scheme() == Some(&Scheme::HTTPS) && next.scheme() != Some(&Scheme::HTTPS) {
+            return Err(redirect_error(format!(
+                "redirect to {next} is not allowed: it would downgrade the connection from https"
+            )));
+        }
+
+        let switch_to_get = status == StatusCode::SEE_OTHER
+            || (!policy.preserve_method
+                && method == Method::POST
+                && matches!(status, StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND));
+        if switch_to_get {
+            method = Method::GET;
+            body = Bytes::new();
+            headers.remove(CONTENT_TYPE);
+            headers.remove(CONTENT_LENGTH);
+            headers.remove(CONTENT_ENCODING);
+        }
+        // credentials are not sent to another origin
+        if origin(&next) != origin(&uri) {
+            headers.remove(HOST);
+            headers.remove(AUTHORIZATION);
+            headers.remove(PROXY_AUTHORIZATION);
+            headers.remove(COOKIE);
+        }
+        uri = next;
+    }
+}
+
+/// Scheme, host and port of a URI, with the default port of the scheme
+fn origin(uri: &http::Uri) -> (Option<&str>, Option<&str>, Option<u16>) {
+    let port = uri.port_u16().or_else(|| match uri.scheme_str() {
+        Some("http") => Some(80),
+        Some("https") => Some(443),
+        _ => None,
+    });
+    (uri.scheme_str(), uri.host(), port)
+}
+
 pin_project! {
     pub(crate) struct BodyStream<B: hyper::body::HttpBody> {
         #[pin]
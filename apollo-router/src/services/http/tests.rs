@@ -29,6 +29,8 @@ use rustls::RootCertStore;
 use rustls::ServerConfig;
 use serde_json_bytes::ByteString;
 use serde_json_bytes::Value;
+use sha2::Digest;
+use sha2::Sha256;
 use tokio::io::AsyncWriteExt;
 use tower::service_fn;
 use tower::BoxError;
@@ -42,8 +44,10 @@ use crate::graphql::Response;
 use crate::plugin::PluginInit;
 use crate::plugin::PluginPrivate;
 use crate::plugins::traffic_shaping::Http2Config;
+use crate::plugins::traffic_shaping::RedirectPolicy;
 use crate::services::http::HttpClientService;
 use crate::services::http::HttpRequest;
+use crate::services::http::HttpResponse;
 use crate::services::router::body::get_body_bytes;
 use crate::services::supergraph;
 use crate::Configuration;
@@ -110,6 +114,7 @@ async fn tls_self_signed() {
         TlsClient {
             certificate_authorities: Some(certificate_pem.into()),
             client_authentication: None,
+            experimental_spki_pins: None,
         },
     );
     let subgraph_service = HttpClientService::from_config(
@@ -144,6 +149,124 @@ async fn tls_self_signed() {
     );
 }
 
+/// Hexadecimal SHA-256 digest of the SubjectPublicKeyInfo of a certificate
+fn spki_pin(certificate: &Certificate) -> String {
+    let (_, certificate) = x509_parser::parse_x509_certificate(&certificate.0).unwrap();
+    hex::encode(Sha256::digest(certificate.public_key().raw))
+}
+
+async fn pinned_request(
+    url: &Uri,
+    certificate_authorities: &str,
+    all_pins: Option<Vec<String>>,
+    subgraph_pins: Option<Vec<String>>,
+) -> Result<HttpResponse, BoxError> {
+    let mut config = Configuration::default();
+    config.tls.subgraph.all.experimental_spki_pins = all_pins;
+    config.tls.subgraph.subgraphs.insert(
+        "test".to_string(),
+        TlsClient {
+            certificate_authorities: Some(certificate_authorities.into()),
+            client_authentication: None,
+            experimental_spki_pins: subgraph_pins,
+        },
+    );
+    let subgraph_service = HttpClientService::from_config(
+        "test",
+        &config,
+        &rustls::RootCertStore::empty(),
+        Http2Config::Enable,
+    )
+    .unwrap();
+
+    subgraph_service
+        .oneshot(HttpRequest {
+            http_request: http::Request::builder()
+                .uri(url.clone())
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .body(r#"{"query":"{ me { name username } }"#.into())
+                .unwrap(),
+            context: Context::new(),
+        })
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tls_spki_pins() {
+    let certificate_pem = include_str!("./testdata/server_self_signed.crt");
+    let key_pem = include_str!("./testdata/server.key");
+
+    let certificates = load_certs(certificate_pem).unwrap();
+    let pin = spki_pin(&certificates[0]);
+    let key = load_key(key_pem).unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    tokio::task::spawn(tls_server(listener, certificates, key, r#"{"data": null}"#));
+    let url = Uri::from_str(&format!("https://localhost:{}", socket_addr.port())).unwrap();
+
+    let other = "00".repeat(32);
+    for (all_pins, subgraph_pins, accepted) in [
+        (None, Some(vec![pin.clone()]), true),
+        (None, Some(vec![other.clone()]), false),
+        (Some(vec![pin.clone()]), None, true),
+        (Some(vec![other.clone()]), None, false),
+        // the pins of the subgraph replace the global pins
+        (Some(vec![other.clone()]), Some(vec![pin.clone()]), true),
+        // an empty list disables pinning for the subgraph
+        (Some(vec![other.clone()]), Some(vec![]), true),
+    ] {
+        let response = pinned_request(&url, certificate_pem, all_pins, subgraph_pins).await;
+        assert_eq!(response.is_ok(), accepted);
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn tls_spki_pins_ignore_intermediates() {
+    let certificate_pem = include_str!("./testdata/server.crt");
+    let ca_pem = include_str!("./testdata/CA/ca.crt");
+    let self_signed_pem = include_str!("./testdata/server_self_signed.crt");
+    let key_pem = include_str!("./testdata/server.key");
+
+    let mut certificates = load_certs(certificate_pem).unwrap();
+    let pin = spki_pin(&certificates[0]);
+    let ca_pin = spki_pin(&load_certs(ca_pem).unwrap()[0]);
+    // another certificate for the same key has the same pin, so renewing a certificate
+    // without changing its key keeps it valid
+    assert_eq!(spki_pin(&load_certs(self_signed_pem).unwrap()[0]), pin);
+    // the server appends a pinned certificate it does not own to its chain
+    certificates.extend(load_certs(ca_pem).unwrap());
+    let key = load_key(key_pem).unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    tokio::task::spawn(tls_server(listener, certificates, key, r#"{"data": null}"#));
+    let url = Uri::from_str(&format!("https://localhost:{}", socket_addr.port())).unwrap();
+
+    for (pin, accepted) in [(pin, true), (ca_pin, false)] {
+        let response = pinned_request(&url, ca_pem, None, Some(vec![pin])).await;
+        assert_eq!(response.is_ok(), accepted);
+    }
+}
+
+#[test]
+fn tls_invalid_spki_pin() {
+    let mut config = Configuration::default();
+    config.tls.subgraph.subgraphs.insert(
+        "test".to_string(),
+        TlsClient::builder()
+            .experimental_spki_pins(vec!["not a digest".to_string()])
+            .build(),
+    );
+    assert!(HttpClientService::from_config(
+        "test",
+        &config,
+        &rustls::RootCertStore::empty(),
+        Http2Config::Enable,
+    )
+    .is_err());
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn tls_custom_root() {
     let certificate_pem = include_str!("./testdata/server.crt");
@@ -167,6 +290,7 @@ async fn tls_custom_root() {
         TlsClient {
             certificate_authorities: Some(ca_pem.into()),
             client_authentication: None,
+            experimental_spki_pins: None,
         },
     );
     let subgraph_service = HttpClientService::from_config(
@@ -277,6 +401,7 @@ async fn tls_client_auth() {
                 certificate_chain: client_certificates,
                 key: client_key,
             }),
+            experimental_spki_pins: None,
         },
     );
     let subgraph_service = HttpClientService::from_config(
@@ -374,6 +499,214 @@ async fn test_subgraph_h2c() {
     );
 }
 
+// starts a local server emulating a subgraph that moved to another path
+async fn emulate_redirecting_subgraph(listener: TcpListener) {
+    async fn handle(request: http::Request<Body>) -> Result<http::Response<Body>, Infallible> {
+        let response = match request.uri().path() {
+            "/moved" => http::Response::builder()
+                .status(StatusCode::TEMPORARY_REDIRECT)
+                .header(http::header::LOCATION, "/graphql")
+                .body(Body::empty()),
+            "/elsewhere" => http::Response::builder()
+                .status(StatusCode::TEMPORARY_REDIRECT)
+                .header(http::header::LOCATION, "http://169.254.169.254/graphql")
+                .body(Body::empty()),
+            // redirects to the location in the query string
+            "/redirect" => http::Response::builder()
+                .status(StatusCode::TEMPORARY_REDIRECT)
+                .header(
+                    http::header::LOCATION,
+                    request.uri().query().unwrap_or_default(),
+                )
+                .body(Body::empty()),
+            // returns the credentials of the request
+            "/credentials" => http::Response::builder()
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .status(StatusCode::OK)
+                .body(
+                    request
+                        .headers()
+                        .get(http::header::AUTHORIZATION)
+                        .map(|value| value.as_bytes().to_vec())
+                        .unwrap_or_default()
+                        .into(),
+                ),
+            _ => http::Response::builder()
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .status(StatusCode::OK)
+                .body(get_body_bytes(request.into_body()).await.unwrap().into()),
+        };
+        Ok(response.unwrap())
+    }
+
+    let make_svc = make_service_fn(|_conn| async { Ok::<_, Infallible>(service_fn(handle)) });
+    let server = Server::from_tcp(listener).unwrap().serve(make_svc);
+    server.await.unwrap();
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subgraph_redirects() {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    tokio::task::spawn(emulate_redirecting_subgraph(listener));
+    let subgraph_service = HttpClientService::new(
+        "test",
+        Http2Config::Disable,
+        rustls::ClientConfig::builder()
+            .with_safe_defaults()
+            .with_native_roots()
+            .with_no_client_auth(),
+    )
+    .expect("can create a HttpService")
+    .with_redirect_policy(Some(RedirectPolicy::default()));
+
+    let request = |path: &str| HttpRequest {
+        http_request: http::Request::builder()
+            .method(http::Method::POST)
+            .uri(Uri::from_str(&format!("http://{socket_addr}{path}")).unwrap())
+            .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+            .body(r#"{"query":"{ me { name } }"}"#.into())
+            .unwrap(),
+        context: Context::new(),
+    };
+
+    // 307 redirects keep the method and body
+    let response = subgraph_service
+        .clone()
+        .oneshot(request("/moved"))
+        .await
+        .unwrap();
+    assert_eq!(response.http_response.status(), StatusCode::OK);
+    assert_eq!(
+        std::str::from_utf8(
+            &get_body_bytes(response.http_response.into_parts().1)
+                .await
+                .unwrap()
+        )
+        .unwrap(),
+        r#"{"query":"{ me { name } }"}"#
+    );
+
+    // redirects to other hosts are refused
+    let error = subgraph_service
+        .clone()
+        .oneshot(request("/elsewhere"))
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("not allowed by the redirect policy"),
+        "{error}"
+    );
+
+    // credentials are only kept on redirects to the same origin
+    let other_listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let other_port = other_listener.local_addr().unwrap().port();
+    tokio::task::spawn(emulate_redirecting_subgraph(other_listener));
+    for (location, credentials) in [
+        ("/credentials".to_string(), "Bearer secret"),
+        (format!("http://127.0.0.1:{other_port}/credentials"), ""),
+    ] {
+        let mut request = request(&format!("/redirect?{location}"));
+        request.http_request.headers_mut().insert(
+            http::header::AUTHORIZATION,
+            http::HeaderValue::from_static("Bearer secret"),
+        );
+        let response = subgraph_service.clone().oneshot(request).await.unwrap();
+        assert_eq!(
+            get_body_bytes(response.http_response.into_parts().1)
+                .await
+                .unwrap(),
+            credentials.as_bytes(),
+            "{location}"
+        );
+    }
+}
+
+// starts a local TLS server redirecting requests to `location`
+async fn tls_redirecting_server(
+    listener: tokio::net::TcpListener,
+    certificates: Vec<Certificate>,
+    key: PrivateKey,
+    location: &'static str,
+) {
+    let acceptor = TlsAcceptor::builder()
+        .with_single_cert(certificates, key)
+        .unwrap()
+        .with_all_versions_alpn()
+        .with_incoming(AddrIncoming::from_listener(listener).unwrap());
+    let service = make_service_fn(|_| async {
+        Ok::<_, io::Error>(service_fn(|_req| async {
+            Ok::<_, io::Error>(
+                http::Response::builder()
+                    .status(StatusCode::TEMPORARY_REDIRECT)
+                    .header(http::header::LOCATION, location)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        }))
+    });
+    let server = Server::builder(acceptor).serve(service);
+    server.await.unwrap()
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_subgraph_redirects_do_not_downgrade_tls() {
+    let certificate_pem = include_str!("./testdata/server_self_signed.crt");
+    let key_pem = include_str!("./testdata/server.key");
+
+    let certificates = load_certs(certificate_pem).unwrap();
+    let key = load_key(key_pem).unwrap();
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let socket_addr = listener.local_addr().unwrap();
+    tokio::task::spawn(tls_redirecting_server(
+        listener,
+        certificates,
+        key,
+        "http://localhost/graphql",
+    ));
+
+    let mut config = Configuration::default();
+    config.tls.subgraph.subgraphs.insert(
+        "test".to_string(),
+        TlsClient {
+            certificate_authorities: Some(certificate_pem.into()),
+            client_authentication: None,
+            experimental_spki_pins: None,
+        },
+    );
+    let subgraph_service = HttpClientService::from_config(
+        "test",
+        &config,
+        &rustls::RootCertStore::empty(),
+        Http2Config::Enable,
+    )
+    .unwrap()
+    .with_redirect_policy(Some(RedirectPolicy::default()));
+
+    let url = Uri::from_str(&format!("https://localhost:{}", socket_addr.port())).unwrap();
+    let error = subgraph_service
+        .oneshot(HttpRequest {
+            http_request: http::Request::builder()
+                .method(http::Method::POST)
+                .uri(url)
+                .header(CONTENT_TYPE, APPLICATION_JSON.essence_str())
+                .body(r#"{"query":"{ me { name username } }"#.into())
+                .unwrap(),
+            context: Context::new(),
+        })
+        .await
+        .unwrap_err();
+    assert!(
+        error
+            .to_string()
+            .contains("downgrade the connection from https"),
+        "{error}"
+    );
+}
+
 // starts a local server emulating a subgraph returning compressed response
 async fn emulate_subgraph_compressed_response(listener: TcpListener) {
     async fn handle(request: http::Request<Body>) -> Result<http::Response<Body>, Infallible> {
@@ -14,6 +14,7 @@ use tokio::fs::read_to_string;
 use tokio::sync::mpsc;
 use tower::BoxError;
 
+use super::signature::ManifestVerifier;
 use crate::uplink::persisted_queries_manifest_stream::MaybePersistedQueriesManifestChunks;
 use crate::uplink::persisted_queries_manifest_stream::PersistedQueriesManifestChunk;
 use crate::uplink::persisted_queries_manifest_stream::PersistedQueriesManifestQuery;
@@ -22,7 +23,24 @@ use crate::uplink::UplinkConfig;
 use crate::Configuration;
 
 /// An in memory cache of persisted queries.
-pub(crate) type PersistedQueryManifest = HashMap<String, String>;
+pub(crate) type PersistedQueryManifest = HashMap<String, PersistedQuery>;
+
+/// A persisted query from the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct PersistedQuery {
+    pub(crate) body: String,
+    /// Complexity of the operation, if it was computed when generating the manifest
+    pub(crate) complexity: Option<OperationComplexity>,
+}
+
+impl From<Operation> for PersistedQuery {
+    fn from(operation: Operation) -> Self {
+        PersistedQuery {
+            body: operation.body,
+            complexity: operation.complexity,
+        }
+    }
+}
 
 /// How the router should respond to requests that are not resolved as the IDs
 /// of an operation in the manifest. (For the most part this means "requests
@@ -126,8 +144,8 @@ impl FreeformGraphQLSafelist {
             normalized_bodies: HashSet::new(),
         };
 
-        for body in manifest.values() {
-            safelist.insert_from_manifest(body);
+        for operation in manifest.values() {
+            safelist.insert_from_manifest(&operation.body);
         }
 
         safelist
@@ -208,11 +226,16 @@ impl PersistedQueryManifestPoller {
     /// Starts polling immediately and this function only returns after all chunks have been fetched
     /// and the [`PersistedQueryManifest`] has been fully populated.
     pub(crate) async fn new(config: Configuration) -> Result<Self, BoxError> {
+        let verifier = match &config.persisted_queries.experimental_manifest_signature {
+            Some(signature) => Some(Arc::new(ManifestVerifier::new(signature).await?)),
+            None => None,
+        };
+
         if let Some(manifest_files) = config.persisted_queries.experimental_local_manifests {
             if manifest_files.is_empty() {
                 return Err("no local persisted query list files specified".into());
             }
-            let mut manifest: HashMap<String, String> = PersistedQueryManifest::new();
+            let mut manifest = PersistedQueryManifest::new();
 
             for local_pq_list in manifest_files {
                 tracing::info!(
@@ -249,8 +272,18 @@ impl PersistedQueryManifestPoller {
                     return Err("persisted query manifest chunk version is not 1".into());
                 }
 
+                if let Some(verifier) = &verifier {
+                    verifier.verify(&manifest_file).map_err(|e| -> BoxError {
+                        format!(
+                            "could not verify local persisted query list file {}: {}",
+                            local_pq_list, e
+                        )
+                        .into()
+                    })?;
+                }
+
                 for operation in manifest_file.operations {
-                    manifest.insert(operation.id, operation.body);
+                    manifest.insert(operation.id.clone(), operation.into());
                 }
             }
 
@@ -320,6 +353,7 @@ impl PersistedQueryManifestPoller {
                 ready_sender,
                 drop_receiver,
                 http_client,
+                verifier,
             ));
 
             // wait for the uplink poller to report its first success and continue
@@ -343,7 +377,7 @@ impl PersistedQueryManifestPoller {
         }
     }
 
-    pub(crate) fn get_operation_body(&self, persisted_query_id: &str) -> Option<String> {
+    pub(crate) fn get_operation(&self, persisted_query_id: &str) -> Option<PersistedQuery> {
         let state = self
             .state
             .read()
@@ -359,7 +393,11 @@ impl PersistedQueryManifestPoller {
             .state
             .read()
             .expect("could not acquire read lock on persisted query manifest state");
-        state.persisted_query_manifest.values().cloned().collect()
+        state
+            .persisted_query_manifest
+            .values()
+            .map(|operation| operation.body.clone())
+            .collect()
     }
 
     pub(crate) fn action_for_freeform_graphql(
@@ -414,6 +452,7 @@ async fn poll_uplink(
     ready_sender: mpsc::Sender<ManifestPollResultOnStartup>,
     mut drop_receiver: mpsc::Receiver<()>,
     http_client: Client,
+    verifier: Option<Arc<ManifestVerifier>>,
 ) {
     let http_client = http_client.clone();
     let mut uplink_executor = stream::select_all(vec![
@@ -423,9 +462,10 @@ async fn poll_uplink(
             Option<PersistedQueryManifest>,
         >(uplink_config.clone(), move |response| {
             let http_client = http_client.clone();
+            let verifier = verifier.clone();
             Box::new(Box::pin(async move {
                 match response {
-                    Some(chunks) => manifest_from_chunks(chunks, http_client)
+                    Some(chunks) => manifest_from_chunks(chunks, http_client, verifier)
                         .await
                         .map(Some)
                         .map_err(|err| {
@@ -557,6 +597,7 @@ async fn poll_uplink(
 async fn manifest_from_chunks(
     new_chunks: Vec<PersistedQueriesManifestChunk>,
     http_client: Client,
+    verifier: Option<Arc<ManifestVerifier>>,
 ) -> Result<PersistedQueryManifest, BoxError> {
     let mut new_persisted_query_manifest = PersistedQueryManifest::new();
     tracing::debug!("ingesting new persisted queries: {:?}", &new_chunks);
@@ -566,6 +607,7 @@ async fn manifest_from_chunks(
             new_chunk,
             &mut new_persisted_query_manifest,
             http_client.clone(),
+            verifier.as_deref(),
         )
         .await?
     }
@@ -582,13 +624,24 @@ async fn add_chunk_to_operations(
     chunk: PersistedQueriesManifestChunk,
     operations: &mut PersistedQueryManifest,
     http_client: Client,
+    verifier: Option<&ManifestVerifier>,
 ) -> Result<(), BoxError> {
     let mut it = chunk.urls.iter().peekable();
     while let Some(chunk_url) = it.next() {
         match fetch_chunk(http_client.clone(), chunk_url).await {
             Ok(chunk) => {
+                // a chunk failing verification is not retried from another URL
+                if let Some(verifier) = verifier {
+                    verifier.verify(&chunk).map_err(|e| -> BoxError {
+                        format!(
+                            "could not verify persisted queries manifest chunk from {}: {}",
+                            chunk_url, e
+                        )
+                        .into()
+                    })?;
+                }
                 for operation in chunk.operations {
-                    operations.insert(operation.id, operation.body);
+                    operations.insert(operation.id.clone(), operation.into());
                 }
                 return Ok(());
             }
@@ -670,6 +723,9 @@ pub(crate) struct SignedUrlChunk {
     pub(crate) format: String,
     pub(crate) version: u64,
     pub(crate) operations: Vec<Operation>,
+    /// JWT signing the operations, verified when `experimental_manifest_signature` is configured
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) signature: Option<String>,
 }
 
 /// A single operation containing an ID and a body,
@@ -677,6 +733,24 @@ pub(crate) struct SignedUrlChunk {
 pub(crate) struct Operation {
     pub(crate) id: String,
     pub(crate) body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) complexity: Option<OperationComplexity>,
+}
+
+/// Complexity of an operation, computed ahead of time by the tool generating the manifest.
+///
+/// Requests using the ID of an operation with a pre-computed complexity skip the
+/// corresponding static analysis: demand control uses the pre-computed cost instead of
+/// estimating it from the query plan, and operations deeper than `limits.max_depth` are
+/// rejected before being parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize, Serialize)]
+pub(crate) struct OperationComplexity {
+    /// Static cost of the operation, as estimated by demand control
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) cost: Option<f64>,
+    /// Depth of the operation, as measured for `limits.max_depth`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) depth: Option<u32>,
 }
 
 #[cfg(test)]
@@ -702,7 +776,12 @@ mod tests {
         )
         .await
         .unwrap();
-        assert_eq!(manifest_manager.get_operation_body(&id), Some(body))
+        assert_eq!(
+            manifest_manager
+                .get_operation(&id)
+                .map(|operation| operation.body),
+            Some(body)
+        )
     }
 
     #[tokio::test(flavor = "multi_thread")]
@@ -735,18 +814,26 @@ mod tests {
         )
         .await
         .unwrap();
-        assert_eq!(manifest_manager.get_operation_body(&id), Some(body))
+        assert_eq!(
+            manifest_manager
+                .get_operation(&id)
+                .map(|operation| operation.body),
+            Some(body)
+        )
     }
 
     #[test]
     fn safelist_body_normalization() {
+        let operation = |body: &str| PersistedQuery {
+            body: body.to_string(),
+            complexity: None,
+        };
         let safelist = FreeformGraphQLSafelist::new(&PersistedQueryManifest::from([(
             "valid-syntax".to_string(),
-            "fragment A on T { a }    query SomeOp { ...A ...B }    fragment,,, B on U{b c  } # yeah"
-                .to_string(),
+            operation("fragment A on T { a }    query SomeOp { ...A ...B }    fragment,,, B on U{b c  } # yeah"),
         ), (
             "invalid-syntax".to_string(),
-            "}}}".to_string()),
+            operation("}}}")),
         ]));
 
         let is_allowed = |body: &str| -> bool {
@@ -791,12 +878,18 @@ mod tests {
                     Some(vec![
                         "tests/fixtures/persisted-queries-manifest.json".to_string()
                     ]),
+                    None,
                 ))
                 .build()
                 .unwrap(),
         )
         .await
         .unwrap();
-        assert_eq!(manifest_manager.get_operation_body(&id), Some(body))
+        assert_eq!(
+            manifest_manager
+                .get_operation(&id)
+                .map(|operation| operation.body),
+            Some(body)
+        )
     }
 }
@@ -1,5 +1,6 @@
 mod id_extractor;
 mod manifest_poller;
+mod signature;
 
 #[cfg(test)]
 use std::sync::Arc;
@@ -8,6 +9,7 @@ use http::header::CACHE_CONTROL;
 use http::HeaderValue;
 use http::StatusCode;
 use id_extractor::PersistedQueryIdExtractor;
+pub(crate) use manifest_poller::OperationComplexity;
 pub(crate) use manifest_poller::PersistedQueryManifestPoller;
 use tower::BoxError;
 
@@ -28,6 +30,9 @@ pub(crate) struct PersistedQueryLayer {
     /// value of the manifest and projected safelist. None if the layer is disabled.
     pub(crate) manifest_poller: Option<PersistedQueryManifestPoller>,
     introspection_enabled: bool,
+    /// `limits.max_depth`, checked against the pre-computed depth of operations, unless the
+    /// limits are in warn only mode
+    max_depth: Option<u32>,
 }
 
 impl PersistedQueryLayer {
@@ -40,11 +45,16 @@ impl PersistedQueryLayer {
                     PersistedQueryManifestPoller::new(configuration.clone()).await?,
                 ),
                 introspection_enabled: configuration.supergraph.introspection,
+                max_depth: configuration
+                    .limits
+                    .max_depth
+                    .filter(|_| !configuration.limits.warn_only),
             })
         } else {
             Ok(Self {
                 manifest_poller: None,
                 introspection_enabled: configuration.supergraph.introspection,
+                max_depth: None,
             })
         }
     }
@@ -110,11 +120,23 @@ impl PersistedQueryLayer {
         } else {
             // if there is no query, look up the persisted query in the manifest
             // and put the body on the `supergraph_request`
-            if let Some(persisted_query_body) =
-                manifest_poller.get_operation_body(persisted_query_id)
-            {
+            if let Some(persisted_query) = manifest_poller.get_operation(persisted_query_id) {
+                if let Some(complexity) = persisted_query.complexity {
+                    if let Some((depth, max_depth)) = complexity
+                        .depth
+                        .zip(self.max_depth)
+                        .filter(|(depth, max_depth)| depth > max_depth)
+                    {
+                        return Err(supergraph_err_max_depth_limit(request, depth, max_depth));
+                    }
+                    // Demand control uses the pre-computed cost instead of estimating it
+                    request
+                        .context
+                        .extensions()
+                        .with_lock(|mut lock| lock.insert(complexity));
+                }
                 let body = request.supergraph_request.body_mut();
-                body.query = Some(persisted_query_body);
+                body.query = Some(persisted_query.body);
                 body.extensions.remove("persistedQuery");
                 // Record that we actually used our ID, so we can skip the
                 // safelist check later.
@@ -352,6 +374,24 @@ fn supergraph_err_pq_id_required(request: SupergraphRequest) -> SupergraphRespon
     )
 }
 
+fn supergraph_err_max_depth_limit(
+    request: SupergraphRequest,
+    depth: u32,
+    max_depth: u32,
+) -> SupergraphResponse {
+    supergraph_err(
+        GraphQLError::builder()
+            .extension_code("MAX_DEPTH_LIMIT")
+            .message("Maximum depth limit exceeded in this operation")
+            .extension("limit.measured", depth)
+            .extension("limit.max", max_depth)
+            .build(),
+        request,
+        ErrorCacheStrategy::DontCache,
+        StatusCode::BAD_REQUEST,
+    )
+}
+
 fn graphql_err(code: &str, message: &str) -> GraphQLError {
     GraphQLError::builder()
         .extension_code(code)
@@ -377,6 +417,7 @@ mod tests {
 
     use super::*;
     use crate::configuration::Apq;
+    use crate::configuration::Limits;
     use crate::configuration::PersistedQueries;
     use crate::configuration::PersistedQueriesSafelist;
     use crate::configuration::Supergraph;
@@ -463,6 +504,73 @@ mod tests {
         assert_eq!(request.supergraph_request.body().query, Some(body));
     }
 
+    #[tokio::test(flavor = "multi_thread")]
+    async fn pq_layer_uses_pre_computed_complexity() {
+        let pq_layer = PersistedQueryLayer::new(
+            &Configuration::fake_builder()
+                .persisted_query(
+                    PersistedQueries::builder()
+                        .enabled(true)
+                        .experimental_local_manifests(vec![
+                            "tests/fixtures/persisted-queries-manifest-complexity.json".to_string(),
+                        ])
+                        .build(),
+                )
+                .operation_limits(Limits {
+                    max_depth: Some(4),
+                    ..Default::default()
+                })
+                .build()
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+        let request = |id: &str| {
+            SupergraphRequest::fake_builder()
+                .extension("persistedQuery", json!({"version": 1, "sha256Hash": id}))
+                .build()
+                .unwrap()
+        };
+
+        let shallow = pq_layer
+            .supergraph_request(request("shallow"))
+            .ok()
+            .expect("pq layer returned response instead of putting the query on the request");
+        assert_eq!(
+            shallow
+                .context
+                .extensions()
+                .with_lock(|lock| lock.get::<OperationComplexity>().cloned()),
+            Some(OperationComplexity {
+                cost: Some(2.0),
+                depth: Some(2),
+            })
+        );
+
+        let unknown = pq_layer
+            .supergraph_request(request("unknown"))
+            .ok()
+            .expect("pq layer returned response instead of putting the query on the request");
+        assert!(unknown
+            .context
+            .extensions()
+            .with_lock(|lock| lock.get::<OperationComplexity>().is_none()));
+
+        let mut deep = pq_layer
+            .supergraph_request(request("deep"))
+            .expect_err("pq layer returned request instead of rejecting the deep operation");
+        assert_eq!(deep.response.status(), 400);
+        let response = deep.next_response().await.unwrap();
+        assert_eq!(
+            response.errors[0].extensions.get("code"),
+            Some(&"MAX_DEPTH_LIMIT".into())
+        );
+        assert_eq!(
+            response.errors[0].extensions.get("limit.measured"),
+            Some(&6.into())
+        );
+    }
+
     #[tokio::test(flavor = "multi_thread")]
     async fn pq_layer_passes_on_to_apq_layer_when_id_not_found() {
         let (_id, _body, manifest) = fake_manifest();
@@ -0,0 +1,209 @@
+//! Verification of the signature of persisted query manifests.
+//!
+//! A signed manifest carries a `signature` field: a JWT signed by the pipeline publishing the
+//! operations, with an `operations` claim mapping the ID of each operation to the hex encoded
+//! SHA-256 hash of its body. A manifest containing an operation that is missing from the claim,
+//! or whose body does not match its hash, is rejected as a whole, so that operations added or
+//! modified after the publication of the manifest never execute.
+
+use std::collections::HashMap;
+
+use jsonwebtoken::decode;
+use jsonwebtoken::decode_header;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::DecodingKey;
+use jsonwebtoken::Validation;
+use serde::Deserialize;
+use sha2::Digest;
+use sha2::Sha256;
+use tokio::fs::read_to_string;
+use tower::BoxError;
+
+use super::manifest_poller::SignedUrlChunk;
+use crate::configuration::PersistedQueriesManifestSignature;
+use crate::plugins::authentication::convert_key_algorithm;
+
+/// Claims of a manifest signature
+#[derive(Debug, Deserialize)]
+struct ManifestClaims {
+    /// SHA-256 hash of the body of each operation, by operation ID
+    operations: HashMap<String, String>,
+    #[serde(flatten)]
+    other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Verifies the signature of persisted query manifests
+#[derive(Debug)]
+pub(crate) struct ManifestVerifier {
+    keys: JwkSet,
+    claims: HashMap<String, serde_json::Value>,
+}
+
+impl ManifestVerifier {
+    pub(crate) async fn new(config: &PersistedQueriesManifestSignature) -> Result<Self, BoxError> {
+        let jwks = read_to_string(&config.jwks)
+            .await
+            .map_err(|e| -> BoxError {
+                format!(
+                    "could not read the persisted query manifest keys file {}: {}",
+                    config.jwks, e
+                )
+                .into()
+            })?;
+        let keys = serde_json::from_str(&jwks).map_err(|e| -> BoxError {
+            format!(
+                "could not parse the persisted query manifest keys file {}: {}",
+                config.jwks, e
+            )
+            .into()
+        })?;
+        Ok(Self {
+            keys,
+            claims: config.claims.clone(),
+        })
+    }
+
+    /// Checks that the chunk is signed by one of the keys, with the expected claims, and that
+    /// every operation of the chunk is covered by the signature
+    pub(crate) fn verify(&self, chunk: &SignedUrlChunk) -> Result<(), BoxError> {
+        let signature = chunk
+            .signature
+            .as_deref()
+            .ok_or("persisted query manifest is not signed")?;
+        let claims = self.decode(signature)?;
+
+        for (name, expected) in &self.claims {
+            if claims.other.get(name) != Some(expected) {
+                return Err(format!(
+                    "persisted query manifest signature does not have the expected '{name}' claim"
+                )
+                .into());
+            }
+        }
+
+        for operation in &chunk.operations {
+            let hash = hex::encode(Sha256::digest(operation.body.as_bytes()));
+            if claims.operations.get(&operation.id) != Some(&hash) {
+                return Err(format!(
+                    "persisted query {} does not match the manifest signature",
+                    operation.id
+                )
+                .into());
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(&self, signature: &str) -> Result<ManifestClaims, BoxError> {
+        let header = decode_header(signature)?;
+        let mut error = None;
+        for jwk in &self.keys.keys {
+            if header.kid.is_some() && jwk.common.key_id != header.kid {
+                continue;
+            }
+            if let Some(algorithm) = jwk.common.key_algorithm {
+                if convert_key_algorithm(algorithm) != Some(header.alg) {
+                    continue;
+                }
+            }
+
+            let mut validation = Validation::new(header.alg);
+            // manifests do not need to expire, but the expiration is checked if it is present
+            validation.required_spec_claims.clear();
+            validation.validate_aud = false;
+            match DecodingKey::from_jwk(jwk)
+                .and_then(|key| decode::<ManifestClaims>(signature, &key, &validation))
+            {
+                Ok(data) => return Ok(data.claims),
+                Err(e) => error = Some(e),
+            }
+        }
+
+        Err(match error {
+            Some(e) => format!("invalid persisted query manifest signature: {e}").into(),
+            None => "no key matches the persisted query manifest signature".into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use jsonwebtoken::encode;
+    use jsonwebtoken::EncodingKey;
+    use jsonwebtoken::Header;
+    use serde_json::json;
+
+    use super::*;
+    use crate::services::layers::persisted_queries::manifest_poller::Operation;
+
+    const SECRET: &[u8] = b"persisted queries signing secret";
+
+    fn verifier() -> ManifestVerifier {
+        ManifestVerifier {
+            keys: serde_json::from_value(json!({
+                "keys": [{
+                    "kty": "oct",
+                    "kid": "pipeline",
+                    "alg": "HS256",
+                    "k": URL_SAFE_NO_PAD.encode(SECRET)
+                }]
+            }))
+            .unwrap(),
+            claims: [("pipeline".to_string(), json!("release"))]
+                .into_iter()
+                .collect(),
+        }
+    }
+
+    fn chunk(body: &str, claims: serde_json::Value) -> SignedUrlChunk {
+        let mut header = Header::new(jsonwebtoken::Algorithm::HS256);
+        header.kid = Some("pipeline".to_string());
+        SignedUrlChunk {
+            format: "apollo-persisted-query-manifest".to_string(),
+            version: 1,
+            operations: vec![Operation {
+                id: "5678".to_string(),
+                body: body.to_string(),
+                complexity: None,
+            }],
+            signature: Some(encode(&header, &claims, &EncodingKey::from_secret(SECRET)).unwrap()),
+        }
+    }
+
+    #[test]
+    fn verifies_signed_manifests() {
+        let verifier = verifier();
+        let signed = json!({
+            "pipeline": "release",
+            "operations": {
+                "5678": hex::encode(Sha256::digest(b"query { typename }"))
+            }
+        });
+        assert!(verifier
+            .verify(&chunk("query { typename }", signed.clone()))
+            .is_ok());
+
+        // an operation modified after the signature
+        assert!(verifier
+            .verify(&chunk("query { me { password } }", signed))
+            .is_err());
+
+        // a signature from another pipeline
+        let other = json!({
+            "pipeline": "dev",
+            "operations": {
+                "5678": hex::encode(Sha256::digest(b"query { typename }"))
+            }
+        });
+        assert!(verifier
+            .verify(&chunk("query { typename }", other))
+            .is_err());
+
+        // a manifest without signature
+        let mut unsigned = chunk("query { typename }", json!({ "operations": {} }));
+        unsigned.signature = None;
+        assert!(verifier.verify(&unsigned).is_err());
+    }
+}
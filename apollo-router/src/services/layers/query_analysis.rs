@@ -7,27 +7,35 @@ use std::sync::Arc;
 use apollo_compiler::ast;
 use apollo_compiler::validation::Valid;
 use apollo_compiler::ExecutableDocument;
+use apollo_compiler::Name;
 use http::StatusCode;
 use lru::LruCache;
 use router_bridge::planner::UsageReporting;
+use sha2::Digest;
+use sha2::Sha256;
 use tokio::sync::Mutex;
 use tokio::task;
 
 use crate::apollo_studio_interop::generate_extended_references;
+use crate::apollo_studio_interop::generate_usage_reporting;
 use crate::apollo_studio_interop::ExtendedReferenceStats;
-use crate::context::OPERATION_KIND;
-use crate::context::OPERATION_NAME;
+use crate::context::keys::OPERATION_KIND;
+use crate::context::keys::OPERATION_NAME;
 use crate::graphql::Error;
 use crate::graphql::ErrorExtension;
 use crate::graphql::IntoGraphQLErrors;
+use crate::json_ext::Object;
 use crate::plugins::authorization::AuthorizationPlugin;
 use crate::plugins::telemetry::config::ApolloMetricsReferenceMode;
+use crate::plugins::telemetry::config::ApolloSignatureNormalizationAlgorithm;
 use crate::plugins::telemetry::config::Conf as TelemetryConfig;
 use crate::plugins::telemetry::consts::QUERY_PARSING_SPAN_NAME;
 use crate::query_planner::fetch::QueryHash;
 use crate::query_planner::OperationKind;
 use crate::services::SupergraphRequest;
 use crate::services::SupergraphResponse;
+use crate::spec::query::conditions;
+use crate::spec::query::conditions::EvaluatedConditions;
 use crate::spec::Query;
 use crate::spec::Schema;
 use crate::spec::SpecError;
@@ -41,35 +49,84 @@ pub(crate) struct QueryAnalysisLayer {
     pub(crate) schema: Arc<Schema>,
     configuration: Arc<Configuration>,
     cache: Arc<Mutex<LruCache<QueryAnalysisKey, Result<(Context, ParsedDocument), SpecError>>>>,
+    conditions_cache: Arc<Mutex<LruCache<ConditionsKey, EvaluatedOperation>>>,
     enable_authorization_directives: bool,
     metrics_reference_mode: ApolloMetricsReferenceMode,
+    signature_normalization_algorithm: ApolloSignatureNormalizationAlgorithm,
 }
 
+/// Operations are cached by hash, so that the cache does not keep the text of large or
+/// malicious operations
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 struct QueryAnalysisKey {
-    query: String,
+    query_hash: [u8; 32],
     operation_name: Option<String>,
+    schema_id: Arc<String>,
 }
 
+impl QueryAnalysisKey {
+    fn new(query: &str, operation_name: Option<String>, schema: &Schema) -> Self {
+        Self {
+            query_hash: Sha256::digest(query.as_bytes()).into(),
+            operation_name,
+            schema_id: schema.schema_id.clone(),
+        }
+    }
+}
+
+/// The evaluation of an operation's conditions only depends on the operation and on the values
+/// of its boolean variables
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+struct ConditionsKey {
+    query: QueryAnalysisKey,
+    variables: Vec<(Name, bool)>,
+}
+
+#[derive(Clone)]
+enum EvaluatedOperation {
+    NoConditions,
+    Kept,
+    Normalized {
+        context: Context,
+        doc: ParsedDocument,
+        usage_reporting: Arc<UsageReporting>,
+    },
+}
+
+/// Usage reporting of the operation sent by the client, when its conditions were evaluated before
+/// query planning. The query plan is computed from the normalized operation, but the operation is
+/// reported with its own signature and referenced fields
+#[derive(Clone)]
+pub(crate) struct OriginalUsageReporting(pub(crate) Arc<UsageReporting>);
+
 impl QueryAnalysisLayer {
     pub(crate) async fn new(schema: Arc<Schema>, configuration: Arc<Configuration>) -> Self {
         let enable_authorization_directives =
             AuthorizationPlugin::enable_directives(&configuration, &schema).unwrap_or(false);
         let metrics_reference_mode = TelemetryConfig::metrics_reference_mode(&configuration);
-
-        Self {
-            schema,
-            cache: Arc::new(Mutex::new(LruCache::new(
+        let signature_normalization_algorithm =
+            TelemetryConfig::signature_normalization_algorithm(&configuration);
+        let limit = configuration
+            .supergraph
+            .experimental_validation_cache
+            .limit
+            .unwrap_or(
                 configuration
                     .supergraph
                     .query_planning
                     .cache
                     .in_memory
                     .limit,
-            ))),
+            );
+
+        Self {
+            schema,
+            cache: Arc::new(Mutex::new(LruCache::new(limit))),
+            conditions_cache: Arc::new(Mutex::new(LruCache::new(limit))),
             enable_authorization_directives,
             configuration,
             metrics_reference_mode,
+            signature_normalization_algorithm,
         }
     }
 
@@ -78,15 +135,32 @@ impl QueryAnalysisLayer {
         query: &str,
         operation_name: Option<&str>,
     ) -> Result<ParsedDocument, SpecError> {
+        // Must be created *outside* of the spawn_blocking or the span is not connected to the
+        // parent
+        let span = tracing::info_span!(QUERY_PARSING_SPAN_NAME, "otel.kind" = "INTERNAL");
+
+        // small operations are faster to parse than to send to another thread
+        if query.len()
+            <= self
+                .configuration
+                .supergraph
+                .experimental_inline_parsing_max_bytes
+        {
+            return span.in_scope(|| {
+                Query::parse_document(
+                    query,
+                    operation_name,
+                    self.schema.as_ref(),
+                    self.configuration.as_ref(),
+                )
+            });
+        }
+
         let query = query.to_string();
         let operation_name = operation_name.map(|o| o.to_string());
         let schema = self.schema.clone();
         let conf = self.configuration.clone();
 
-        // Must be created *outside* of the spawn_blocking or the span is not connected to the
-        // parent
-        let span = tracing::info_span!(QUERY_PARSING_SPAN_NAME, "otel.kind" = "INTERNAL");
-
         task::spawn_blocking(move || {
             span.in_scope(|| {
                 Query::parse_document(
@@ -101,6 +175,112 @@ impl QueryAnalysisLayer {
         .expect("parse_document task panicked")
     }
 
+    /// Stores the operation name and kind, and the authorization requirements of the operation
+    /// in a new context
+    fn analyze(&self, doc: &ParsedDocument, op_name: Option<&str>) -> Context {
+        let context = Context::new();
+
+        let operation = doc.executable.operations.get(op_name).ok();
+        let operation_name = operation
+            .as_ref()
+            .and_then(|operation| operation.name.as_ref().map(|s| s.as_str().to_owned()));
+
+        if self.enable_authorization_directives {
+            AuthorizationPlugin::query_analysis(
+                doc,
+                operation_name.as_deref(),
+                &self.schema,
+                &context,
+            );
+        }
+
+        context
+            .insert_typed(&OPERATION_NAME, operation_name)
+            .expect("cannot insert operation name into context; this is a bug");
+        let operation_kind = operation.map(|op| OperationKind::from(op.operation_type));
+        // FIXME: I think we should not add an operation kind by default. If it's an invalid graphql operation for example it might be useful to detect there isn't operation_kind
+        context
+            .insert_typed(&OPERATION_KIND, operation_kind.unwrap_or_default())
+            .expect("cannot insert operation kind in the context; this is a bug");
+
+        context
+    }
+
+    /// Evaluates the `@skip` and `@include` conditions of the operation with the request's
+    /// variables, so that the query plan cache key is computed from the normalized operation.
+    /// Evaluation results are cached by operation and values of the boolean variables
+    async fn evaluate_conditions(
+        &self,
+        query: &str,
+        doc: &ParsedDocument,
+        op_name: Option<&str>,
+        variables: &Object,
+    ) -> Option<(Context, ParsedDocument, Arc<UsageReporting>)> {
+        let key = ConditionsKey {
+            query: QueryAnalysisKey::new(query, op_name.map(str::to_string), &self.schema),
+            variables: conditions::variable_values(&doc.ast, variables),
+        };
+        let entry = self.conditions_cache.lock().await.get(&key).cloned();
+        let evaluated = match entry {
+            Some(evaluated) => evaluated,
+            None => {
+                let evaluated = self.normalize(doc, op_name, variables).await;
+                (*self.conditions_cache.lock().await).put(key, evaluated.clone());
+                evaluated
+            }
+        };
+
+        let normalized = match evaluated {
+            EvaluatedOperation::NoConditions => return None,
+            EvaluatedOperation::Kept => None,
+            EvaluatedOperation::Normalized {
+                context,
+                doc,
+                usage_reporting,
+            } => Some((context, doc, usage_reporting)),
+        };
+        u64_counter!(
+            "apollo.router.query_planning.conditions",
+            "Number of operations with @skip or @include conditions evaluated before query planning",
+            1,
+            normalized = normalized.is_some()
+        );
+        normalized
+    }
+
+    async fn normalize(
+        &self,
+        doc: &ParsedDocument,
+        op_name: Option<&str>,
+        variables: &Object,
+    ) -> EvaluatedOperation {
+        let normalized =
+            match conditions::evaluate(self.schema.api_schema(), &doc.ast, op_name, variables) {
+                Ok(EvaluatedConditions::NoConditions) => return EvaluatedOperation::NoConditions,
+                Ok(EvaluatedConditions::Normalized(normalized)) => normalized.to_string(),
+                Ok(EvaluatedConditions::Kept) | Err(_) => return EvaluatedOperation::Kept,
+            };
+
+        // the normalized operation should be valid, but the original one is used if it is not
+        match self.parse_document(&normalized, op_name).await {
+            Ok(normalized) => EvaluatedOperation::Normalized {
+                context: self.analyze(&normalized, op_name),
+                doc: normalized,
+                usage_reporting: Arc::new(
+                    generate_usage_reporting(
+                        &doc.executable,
+                        &doc.executable,
+                        &op_name.map(str::to_string),
+                        self.schema.supergraph_schema(),
+                        &self.signature_normalization_algorithm,
+                    )
+                    .result,
+                ),
+            },
+            Err(_) => EvaluatedOperation::Kept,
+        }
+    }
+
     pub(crate) async fn supergraph_request(
         &self,
         request: SupergraphRequest,
@@ -135,27 +315,22 @@ impl QueryAnalysisLayer {
             .query
             .clone()
             .expect("query presence was already checked");
-        let entry = self
-            .cache
-            .lock()
-            .await
-            .get(&QueryAnalysisKey {
-                query: query.clone(),
-                operation_name: op_name.clone(),
-            })
-            .cloned();
+        let key = QueryAnalysisKey::new(&query, op_name.clone(), &self.schema);
+        let entry = self.cache.lock().await.get(&key).cloned();
 
         let res = match entry {
             None => {
-                match self.parse_document(&query, op_name.as_deref()).await {
+                let parsed = self.parse_document(&query, op_name.as_deref()).await;
+                u64_counter!(
+                    "apollo.router.query_analysis.cache",
+                    "Number of lookups in the cache of operation parsing and validation results",
+                    1,
+                    result = "miss",
+                    valid = parsed.is_ok()
+                );
+                match parsed {
                     Err(errors) => {
-                        (*self.cache.lock().await).put(
-                            QueryAnalysisKey {
-                                query,
-                                operation_name: op_name,
-                            },
-                            Err(errors.clone()),
-                        );
+                        (*self.cache.lock().await).put(key, Err(errors.clone()));
                         let errors = match errors.into_graphql_errors() {
                             Ok(v) => v,
                             Err(errors) => vec![Error::builder()
@@ -172,51 +347,49 @@ impl QueryAnalysisLayer {
                             .expect("response is valid"));
                     }
                     Ok(doc) => {
-                        let context = Context::new();
+                        let context = self.analyze(&doc, op_name.as_deref());
 
-                        let operation = doc.executable.operations.get(op_name.as_deref()).ok();
-                        let operation_name = operation.as_ref().and_then(|operation| {
-                            operation.name.as_ref().map(|s| s.as_str().to_owned())
-                        });
-
-                        if self.enable_authorization_directives {
-                            AuthorizationPlugin::query_analysis(
-                                &doc,
-                                operation_name.as_deref(),
-                                &self.schema,
-                                &context,
-                            );
-                        }
-
-                        context
-                            .insert(OPERATION_NAME, operation_name)
-                            .expect("cannot insert operation name into context; this is a bug");
-                        let operation_kind =
-                            operation.map(|op| OperationKind::from(op.operation_type));
-                        // FIXME: I think we should not add an operation kind by default. If it's an invalid graphql operation for example it might be useful to detect there isn't operation_kind
-                        context
-                            .insert(OPERATION_KIND, operation_kind.unwrap_or_default())
-                            .expect("cannot insert operation kind in the context; this is a bug");
-
-                        (*self.cache.lock().await).put(
-                            QueryAnalysisKey {
-                                query,
-                                operation_name: op_name.clone(),
-                            },
-                            Ok((context.clone(), doc.clone())),
-                        );
+                        (*self.cache.lock().await).put(key, Ok((context.clone(), doc.clone())));
 
                         Ok((context, doc))
                     }
                 }
             }
-            Some(c) => c,
+            Some(c) => {
+                u64_counter!(
+                    "apollo.router.query_analysis.cache",
+                    "Number of lookups in the cache of operation parsing and validation results",
+                    1,
+                    result = "hit",
+                    valid = c.is_ok()
+                );
+                c
+            }
         };
 
-        match res {
-            Ok((context, doc)) => {
-                request.context.extend(&context);
+        let res = match res {
+            Ok((context, doc))
+                if self
+                    .configuration
+                    .supergraph
+                    .query_planning
+                    .experimental_evaluate_conditions =>
+            {
+                let normalized = self
+                    .evaluate_conditions(
+                        &query,
+                        &doc,
+                        op_name.as_deref(),
+                        &request.supergraph_request.body().variables,
+                    )
+                    .await;
+                Ok((context, doc, normalized))
+            }
+            res => res.map(|(context, doc)| (context, doc, None)),
+        };
 
+        match res {
+            Ok((context, doc, normalized)) => {
                 let extended_ref_stats = if matches!(
                     self.metrics_reference_mode,
                     ApolloMetricsReferenceMode::Extended
@@ -231,6 +404,19 @@ impl QueryAnalysisLayer {
                     None
                 };
 
+                // the normalized operation is planned and executed, but the operation sent by
+                // the client is reported
+                let (context, doc) = match normalized {
+                    Some((context, normalized, usage_reporting)) => {
+                        request.context.extensions().with_lock(|mut lock| {
+                            lock.insert(OriginalUsageReporting(usage_reporting))
+                        });
+                        (context, normalized)
+                    }
+                    None => (context, doc),
+                };
+                request.context.extend(&context);
+
                 request.context.extensions().with_lock(|mut lock| {
                     lock.insert::<ParsedDocument>(doc.clone());
                     if let Some(stats) = extended_ref_stats {
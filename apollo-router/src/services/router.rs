@@ -36,6 +36,7 @@ pub type Body = hyper::Body;
 pub type Error = hyper::Error;
 
 pub mod body;
+mod retryable;
 pub(crate) mod service;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,208 @@
+//! Classification of the errors returned to clients as retryable or not.
+//!
+//! Errors caused by transient conditions, like timeouts, rate limiting or unavailable subgraphs,
+//! are retryable, with a suggested backoff. Errors caused by the request itself, like validation
+//! errors, are not: the retried request would fail the same way.
+
+use http::header::CONTENT_LENGTH;
+use serde_json_bytes::Value;
+use tower::BoxError;
+
+use crate::configuration::RetryableErrors;
+use crate::graphql;
+use crate::plugins::traffic_shaping::RETRY_AFTER_EXTENSION;
+use crate::services::router;
+use crate::services::router::body::get_body_bytes;
+
+const RETRYABLE: &str = "retryable";
+
+/// Codes of the errors caused by transient conditions
+const RETRYABLE_CODES: &[&str] = &[
+    "ADMISSION_QUEUE_REJECTED",
+    "MEMORY_LIMIT_EXCEEDED",
+    "REQUEST_TIMEOUT",
+    "REQUEST_RATE_LIMITED",
+    "SUBREQUEST_BACKING_OFF",
+    "SUBREQUEST_WEBSOCKET_ERROR",
+    "SUBSCRIPTION_SCHEMA_RELOAD",
+];
+
+/// HTTP status codes of failed subgraph requests that can succeed when retried
+const RETRYABLE_STATUS_CODES: &[u64] = &[408, 429, 502, 503, 504];
+
+/// Adds the `retryable` extension to errors, and the suggested backoff to retryable ones. The
+/// backoff is the delay asked by the subgraph, if any
+pub(crate) fn classify(config: &RetryableErrors, errors: &mut [graphql::Error]) {
+    let backoff = u64::try_from(config.backoff.as_millis()).unwrap_or(u64::MAX);
+    for error in errors {
+        // Keep the classification of errors coming from subgraphs
+        if error.extensions.contains_key(RETRYABLE) {
+            continue;
+        }
+        let retryable = is_retryable(config, error);
+        error.extensions.insert(RETRYABLE, retryable.into());
+        if retryable && !error.extensions.contains_key(RETRY_AFTER_EXTENSION) {
+            error
+                .extensions
+                .insert(RETRY_AFTER_EXTENSION, backoff.into());
+        }
+    }
+}
+
+/// Classifies the errors of the responses rejected by plugins before the request reaches the
+/// GraphQL pipeline, like the ones of admission control and the memory limit. The responses of
+/// the pipeline are successful or already classified
+pub(crate) async fn classify_rejection(
+    config: &RetryableErrors,
+    response: router::Response,
+) -> Result<router::Response, BoxError> {
+    if response.response.status().is_success() {
+        return Ok(response);
+    }
+    let router::Response { response, context } = response;
+    let (mut parts, body) = response.into_parts();
+    let bytes = get_body_bytes(body).await?;
+    let body = match serde_json::from_slice::<graphql::Response>(&bytes) {
+        Ok(mut graphql_response) if !graphql_response.errors.is_empty() => {
+            classify(config, &mut graphql_response.errors);
+            parts.headers.remove(CONTENT_LENGTH);
+            serde_json::to_vec(&graphql_response)?.into()
+        }
+        _ => bytes,
+    };
+    Ok(router::Response {
+        response: http::Response::from_parts(parts, router::Body::from(body)),
+        context,
+    })
+}
+
+fn is_retryable(config: &RetryableErrors, error: &graphql::Error) -> bool {
+    let code = error.extensions.get("code").and_then(Value::as_str);
+    if let Some(retryable) = code.and_then(|code| config.codes.get(code)) {
+        return *retryable;
+    }
+    match code {
+        Some("SUBREQUEST_HTTP_ERROR") => {
+            // Without a status code, the subgraph could not be reached
+            match error
+                .extensions
+                .get("http")
+                .and_then(|http| http.as_object()?.get("status"))
+            {
+                Some(status) => status
+                    .as_u64()
+                    .is_some_and(|status| RETRYABLE_STATUS_CODES.contains(&status)),
+                None => true,
+            }
+        }
+        Some(code) => RETRYABLE_CODES.contains(&code),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use http::StatusCode;
+    use serde_json_bytes::json;
+
+    use super::*;
+    use crate::Context;
+
+    fn error(code: &str) -> graphql::Error {
+        graphql::Error::builder()
+            .message("error")
+            .extension_code(code)
+            .build()
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let config = RetryableErrors {
+            enabled: true,
+            backoff: Duration::from_millis(250),
+            codes: [("SERVICE_WARMING_UP".to_string(), true)].into(),
+        };
+        let mut errors = vec![
+            error("GRAPHQL_VALIDATION_FAILED"),
+            error("REQUEST_TIMEOUT"),
+            error("SERVICE_WARMING_UP"),
+            graphql::Error::builder()
+                .message("HTTP fetch failed from 'products'")
+                .extension_code("SUBREQUEST_HTTP_ERROR")
+                .extension("http", json!({ "status": 503 }))
+                .build(),
+            graphql::Error::builder()
+                .message("HTTP fetch failed from 'products'")
+                .extension_code("SUBREQUEST_HTTP_ERROR")
+                .extension("http", json!({ "status": 400 }))
+                .build(),
+            graphql::Error::builder()
+                .message("The subgraph asked to retry later")
+                .extension_code("SUBREQUEST_BACKING_OFF")
+                .extension(RETRY_AFTER_EXTENSION, 30_000)
+                .build(),
+            graphql::Error::builder()
+                .message("from the subgraph")
+                .extension_code("REQUEST_TIMEOUT")
+                .extension("retryable", false)
+                .build(),
+        ];
+        classify(&config, &mut errors);
+
+        let retryable: Vec<_> = errors
+            .iter()
+            .map(|error| {
+                (
+                    error.extensions.get("retryable").cloned(),
+                    error.extensions.get(RETRY_AFTER_EXTENSION).cloned(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            retryable,
+            vec![
+                (Some(false.into()), None),
+                (Some(true.into()), Some(250.into())),
+                (Some(true.into()), Some(250.into())),
+                (Some(true.into()), Some(250.into())),
+                (Some(false.into()), None),
+                (Some(true.into()), Some(30_000.into())),
+                (Some(false.into()), None),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn classifies_rejections() {
+        let config = RetryableErrors {
+            enabled: true,
+            backoff: Duration::from_millis(250),
+            codes: Default::default(),
+        };
+        let response = router::Response::infallible_builder()
+            .error(
+                graphql::Error::builder()
+                    .message("the router is over its memory limit")
+                    .extension_code("MEMORY_LIMIT_EXCEEDED")
+                    .build(),
+            )
+            .status_code(StatusCode::SERVICE_UNAVAILABLE)
+            .context(Context::new())
+            .build();
+
+        let response = classify_rejection(&config, response).await.unwrap();
+        assert_eq!(response.response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = get_body_bytes(response.response.into_body()).await.unwrap();
+        let response: graphql::Response = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            response.errors[0].extensions.get(RETRYABLE),
+            Some(&true.into())
+        );
+        assert_eq!(
+            response.errors[0].extensions.get(RETRY_AFTER_EXTENSION),
+            Some(&250.into())
+        );
+    }
+}
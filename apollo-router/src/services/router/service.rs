@@ -33,6 +33,7 @@ use tower::ServiceExt;
 use tower_service::Service;
 use tracing::Instrument;
 
+use super::retryable;
 use super::ClientRequestAccepts;
 use crate::axum_factory::CanceledRequest;
 use crate::batching::Batch;
@@ -40,6 +41,7 @@ use crate::batching::BatchQuery;
 use crate::cache::DeduplicatingCache;
 use crate::configuration::Batching;
 use crate::configuration::BatchingMode;
+use crate::configuration::RetryableErrors;
 use crate::context::CONTAINS_GRAPHQL_ERROR;
 use crate::graphql;
 use crate::http_ext;
@@ -96,6 +98,8 @@ pub(crate) struct RouterService {
     query_analysis_layer: QueryAnalysisLayer,
     http_max_request_bytes: usize,
     batching: Batching,
+    /// Set when errors are classified as retryable
+    retryable_errors: Option<Arc<RetryableErrors>>,
 }
 
 impl RouterService {
@@ -106,6 +110,7 @@ impl RouterService {
         query_analysis_layer: QueryAnalysisLayer,
         http_max_request_bytes: usize,
         batching: Batching,
+        retryable_errors: Option<Arc<RetryableErrors>>,
     ) -> Self {
         RouterService {
             supergraph_creator,
@@ -114,6 +119,7 @@ impl RouterService {
             query_analysis_layer,
             http_max_request_bytes,
             batching,
+            retryable_errors,
         }
     }
 }
@@ -269,6 +275,15 @@ impl RouterService {
         let (mut parts, mut body) = response.into_parts();
         process_vary_header(&mut parts.headers);
 
+        if let Some(retryable_errors) = self.retryable_errors.clone() {
+            body = body
+                .map(move |mut response| {
+                    retryable::classify(&retryable_errors, &mut response.errors);
+                    response
+                })
+                .boxed();
+        }
+
         if context
             .extensions()
             .with_lock(|lock| lock.get::<CanceledRequest>().is_some())
@@ -378,6 +393,7 @@ impl RouterService {
                         monotonic_counter.apollo.router.graphql_error = 1u64,
                         code = "INVALID_ACCEPT_HEADER"
                     );
+                    crate::diagnose::record_errors(Some("INVALID_ACCEPT_HEADER"), 1);
                     // Useful for selector in spans/instruments/events
                     context.insert_json_value(
                         CONTAINS_GRAPHQL_ERROR,
@@ -827,6 +843,7 @@ impl RouterService {
         }
 
         for (code, count) in map {
+            crate::diagnose::record_errors(code, count);
             match code {
                 None => {
                     tracing::info!(monotonic_counter.apollo.router.graphql_error = count,);
@@ -867,6 +884,7 @@ pub(crate) struct RouterCreator {
     query_analysis_layer: QueryAnalysisLayer,
     http_max_request_bytes: usize,
     batching: Batching,
+    retryable_errors: Option<Arc<RetryableErrors>>,
 }
 
 impl ServiceFactory<router::Request> for RouterCreator {
@@ -891,6 +909,19 @@ impl RouterFactory for RouterCreator {
             .for_each(|p| mm.extend(p.web_endpoints()));
         mm
     }
+
+    fn activated(&self, previous: &Self) {
+        let schema = self.supergraph_creator.schema();
+        let previous_schema = previous.supergraph_creator.schema();
+        if schema.schema_id == previous_schema.schema_id {
+            return;
+        }
+        let change = schema.changes_from(&previous_schema);
+        self.supergraph_creator
+            .plugins()
+            .values()
+            .for_each(|p| p.schema_changed(&change));
+    }
 }
 
 impl RouterCreator {
@@ -918,6 +949,18 @@ impl RouterCreator {
             http_max_request_bytes: configuration.limits.http_max_request_bytes,
             persisted_query_layer,
             batching: configuration.batching.clone(),
+            retryable_errors: configuration
+                .supergraph
+                .experimental_retryable_errors
+                .enabled
+                .then(|| {
+                    Arc::new(
+                        configuration
+                            .supergraph
+                            .experimental_retryable_errors
+                            .clone(),
+                    )
+                }),
         })
     }
 
@@ -936,17 +979,30 @@ impl RouterCreator {
             self.query_analysis_layer.clone(),
             self.http_max_request_bytes,
             self.batching.clone(),
+            self.retryable_errors.clone(),
         ));
 
+        let service = self
+            .supergraph_creator
+            .plugins()
+            .iter()
+            .rev()
+            .fold(router_service.boxed(), |acc, (_, e)| e.router_service(acc));
+        // errors returned by plugins before the request reaches the router service are classified
+        // here
+        let service = match self.retryable_errors.clone() {
+            Some(retryable_errors) => service
+                .and_then(move |response| {
+                    let retryable_errors = retryable_errors.clone();
+                    async move { retryable::classify_rejection(&retryable_errors, response).await }
+                })
+                .boxed(),
+            None => service,
+        };
+
         ServiceBuilder::new()
             .layer(self.static_page.clone())
-            .service(
-                self.supergraph_creator
-                    .plugins()
-                    .iter()
-                    .rev()
-                    .fold(router_service.boxed(), |acc, (_, e)| e.router_service(acc)),
-            )
+            .service(service)
     }
 }
 
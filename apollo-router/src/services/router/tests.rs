@@ -17,6 +17,7 @@ use crate::graphql;
 use crate::services::router;
 use crate::services::router::body::get_body_bytes;
 use crate::services::router::service::from_supergraph_mock_callback;
+use crate::services::router::service::from_supergraph_mock_callback_and_configuration;
 use crate::services::router::service::process_vary_header;
 use crate::services::subgraph;
 use crate::services::supergraph;
@@ -24,6 +25,7 @@ use crate::services::SupergraphRequest;
 use crate::services::SupergraphResponse;
 use crate::services::MULTIPART_DEFER_CONTENT_TYPE;
 use crate::test_harness::make_fake_batch;
+use crate::Configuration;
 use crate::Context;
 
 // Test Vary processing
@@ -571,3 +573,61 @@ async fn escaped_quotes_in_string_literal() {
     // The string literal made it through unchanged:
     assert!(subgraph_query.contains(r#"reviewsForAuthor(authorID:"\"1\"")"#));
 }
+
+#[tokio::test]
+async fn it_classifies_retryable_errors() {
+    let configuration: Configuration =
+        "supergraph:\n  experimental_retryable_errors:\n    enabled: true\n    backoff: 500ms\n"
+            .parse()
+            .unwrap();
+    let mut router_service = from_supergraph_mock_callback_and_configuration(
+        move |req| {
+            SupergraphResponse::error_builder()
+                .error(
+                    graphql::Error::builder()
+                        .message("request timed out")
+                        .extension_code("REQUEST_TIMEOUT")
+                        .build(),
+                )
+                .status_code(http::StatusCode::GATEWAY_TIMEOUT)
+                .context(req.context)
+                .build()
+        },
+        Arc::new(configuration),
+    )
+    .await;
+
+    let mut first_error = |request: SupergraphRequest| {
+        let response = router_service.call(request.try_into().unwrap());
+        async move {
+            let response = response
+                .await
+                .unwrap()
+                .into_graphql_response_stream()
+                .await
+                .next()
+                .await
+                .unwrap()
+                .unwrap();
+            response.errors[0].extensions.clone()
+        }
+    };
+
+    let timeout = first_error(SupergraphRequest::canned_builder().build().unwrap()).await;
+    assert_eq!(timeout.get("retryable"), Some(&true.into()));
+    assert_eq!(timeout.get("retry_after_ms"), Some(&500.into()));
+
+    let invalid = first_error(
+        SupergraphRequest::fake_builder()
+            .query("{ unknownField }")
+            .build()
+            .unwrap(),
+    )
+    .await;
+    assert_eq!(
+        invalid.get("code"),
+        Some(&"GRAPHQL_VALIDATION_FAILED".into())
+    );
+    assert_eq!(invalid.get("retryable"), Some(&false.into()));
+    assert_eq!(invalid.get("retry_after_ms"), None);
+}
@@ -0,0 +1,246 @@
+//! Input constraints declared on arguments with the `@constraint` directive.
+//!
+//! The constraints spec is linked by the supergraph like other Apollo specs:
+//!
+//! ```graphql
+//! extend schema @link(url: "https://specs.apollo.dev/constraints/v0.1", import: ["@constraint"])
+//!
+//! directive @constraint(maxLength: Int, maxItems: Int, min: Float, max: Float) on ARGUMENT_DEFINITION
+//! ```
+//!
+//! Arguments are collected when the operation is parsed, with the variables they use, and their
+//! values are checked with the other variables, before query planning.
+
+use apollo_compiler::ast;
+use apollo_compiler::executable;
+use apollo_compiler::ExecutableDocument;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json_bytes::ByteString;
+use tower::BoxError;
+
+use super::query::parse_hir_value;
+use super::query::traverse;
+use super::Schema;
+use crate::json_ext::Object;
+use crate::json_ext::Value;
+
+pub(crate) const CONSTRAINT_DIRECTIVE_NAME: &str = "constraint";
+pub(crate) const CONSTRAINTS_SPEC_BASE_URL: &str = "https://specs.apollo.dev/constraints";
+pub(crate) const CONSTRAINTS_SPEC_VERSION_RANGE: &str = ">=0.1.0, <=0.1.0";
+
+/// Limits on the value of an argument
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Constraint {
+    /// Maximum number of characters of strings
+    max_length: Option<usize>,
+    /// Maximum number of items of the list
+    max_items: Option<usize>,
+    /// Minimum value of numbers
+    min: Option<f64>,
+    /// Maximum value of numbers
+    max: Option<f64>,
+}
+
+impl Constraint {
+    fn from_directive(directive: &ast::Directive) -> Self {
+        let int = |name: &str| {
+            directive
+                .argument_by_name(name)
+                .and_then(|value| value.to_i32())
+                .and_then(|value| usize::try_from(value).ok())
+        };
+        let float = |name: &str| {
+            directive
+                .argument_by_name(name)
+                .and_then(|value| value.to_f64())
+        };
+        Constraint {
+            max_length: int("maxLength"),
+            max_items: int("maxItems"),
+            min: float("min"),
+            max: float("max"),
+        }
+    }
+
+    /// Checks a value, returning the violated limit
+    pub(crate) fn check(&self, value: &Value) -> Result<(), String> {
+        if let (Value::Array(items), Some(max_items)) = (value, self.max_items) {
+            if items.len() > max_items {
+                return Err(format!("the list has more than {max_items} items"));
+            }
+        }
+        self.check_items(value)
+    }
+
+    /// Checks the strings and numbers of a value, including the items of lists
+    fn check_items(&self, value: &Value) -> Result<(), String> {
+        match value {
+            Value::Array(items) => items.iter().try_for_each(|item| self.check_items(item)),
+            Value::String(string) => match self.max_length {
+                Some(max_length) if string.as_str().chars().count() > max_length => {
+                    Err(format!("the string is longer than {max_length} characters"))
+                }
+                _ => Ok(()),
+            },
+            Value::Number(number) => {
+                let Some(number) = number.as_f64() else {
+                    return Ok(());
+                };
+                match (self.min, self.max) {
+                    (Some(min), _) if number < min => Err(format!("the value is lower than {min}")),
+                    (_, Some(max)) if number > max => {
+                        Err(format!("the value is greater than {max}"))
+                    }
+                    _ => Ok(()),
+                }
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Value of an argument, where variables are resolved when the request is validated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ArgumentValue {
+    Variable(ByteString),
+    List(Vec<ArgumentValue>),
+    Literal(Value),
+}
+
+impl ArgumentValue {
+    fn from_hir(value: &executable::Value) -> Option<Self> {
+        match value {
+            executable::Value::Variable(name) => {
+                Some(ArgumentValue::Variable(name.as_str().into()))
+            }
+            executable::Value::List(items) if contains_variables(value) => items
+                .iter()
+                .map(|item| ArgumentValue::from_hir(item))
+                .collect::<Option<_>>()
+                .map(ArgumentValue::List),
+            // variables in input objects are not resolved, input object arguments are not
+            // constrained
+            _ if contains_variables(value) => None,
+            _ => parse_hir_value(value).map(ArgumentValue::Literal),
+        }
+    }
+
+    /// Resolves the variables used in the value. `variable` returns `None` for unknown variables
+    fn resolve<'v>(&self, variable: &impl Fn(&str) -> Option<&'v Value>) -> Value {
+        match self {
+            ArgumentValue::Variable(name) => variable(name.as_str()).cloned().unwrap_or_default(),
+            ArgumentValue::List(items) => {
+                Value::Array(items.iter().map(|item| item.resolve(variable)).collect())
+            }
+            ArgumentValue::Literal(value) => value.clone(),
+        }
+    }
+}
+
+fn contains_variables(value: &executable::Value) -> bool {
+    match value {
+        executable::Value::Variable(_) => true,
+        executable::Value::List(items) => items.iter().any(|item| contains_variables(item)),
+        executable::Value::Object(fields) => {
+            fields.iter().any(|(_, value)| contains_variables(value))
+        }
+        _ => false,
+    }
+}
+
+/// Constrained argument of a field selected by an operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ConstrainedArgument {
+    /// Argument coordinate, like `Query.users(name:)`
+    pub(crate) coordinate: String,
+    pub(crate) constraint: Constraint,
+    pub(crate) value: ArgumentValue,
+}
+
+impl ConstrainedArgument {
+    /// Collects the constrained arguments of an operation. Returns nothing if the supergraph does
+    /// not link the constraints spec
+    pub(crate) fn collect(
+        schema: &Schema,
+        document: &ExecutableDocument,
+        operation_name: Option<&str>,
+    ) -> Result<Vec<Self>, BoxError> {
+        let Some(directive_name) = Schema::directive_name(
+            schema.supergraph_schema(),
+            CONSTRAINTS_SPEC_BASE_URL,
+            CONSTRAINTS_SPEC_VERSION_RANGE,
+            CONSTRAINT_DIRECTIVE_NAME,
+        ) else {
+            return Ok(Vec::new());
+        };
+        let mut visitor = ConstraintsVisitor {
+            schema: schema.supergraph_schema(),
+            directive_name,
+            arguments: Vec::new(),
+        };
+        traverse::document(&mut visitor, document, operation_name)?;
+        Ok(visitor.arguments)
+    }
+}
+
+struct ConstraintsVisitor<'a> {
+    schema: &'a apollo_compiler::Schema,
+    directive_name: String,
+    arguments: Vec<ConstrainedArgument>,
+}
+
+impl<'a> traverse::Visitor for ConstraintsVisitor<'a> {
+    fn schema(&self) -> &apollo_compiler::Schema {
+        self.schema
+    }
+
+    fn field(
+        &mut self,
+        parent_type: &str,
+        field_def: &ast::FieldDefinition,
+        node: &executable::Field,
+    ) -> Result<(), BoxError> {
+        for argument_def in &field_def.arguments {
+            let Some(directive) = argument_def.directives.get(&self.directive_name) else {
+                continue;
+            };
+            let Some(value) = node
+                .arguments
+                .iter()
+                .find(|argument| argument.name == argument_def.name)
+                .and_then(|argument| ArgumentValue::from_hir(&argument.value))
+            else {
+                continue;
+            };
+            self.arguments.push(ConstrainedArgument {
+                coordinate: format!("{parent_type}.{}({}:)", field_def.name, argument_def.name),
+                constraint: Constraint::from_directive(directive),
+                value,
+            });
+        }
+        traverse::field(self, field_def, node)
+    }
+}
+
+/// Checks the constrained arguments of an operation, returning the coordinates and the violated
+/// limits
+pub(crate) fn check_arguments<'a, 'v>(
+    arguments: &'a [ConstrainedArgument],
+    variables: &'v Object,
+    default_value: impl Fn(&str) -> Option<&'v Value>,
+) -> Vec<(&'a str, String)> {
+    arguments
+        .iter()
+        .filter_map(|argument| {
+            let value = argument
+                .value
+                .resolve(&|name| variables.get(name).or_else(|| default_value(name)));
+            argument
+                .constraint
+                .check(&value)
+                .err()
+                .map(|reason| (argument.coordinate.as_str(), reason))
+        })
+        .collect()
+}
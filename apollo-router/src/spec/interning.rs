@@ -0,0 +1,264 @@
+//! Sharing of the strings of subgraph schemas with the supergraph schema.
+//!
+//! The supergraph schema already contains the names and descriptions of every subgraph schema,
+//! but the subgraph schemas are parsed from their own SDL, so each of their names and
+//! descriptions is allocated again. For very large graphs, this duplicates most of the memory
+//! used by the schemas. Interning replaces the strings of a subgraph schema with the equal
+//! strings of the supergraph schema, so that they are only allocated once.
+//!
+//! The API schema does not need interning: it is built by cloning the supergraph schema and
+//! removing its inaccessible elements, so its strings are already those of the supergraph schema.
+
+use std::collections::HashMap;
+
+use apollo_compiler::collections::IndexMap;
+use apollo_compiler::schema::Component;
+use apollo_compiler::schema::ExtendedType;
+use apollo_compiler::schema::FieldDefinition;
+use apollo_compiler::schema::InputValueDefinition;
+use apollo_compiler::schema::Type;
+use apollo_compiler::Name;
+use apollo_compiler::Node;
+use apollo_compiler::Schema;
+
+/// Names and descriptions of a schema, to be shared with other schemas
+pub(crate) struct Interner<'a> {
+    names: HashMap<&'a str, Name>,
+    descriptions: HashMap<&'a str, Node<str>>,
+}
+
+impl<'a> Interner<'a> {
+    pub(crate) fn new(schema: &'a Schema) -> Self {
+        let mut interner = Interner {
+            names: HashMap::new(),
+            descriptions: HashMap::new(),
+        };
+        for (type_name, ty) in &schema.types {
+            interner.add_name(type_name);
+            match ty {
+                ExtendedType::Scalar(scalar) => interner.add_description(&scalar.description),
+                ExtendedType::Object(object) => {
+                    interner.add_description(&object.description);
+                    interner.add_fields(&object.fields);
+                }
+                ExtendedType::Interface(interface) => {
+                    interner.add_description(&interface.description);
+                    interner.add_fields(&interface.fields);
+                }
+                ExtendedType::Union(union) => interner.add_description(&union.description),
+                ExtendedType::Enum(enum_type) => {
+                    interner.add_description(&enum_type.description);
+                    for (value_name, value) in &enum_type.values {
+                        interner.add_name(value_name);
+                        interner.add_description(&value.description);
+                    }
+                }
+                ExtendedType::InputObject(input_object) => {
+                    interner.add_description(&input_object.description);
+                    for (field_name, field) in &input_object.fields {
+                        interner.add_name(field_name);
+                        interner.add_description(&field.description);
+                    }
+                }
+            }
+        }
+        interner
+    }
+
+    fn add_name(&mut self, name: &'a Name) {
+        self.names
+            .entry(name.as_str())
+            .or_insert_with(|| name.clone());
+    }
+
+    fn add_description(&mut self, description: &'a Option<Node<str>>) {
+        if let Some(description) = description {
+            self.descriptions
+                .entry(&**description)
+                .or_insert_with(|| description.clone());
+        }
+    }
+
+    fn add_fields(&mut self, fields: &'a IndexMap<Name, Component<FieldDefinition>>) {
+        for (field_name, field) in fields {
+            self.add_name(field_name);
+            self.add_description(&field.description);
+            for argument in &field.arguments {
+                self.add_name(&argument.name);
+                self.add_description(&argument.description);
+            }
+        }
+    }
+
+    /// Replaces the names and descriptions of the schema with the equal ones of the interner
+    pub(crate) fn intern(&self, schema: &mut Schema) {
+        self.intern_keys(&mut schema.types);
+        for ty in schema.types.values_mut() {
+            match ty {
+                ExtendedType::Scalar(scalar) => {
+                    let scalar = scalar.make_mut();
+                    self.intern_name(&mut scalar.name);
+                    self.intern_description(&mut scalar.description);
+                }
+                ExtendedType::Object(object) => {
+                    let object = object.make_mut();
+                    self.intern_name(&mut object.name);
+                    self.intern_description(&mut object.description);
+                    self.intern_keys(&mut object.fields);
+                    for field in object.fields.values_mut() {
+                        self.intern_field(field.make_mut());
+                    }
+                }
+                ExtendedType::Interface(interface) => {
+                    let interface = interface.make_mut();
+                    self.intern_name(&mut interface.name);
+                    self.intern_description(&mut interface.description);
+                    self.intern_keys(&mut interface.fields);
+                    for field in interface.fields.values_mut() {
+                        self.intern_field(field.make_mut());
+                    }
+                }
+                ExtendedType::Union(union) => {
+                    let union = union.make_mut();
+                    self.intern_name(&mut union.name);
+                    self.intern_description(&mut union.description);
+                }
+                ExtendedType::Enum(enum_type) => {
+                    let enum_type = enum_type.make_mut();
+                    self.intern_name(&mut enum_type.name);
+                    self.intern_description(&mut enum_type.description);
+                    self.intern_keys(&mut enum_type.values);
+                    for value in enum_type.values.values_mut() {
+                        let value = value.make_mut();
+                        self.intern_name(&mut value.value);
+                        self.intern_description(&mut value.description);
+                    }
+                }
+                ExtendedType::InputObject(input_object) => {
+                    let input_object = input_object.make_mut();
+                    self.intern_name(&mut input_object.name);
+                    self.intern_description(&mut input_object.description);
+                    self.intern_keys(&mut input_object.fields);
+                    for field in input_object.fields.values_mut() {
+                        self.intern_input_value(field.make_mut());
+                    }
+                }
+            }
+        }
+    }
+
+    fn intern_field(&self, field: &mut FieldDefinition) {
+        self.intern_name(&mut field.name);
+        self.intern_description(&mut field.description);
+        self.intern_type(&mut field.ty);
+        for argument in &mut field.arguments {
+            self.intern_input_value(argument.make_mut());
+        }
+    }
+
+    fn intern_input_value(&self, input_value: &mut InputValueDefinition) {
+        self.intern_name(&mut input_value.name);
+        self.intern_description(&mut input_value.description);
+        self.intern_type(input_value.ty.make_mut());
+    }
+
+    fn intern_type(&self, ty: &mut Type) {
+        match ty {
+            Type::Named(name) | Type::NonNullNamed(name) => self.intern_name(name),
+            Type::List(inner) | Type::NonNullList(inner) => self.intern_type(inner),
+        }
+    }
+
+    /// Map keys cannot be modified in place, so the map is rebuilt in the same order
+    fn intern_keys<V>(&self, map: &mut IndexMap<Name, V>) {
+        *map = std::mem::take(map)
+            .into_iter()
+            .map(|(mut name, value)| {
+                self.intern_name(&mut name);
+                (name, value)
+            })
+            .collect();
+    }
+
+    fn intern_name(&self, name: &mut Name) {
+        if let Some(shared) = self.names.get(name.as_str()) {
+            *name = shared.clone();
+        }
+    }
+
+    fn intern_description(&self, description: &mut Option<Node<str>>) {
+        if let Some(shared) = description
+            .as_deref()
+            .and_then(|description| self.descriptions.get(description))
+        {
+            *description = Some(shared.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SUBGRAPH: &str = r#"
+        type Query {
+            "The current user"
+            me: User
+        }
+        type User {
+            "The name of the user"
+            name(format: String): String
+        }
+    "#;
+
+    #[test]
+    fn shares_names_and_descriptions() {
+        let supergraph = Schema::parse_and_validate(
+            r#"
+            type Query {
+                "The current user"
+                me: User
+            }
+            type User {
+                id: ID!
+                "The name of the user"
+                name(format: String): String
+            }
+            "#,
+            "supergraph.graphql",
+        )
+        .unwrap();
+        let mut subgraph = Schema::parse_and_validate(SUBGRAPH, "subgraph.graphql")
+            .unwrap()
+            .into_inner();
+
+        Interner::new(&supergraph).intern(&mut subgraph);
+
+        let supergraph_me = supergraph.type_field("Query", "me").unwrap();
+        let subgraph_me = subgraph.type_field("Query", "me").unwrap();
+        assert_eq!(subgraph_me.description, supergraph_me.description);
+        assert!(std::ptr::eq(
+            subgraph_me.description.as_deref().unwrap().as_ptr(),
+            supergraph_me.description.as_deref().unwrap().as_ptr(),
+        ));
+        assert!(std::ptr::eq(
+            subgraph_me.ty.inner_named_type().as_str().as_ptr(),
+            supergraph_me.ty.inner_named_type().as_str().as_ptr(),
+        ));
+
+        let supergraph_name = supergraph.type_field("User", "name").unwrap();
+        let subgraph_name = subgraph.type_field("User", "name").unwrap();
+        assert!(std::ptr::eq(
+            subgraph_name.arguments[0].name.as_str().as_ptr(),
+            supergraph_name.arguments[0].name.as_str().as_ptr(),
+        ));
+
+        // interning does not change the schema
+        assert_eq!(
+            subgraph.to_string(),
+            Schema::parse_and_validate(SUBGRAPH, "subgraph.graphql")
+                .unwrap()
+                .to_string()
+        );
+    }
+}
@@ -2,8 +2,10 @@
 #![cfg_attr(not(test), deny(clippy::expect_used))]
 #![cfg_attr(not(test), deny(clippy::panic))]
 
+pub(crate) mod constraints;
 mod field_type;
 mod fragments;
+pub(crate) mod interning;
 pub(crate) mod operation_limits;
 pub(crate) mod query;
 mod schema;
@@ -15,6 +17,7 @@ pub(crate) use fragments::*;
 pub(crate) use query::Query;
 pub(crate) use query::TYPENAME;
 pub(crate) use schema::Schema;
+pub use schema::SchemaChange;
 pub(crate) use selection::*;
 use serde::Deserialize;
 use serde::Serialize;
@@ -19,6 +19,15 @@ pub(crate) struct OperationLimits<T> {
 
 /// If it swims like a burrito and quacks like a burrito…
 impl<A> OperationLimits<A> {
+    fn as_ref(&self) -> OperationLimits<&A> {
+        OperationLimits {
+            depth: &self.depth,
+            height: &self.height,
+            root_fields: &self.root_fields,
+            aliases: &self.aliases,
+        }
+    }
+
     fn map<B>(self, mut f: impl FnMut(A) -> B) -> OperationLimits<B> {
         OperationLimits {
             depth: f(self.depth),
@@ -42,6 +51,20 @@ impl<A> OperationLimits<A> {
     }
 }
 
+/// A limit exceeded by an operation, with hints pointing at the part of the operation
+/// responsible for it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct ExceededLimit {
+    /// The value measured for the operation
+    pub(crate) measured: u32,
+    /// The configured maximum
+    pub(crate) max: u32,
+    /// Response path to the first field that pushed the measurement over the maximum
+    pub(crate) path: Option<Vec<String>>,
+    /// Schema coordinate (`Type.field`) of that field
+    pub(crate) coordinate: Option<String>,
+}
+
 impl OperationLimits<bool> {
     fn any(&self) -> bool {
         // make the compile warn if we forget one
@@ -62,20 +85,33 @@ pub(crate) fn check(
     query: &str,
     document: &ExecutableDocument,
     operation_name: Option<&str>,
-) -> Result<(), OperationLimits<bool>> {
+) -> Result<(), OperationLimits<Option<ExceededLimit>>> {
     let config_limits = &configuration.limits;
-    let max = OperationLimits {
-        depth: config_limits.max_depth,
-        height: config_limits.max_height,
-        root_fields: config_limits.max_root_fields,
-        aliases: config_limits.max_aliases,
-    };
     let Ok(operation) = document.operations.get(operation_name) else {
         // Undefined or ambiguous operation name.
         // The request is invalid and will be rejected by some other part of the router,
         // if it wasn’t already before we got to this code path.
         return Ok(());
     };
+    let max = if operation.is_introspection(document) {
+        OperationLimits {
+            depth: config_limits
+                .introspection_max_depth
+                .or(config_limits.max_depth),
+            height: config_limits
+                .introspection_max_height
+                .or(config_limits.max_height),
+            root_fields: config_limits.max_root_fields,
+            aliases: config_limits.max_aliases,
+        }
+    } else {
+        OperationLimits {
+            depth: config_limits.max_depth,
+            height: config_limits.max_height,
+            root_fields: config_limits.max_root_fields,
+            aliases: config_limits.max_aliases,
+        }
+    };
 
     let mut fragment_cache = HashMap::new();
     let measured = count(document, &mut fragment_cache, &operation.selection_set);
@@ -89,19 +125,32 @@ pub(crate) fn check(
         return Ok(());
     }
 
-    let exceeded = max.combine(measured, |_, config, measured| {
-        if let Some(limit) = config {
-            measured > limit
-        } else {
-            false
-        }
+    let exceeded = max.combine(measured, |ident, config, measured| {
+        let max = config.filter(|max| measured > *max)?;
+        let (path, coordinate) =
+            offending_field(document, &operation.selection_set, ident, max).unzip();
+        Some(ExceededLimit {
+            measured,
+            max,
+            path,
+            coordinate,
+        })
     });
-    if exceeded.any() {
+    if exceeded.as_ref().map(Option::is_some).any() {
         let mut messages = Vec::new();
-        max.combine(measured, |ident, max, measured| {
-            if let Some(max) = max {
-                if measured > max {
-                    messages.push(format!("{ident}: {measured}, max_{ident}: {max}"))
+        exceeded.as_ref().combine(measured, |ident, exceeded, _| {
+            if let Some(ExceededLimit {
+                measured,
+                max,
+                coordinate,
+                ..
+            }) = exceeded
+            {
+                match coordinate {
+                    Some(coordinate) => messages.push(format!(
+                        "{ident}: {measured}, max_{ident}: {max} (at {coordinate})"
+                    )),
+                    None => messages.push(format!("{ident}: {measured}, max_{ident}: {max}")),
                 }
             }
         });
@@ -117,6 +166,115 @@ pub(crate) fn check(
     Ok(())
 }
 
+/// Position of a field within the operation, as seen by [`count`]
+struct FieldPosition {
+    depth: u32,
+    is_alias: bool,
+    /// Whether this is the first field with this response key in its selection set
+    first_in_selection_set: bool,
+    /// Whether this field is directly in the operation’s root selection set
+    at_root: bool,
+}
+
+/// Finds the first field that makes the operation go over `max` for the given limit,
+/// returning its response path and schema coordinate.
+///
+/// This is only called once a limit is known to be exceeded, to give clients
+/// something more actionable than the limit name.
+fn offending_field(
+    document: &executable::ExecutableDocument,
+    selection_set: &executable::SelectionSet,
+    limit: &'static str,
+    max: u32,
+) -> Option<(Vec<String>, String)> {
+    let mut counted = 0;
+    let is_offending = move |position: &FieldPosition| {
+        let counts = match limit {
+            "depth" => return position.depth > max,
+            "height" => position.first_in_selection_set,
+            "root_fields" => position.at_root && position.first_in_selection_set,
+            "aliases" => position.is_alias,
+            _ => return false,
+        };
+        if counts {
+            counted += 1;
+        }
+        counted > max
+    };
+    let mut walker = OffendingFieldWalker {
+        document,
+        fragments_in_progress: HashSet::new(),
+        path: Vec::new(),
+        is_offending,
+    };
+    walker.walk(selection_set, 0, true)
+}
+
+struct OffendingFieldWalker<'a, F> {
+    document: &'a executable::ExecutableDocument,
+    fragments_in_progress: HashSet<&'a Name>,
+    path: Vec<&'a Name>,
+    is_offending: F,
+}
+
+impl<'a, F> OffendingFieldWalker<'a, F>
+where
+    F: FnMut(&FieldPosition) -> bool,
+{
+    /// Visits fields in document order, following the same rules as [`count`]
+    fn walk(
+        &mut self,
+        selection_set: &'a executable::SelectionSet,
+        depth: u32,
+        at_root: bool,
+    ) -> Option<(Vec<String>, String)> {
+        let mut fields_seen = HashSet::new();
+        for selection in &selection_set.selections {
+            let found = match selection {
+                executable::Selection::Field(field) => {
+                    let used_name = field.alias.as_ref().unwrap_or(&field.name);
+                    let position = FieldPosition {
+                        depth: depth + 1,
+                        is_alias: field.alias.is_some(),
+                        first_in_selection_set: fields_seen.insert(used_name),
+                        at_root,
+                    };
+                    self.path.push(used_name);
+                    let found = if (self.is_offending)(&position) {
+                        Some((
+                            self.path.iter().map(|name| name.to_string()).collect(),
+                            format!("{}.{}", selection_set.ty, field.name),
+                        ))
+                    } else {
+                        self.walk(&field.selection_set, depth + 1, false)
+                    };
+                    self.path.pop();
+                    found
+                }
+                executable::Selection::InlineFragment(fragment) => {
+                    self.walk(&fragment.selection_set, depth, false)
+                }
+                executable::Selection::FragmentSpread(fragment) => {
+                    let name = &fragment.fragment_name;
+                    match self.document.fragments.get(name) {
+                        Some(definition) if self.fragments_in_progress.insert(name) => {
+                            let found = self.walk(&definition.selection_set, depth, false);
+                            self.fragments_in_progress.remove(name);
+                            found
+                        }
+                        // Undefined or recursive fragment, the operation is invalid
+                        _ => None,
+                    }
+                }
+            };
+            if found.is_some() {
+                return found;
+            }
+        }
+        None
+    }
+}
+
 enum Computation<T> {
     InProgress,
     Done(T),
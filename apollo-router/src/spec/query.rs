@@ -35,6 +35,8 @@ use crate::query_planner::fetch::OperationKind;
 use crate::query_planner::fetch::QueryHash;
 use crate::services::layers::query_analysis::ParsedDocument;
 use crate::services::layers::query_analysis::ParsedDocumentInner;
+use crate::spec::constraints;
+use crate::spec::constraints::ConstrainedArgument;
 use crate::spec::schema::ApiSchema;
 use crate::spec::FieldType;
 use crate::spec::Fragments;
@@ -45,6 +47,7 @@ use crate::spec::SpecError;
 use crate::Configuration;
 
 pub(crate) mod change;
+pub(crate) mod conditions;
 pub(crate) mod subselections;
 pub(crate) mod transform;
 pub(crate) mod traverse;
@@ -349,11 +352,17 @@ impl Query {
             conditional_defer_variable_names: IndexSet::default(),
         };
         let fragments = Fragments::from_hir(document, schema, &mut defer_stats)?;
-        let operations = document
+        let mut operations = document
             .operations
             .iter()
             .map(|operation| Operation::from_hir(operation, schema, &mut defer_stats, &fragments))
             .collect::<Result<Vec<_>, SpecError>>()?;
+        for operation in &mut operations {
+            operation.constrained_arguments =
+                ConstrainedArgument::collect(schema, document, operation.name.as_deref()).map_err(
+                    |e| SpecError::TransformError(format!("could not collect constraints: {e}")),
+                )?;
+        }
 
         let mut visitor =
             QueryHashVisitor::new(schema.supergraph_schema(), &schema.raw_sdl, document);
@@ -986,6 +995,31 @@ impl Query {
                     })
                 },
             )
+            .chain(
+                self.operations
+                    .iter()
+                    .filter(|operation| {
+                        operation_name.is_none() || operation.name.as_deref() == operation_name
+                    })
+                    .flat_map(|operation| {
+                        constraints::check_arguments(
+                            &operation.constrained_arguments,
+                            &request.variables,
+                            |name| {
+                                operation_variable_types
+                                    .get(name)
+                                    .and_then(|variable| variable.default_value.as_ref())
+                            },
+                        )
+                    })
+                    .map(|(argument, reason)| {
+                        FetchError::ValidationConstraintViolation {
+                            argument: argument.to_string(),
+                            reason,
+                        }
+                        .to_graphql_error(None)
+                    }),
+            )
             .collect::<Vec<_>>();
 
         if errors.is_empty() {
@@ -1105,6 +1139,8 @@ pub(crate) struct Operation {
     type_name: String,
     pub(crate) selection_set: Vec<Selection>,
     variables: HashMap<ByteString, Variable>,
+    /// Arguments limited by the constraints spec, checked with the variables
+    constrained_arguments: Vec<ConstrainedArgument>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1155,6 +1191,7 @@ impl Operation {
             type_name,
             variables,
             kind,
+            constrained_arguments: Vec::new(),
         })
     }
 
@@ -0,0 +1,458 @@
+//! Evaluation of the `@skip` and `@include` conditions of an operation before query planning.
+//!
+//! Operations that only differ by their conditions, like `{ a @include(if: true) }` and `{ a }`,
+//! or by the values of the variables used in their conditions, are normalized to the same
+//! document, so that they share the same query plan. Conditions are only evaluated when it is
+//! safe: variables without a boolean value are kept, and the document is kept as is if removing
+//! selections would change the shape of the response.
+
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+use apollo_compiler::ast;
+use apollo_compiler::Name;
+use tower::BoxError;
+
+use super::transform;
+use crate::json_ext::Object;
+
+const SKIP_DIRECTIVE_NAME: &str = "skip";
+const INCLUDE_DIRECTIVE_NAME: &str = "include";
+
+/// Result of evaluating the conditions of an operation
+#[derive(Debug)]
+pub(crate) enum EvaluatedConditions {
+    /// The operation has no `@skip` or `@include` conditions
+    NoConditions,
+    /// The conditions could not be safely evaluated
+    Kept,
+    /// The operation with its evaluated conditions removed
+    Normalized(ast::Document),
+}
+
+/// Evaluates the `@skip` and `@include` conditions of the operation, with literal values or the
+/// values of the request's variables. Documents with multiple operations are kept as is
+pub(crate) fn evaluate(
+    schema: &apollo_compiler::Schema,
+    document: &ast::Document,
+    operation_name: Option<&str>,
+    variables: &Object,
+) -> Result<EvaluatedConditions, BoxError> {
+    let mut operations = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            ast::Definition::OperationDefinition(operation) => Some(operation),
+            _ => None,
+        });
+    let (Some(operation), None) = (operations.next(), operations.next()) else {
+        return Ok(EvaluatedConditions::Kept);
+    };
+    let name = operation.name.as_ref().map(|name| name.as_str());
+    if operation_name.is_some_and(|operation_name| name != Some(operation_name)) {
+        return Ok(EvaluatedConditions::Kept);
+    }
+
+    let mut visitor = ConditionsVisitor {
+        schema,
+        values: boolean_values(operation, variables).collect(),
+        conditions: 0,
+        evaluated: 0,
+        changes_response: false,
+        removed_fragments: HashSet::new(),
+    };
+    let normalized = transform::document(&mut visitor, document)?;
+
+    if visitor.conditions == 0 {
+        return Ok(EvaluatedConditions::NoConditions);
+    }
+    if visitor.evaluated == 0 || visitor.changes_response {
+        return Ok(EvaluatedConditions::Kept);
+    }
+    // the operation is removed if all its root fields are skipped
+    match remove_unused_definitions(normalized) {
+        Some(normalized) => Ok(EvaluatedConditions::Normalized(normalized)),
+        None => Ok(EvaluatedConditions::Kept),
+    }
+}
+
+struct ConditionsVisitor<'a> {
+    schema: &'a apollo_compiler::Schema,
+    values: HashMap<Name, bool>,
+    /// Number of `@skip` and `@include` directives
+    conditions: usize,
+    /// Number of directives evaluated and removed
+    evaluated: usize,
+    /// Set when a field would lose all its subselections, which is not valid GraphQL
+    changes_response: bool,
+    removed_fragments: HashSet<Name>,
+}
+
+impl<'a> ConditionsVisitor<'a> {
+    /// Returns the directives without the evaluated conditions, or `None` if the selection is
+    /// skipped
+    fn evaluate(&mut self, directives: &ast::DirectiveList) -> Option<ast::DirectiveList> {
+        let mut included = true;
+        let mut kept = Vec::with_capacity(directives.len());
+        for directive in directives.iter() {
+            let skip = match directive.name.as_str() {
+                SKIP_DIRECTIVE_NAME => true,
+                INCLUDE_DIRECTIVE_NAME => false,
+                _ => {
+                    kept.push(directive.clone());
+                    continue;
+                }
+            };
+            self.conditions += 1;
+            let value = match directive.argument_by_name("if").map(|value| &**value) {
+                Some(ast::Value::Boolean(value)) => Some(*value),
+                Some(ast::Value::Variable(name)) => self.values.get(name).copied(),
+                _ => None,
+            };
+            match value {
+                Some(value) => {
+                    self.evaluated += 1;
+                    included &= value != skip;
+                }
+                None => kept.push(directive.clone()),
+            }
+        }
+        included.then_some(ast::DirectiveList(kept))
+    }
+}
+
+impl<'a> transform::Visitor for ConditionsVisitor<'a> {
+    fn schema(&self) -> &apollo_compiler::Schema {
+        self.schema
+    }
+
+    fn fragment_definition(
+        &mut self,
+        def: &ast::FragmentDefinition,
+    ) -> Result<Option<ast::FragmentDefinition>, BoxError> {
+        let new = transform::fragment_definition(self, def)?;
+        if new.is_none() {
+            self.removed_fragments.insert(def.name.clone());
+        }
+        Ok(new)
+    }
+
+    fn field(
+        &mut self,
+        _parent_type: &str,
+        field_def: &ast::FieldDefinition,
+        def: &ast::Field,
+    ) -> Result<Option<ast::Field>, BoxError> {
+        let Some(directives) = self.evaluate(&def.directives) else {
+            return Ok(None);
+        };
+        let new = transform::field(self, field_def, def)?;
+        if new.is_none() {
+            self.changes_response = true;
+        }
+        Ok(new.map(|field| ast::Field {
+            directives,
+            ..field
+        }))
+    }
+
+    fn fragment_spread(
+        &mut self,
+        def: &ast::FragmentSpread,
+    ) -> Result<Option<ast::FragmentSpread>, BoxError> {
+        if self.removed_fragments.contains(&def.fragment_name) {
+            return Ok(None);
+        }
+        let Some(directives) = self.evaluate(&def.directives) else {
+            return Ok(None);
+        };
+        Ok(Some(ast::FragmentSpread {
+            fragment_name: def.fragment_name.clone(),
+            directives,
+        }))
+    }
+
+    fn inline_fragment(
+        &mut self,
+        parent_type: &str,
+        def: &ast::InlineFragment,
+    ) -> Result<Option<ast::InlineFragment>, BoxError> {
+        let Some(directives) = self.evaluate(&def.directives) else {
+            return Ok(None);
+        };
+        Ok(
+            transform::inline_fragment(self, parent_type, def)?.map(|fragment| {
+                ast::InlineFragment {
+                    directives,
+                    ..fragment
+                }
+            }),
+        )
+    }
+}
+
+/// Values of the variables of the document's operations that can be used in conditions: the
+/// evaluation of a document only depends on these values
+pub(crate) fn variable_values(document: &ast::Document, variables: &Object) -> Vec<(Name, bool)> {
+    document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            ast::Definition::OperationDefinition(operation) => Some(operation),
+            _ => None,
+        })
+        .flat_map(|operation| boolean_values(operation, variables))
+        .collect()
+}
+
+// variables without a boolean value will fail validation, or are not used in conditions
+fn boolean_values<'a>(
+    operation: &'a ast::OperationDefinition,
+    variables: &'a Object,
+) -> impl Iterator<Item = (Name, bool)> + 'a {
+    operation.variables.iter().filter_map(|definition| {
+        let value = match variables.get(definition.name.as_str()) {
+            Some(value) => value.as_bool()?,
+            None => match definition.default_value.as_deref() {
+                Some(ast::Value::Boolean(value)) => *value,
+                _ => return None,
+            },
+        };
+        Some((definition.name.clone(), value))
+    })
+}
+
+/// Removes the fragments and variables only used by skipped selections or evaluated conditions,
+/// since unused definitions fail validation
+fn remove_unused_definitions(document: ast::Document) -> Option<ast::Document> {
+    let fragments = transform::collect_fragments(&document);
+    let operation = document
+        .definitions
+        .iter()
+        .find_map(|definition| match definition {
+            ast::Definition::OperationDefinition(operation) => Some(operation),
+            _ => None,
+        })?;
+
+    let mut used = UsedDefinitions::default();
+    used.directives(&operation.directives);
+    used.selection_set(&operation.selection_set, &fragments);
+    let UsedDefinitions {
+        fragments: used_fragments,
+        variables: used_variables,
+    } = used;
+
+    let definitions = document
+        .definitions
+        .iter()
+        .filter_map(|definition| match definition {
+            ast::Definition::OperationDefinition(operation) => {
+                let mut operation = operation.clone();
+                operation
+                    .make_mut()
+                    .variables
+                    .retain(|variable| used_variables.contains(&variable.name));
+                Some(ast::Definition::OperationDefinition(operation))
+            }
+            ast::Definition::FragmentDefinition(fragment) => used_fragments
+                .contains(&fragment.name)
+                .then(|| definition.clone()),
+            _ => Some(definition.clone()),
+        })
+        .collect();
+
+    Some(ast::Document {
+        sources: document.sources.clone(),
+        definitions,
+    })
+}
+
+#[derive(Default)]
+struct UsedDefinitions {
+    fragments: HashSet<Name>,
+    variables: HashSet<Name>,
+}
+
+impl UsedDefinitions {
+    fn selection_set(
+        &mut self,
+        selection_set: &[ast::Selection],
+        fragments: &HashMap<&Name, &ast::FragmentDefinition>,
+    ) {
+        for selection in selection_set {
+            match selection {
+                ast::Selection::Field(field) => {
+                    for argument in &field.arguments {
+                        self.value(&argument.value);
+                    }
+                    self.directives(&field.directives);
+                    self.selection_set(&field.selection_set, fragments);
+                }
+                ast::Selection::FragmentSpread(spread) => {
+                    self.directives(&spread.directives);
+                    if self.fragments.insert(spread.fragment_name.clone()) {
+                        if let Some(fragment) = fragments.get(&spread.fragment_name) {
+                            self.directives(&fragment.directives);
+                            self.selection_set(&fragment.selection_set, fragments);
+                        }
+                    }
+                }
+                ast::Selection::InlineFragment(fragment) => {
+                    self.directives(&fragment.directives);
+                    self.selection_set(&fragment.selection_set, fragments);
+                }
+            }
+        }
+    }
+
+    fn directives(&mut self, directives: &ast::DirectiveList) {
+        for directive in directives.iter() {
+            for argument in &directive.arguments {
+                self.value(&argument.value);
+            }
+        }
+    }
+
+    fn value(&mut self, value: &ast::Value) {
+        match value {
+            ast::Value::Variable(name) => {
+                self.variables.insert(name.clone());
+            }
+            ast::Value::List(items) => items.iter().for_each(|item| self.value(item)),
+            ast::Value::Object(fields) => fields.iter().for_each(|(_, value)| self.value(value)),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json_bytes::json;
+
+    use super::*;
+
+    const SCHEMA: &str = "
+        type Query {
+            me: User
+            user(id: ID!): User
+        }
+
+        type User {
+            id: ID!
+            name: String
+            friends: [User]
+        }
+    ";
+
+    fn evaluate_query(query: &str, variables: serde_json_bytes::Value) -> EvaluatedConditions {
+        let schema = apollo_compiler::Schema::parse_and_validate(SCHEMA, "schema.graphql").unwrap();
+        let document = ast::Document::parse(query, "query.graphql").unwrap();
+        document.to_executable_validate(&schema).unwrap();
+        let variables = variables.as_object().unwrap().clone();
+        evaluate(&schema, &document, None, &variables).unwrap()
+    }
+
+    fn normalized(expected: &str) -> String {
+        ast::Document::parse(expected, "expected.graphql")
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn evaluates_literal_and_variable_conditions() {
+        let query = "
+            query($withName: Boolean!, $skipFriends: Boolean = true, $id: ID!) {
+                me @include(if: true) {
+                    id
+                    name @include(if: $withName)
+                    ...Friends @skip(if: $skipFriends)
+                }
+                user(id: $id) @skip(if: false) { id }
+            }
+
+            fragment Friends on User {
+                friends { id }
+            }
+        ";
+        let EvaluatedConditions::Normalized(document) =
+            evaluate_query(query, json!({ "withName": false, "id": "1" }))
+        else {
+            panic!("the conditions should be evaluated");
+        };
+        assert_eq!(
+            document.to_string(),
+            normalized("query($id: ID!) { me { id } user(id: $id) { id } }")
+        );
+
+        // the same operation without the conditions gets the same document
+        let EvaluatedConditions::Normalized(other) = evaluate_query(
+            "query($id: ID!, $yes: Boolean!) { me @include(if: $yes) { id } user(id: $id) { id } }",
+            json!({ "yes": true, "id": "2" }),
+        ) else {
+            panic!("the conditions should be evaluated");
+        };
+        assert_eq!(document.to_string(), other.to_string());
+    }
+
+    #[test]
+    fn keeps_conditions_that_cannot_be_evaluated() {
+        assert!(matches!(
+            evaluate_query("{ me { id } }", json!({})),
+            EvaluatedConditions::NoConditions
+        ));
+
+        // the variable has no value
+        assert!(matches!(
+            evaluate_query(
+                "query($yes: Boolean) { me @include(if: $yes) { id } }",
+                json!({})
+            ),
+            EvaluatedConditions::Kept
+        ));
+
+        // `me` would lose all its subselections
+        assert!(matches!(
+            evaluate_query(
+                "query($no: Boolean!) { me { id @include(if: $no) } }",
+                json!({ "no": false })
+            ),
+            EvaluatedConditions::Kept
+        ));
+
+        // the whole operation is skipped
+        assert!(matches!(
+            evaluate_query("{ me @skip(if: true) { id } }", json!({})),
+            EvaluatedConditions::Kept
+        ));
+
+        // only the conditions with values are evaluated
+        let EvaluatedConditions::Normalized(document) = evaluate_query(
+            "query($a: Boolean!, $b: Boolean) { me @include(if: $a) { id @skip(if: $b) name } }",
+            json!({ "a": true }),
+        ) else {
+            panic!("the conditions should be evaluated");
+        };
+        assert_eq!(
+            document.to_string(),
+            normalized("query($b: Boolean) { me { id @skip(if: $b) name } }")
+        );
+    }
+
+    #[test]
+    fn variable_values_only_keep_boolean_values() {
+        let document = ast::Document::parse(
+            "query($a: Boolean!, $b: Boolean = false, $c: Boolean, $id: ID!) { me { id } }",
+            "query.graphql",
+        )
+        .unwrap();
+        let variables = json!({ "a": true, "c": null, "id": "1" });
+        let values = variable_values(&document, variables.as_object().unwrap());
+        assert_eq!(
+            values,
+            vec![
+                (Name::new("a").unwrap(), true),
+                (Name::new("b").unwrap(), false)
+            ]
+        );
+    }
+}
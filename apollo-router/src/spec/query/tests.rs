@@ -1833,6 +1833,96 @@ fn variable_validation() {
     assert!(res.is_ok(), "validation should have succeeded: {:?}", res);
 }
 
+#[test]
+fn argument_constraints_validation() {
+    let schema = with_supergraph_boilerplate_fed2(
+        r#"
+        extend schema
+            @link(url: "https://specs.apollo.dev/constraints/v0.1", import: ["@constraint"])
+
+        directive @constraint(maxLength: Int, maxItems: Int, min: Float, max: Float) on ARGUMENT_DEFINITION
+
+        type Query {
+            search(text: String @constraint(maxLength: 5)): String
+            users(ids: [ID] @constraint(maxItems: 2, maxLength: 3)): String
+            page(size: Int @constraint(min: 1, max: 100)): String
+        }
+        "#,
+        "Query",
+    );
+
+    let violations = |query: &str, variables: Value| {
+        run_validation!(schema, query, variables)
+            .err()
+            .map(|response| {
+                response
+                    .errors
+                    .iter()
+                    .map(|error| {
+                        assert_eq!(
+                            error.extensions.get("code"),
+                            Some(&"VALIDATION_CONSTRAINT_VIOLATION".into())
+                        );
+                        error
+                            .extensions
+                            .get("argument")
+                            .and_then(|argument| argument.as_str())
+                            .unwrap()
+                            .to_string()
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+
+    assert!(violations("query($t:String){search(text:$t)}", json!({"t": "short"})).is_empty());
+    assert_eq!(
+        violations(
+            "query($t:String){search(text:$t)}",
+            json!({"t": "too long"})
+        ),
+        ["Query.search(text:)"]
+    );
+    assert_eq!(
+        violations(r#"{search(text:"too long")}"#, json!({})),
+        ["Query.search(text:)"]
+    );
+    assert_eq!(
+        violations(r#"query($t:String="too long"){search(text:$t)}"#, json!({})),
+        ["Query.search(text:)"]
+    );
+
+    assert!(violations("query($i:[ID]){users(ids:$i)}", json!({"i": ["a", "b"]})).is_empty());
+    assert_eq!(
+        violations(
+            "query($i:[ID]){users(ids:$i)}",
+            json!({"i": ["a", "b", "c"]})
+        ),
+        ["Query.users(ids:)"]
+    );
+    assert_eq!(
+        violations(
+            r#"query($i:ID){users(ids:["a", $i])}"#,
+            json!({"i": "long"})
+        ),
+        ["Query.users(ids:)"]
+    );
+
+    assert!(violations("query($s:Int){page(size:$s)}", json!({"s": 100})).is_empty());
+    assert!(violations("query($s:Int){page(size:$s)}", json!({})).is_empty());
+    assert_eq!(
+        violations("query($s:Int){page(size:$s)}", json!({"s": 0})),
+        ["Query.page(size:)"]
+    );
+    assert_eq!(
+        violations(
+            "query($s:Int){...F} fragment F on Query {page(size:$s)}",
+            json!({"s": 101})
+        ),
+        ["Query.page(size:)"]
+    );
+}
+
 #[test]
 fn filter_root_errors() {
     let schema = "type Query {
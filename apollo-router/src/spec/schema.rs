@@ -15,6 +15,7 @@ use apollo_federation::Supergraph;
 use http::Uri;
 use semver::Version;
 use semver::VersionReq;
+use serde::Serialize;
 use sha2::Digest;
 use sha2::Sha256;
 
@@ -33,6 +34,27 @@ pub(crate) struct Schema {
     pub(crate) schema_id: Arc<String>,
 }
 
+/// Summary of the differences between two supergraphs, sent to plugins when a new one is activated
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct SchemaChange {
+    /// Hash of the previous supergraph
+    pub previous_schema_hash: String,
+    /// Hash of the new supergraph
+    pub schema_hash: String,
+    /// Types of the API schema that were added
+    pub added_types: Vec<String>,
+    /// Types of the API schema that were removed
+    pub removed_types: Vec<String>,
+    /// Types of the API schema that were modified
+    pub changed_types: Vec<String>,
+    /// Subgraphs that were added
+    pub added_subgraphs: Vec<String>,
+    /// Subgraphs that were removed
+    pub removed_subgraphs: Vec<String>,
+}
+
 /// Wrapper type to distinguish from `Schema::definitions` for the supergraph schema
 #[derive(Debug)]
 pub(crate) struct ApiSchema(pub(crate) ValidFederationSchema);
@@ -148,6 +170,53 @@ impl Schema {
         format!("{:x}", hasher.finalize())
     }
 
+    /// Summarizes the changes from the previous supergraph to this one
+    pub(crate) fn changes_from(&self, previous: &Schema) -> SchemaChange {
+        let types = &self.api_schema().types;
+        let previous_types = &previous.api_schema().types;
+        let mut change = SchemaChange {
+            previous_schema_hash: previous.schema_id.to_string(),
+            schema_hash: self.schema_id.to_string(),
+            ..Default::default()
+        };
+        for (name, ty) in types {
+            match previous_types.get(name) {
+                None => change.added_types.push(name.to_string()),
+                Some(previous_ty) if previous_ty != ty => {
+                    change.changed_types.push(name.to_string())
+                }
+                Some(_) => {}
+            }
+        }
+        change.removed_types = previous_types
+            .keys()
+            .filter(|name| !types.contains_key(*name))
+            .map(|name| name.to_string())
+            .collect();
+        change.added_subgraphs = self
+            .subgraphs
+            .keys()
+            .filter(|name| !previous.subgraphs.contains_key(*name))
+            .cloned()
+            .collect();
+        change.removed_subgraphs = previous
+            .subgraphs
+            .keys()
+            .filter(|name| !self.subgraphs.contains_key(*name))
+            .cloned()
+            .collect();
+        for names in [
+            &mut change.added_types,
+            &mut change.removed_types,
+            &mut change.changed_types,
+            &mut change.added_subgraphs,
+            &mut change.removed_subgraphs,
+        ] {
+            names.sort();
+        }
+        change
+    }
+
     /// Extracts a string containing the entire [`Schema`].
     pub(crate) fn as_string(&self) -> &Arc<String> {
         &self.raw_sdl
@@ -575,6 +644,59 @@ mod tests {
         }
     }
 
+    #[test]
+    fn changes_from() {
+        let previous = Schema::parse(
+            &with_supergraph_boilerplate(
+                r#"
+            type Query {
+              me: User
+              removed: String
+            }
+            type User {
+              name: String
+            }
+            type Removed {
+              id: ID
+            }
+            "#,
+            ),
+            &Default::default(),
+        )
+        .unwrap();
+        let schema = Schema::parse(
+            &with_supergraph_boilerplate(
+                r#"
+            type Query {
+              me: User
+            }
+            type User {
+              name: String
+            }
+            type Added {
+              id: ID
+            }
+            "#,
+            ),
+            &Default::default(),
+        )
+        .unwrap();
+
+        let change = schema.changes_from(&previous);
+        assert_eq!(change.previous_schema_hash, *previous.schema_id);
+        assert_eq!(change.schema_hash, *schema.schema_id);
+        assert_eq!(change.added_types, ["Added"]);
+        assert_eq!(change.removed_types, ["Removed"]);
+        assert_eq!(change.changed_types, ["Query"]);
+        assert!(change.added_subgraphs.is_empty());
+        assert!(change.removed_subgraphs.is_empty());
+
+        let unchanged = schema.changes_from(&schema);
+        assert!(unchanged.added_types.is_empty());
+        assert!(unchanged.removed_types.is_empty());
+        assert!(unchanged.changed_types.is_empty());
+    }
+
     // test for https://github.com/apollographql/federation/pull/1769
     #[test]
     fn inaccessible_on_non_core() {
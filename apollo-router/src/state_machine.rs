@@ -236,6 +236,13 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
                                 event = STATE_CHANGE,
                                 "reload complete"
                             );
+                            if let Running {
+                                router_service_factory: new_router_service_factory,
+                                ..
+                            } = &new_state
+                            {
+                                new_router_service_factory.activated(router_service_factory);
+                            }
                             Some(new_state)
                         }
                         Err(e) => {
@@ -418,6 +425,7 @@ impl<FA: RouterSuperServiceFactory> State<FA> {
 
         let metrics =
             apollo_opentelemetry_initialized().then(|| Metrics::new(&configuration, &license));
+        crate::diagnose::running(configuration.clone(), schema.clone());
 
         Ok(Running {
             configuration,
@@ -0,0 +1,368 @@
+//! Probes a subgraph for compatibility with federation, without composing a supergraph.
+//!
+//! Each check sends a request that the router would send at runtime (or that a subgraph library
+//! is expected to handle) and compares the response with what the router expects.
+
+use std::fmt;
+use std::time::Duration;
+
+use apollo_compiler::Schema;
+use http::HeaderMap;
+use http::HeaderValue;
+use serde_json_bytes::json;
+use serde_json_bytes::Value;
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::connect_async_tls_with_config;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use url::Url;
+
+use crate::graphql;
+use crate::protocols::websocket::WebSocketProtocol;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Result of a single check
+#[derive(Debug, PartialEq)]
+pub(crate) enum Outcome {
+    Pass(String),
+    Warn(String),
+    Fail(String),
+    Skip(String),
+}
+
+#[derive(Debug)]
+pub(crate) struct Check {
+    pub(crate) name: &'static str,
+    pub(crate) outcome: Outcome,
+}
+
+/// Results of all checks against a subgraph
+#[derive(Debug)]
+pub(crate) struct Report {
+    pub(crate) checks: Vec<Check>,
+}
+
+impl Report {
+    pub(crate) fn has_failures(&self) -> bool {
+        self.checks
+            .iter()
+            .any(|check| matches!(check.outcome, Outcome::Fail(_)))
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for check in &self.checks {
+            let (status, detail) = match &check.outcome {
+                Outcome::Pass(detail) => ("PASS", detail),
+                Outcome::Warn(detail) => ("WARN", detail),
+                Outcome::Fail(detail) => ("FAIL", detail),
+                Outcome::Skip(detail) => ("SKIP", detail),
+            };
+            writeln!(f, "[{status}] {}: {detail}", check.name)?;
+        }
+        Ok(())
+    }
+}
+
+struct Probe {
+    client: reqwest::Client,
+    url: Url,
+    headers: HeaderMap,
+}
+
+impl Probe {
+    async fn send(&self, request: &graphql::Request) -> Result<(u16, Value), String> {
+        let response = self
+            .client
+            .post(self.url.clone())
+            .headers(self.headers.clone())
+            .json(request)
+            .timeout(TIMEOUT)
+            .send()
+            .await
+            .map_err(|e| format!("request failed: {e}"))?;
+        let status = response.status().as_u16();
+        let body = response
+            .json::<Value>()
+            .await
+            .map_err(|e| format!("the response with status {status} is not JSON: {e}"))?;
+        Ok((status, body))
+    }
+}
+
+/// Runs all checks against the subgraph at `url`
+pub(crate) async fn run(url: Url, headers: HeaderMap) -> Report {
+    let probe = Probe {
+        client: reqwest::Client::new(),
+        url,
+        headers,
+    };
+    let mut checks = Vec::new();
+
+    let schema = match check_service_sdl(&probe).await {
+        Ok((schema, outcome)) => {
+            checks.push(Check {
+                name: "service SDL",
+                outcome,
+            });
+            Some(schema)
+        }
+        Err(outcome) => {
+            checks.push(Check {
+                name: "service SDL",
+                outcome,
+            });
+            None
+        }
+    };
+
+    checks.push(Check {
+        name: "entities",
+        outcome: match &schema {
+            None => Outcome::Skip("the schema is not available".to_string()),
+            Some(schema) if !schema.types.values().any(|ty| ty.directives().has("key")) => {
+                Outcome::Skip("the schema does not define entities".to_string())
+            }
+            Some(_) => check_entities(&probe).await,
+        },
+    });
+
+    checks.push(Check {
+        name: "error format",
+        outcome: check_errors(&probe).await,
+    });
+
+    checks.push(Check {
+        name: "subscriptions",
+        outcome: match &schema {
+            None => Outcome::Skip("the schema is not available".to_string()),
+            Some(schema) if schema.schema_definition.subscription.is_none() => {
+                Outcome::Skip("the schema does not define subscriptions".to_string())
+            }
+            Some(_) => check_subscriptions(&probe).await,
+        },
+    });
+
+    Report { checks }
+}
+
+async fn check_service_sdl(probe: &Probe) -> Result<(Schema, Outcome), Outcome> {
+    let request = graphql::Request::builder()
+        .query("query SubgraphCheckService { _service { sdl } }")
+        .build();
+    let (status, body) = probe.send(&request).await.map_err(Outcome::Fail)?;
+    let Some(sdl) = body
+        .get("data")
+        .and_then(|data| data.get("_service"))
+        .and_then(|service| service.get("sdl"))
+        .and_then(|sdl| sdl.as_str())
+    else {
+        return Err(Outcome::Fail(format!(
+            "`_service {{ sdl }}` did not return the schema (status {status}): {}",
+            errors_summary(&body)
+        )));
+    };
+
+    // subgraphs commonly extend the implicit schema definition and root types
+    let parsed = Schema::builder()
+        .adopt_orphan_extensions()
+        .parse(sdl, "subgraph.graphql")
+        .build();
+    let (schema, parse_errors) = match parsed {
+        Ok(schema) => (schema, None),
+        Err(invalid) => (invalid.partial, Some(invalid.errors.to_string())),
+    };
+    let version = schema
+        .schema_definition
+        .directives
+        .iter()
+        .filter(|directive| directive.name == "link")
+        .filter_map(|directive| directive.argument_by_name("url")?.as_str())
+        .find_map(|url| url.strip_prefix("https://specs.apollo.dev/federation/"))
+        .map(|version| format!("federation {version}"))
+        .unwrap_or_else(|| "federation v1 (no @link to the federation spec)".to_string());
+
+    let outcome = match parse_errors {
+        None => Outcome::Pass(format!(
+            "{version}, {} types",
+            schema.types.values().filter(|ty| !ty.is_built_in()).count()
+        )),
+        Some(errors) => Outcome::Fail(format!("{version}, the schema does not parse:\n{errors}")),
+    };
+    Ok((schema, outcome))
+}
+
+async fn check_entities(probe: &Probe) -> Outcome {
+    let request = graphql::Request::builder()
+        .query("query SubgraphCheckEntities($representations: [_Any!]!) { _entities(representations: $representations) { __typename } }")
+        .variable("representations", json!([]))
+        .build();
+    match probe.send(&request).await {
+        Err(error) => Outcome::Fail(error),
+        Ok((_, body)) => match body.get("data").and_then(|data| data.get("_entities")) {
+            Some(Value::Array(entities)) if entities.is_empty() => {
+                Outcome::Pass("`_entities` accepts an empty list of representations".to_string())
+            }
+            Some(entities) => Outcome::Fail(format!(
+                "`_entities` with no representations should return an empty list, got {entities}"
+            )),
+            None => Outcome::Fail(format!(
+                "`_entities` did not return data: {}",
+                errors_summary(&body)
+            )),
+        },
+    }
+}
+
+async fn check_errors(probe: &Probe) -> Outcome {
+    // the router forwards subgraph errors to clients, they must follow the GraphQL spec
+    let request = graphql::Request::builder()
+        .query("query SubgraphCheckErrors {")
+        .build();
+    let (status, body) = match probe.send(&request).await {
+        Ok(response) => response,
+        Err(error) => return Outcome::Fail(error),
+    };
+    match body.get("errors") {
+        Some(Value::Array(errors))
+            if !errors.is_empty()
+                && errors
+                    .iter()
+                    .all(|error| error.get("message").and_then(Value::as_str).is_some()) =>
+        {
+            if matches!(body.get("data"), None | Some(Value::Null)) {
+                Outcome::Pass(format!(
+                    "invalid queries get a list of errors with messages (status {status})"
+                ))
+            } else {
+                Outcome::Warn("invalid queries get errors along with non null data".to_string())
+            }
+        }
+        _ => Outcome::Fail(format!(
+            "invalid queries should get a list of errors with messages, got: {body}"
+        )),
+    }
+}
+
+async fn check_subscriptions(probe: &Probe) -> Outcome {
+    let mut url = probe.url.clone();
+    let scheme = if url.scheme() == "https" { "wss" } else { "ws" };
+    if url.set_scheme(scheme).is_err() {
+        return Outcome::Fail(format!("cannot build a websocket URL from {}", probe.url));
+    }
+
+    let mut supported = Vec::new();
+    for protocol in [
+        WebSocketProtocol::GraphqlWs,
+        WebSocketProtocol::SubscriptionsTransportWs,
+    ] {
+        let protocol = HeaderValue::from(protocol);
+        let mut request = match url.as_str().into_client_request() {
+            Ok(request) => request,
+            Err(e) => return Outcome::Fail(format!("invalid websocket request: {e}")),
+        };
+        request.headers_mut().extend(probe.headers.clone());
+        request
+            .headers_mut()
+            .insert(http::header::SEC_WEBSOCKET_PROTOCOL, protocol.clone());
+
+        let connection = if scheme == "wss" {
+            tokio::time::timeout(
+                TIMEOUT,
+                connect_async_tls_with_config(request, None, false, None),
+            )
+            .await
+        } else {
+            tokio::time::timeout(TIMEOUT, connect_async(request)).await
+        };
+        if let Ok(Ok((_stream, response))) = connection {
+            if response
+                .headers()
+                .get(http::header::SEC_WEBSOCKET_PROTOCOL)
+                .is_some_and(|accepted| accepted == protocol)
+            {
+                supported.push(protocol.to_str().unwrap_or_default().to_string());
+            }
+        }
+    }
+
+    if supported.is_empty() {
+        Outcome::Warn(format!(
+            "no websocket protocol accepted at {url}, subscriptions can only use callback mode"
+        ))
+    } else {
+        Outcome::Pass(format!("websocket protocols: {}", supported.join(", ")))
+    }
+}
+
+fn errors_summary(body: &Value) -> String {
+    match body.get("errors") {
+        Some(errors) => errors.to_string(),
+        None => body.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use wiremock::matchers::body_string_contains;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn conformant_subgraph() {
+        let server = MockServer::start().await;
+        let sdl = r#"
+            extend schema @link(url: "https://specs.apollo.dev/federation/v2.5", import: ["@key"])
+            type Query { me: User }
+            type User @key(fields: "id") { id: ID! }
+        "#;
+        Mock::given(body_string_contains("SubgraphCheckService"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "data": { "_service": { "sdl": sdl } } })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(body_string_contains("SubgraphCheckEntities"))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(json!({ "data": { "_entities": [] } })),
+            )
+            .mount(&server)
+            .await;
+        Mock::given(body_string_contains("SubgraphCheckErrors"))
+            .respond_with(ResponseTemplate::new(400).set_body_json(
+                json!({ "errors": [{ "message": "Syntax Error: Expected Name, found <EOF>." }] }),
+            ))
+            .mount(&server)
+            .await;
+
+        let report = run(server.uri().parse().unwrap(), HeaderMap::new()).await;
+        assert!(!report.has_failures(), "{report}");
+        assert_eq!(
+            report.checks[0].outcome,
+            Outcome::Pass("federation v2.5, 2 types".to_string())
+        );
+        assert!(matches!(report.checks[3].outcome, Outcome::Skip(_)));
+    }
+
+    #[tokio::test]
+    async fn missing_service_sdl() {
+        let server = MockServer::start().await;
+        Mock::given(body_string_contains("query"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(
+                json!({ "errors": [{ "message": "Cannot query field \"_service\" on type \"Query\"." }] }),
+            ))
+            .mount(&server)
+            .await;
+
+        let report = run(server.uri().parse().unwrap(), HeaderMap::new()).await;
+        assert!(report.has_failures());
+        assert!(matches!(report.checks[0].outcome, Outcome::Fail(_)));
+        assert!(matches!(report.checks[1].outcome, Outcome::Skip(_)));
+        assert!(matches!(report.checks[2].outcome, Outcome::Pass(_)));
+    }
+}
@@ -346,6 +346,14 @@ impl LicenseEnforcementReport {
                 .path("$.limits.max_height")
                 .name("Operation height limiting")
                 .build(),
+            ConfigurationRestriction::builder()
+                .path("$.limits.introspection_max_depth")
+                .name("Introspection depth limiting")
+                .build(),
+            ConfigurationRestriction::builder()
+                .path("$.limits.introspection_max_height")
+                .name("Introspection height limiting")
+                .build(),
             ConfigurationRestriction::builder()
                 .path("$.limits.max_root_fields")
                 .name("Operation root fields limiting")
@@ -14,6 +14,8 @@ use tower::BoxError;
 use tracing::instrument::WithSubscriber;
 use url::Url;
 
+use crate::refresh::RefreshSource;
+
 pub(crate) mod license_enforcement;
 pub(crate) mod license_stream;
 pub(crate) mod persisted_queries_manifest_stream;
@@ -227,6 +229,7 @@ where
     let task = async move {
         let mut last_id = None;
         let mut endpoints = uplink_config.endpoints.unwrap_or_default();
+        let source = RefreshSource::register("uplink", query);
         loop {
             let variables = UplinkRequest {
                 graph_ref: uplink_config.apollo_graph_ref.to_string(),
@@ -250,7 +253,7 @@ where
                         status = "success",
                         query
                     );
-                    match response {
+                    let delay = match response {
                         UplinkResponse::New {
                             id,
                             response,
@@ -263,6 +266,7 @@ where
                                 tracing::debug!("failed to push to stream. This is likely to be because the router is shutting down: {e}");
                                 break;
                             }
+                            source.succeeded(uplink_config.poll_interval)
                         }
                         UplinkResponse::Unchanged { id, delay } => {
                             // Preserve behavior for schema uplink errors where id and delay are not reset if they are not provided on error.
@@ -272,6 +276,7 @@ where
                             if let Some(delay) = delay {
                                 uplink_config.poll_interval = Duration::from_secs(delay);
                             }
+                            source.succeeded(uplink_config.poll_interval)
                         }
                         UplinkResponse::Error {
                             retry_later,
@@ -279,19 +284,22 @@ where
                             code,
                         } => {
                             let err = if retry_later {
-                                Err(Error::UplinkError { code, message })
+                                Error::UplinkError { code, message }
                             } else {
-                                Err(Error::UplinkErrorNoRetry { code, message })
+                                Error::UplinkErrorNoRetry { code, message }
                             };
-                            if let Err(e) = sender.send(err).await {
+                            let delay = source.failed(uplink_config.poll_interval, &err);
+                            if let Err(e) = sender.send(Err(err)).await {
                                 tracing::debug!("failed to send error to uplink stream. This is likely to be because the router is shutting down: {e}");
                                 break;
                             }
                             if !retry_later {
                                 break;
                             }
+                            delay
                         }
-                    }
+                    };
+                    source.wait(delay).await;
                 }
                 Err(err) => {
                     tracing::info!(
@@ -299,14 +307,14 @@ where
                         status = "failure",
                         query
                     );
+                    let delay = source.failed(uplink_config.poll_interval, &err);
                     if let Err(e) = sender.send(Err(err)).await {
                         tracing::debug!("failed to send error to uplink stream. This is likely to be because the router is shutting down: {e}");
                         break;
                     }
+                    source.wait(delay).await;
                 }
             }
-
-            tokio::time::sleep(uplink_config.poll_interval).await;
         }
     };
     drop(tokio::task::spawn(task.with_current_subscriber()));
@@ -9,6 +9,9 @@ use tower::BoxError;
 
 const FILE_CONFIG: &str = include_str!("../fixtures/file_upload/default.router.yaml");
 const FILE_CONFIG_LARGE_LIMITS: &str = include_str!("../fixtures/file_upload/large.router.yaml");
+const FILE_CONFIG_TOTAL_SIZE: &str = include_str!("../fixtures/file_upload/total_size.router.yaml");
+const FILE_CONFIG_CONTENT_TYPES: &str =
+    include_str!("../fixtures/file_upload/content_types.router.yaml");
 
 /// Create a valid handler for the [helper::FileUploadTestServer].
 macro_rules! make_handler {
@@ -662,6 +665,84 @@ async fn it_fails_with_file_size_limit() -> Result<(), BoxError> {
         .await
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn it_fails_with_total_size_limit() -> Result<(), BoxError> {
+    // Create a file that passes the file size limit (512KB) but not the total size limit (300KB)
+    const FILE_SIZE: usize = 400 * 1000;
+    const FILE_CHUNK: [u8; FILE_SIZE] = [0xAA; FILE_SIZE];
+
+    let request = helper::create_request(
+        vec!["fat.payload.bin"],
+        vec![tokio_stream::once(Ok(bytes::Bytes::from_static(
+            &FILE_CHUNK,
+        )))],
+    );
+
+    // Run the test
+    helper::FileUploadTestServer::builder()
+        .config(FILE_CONFIG_TOTAL_SIZE)
+        .handler(make_handler!(helper::always_fail))
+        .request(request)
+        .subgraph_mapping("uploads", "/")
+        .build()
+        .run_test(|response| {
+            insta::assert_json_snapshot!(response, @r###"
+            {
+              "errors": [
+                {
+                  "message": "HTTP fetch failed from 'uploads': HTTP fetch failed from 'uploads': error from user's HttpBody stream: error reading a body from connection: Exceeded the limit of 300.0 KB on the total size of files in a single request.",
+                  "path": [],
+                  "extensions": {
+                    "code": "SUBREQUEST_HTTP_ERROR",
+                    "service": "uploads",
+                    "reason": "HTTP fetch failed from 'uploads': error from user's HttpBody stream: error reading a body from connection: Exceeded the limit of 300.0 KB on the total size of files in a single request."
+                  }
+                }
+              ]
+            }
+            "###);
+        })
+        .await
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn it_fails_with_content_type_limit() -> Result<(), BoxError> {
+    // The files created by the helper have no content type, which is rejected when the
+    // allowed content types are restricted
+    let request = helper::create_request(
+        vec!["example.txt"],
+        vec![tokio_stream::once(Ok(bytes::Bytes::from_static(
+            b"Hello, world!",
+        )))],
+    );
+
+    // Run the test
+    helper::FileUploadTestServer::builder()
+        .config(FILE_CONFIG_CONTENT_TYPES)
+        .handler(make_handler!(helper::always_fail))
+        .request(request)
+        .subgraph_mapping("uploads", "/")
+        .build()
+        .run_test(|response| {
+            insta::assert_json_snapshot!(response, @r###"
+            {
+              "errors": [
+                {
+                  "message": "HTTP fetch failed from 'uploads': HTTP fetch failed from 'uploads': error from user's HttpBody stream: error reading a body from connection: Content type of 'example.txt' file is not allowed: unknown.",
+                  "path": [],
+                  "extensions": {
+                    "code": "SUBREQUEST_HTTP_ERROR",
+                    "service": "uploads",
+                    "reason": "HTTP fetch failed from 'uploads': error from user's HttpBody stream: error reading a body from connection: Content type of 'example.txt' file is not allowed: unknown."
+                  }
+                }
+              ]
+            }
+            "###);
+        })
+        .await
+}
+
 #[tokio::test(flavor = "multi_thread")]
 async fn it_fails_invalid_multipart_order() -> Result<(), BoxError> {
     use reqwest::multipart::Form;
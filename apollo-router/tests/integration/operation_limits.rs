@@ -223,6 +223,85 @@ async fn test_warn_only() {
     assert_eq!(execution_count(), 2);
 }
 
+#[tokio::test(flavor = "multi_thread")]
+async fn test_error_hints() {
+    let (mut service, execution_count) = build_test_harness(json!({
+        "max_root_fields": 1,
+        "max_aliases": 1,
+        "max_depth": 2,
+    }))
+    .await;
+
+    let query = "{
+        me { id }
+        topProducts {
+            productName: name
+            reviews { reviewBody: body }
+        }
+    }";
+    let response = run_request(&mut service, query).await;
+    assert_eq!(execution_count(), 0);
+    let hints: Vec<_> = response
+        .errors
+        .iter()
+        .map(|err| {
+            json!({
+                "code": err.extensions.get("code"),
+                "measured": err.extensions.get("limit.measured"),
+                "max": err.extensions.get("limit.max"),
+                "path": err.extensions.get("limit.path"),
+                "coordinate": err.extensions.get("limit.coordinate"),
+            })
+        })
+        .collect();
+    assert_eq!(
+        hints,
+        vec![
+            json!({
+                "code": "MAX_DEPTH_LIMIT",
+                "measured": 3,
+                "max": 2,
+                "path": ["topProducts", "reviews", "reviewBody"],
+                "coordinate": "Review.body",
+            }),
+            json!({
+                "code": "MAX_ROOT_FIELDS_LIMIT",
+                "measured": 2,
+                "max": 1,
+                "path": ["topProducts"],
+                "coordinate": "Query.topProducts",
+            }),
+            json!({
+                "code": "MAX_ALIASES_LIMIT",
+                "measured": 2,
+                "max": 1,
+                "path": ["topProducts", "reviews", "reviewBody"],
+                "coordinate": "Review.body",
+            }),
+        ]
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_introspection_limits() {
+    let (mut service, _) = build_test_harness(json!({
+        "max_depth": 2,
+        "introspection_max_depth": 4,
+    }))
+    .await;
+
+    // introspection operations use their own depth limit
+    let query = "{ __schema { types { fields { name } } } }";
+    expect_errors(run_request(&mut service, query).await, &[]);
+
+    let query = "{ __schema { types { fields { type { name } } } } }";
+    expect_errors(run_request(&mut service, query).await, &["MAX_DEPTH_LIMIT"]);
+
+    // other operations still use the general one
+    let query = "{ topProducts { reviews { body } } }";
+    expect_errors(run_request(&mut service, query).await, &["MAX_DEPTH_LIMIT"]);
+}
+
 async fn build_test_harness(
     limits_config: serde_json::Value,
 ) -> (supergraph::BoxCloneService, impl Fn() -> u32) {
@@ -232,6 +311,7 @@ async fn build_test_harness(
     let service = TestHarness::builder()
         .configuration_json(json!({
             "limits": limits_config,
+            "supergraph": { "introspection": true },
             "include_subgraph_errors": { "all": true },
         }))
         .unwrap()